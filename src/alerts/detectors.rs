@@ -0,0 +1,470 @@
+use anyhow::{Context, Result, ensure};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::{AlertKind, Severity};
+use crate::api::{Device, DeviceStats};
+use crate::history::parse_duration;
+
+/// Built-in detectors as written in the `[detectors]` section of the rules file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetectorConfig {
+    /// Alert when the share rejection ratio exceeds this percentage
+    #[serde(default)]
+    pub rejection_ratio: Option<f64>,
+    /// Window the rejection ratio is computed over
+    #[serde(default = "default_rejection_window")]
+    pub rejection_window: String,
+    /// Shares needed in the window before the ratio is judged; fewer
+    /// clears an active rejection alert
+    #[serde(default = "default_rejection_min_shares")]
+    pub rejection_min_shares: u64,
+    /// Alert when no share was accepted for this long (e.g. `20m`)
+    #[serde(default)]
+    pub stalled_shares: Option<String>,
+    /// Alert when the uptime counter goes backwards
+    #[serde(default)]
+    pub reboot: bool,
+    /// Alert when the pool differs from the first one seen
+    #[serde(default)]
+    pub pool_change: bool,
+    /// Alert when the pool differs from this one (implies `pool_change`)
+    #[serde(default)]
+    pub expected_pool: Option<String>,
+}
+
+fn default_rejection_window() -> String {
+    "30m".to_string()
+}
+
+fn default_rejection_min_shares() -> u64 {
+    20
+}
+
+/// Validated detector settings
+#[derive(Debug, Clone, Default)]
+pub struct Detectors {
+    pub rejection: Option<RejectionDetector>,
+    pub stalled_after: Option<chrono::Duration>,
+    pub reboot: bool,
+    pub pool: Option<PoolDetector>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RejectionDetector {
+    /// Highest acceptable rejection percentage
+    pub threshold: f64,
+    pub window: chrono::Duration,
+    pub min_shares: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolDetector {
+    /// Expect the pool each device reported first
+    FirstSeen,
+    Expected(String),
+}
+
+impl TryFrom<DetectorConfig> for Detectors {
+    type Error = anyhow::Error;
+
+    fn try_from(config: DetectorConfig) -> Result<Self> {
+        let rejection = match config.rejection_ratio {
+            Some(threshold) => {
+                ensure!(
+                    (0.0..100.0).contains(&threshold),
+                    "'rejection_ratio' must be a percentage below 100"
+                );
+                Some(RejectionDetector {
+                    threshold,
+                    window: parse_duration(&config.rejection_window)
+                        .context("Invalid 'rejection_window'")?,
+                    min_shares: config.rejection_min_shares,
+                })
+            }
+            None => None,
+        };
+        let stalled_after = config
+            .stalled_shares
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .context("Invalid 'stalled_shares'")?;
+        let pool = match config.expected_pool {
+            Some(pool) => Some(PoolDetector::Expected(normalize_pool(&pool))),
+            None if config.pool_change => Some(PoolDetector::FirstSeen),
+            None => None,
+        };
+
+        Ok(Self {
+            rejection,
+            stalled_after,
+            reboot: config.reboot,
+            pool,
+        })
+    }
+}
+
+impl Detectors {
+    pub fn is_empty(&self) -> bool {
+        self.rejection.is_none()
+            && self.stalled_after.is_none()
+            && !self.reboot
+            && self.pool.is_none()
+    }
+}
+
+/// Compare pools without scheme, trailing slash or case
+fn normalize_pool(pool: &str) -> String {
+    let pool = pool.trim();
+    let pool = pool.split_once("://").map_or(pool, |(_, rest)| rest);
+    pool.trim_end_matches('/').to_lowercase()
+}
+
+/// What a detector found in a sample
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    /// A condition that stays firing until `active` turns false
+    Condition {
+        kind: AlertKind,
+        severity: Severity,
+        active: bool,
+        message: String,
+    },
+    /// Something that happened once
+    Event {
+        kind: AlertKind,
+        severity: Severity,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ShareSample {
+    timestamp: DateTime<Utc>,
+    accepted: u64,
+    rejected: u64,
+}
+
+/// Per-device history the detectors need
+#[derive(Debug, Clone, Default)]
+pub struct DeviceTracker {
+    last_uptime: Option<u64>,
+    shares: VecDeque<ShareSample>,
+    /// Accepted share count and when it last went up
+    last_accepted: Option<(u64, DateTime<Utc>)>,
+    first_pool: Option<String>,
+}
+
+impl DeviceTracker {
+    /// Forget share progress, e.g. after the device was unreachable
+    pub fn reset_shares(&mut self) {
+        self.shares.clear();
+        self.last_accepted = None;
+    }
+
+    /// Run the detectors on a new sample
+    pub fn check(
+        &mut self,
+        detectors: &Detectors,
+        device: &Device,
+        stats: &DeviceStats,
+        now: DateTime<Utc>,
+    ) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let name = &device.name;
+
+        // A restarted device starts its counters from zero
+        let rebooted = self
+            .last_uptime
+            .is_some_and(|uptime| stats.uptime_seconds < uptime);
+        self.last_uptime = Some(stats.uptime_seconds);
+        if rebooted {
+            self.reset_shares();
+            if detectors.reboot {
+                findings.push(Finding::Event {
+                    kind: AlertKind::Reboot,
+                    severity: Severity::Warning,
+                    message: format!(
+                        "🔄 {name} restarted unexpectedly (uptime {uptime}s)",
+                        uptime = stats.uptime_seconds
+                    ),
+                });
+            }
+        }
+
+        if let Some(rejection) = detectors.rejection {
+            findings.extend(self.check_rejections(rejection, name, stats, now));
+        }
+
+        if let Some(stalled_after) = detectors.stalled_after {
+            let (accepted, since) = match self.last_accepted {
+                Some((accepted, since)) if stats.shares_accepted <= accepted => (accepted, since),
+                _ => (stats.shares_accepted, now),
+            };
+            self.last_accepted = Some((accepted, since));
+
+            let stalled = now.signed_duration_since(since) >= stalled_after;
+            findings.push(Finding::Condition {
+                kind: AlertKind::Stalled,
+                severity: Severity::Critical,
+                active: stalled,
+                message: if stalled {
+                    format!(
+                        "⏸️ {name} has not had a share accepted for {minutes} min",
+                        minutes = now.signed_duration_since(since).num_minutes()
+                    )
+                } else {
+                    format!("✅ {name} is getting shares accepted again")
+                },
+            });
+        }
+
+        if let Some(ref pool) = detectors.pool
+            && let Some(ref current) = stats.pool_url
+        {
+            let current = normalize_pool(current);
+            let expected = match pool {
+                PoolDetector::Expected(expected) => expected.clone(),
+                PoolDetector::FirstSeen => self.first_pool.get_or_insert(current.clone()).clone(),
+            };
+            let changed = current != expected;
+            findings.push(Finding::Condition {
+                kind: AlertKind::PoolChanged,
+                severity: Severity::Critical,
+                active: changed,
+                message: if changed {
+                    format!("🔀 {name} is mining on {current} instead of {expected}")
+                } else {
+                    format!("✅ {name} is back on {expected}")
+                },
+            });
+        }
+
+        findings
+    }
+
+    fn check_rejections(
+        &mut self,
+        rejection: RejectionDetector,
+        name: &str,
+        stats: &DeviceStats,
+        now: DateTime<Utc>,
+    ) -> Option<Finding> {
+        // Counters that went down mean a reset we did not see as a reboot
+        if self.shares.back().is_some_and(|last| {
+            stats.shares_accepted < last.accepted || stats.shares_rejected < last.rejected
+        }) {
+            self.shares.clear();
+        }
+        self.shares.push_back(ShareSample {
+            timestamp: now,
+            accepted: stats.shares_accepted,
+            rejected: stats.shares_rejected,
+        });
+        // Keep one sample at or beyond the window edge as the starting point
+        while self
+            .shares
+            .get(1)
+            .is_some_and(|next| now.signed_duration_since(next.timestamp) >= rejection.window)
+        {
+            self.shares.pop_front();
+        }
+
+        let first = self.shares.front()?;
+        let accepted = stats.shares_accepted - first.accepted;
+        let rejected = stats.shares_rejected - first.rejected;
+        let total = accepted + rejected;
+        // Too few shares to judge: a quiet device must not keep an old alert open
+        if total < rejection.min_shares {
+            return Some(Finding::Condition {
+                kind: AlertKind::Rejections,
+                severity: Severity::Warning,
+                active: false,
+                message: format!("✅ {name} submitted only {total} shares in the rejection window"),
+            });
+        }
+
+        let ratio = rejected as f64 / total as f64 * 100.0;
+        let active = ratio > rejection.threshold;
+        Some(Finding::Condition {
+            kind: AlertKind::Rejections,
+            severity: Severity::Warning,
+            active,
+            message: if active {
+                format!(
+                    "❌ {name} rejected {ratio:.1}% of shares ({rejected}/{total}), above {threshold:.1}%",
+                    threshold = rejection.threshold
+                )
+            } else {
+                format!("✅ {name} rejection ratio back to {ratio:.1}%")
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStatus, DeviceType};
+
+    fn device() -> Device {
+        Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn stats(accepted: u64, rejected: u64, uptime: u64, pool: &str) -> DeviceStats {
+        DeviceStats {
+            timestamp: Utc::now(),
+            hashrate_mhs: 1000.0,
+            temperature_celsius: 50.0,
+            power_watts: 15.0,
+            fan_speed_rpm: 4000,
+            shares_accepted: accepted,
+            shares_rejected: rejected,
+            uptime_seconds: uptime,
+            pool_url: Some(pool.to_string()),
+            wifi_rssi: None,
+            voltage: None,
+            frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    fn detectors(toml: &str) -> Result<Detectors> {
+        let config: DetectorConfig = toml::from_str(toml)?;
+        Detectors::try_from(config)
+    }
+
+    fn conditions(findings: &[Finding], wanted: AlertKind) -> Vec<bool> {
+        findings
+            .iter()
+            .filter_map(|finding| match finding {
+                Finding::Condition { kind, active, .. } if *kind == wanted => Some(*active),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rejection_ratio_over_window() -> Result<()> {
+        let detectors = detectors(
+            "rejection_ratio = 5\nrejection_window = \"10m\"\nrejection_min_shares = 20",
+        )?;
+        let device = device();
+        let mut tracker = DeviceTracker::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+        let pool = "public-pool.io";
+
+        // Not enough shares in the window yet
+        let findings = tracker.check(&detectors, &device, &stats(100, 0, 60, pool), at(0));
+        assert_eq!(conditions(&findings, AlertKind::Rejections), vec![false]);
+
+        // 3 of 30 new shares rejected: 10%
+        let findings = tracker.check(&detectors, &device, &stats(127, 3, 120, pool), at(5));
+        assert_eq!(conditions(&findings, AlertKind::Rejections), vec![true]);
+
+        // Old rejections age out of the 10 minute window
+        tracker.check(&detectors, &device, &stats(160, 3, 180, pool), at(12));
+        let findings = tracker.check(&detectors, &device, &stats(200, 3, 240, pool), at(16));
+        assert_eq!(conditions(&findings, AlertKind::Rejections), vec![false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quiet_device_clears_rejection_alert() -> Result<()> {
+        let detectors = detectors(
+            "rejection_ratio = 5\nrejection_window = \"10m\"\nrejection_min_shares = 20",
+        )?;
+        let device = device();
+        let mut tracker = DeviceTracker::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+        let pool = "public-pool.io";
+
+        tracker.check(&detectors, &device, &stats(100, 0, 60, pool), at(0));
+        let findings = tracker.check(&detectors, &device, &stats(127, 3, 120, pool), at(5));
+        assert_eq!(conditions(&findings, AlertKind::Rejections), vec![true]);
+
+        // No shares at all since then
+        let findings = tracker.check(&detectors, &device, &stats(127, 3, 780, pool), at(16));
+        assert_eq!(conditions(&findings, AlertKind::Rejections), vec![false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stall_reboot_and_pool_change() -> Result<()> {
+        let detectors = detectors(
+            "stalled_shares = \"20m\"\nreboot = true\nexpected_pool = \"stratum+tcp://Public-Pool.io/\"",
+        )?;
+        let device = device();
+        let mut tracker = DeviceTracker::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+
+        let findings = tracker.check(
+            &detectors,
+            &device,
+            &stats(10, 0, 600, "public-pool.io"),
+            at(0),
+        );
+        assert_eq!(conditions(&findings, AlertKind::Stalled), vec![false]);
+        assert_eq!(conditions(&findings, AlertKind::PoolChanged), vec![false]);
+
+        let findings = tracker.check(
+            &detectors,
+            &device,
+            &stats(10, 0, 1800, "public-pool.io"),
+            at(20),
+        );
+        assert_eq!(conditions(&findings, AlertKind::Stalled), vec![true]);
+
+        // Uptime went backwards on a failover pool
+        let findings = tracker.check(
+            &detectors,
+            &device,
+            &stats(1, 0, 30, "solo.ckpool.org"),
+            at(21),
+        );
+        assert!(findings.iter().any(|f| matches!(
+            f,
+            Finding::Event {
+                kind: AlertKind::Reboot,
+                ..
+            }
+        )));
+        assert_eq!(conditions(&findings, AlertKind::Stalled), vec![false]);
+        assert_eq!(conditions(&findings, AlertKind::PoolChanged), vec![true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_change_against_first_seen() -> Result<()> {
+        let detectors = detectors("pool_change = true")?;
+        let device = device();
+        let mut tracker = DeviceTracker::default();
+        let now = Utc::now();
+
+        let findings = tracker.check(&detectors, &device, &stats(1, 0, 10, "a.pool"), now);
+        assert_eq!(conditions(&findings, AlertKind::PoolChanged), vec![false]);
+        let findings = tracker.check(&detectors, &device, &stats(2, 0, 20, "b.pool"), now);
+        assert_eq!(conditions(&findings, AlertKind::PoolChanged), vec![true]);
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+pub mod detectors;
+pub mod rules;
+pub mod sinks;
+
+pub use detectors::*;
+pub use rules::*;
+pub use sinks::*;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString, VariantNames};
+
+/// What an alert is about, used to route alerts to sinks
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    VariantNames,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum AlertKind {
+    /// Device temperature above the threshold
+    Temperature,
+    /// Hashrate dropped
+    Hashrate,
+    /// Device stopped responding
+    Offline,
+    /// The thermal guard changed device settings
+    ThermalGuard,
+    /// Any other metric rule
+    Metric,
+    /// Too many rejected shares
+    Rejections,
+    /// No accepted shares for a while
+    Stalled,
+    /// The uptime counter went backwards
+    Reboot,
+    /// The device mines on a different pool than expected
+    PoolChanged,
+    /// The device beat its all-time best share difficulty
+    PersonalBest,
+    /// The device found a share close to the network difficulty
+    NearBlock,
+}
+
+impl AlertKind {
+    /// Whether alerts of this kind are one-off events that never resolve
+    pub fn is_event(&self) -> bool {
+        matches!(
+            self,
+            AlertKind::ThermalGuard
+                | AlertKind::Reboot
+                | AlertKind::PersonalBest
+                | AlertKind::NearBlock
+        )
+    }
+}
+
+/// How urgent an alert is
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    VariantNames,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ️",
+            Severity::Warning => "⚠️",
+            Severity::Critical => "🚨",
+        }
+    }
+}
+
+/// Whether an alert condition started or ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// Alert raised while monitoring devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub timestamp: DateTime<Utc>,
+    pub kind: AlertKind,
+    pub severity: Severity,
+    pub state: AlertState,
+    /// Name of the rule that raised the alert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    pub message: String,
+    pub device_ip: String,
+}
@@ -0,0 +1,884 @@
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{
+    Alert, AlertKind, AlertState, DetectorConfig, Detectors, DeviceTracker, Finding, Severity,
+};
+use crate::api::{Device, DeviceFilter, DeviceStats};
+use crate::history::{HistoryMetric, SeriesPoint, parse_duration};
+use crate::output::format_hashrate;
+
+/// Alert rule as written in the rules file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub name: String,
+    pub metric: HistoryMetric,
+    /// Fire when the value is above this threshold
+    #[serde(default)]
+    pub above: Option<f64>,
+    /// Fire when the value is below this threshold
+    #[serde(default)]
+    pub below: Option<f64>,
+    /// How long the condition must hold before firing (e.g. `2m`)
+    #[serde(default, rename = "for")]
+    pub duration: Option<String>,
+    /// Compare against the rolling average over this window, thresholds become percentages
+    #[serde(default)]
+    pub baseline: Option<String>,
+    /// Value the metric has to cross back over to resolve, defaults to the threshold
+    #[serde(default)]
+    pub clear: Option<f64>,
+    /// Only apply to devices of this type (e.g. `bitaxe-gamma`, `nerdqaxe`)
+    #[serde(default)]
+    pub device_type: Option<String>,
+    /// Only apply to these device names or IP addresses
+    #[serde(default)]
+    pub devices: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Which side of the threshold fires the rule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Above(f64),
+    Below(f64),
+}
+
+impl Comparison {
+    fn threshold(&self) -> f64 {
+        match *self {
+            Comparison::Above(threshold) | Comparison::Below(threshold) => threshold,
+        }
+    }
+
+    fn breached(&self, value: f64) -> bool {
+        match *self {
+            Comparison::Above(threshold) => value > threshold,
+            Comparison::Below(threshold) => value < threshold,
+        }
+    }
+
+    fn cleared(&self, value: f64, clear: f64) -> bool {
+        match self {
+            Comparison::Above(_) => value < clear,
+            Comparison::Below(_) => value > clear,
+        }
+    }
+}
+
+/// Validated alert rule
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: HistoryMetric,
+    pub comparison: Comparison,
+    pub clear: f64,
+    pub duration: chrono::Duration,
+    pub baseline: Option<chrono::Duration>,
+    pub device_type: Option<DeviceFilter>,
+    pub devices: Vec<String>,
+    pub severity: Severity,
+}
+
+impl TryFrom<RuleConfig> for AlertRule {
+    type Error = anyhow::Error;
+
+    fn try_from(config: RuleConfig) -> Result<Self> {
+        let comparison = match (config.above, config.below) {
+            (Some(above), None) => Comparison::Above(above),
+            (None, Some(below)) => Comparison::Below(below),
+            _ => bail!("Exactly one of 'above' or 'below' must be set"),
+        };
+
+        let clear = config.clear.unwrap_or(comparison.threshold());
+        match comparison {
+            Comparison::Above(threshold) => ensure!(
+                clear <= threshold,
+                "'clear' must not be above the 'above' threshold"
+            ),
+            Comparison::Below(threshold) => ensure!(
+                clear >= threshold,
+                "'clear' must not be below the 'below' threshold"
+            ),
+        }
+
+        let duration = match config.duration {
+            Some(ref duration) => parse_duration(duration).context("Invalid 'for'")?,
+            None => chrono::Duration::zero(),
+        };
+        let baseline = config
+            .baseline
+            .as_deref()
+            .map(parse_duration)
+            .transpose()
+            .context("Invalid 'baseline'")?;
+        let device_type = config
+            .device_type
+            .as_deref()
+            .map(|filter| {
+                DeviceFilter::from_str(filter)
+                    .map_err(|e| anyhow::anyhow!("Invalid 'device_type': {e}"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            name: config.name,
+            metric: config.metric,
+            comparison,
+            clear,
+            duration,
+            baseline,
+            device_type,
+            devices: config.devices,
+            severity: config.severity,
+        })
+    }
+}
+
+impl AlertRule {
+    /// Whether the rule applies to the device
+    pub fn applies_to(&self, device: &Device) -> bool {
+        if let Some(filter) = self.device_type
+            && !filter.matches(device.device_type)
+        {
+            return false;
+        }
+        self.devices.is_empty()
+            || self
+                .devices
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(&device.name) || *d == device.ip_address)
+    }
+
+    fn kind(&self) -> AlertKind {
+        match self.metric {
+            HistoryMetric::Temperature => AlertKind::Temperature,
+            HistoryMetric::Hashrate | HistoryMetric::HashrateHealth => AlertKind::Hashrate,
+            _ => AlertKind::Metric,
+        }
+    }
+
+    /// Describe an observed value against the rule
+    fn describe(&self, observed: f64) -> String {
+        let metric = self.metric;
+        match self.baseline {
+            Some(window) => format!(
+                "{metric} {percent:.1}% {direction} its {window} average",
+                percent = observed.abs(),
+                direction = if observed < 0.0 { "below" } else { "above" },
+                window = format_window(window)
+            ),
+            None => {
+                let (side, threshold) = match self.comparison {
+                    Comparison::Above(threshold) => ("above", threshold),
+                    Comparison::Below(threshold) => ("below", threshold),
+                };
+                format!(
+                    "{metric} {value} {side} {threshold}",
+                    value = format_metric(metric, observed),
+                    threshold = format_metric(metric, threshold)
+                )
+            }
+        }
+    }
+
+    /// Rule for the `--temp-alert` shorthand
+    pub fn temperature_above(threshold: f64) -> Self {
+        Self {
+            name: "temperature".to_string(),
+            metric: HistoryMetric::Temperature,
+            comparison: Comparison::Above(threshold),
+            clear: threshold - 2.0,
+            duration: chrono::Duration::zero(),
+            baseline: None,
+            device_type: None,
+            devices: Vec::new(),
+            severity: Severity::Warning,
+        }
+    }
+
+    /// Rule for the `--hashrate-alert` shorthand: a drop against the 10 minute average
+    pub fn hashrate_drop(percent: f64) -> Self {
+        Self {
+            name: "hashrate-drop".to_string(),
+            metric: HistoryMetric::Hashrate,
+            comparison: Comparison::Below(-percent),
+            clear: -percent / 2.0,
+            duration: chrono::Duration::zero(),
+            baseline: Some(chrono::Duration::minutes(10)),
+            device_type: None,
+            devices: Vec::new(),
+            severity: Severity::Warning,
+        }
+    }
+
+    /// Rule for the `--health-alert` shorthand: hashrate below a percentage of
+    /// the expected one for 5 minutes
+    pub fn hashrate_health_below(percent: f64) -> Self {
+        Self {
+            name: "hashrate-health".to_string(),
+            metric: HistoryMetric::HashrateHealth,
+            comparison: Comparison::Below(percent),
+            clear: percent + 2.0,
+            duration: chrono::Duration::minutes(5),
+            baseline: None,
+            device_type: None,
+            devices: Vec::new(),
+            severity: Severity::Warning,
+        }
+    }
+}
+
+fn format_window(window: chrono::Duration) -> String {
+    let seconds = window.num_seconds();
+    match seconds {
+        s if s > 0 && s % 3600 == 0 => format!("{hours}h", hours = s / 3600),
+        s if s > 0 && s % 60 == 0 => format!("{minutes}m", minutes = s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+fn format_metric(metric: HistoryMetric, value: f64) -> String {
+    match metric {
+        HistoryMetric::Hashrate => format_hashrate(value),
+        HistoryMetric::Temperature => format!("{value:.1}°C"),
+        HistoryMetric::Power => format!("{value:.1}W"),
+        HistoryMetric::Fan => format!("{value:.0} RPM"),
+        HistoryMetric::Voltage => format!("{value:.0} mV"),
+        HistoryMetric::Frequency => format!("{value:.0} MHz"),
+        HistoryMetric::Rssi => format!("{value:.0} dBm"),
+        HistoryMetric::HashrateHealth => format!("{value:.1}%"),
+        HistoryMetric::SharesAccepted | HistoryMetric::SharesRejected => format!("{value:.0}"),
+    }
+}
+
+/// Alert rules file (TOML)
+///
+/// ```toml
+/// [[rules]]
+/// name = "hot"
+/// metric = "temperature"
+/// above = 75
+/// for = "2m"
+/// clear = 70
+/// severity = "critical"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesConfig {
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    #[serde(default)]
+    pub detectors: DetectorConfig,
+}
+
+/// Rules and detectors evaluated by the alert engine
+#[derive(Debug, Clone, Default)]
+pub struct AlertRules {
+    pub rules: Vec<AlertRule>,
+    pub detectors: Detectors,
+}
+
+impl AlertRules {
+    /// Add a rule, refusing one named like a rule that is already loaded
+    ///
+    /// Alert state is keyed by rule name, so two rules with one name would share it.
+    pub fn add(&mut self, rule: AlertRule) -> Result<()> {
+        if self.rules.iter().any(|r| r.name == rule.name) {
+            bail!(
+                "Rule '{name}' is already defined, rename the rule in the rules file or drop the shorthand option",
+                name = rule.name
+            );
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+}
+
+impl RulesConfig {
+    /// Load and validate a rules file
+    pub fn load(path: &Path) -> Result<AlertRules> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rules file {path}", path = path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid rules file {path}", path = path.display()))
+    }
+
+    /// Parse and validate rules file contents
+    pub fn parse(content: &str) -> Result<AlertRules> {
+        let config: RulesConfig = toml::from_str(content).context("Failed to parse TOML")?;
+
+        let mut names = HashSet::new();
+        let rules = config
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let name = rule.name.clone();
+                ensure!(names.insert(name.clone()), "Duplicate rule '{name}'");
+                AlertRule::try_from(rule).with_context(|| format!("Invalid rule '{name}'"))
+            })
+            .collect::<Result<_>>()?;
+        let detectors = Detectors::try_from(config.detectors).context("Invalid [detectors]")?;
+
+        Ok(AlertRules { rules, detectors })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RuleState {
+    /// Condition holds but not for long enough yet
+    Pending {
+        since: DateTime<Utc>,
+    },
+    Firing,
+}
+
+/// Evaluates alert rules against incoming stats, tracking firing and resolved states
+#[derive(Debug, Clone, Default)]
+pub struct AlertEngine {
+    /// State per (rule name, device IP) while the condition holds
+    states: HashMap<(String, String), RuleState>,
+    /// Recent metric samples per (device IP, metric) for baselines
+    samples: HashMap<(String, HistoryMetric), VecDeque<SeriesPoint>>,
+    /// Alerts currently firing, by kind, rule name (empty for built-ins) and device IP
+    active: HashMap<(AlertKind, String, String), Alert>,
+    offline: HashSet<String>,
+    /// Per-device state of the built-in detectors
+    trackers: HashMap<String, DeviceTracker>,
+}
+
+impl AlertEngine {
+    /// Alerts that are currently firing
+    pub fn active(&self) -> Vec<&Alert> {
+        let mut active: Vec<_> = self.active.values().collect();
+        active.sort_by_key(|a| a.timestamp);
+        active
+    }
+
+    /// Evaluate all rules for a device, returning the alerts that fired or resolved
+    pub fn evaluate(
+        &mut self,
+        rules: &AlertRules,
+        device: &Device,
+        stats: &DeviceStats,
+        now: DateTime<Utc>,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        alerts.extend(self.device_online(device, now));
+
+        for rule in rules.rules.iter().filter(|rule| rule.applies_to(device)) {
+            let Some(value) = rule.metric.value(stats) else {
+                continue;
+            };
+            let observed = match rule.baseline {
+                Some(window) => match self.baseline(&device.ip_address, rule.metric, window, now) {
+                    Some(average) if average != 0.0 => (value - average) / average * 100.0,
+                    _ => continue,
+                },
+                None => value,
+            };
+
+            let key = (rule.name.clone(), device.ip_address.clone());
+            let active_key = (rule.kind(), rule.name.clone(), device.ip_address.clone());
+            let breached = rule.comparison.breached(observed);
+            match self.states.get(&key).copied() {
+                None | Some(RuleState::Pending { .. }) if !breached => {
+                    self.states.remove(&key);
+                }
+                None if rule.duration > chrono::Duration::zero() => {
+                    self.states.insert(key, RuleState::Pending { since: now });
+                }
+                Some(RuleState::Pending { since })
+                    if now.signed_duration_since(since) < rule.duration => {}
+                None | Some(RuleState::Pending { .. }) => {
+                    let alert = Alert {
+                        timestamp: now,
+                        kind: rule.kind(),
+                        severity: rule.severity,
+                        state: AlertState::Firing,
+                        rule: Some(rule.name.clone()),
+                        message: format!(
+                            "{icon} {name} {rule}: {description}",
+                            icon = rule.severity.icon(),
+                            name = device.name,
+                            rule = rule.name,
+                            description = rule.describe(observed)
+                        ),
+                        device_ip: device.ip_address.clone(),
+                    };
+                    self.states.insert(key, RuleState::Firing);
+                    self.active.insert(active_key, alert.clone());
+                    alerts.push(alert);
+                }
+                Some(RuleState::Firing) if rule.comparison.cleared(observed, rule.clear) => {
+                    self.states.remove(&key);
+                    self.active.remove(&active_key);
+                    alerts.push(Alert {
+                        timestamp: now,
+                        kind: rule.kind(),
+                        severity: rule.severity,
+                        state: AlertState::Resolved,
+                        rule: Some(rule.name.clone()),
+                        message: format!(
+                            "✅ {name} {rule} resolved: {description}",
+                            name = device.name,
+                            rule = rule.name,
+                            description = rule.describe(observed)
+                        ),
+                        device_ip: device.ip_address.clone(),
+                    });
+                }
+                Some(RuleState::Firing) => {}
+            }
+        }
+
+        self.record_samples(&rules.rules, &device.ip_address, stats, now);
+
+        if !rules.detectors.is_empty() {
+            let findings = self
+                .trackers
+                .entry(device.ip_address.clone())
+                .or_default()
+                .check(&rules.detectors, device, stats, now);
+            for finding in findings {
+                alerts.extend(self.apply_finding(finding, device, now));
+            }
+        }
+        alerts
+    }
+
+    /// Turn a detector finding into a firing or resolved alert on state changes
+    fn apply_finding(
+        &mut self,
+        finding: Finding,
+        device: &Device,
+        now: DateTime<Utc>,
+    ) -> Option<Alert> {
+        let (kind, severity, state, message) = match finding {
+            Finding::Event {
+                kind,
+                severity,
+                message,
+            } => (kind, severity, AlertState::Firing, message),
+            Finding::Condition {
+                kind,
+                severity,
+                active,
+                message,
+            } => {
+                let key = (kind, String::new(), device.ip_address.clone());
+                match (active, self.active.contains_key(&key)) {
+                    (true, false) => (kind, severity, AlertState::Firing, message),
+                    (false, true) => {
+                        self.active.remove(&key);
+                        (kind, severity, AlertState::Resolved, message)
+                    }
+                    _ => return None,
+                }
+            }
+        };
+
+        let alert = Alert {
+            timestamp: now,
+            kind,
+            severity,
+            state,
+            rule: None,
+            message,
+            device_ip: device.ip_address.clone(),
+        };
+        if state == AlertState::Firing && !kind.is_event() {
+            self.active.insert(
+                (kind, String::new(), device.ip_address.clone()),
+                alert.clone(),
+            );
+        }
+        Some(alert)
+    }
+
+    /// Mark a device offline, firing an alert the first time
+    pub fn device_offline(&mut self, device: &Device, now: DateTime<Utc>) -> Option<Alert> {
+        if !self.offline.insert(device.ip_address.clone()) {
+            return None;
+        }
+
+        let alert = Alert {
+            timestamp: now,
+            kind: AlertKind::Offline,
+            severity: Severity::Critical,
+            state: AlertState::Firing,
+            rule: None,
+            message: format!("🔌 {name} went offline", name = device.name),
+            device_ip: device.ip_address.clone(),
+        };
+        self.active.insert(
+            (AlertKind::Offline, String::new(), device.ip_address.clone()),
+            alert.clone(),
+        );
+        if let Some(tracker) = self.trackers.get_mut(&device.ip_address) {
+            tracker.reset_shares();
+        }
+        Some(alert)
+    }
+
+    fn device_online(&mut self, device: &Device, now: DateTime<Utc>) -> Option<Alert> {
+        if !self.offline.remove(&device.ip_address) {
+            return None;
+        }
+
+        self.active
+            .remove(&(AlertKind::Offline, String::new(), device.ip_address.clone()));
+        Some(Alert {
+            timestamp: now,
+            kind: AlertKind::Offline,
+            severity: Severity::Critical,
+            state: AlertState::Resolved,
+            rule: None,
+            message: format!("✅ {name} is back online", name = device.name),
+            device_ip: device.ip_address.clone(),
+        })
+    }
+
+    fn baseline(
+        &self,
+        ip: &str,
+        metric: HistoryMetric,
+        window: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> Option<f64> {
+        let samples = self.samples.get(&(ip.to_string(), metric))?;
+        let values: Vec<f64> = samples
+            .iter()
+            .filter(|point| now.signed_duration_since(point.timestamp) <= window)
+            .map(|point| point.value)
+            .collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    fn record_samples(
+        &mut self,
+        rules: &[AlertRule],
+        ip: &str,
+        stats: &DeviceStats,
+        now: DateTime<Utc>,
+    ) {
+        let mut windows: HashMap<HistoryMetric, chrono::Duration> = HashMap::new();
+        for rule in rules {
+            if let Some(window) = rule.baseline {
+                let longest = windows.entry(rule.metric).or_insert(window);
+                *longest = (*longest).max(window);
+            }
+        }
+
+        for (metric, window) in windows {
+            let Some(value) = metric.value(stats) else {
+                continue;
+            };
+            let samples = self.samples.entry((ip.to_string(), metric)).or_default();
+            samples.push_back(SeriesPoint {
+                timestamp: now,
+                value,
+            });
+            while samples
+                .front()
+                .is_some_and(|point| now.signed_duration_since(point.timestamp) > window)
+            {
+                samples.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStatus, DeviceType};
+
+    fn device() -> Device {
+        Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn stats(temperature: f64, hashrate: f64) -> DeviceStats {
+        DeviceStats {
+            timestamp: Utc::now(),
+            hashrate_mhs: hashrate,
+            temperature_celsius: temperature,
+            power_watts: 15.0,
+            fan_speed_rpm: 4000,
+            shares_accepted: 10,
+            shares_rejected: 0,
+            uptime_seconds: 3600,
+            pool_url: None,
+            wifi_rssi: None,
+            voltage: None,
+            frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    fn states(alerts: &[Alert]) -> Vec<AlertState> {
+        alerts.iter().map(|a| a.state).collect()
+    }
+
+    #[test]
+    fn test_parse_rules() -> Result<()> {
+        let rules = RulesConfig::parse(
+            r#"
+            [[rules]]
+            name = "hot"
+            metric = "temperature"
+            above = 75
+            for = "2m"
+            clear = 70
+            device_type = "bitaxe"
+            severity = "critical"
+            "#,
+        )?
+        .rules;
+        assert_eq!(rules[0].comparison, Comparison::Above(75.0));
+        assert_eq!(rules[0].duration, chrono::Duration::minutes(2));
+        assert_eq!(rules[0].severity, Severity::Critical);
+        assert!(rules[0].applies_to(&device()));
+
+        let invalid = [
+            "[[rules]]\nname = \"x\"\nmetric = \"temperature\"",
+            "[[rules]]\nname = \"x\"\nmetric = \"temperature\"\nabove = 1\nbelow = 0",
+            "[[rules]]\nname = \"x\"\nmetric = \"temperature\"\nabove = 70\nclear = 75",
+            "[[rules]]\nname = \"x\"\nmetric = \"humidity\"\nabove = 70",
+            "[[rules]]\nname = \"x\"\nmetric = \"temperature\"\nabove = 70\ndevice_type = \"antminer\"",
+            "[[rules]]\nname = \"x\"\nmetric = \"temperature\"\nabove = 70\n[[rules]]\nname = \"x\"\nmetric = \"power\"\nabove = 20",
+        ];
+        for content in invalid {
+            assert!(RulesConfig::parse(content).is_err(), "accepted: {content}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_duration_and_hysteresis() -> Result<()> {
+        let rules = RulesConfig::parse(
+            "[[rules]]\nname = \"hot\"\nmetric = \"temperature\"\nabove = 75\nfor = \"2m\"\nclear = 70",
+        )?;
+        let device = device();
+        let mut engine = AlertEngine::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+
+        // Pending, then a dip resets the timer
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(76.0, 1000.0), at(0))
+                .is_empty()
+        );
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(74.0, 1000.0), at(1))
+                .is_empty()
+        );
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(76.0, 1000.0), at(2))
+                .is_empty()
+        );
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(77.0, 1000.0), at(3))
+                .is_empty()
+        );
+
+        // Fires once after holding for 2 minutes, then stays quiet
+        let fired = engine.evaluate(&rules, &device, &stats(77.0, 1000.0), at(4));
+        assert_eq!(states(&fired), vec![AlertState::Firing]);
+        assert_eq!(engine.active().len(), 1);
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(78.0, 1000.0), at(5))
+                .is_empty()
+        );
+
+        // Below the threshold but above the clear value keeps firing
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(72.0, 1000.0), at(6))
+                .is_empty()
+        );
+
+        let resolved = engine.evaluate(&rules, &device, &stats(69.0, 1000.0), at(7));
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+        assert!(engine.active().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hashrate_health_rule() -> Result<()> {
+        let rules = AlertRules {
+            rules: vec![AlertRule::hashrate_health_below(90.0)],
+            ..Default::default()
+        };
+        let device = device();
+        let mut engine = AlertEngine::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+        let health = |hashrate: f64| stats(60.0, hashrate).with_expected_hashrate(Some(1000.0));
+
+        // Devices without an expected hashrate are never evaluated
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(60.0, 100.0), at(0))
+                .is_empty()
+        );
+        assert!(
+            engine
+                .evaluate(&rules, &device, &health(800.0), at(1))
+                .is_empty()
+        );
+
+        // Fires once the hashrate stayed low for 5 minutes
+        let fired = engine.evaluate(&rules, &device, &health(850.0), at(6));
+        assert_eq!(states(&fired), vec![AlertState::Firing]);
+        assert_eq!(fired[0].kind, AlertKind::Hashrate);
+        assert!(fired[0].message.contains("85.0% below 90.0%"));
+
+        let resolved = engine.evaluate(&rules, &device, &health(950.0), at(7));
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_and_scope() -> Result<()> {
+        let rules = RulesConfig::parse(
+            r#"
+            [[rules]]
+            name = "drop"
+            metric = "hashrate"
+            below = -20
+            clear = -5
+            baseline = "10m"
+
+            [[rules]]
+            name = "nerd-hot"
+            metric = "temperature"
+            above = 50
+            device_type = "nerdqaxe"
+            "#,
+        )?;
+        let device = device();
+        let mut engine = AlertEngine::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+
+        // No baseline yet, and the temperature rule is scoped to other devices
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(60.0, 1000.0), at(0))
+                .is_empty()
+        );
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(60.0, 1000.0), at(1))
+                .is_empty()
+        );
+
+        let fired = engine.evaluate(&rules, &device, &stats(60.0, 700.0), at(2));
+        assert_eq!(states(&fired), vec![AlertState::Firing]);
+        assert!(fired[0].message.contains("30.0% below its 10m average"));
+
+        // 800 is ~11% under the average including the low sample: neither firing nor cleared
+        assert!(
+            engine
+                .evaluate(&rules, &device, &stats(60.0, 800.0), at(3))
+                .is_empty()
+        );
+        let resolved = engine.evaluate(&rules, &device, &stats(60.0, 1000.0), at(4));
+        assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detectors_fire_once_and_resolve() -> Result<()> {
+        let rules = RulesConfig::parse("[detectors]\nstalled_shares = \"10m\"\nreboot = true")?;
+        let device = device();
+        let mut engine = AlertEngine::default();
+        let start = Utc::now();
+        let at = |m: i64| start + chrono::Duration::minutes(m);
+        let sample = |accepted: u64, uptime: u64| DeviceStats {
+            shares_accepted: accepted,
+            uptime_seconds: uptime,
+            ..stats(50.0, 1000.0)
+        };
+
+        assert!(
+            engine
+                .evaluate(&rules, &device, &sample(5, 100), at(0))
+                .is_empty()
+        );
+        let stalled = engine.evaluate(&rules, &device, &sample(5, 700), at(10));
+        assert_eq!(stalled[0].kind, AlertKind::Stalled);
+        assert!(
+            engine
+                .evaluate(&rules, &device, &sample(5, 760), at(11))
+                .is_empty()
+        );
+
+        // A reboot is a one-off event, and the restarted counters clear the stall
+        let alerts = engine.evaluate(&rules, &device, &sample(0, 5), at(12));
+        let kinds: Vec<_> = alerts.iter().map(|a| (a.kind, a.state)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (AlertKind::Reboot, AlertState::Firing),
+                (AlertKind::Stalled, AlertState::Resolved),
+            ]
+        );
+        assert!(engine.active().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_shorthand_rule_names_must_be_unique() -> Result<()> {
+        let mut rules = RulesConfig::parse(
+            "[[rules]]\nname = \"temperature\"\nmetric = \"temperature\"\nabove = 80",
+        )?;
+        assert!(rules.add(AlertRule::temperature_above(75.0)).is_err());
+        rules.add(AlertRule::hashrate_drop(20.0))?;
+        assert!(rules.add(AlertRule::hashrate_drop(10.0)).is_err());
+        assert_eq!(rules.rules.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_offline_fires_once_and_resolves() -> Result<()> {
+        let device = device();
+        let mut engine = AlertEngine::default();
+        let now = Utc::now();
+
+        assert!(engine.device_offline(&device, now).is_some());
+        assert!(engine.device_offline(&device, now).is_none());
+        assert_eq!(engine.active().len(), 1);
+
+        let back = engine.evaluate(&AlertRules::default(), &device, &stats(50.0, 1000.0), now);
+        assert_eq!(states(&back), vec![AlertState::Resolved]);
+        assert!(engine.active().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,819 @@
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+use super::{Alert, AlertKind, AlertState, Severity};
+use crate::history::parse_duration;
+
+/// Timeout of a single delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for queued alerts to go out when the monitor stops
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where alerts are delivered to
+///
+/// Flattened into `SinkConfig`, which takes its shared keys first, so any key
+/// left over that the sink type doesn't know is a typo and rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkTarget {
+    /// POST the alert as JSON to an URL
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Publish to an ntfy topic URL (e.g. `https://ntfy.sh/my-miners`)
+    Ntfy {
+        url: String,
+        #[serde(default)]
+        token: Option<String>,
+        /// Message priority from 1 (min) to 5 (max)
+        #[serde(default)]
+        priority: Option<u8>,
+    },
+    /// Send to a Gotify server with an application token
+    Gotify {
+        url: String,
+        token: String,
+        #[serde(default)]
+        priority: Option<u8>,
+    },
+    /// Send an email through an SMTP relay
+    Smtp {
+        host: String,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        security: SmtpSecurity,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    /// Run a program with the alert as JSON on stdin
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Connection security of an SMTP sink
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS (port 587)
+    #[default]
+    Starttls,
+    /// TLS from the start (port 465)
+    Tls,
+    /// No encryption, only for relays on the local network
+    None,
+}
+
+/// One alert sink of the notification file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkConfig {
+    /// Name used in log messages, defaults to the sink type
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub target: SinkTarget,
+    /// Alert kinds delivered to this sink, all kinds when empty
+    #[serde(default)]
+    pub alerts: Vec<AlertKind>,
+    /// Lowest severity delivered to this sink
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+    /// Also deliver alerts when they resolve
+    #[serde(default = "default_resolved")]
+    pub resolved: bool,
+    /// Additional attempts after a failed delivery
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: String,
+    /// Maximum number of alerts delivered per `rate_window`
+    #[serde(default)]
+    pub rate_limit: Option<usize>,
+    #[serde(default = "default_rate_window")]
+    pub rate_window: String,
+}
+
+fn default_resolved() -> bool {
+    true
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay() -> String {
+    "5s".to_string()
+}
+
+fn default_rate_window() -> String {
+    "1h".to_string()
+}
+
+impl SinkConfig {
+    /// Name used in log messages
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            match self.target {
+                SinkTarget::Webhook { .. } => "webhook",
+                SinkTarget::Ntfy { .. } => "ntfy",
+                SinkTarget::Gotify { .. } => "gotify",
+                SinkTarget::Smtp { .. } => "smtp",
+                SinkTarget::Exec { .. } => "exec",
+            }
+            .to_string()
+        })
+    }
+
+    /// Whether the alert passes the sink's filters
+    pub fn accepts(&self, alert: &Alert) -> bool {
+        (self.alerts.is_empty() || self.alerts.contains(&alert.kind))
+            && self.min_severity.is_none_or(|min| alert.severity >= min)
+            && (self.resolved || alert.state == AlertState::Firing)
+    }
+}
+
+/// Notification file (TOML) listing alert sinks
+///
+/// ```toml
+/// [[sinks]]
+/// type = "ntfy"
+/// url = "https://ntfy.sh/my-miners"
+/// alerts = ["offline", "temperature"]
+/// rate_limit = 10
+/// rate_window = "1h"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl NotifyConfig {
+    /// Load and validate a notification file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read notification file {path}",
+                path = path.display()
+            )
+        })?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid notification file {path}", path = path.display()))
+    }
+
+    /// Parse and validate notification file contents
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: NotifyConfig = toml::from_str(content).context("Failed to parse TOML")?;
+        ensure!(!config.sinks.is_empty(), "No [[sinks]] defined");
+
+        // Building the sinks validates URLs, addresses and durations
+        for sink in &config.sinks {
+            Sink::new(sink.clone())
+                .with_context(|| format!("Invalid sink '{name}'", name = sink.display_name()))?;
+        }
+        Ok(config)
+    }
+}
+
+struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+enum Transport {
+    Http(reqwest::Client),
+    Smtp(Box<Mailer>),
+    Exec,
+}
+
+/// Alert sink with its delivery state
+pub struct Sink {
+    config: SinkConfig,
+    transport: Transport,
+    retry_delay: Duration,
+    rate_window: chrono::Duration,
+    delivered: VecDeque<DateTime<Utc>>,
+}
+
+/// Result of offering an alert to a sink
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// The sink does not take this alert kind
+    Filtered,
+    /// The sink reached its rate limit
+    RateLimited,
+    Failed(String),
+}
+
+impl Sink {
+    pub fn new(config: SinkConfig) -> Result<Self> {
+        let retry_delay = parse_duration(&config.retry_delay)
+            .context("Invalid retry_delay")?
+            .to_std()
+            .context("Invalid retry_delay")?;
+        let rate_window = parse_duration(&config.rate_window).context("Invalid rate_window")?;
+
+        let transport = match config.target {
+            SinkTarget::Webhook { ref url, .. }
+            | SinkTarget::Ntfy { ref url, .. }
+            | SinkTarget::Gotify { ref url, .. } => {
+                url::Url::parse(url).with_context(|| format!("Invalid URL '{url}'"))?;
+                Transport::Http(
+                    reqwest::Client::builder()
+                        .timeout(DELIVERY_TIMEOUT)
+                        .build()
+                        .context("Failed to create HTTP client")?,
+                )
+            }
+            SinkTarget::Smtp {
+                ref host,
+                port,
+                security,
+                ref username,
+                ref password,
+                ref from,
+                ref to,
+            } => {
+                ensure!(!to.is_empty(), "No recipients in 'to'");
+                let from: Mailbox = from
+                    .parse()
+                    .with_context(|| format!("Invalid sender address '{from}'"))?;
+                let to = to
+                    .iter()
+                    .map(|address| {
+                        address
+                            .parse()
+                            .with_context(|| format!("Invalid recipient address '{address}'"))
+                    })
+                    .collect::<Result<Vec<Mailbox>>>()?;
+
+                let mut builder = match security {
+                    SmtpSecurity::Starttls => {
+                        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                            .with_context(|| format!("Invalid SMTP host '{host}'"))?
+                    }
+                    SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                        .with_context(|| format!("Invalid SMTP host '{host}'"))?,
+                    SmtpSecurity::None => {
+                        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+                    }
+                };
+                if let Some(port) = port {
+                    builder = builder.port(port);
+                }
+                match (username, password) {
+                    (Some(username), Some(password)) => {
+                        builder = builder
+                            .credentials(Credentials::new(username.clone(), password.clone()));
+                    }
+                    (None, None) => {}
+                    _ => bail!("SMTP username and password must be set together"),
+                }
+
+                Transport::Smtp(Box::new(Mailer {
+                    transport: builder.timeout(Some(DELIVERY_TIMEOUT)).build(),
+                    from,
+                    to,
+                }))
+            }
+            SinkTarget::Exec { ref command, .. } => {
+                ensure!(!command.trim().is_empty(), "Empty command");
+                Transport::Exec
+            }
+        };
+
+        Ok(Self {
+            config,
+            transport,
+            retry_delay,
+            rate_window,
+            delivered: VecDeque::new(),
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.config.display_name()
+    }
+
+    /// Deliver an alert, honouring the filter, rate limit and retries
+    pub async fn offer(&mut self, alert: &Alert) -> Delivery {
+        if !self.config.accepts(alert) {
+            return Delivery::Filtered;
+        }
+
+        let now = Utc::now();
+        while self
+            .delivered
+            .front()
+            .is_some_and(|sent| now.signed_duration_since(*sent) >= self.rate_window)
+        {
+            self.delivered.pop_front();
+        }
+        if let Some(limit) = self.config.rate_limit
+            && self.delivered.len() >= limit
+        {
+            return Delivery::RateLimited;
+        }
+
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let result = match timeout(DELIVERY_TIMEOUT, self.send(alert)).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("Timed out")),
+            };
+            match result {
+                Ok(()) => {
+                    self.delivered.push_back(now);
+                    return Delivery::Sent;
+                }
+                Err(e) if attempt >= self.config.retries => {
+                    return Delivery::Failed(format!("{e:#}"));
+                }
+                Err(e) => {
+                    tracing::debug!(
+                        "Alert delivery to {name} failed (attempt {attempt}): {e:#}",
+                        name = self.name(),
+                        attempt = attempt + 1
+                    );
+                    sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let title = match alert.state {
+            AlertState::Firing => format!("axectl {kind} alert", kind = alert.kind),
+            AlertState::Resolved => format!("axectl {kind} resolved", kind = alert.kind),
+        };
+
+        match (&self.config.target, &self.transport) {
+            (SinkTarget::Webhook { url, headers }, Transport::Http(client)) => {
+                let mut request = client.post(url).json(alert);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                check_response(request.send().await?).await
+            }
+            (
+                SinkTarget::Ntfy {
+                    url,
+                    token,
+                    priority,
+                },
+                Transport::Http(client),
+            ) => {
+                let mut request = client
+                    .post(url)
+                    .header("Title", &title)
+                    .header(
+                        "Tags",
+                        format!(
+                            "{kind},{severity}",
+                            kind = alert.kind,
+                            severity = alert.severity
+                        ),
+                    )
+                    .body(alert.message.clone());
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                if let Some(priority) = priority {
+                    request = request.header("Priority", priority.to_string());
+                }
+                check_response(request.send().await?).await
+            }
+            (
+                SinkTarget::Gotify {
+                    url,
+                    token,
+                    priority,
+                },
+                Transport::Http(client),
+            ) => {
+                let request = client
+                    .post(format!("{base}/message", base = url.trim_end_matches('/')))
+                    .header("X-Gotify-Key", token)
+                    .json(&serde_json::json!({
+                        "title": title,
+                        "message": alert.message,
+                        "priority": priority.unwrap_or(match alert.severity {
+                            Severity::Info => 2,
+                            Severity::Warning => 5,
+                            Severity::Critical => 8,
+                        }),
+                    }));
+                check_response(request.send().await?).await
+            }
+            (SinkTarget::Smtp { .. }, Transport::Smtp(mailer)) => {
+                let mut message = Message::builder().from(mailer.from.clone());
+                for recipient in &mailer.to {
+                    message = message.to(recipient.clone());
+                }
+                let body = format!(
+                    "{message}\n\nDevice: {ip}\nTime: {time}\n",
+                    message = alert.message,
+                    ip = alert.device_ip,
+                    time = alert.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+                );
+                let message = message
+                    .subject(format!("{title}: {message}", message = alert.message))
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(body)
+                    .context("Failed to build email")?;
+                mailer
+                    .transport
+                    .send(message)
+                    .await
+                    .context("SMTP delivery failed")?;
+                Ok(())
+            }
+            (SinkTarget::Exec { command, args }, Transport::Exec) => {
+                run_hook(command, args, alert).await
+            }
+            _ => bail!("Sink transport does not match its type"),
+        }
+    }
+}
+
+async fn check_response(response: reqwest::Response) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    bail!("HTTP {status}: {body}", body = body.trim())
+}
+
+async fn run_hook(command: &str, args: &[String], alert: &Alert) -> Result<()> {
+    let payload = serde_json::to_vec(alert).context("Failed to serialize alert")?;
+    let mut child = Command::new(command)
+        .args(args)
+        .env("AXECTL_ALERT_KIND", alert.kind.to_string())
+        .env("AXECTL_ALERT_SEVERITY", alert.severity.to_string())
+        .env("AXECTL_ALERT_STATE", alert.state.to_string())
+        .env("AXECTL_ALERT_MESSAGE", &alert.message)
+        .env("AXECTL_ALERT_DEVICE_IP", &alert.device_ip)
+        .env("AXECTL_ALERT_TIMESTAMP", alert.timestamp.to_rfc3339())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {command}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The hook may ignore stdin, so a closed pipe is fine
+        let _ = stdin.write_all(&payload).await;
+    }
+
+    let output = child.wait_with_output().await?;
+    ensure!(
+        output.status.success(),
+        "{command} exited with {status}: {stderr}",
+        status = output.status,
+        stderr = String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(())
+}
+
+/// Delivers alerts to every configured sink
+pub struct Notifier {
+    sinks: Vec<Sink>,
+}
+
+impl Notifier {
+    pub fn new(config: &NotifyConfig) -> Result<Self> {
+        let sinks = config
+            .sinks
+            .iter()
+            .cloned()
+            .map(Sink::new)
+            .collect::<Result<_>>()?;
+        Ok(Self { sinks })
+    }
+
+    /// Offer an alert to all sinks concurrently
+    pub async fn deliver(&mut self, alert: &Alert) -> Vec<(String, Delivery)> {
+        join_all(self.sinks.iter_mut().map(|sink| async move {
+            let delivery = sink.offer(alert).await;
+            (sink.name(), delivery)
+        }))
+        .await
+    }
+
+    /// Deliver alerts in the background so slow sinks never hold up polling
+    pub fn spawn(mut self) -> NotifierHandle {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Alert>();
+        let task = tokio::spawn(async move {
+            while let Some(alert) = rx.recv().await {
+                for (name, delivery) in self.deliver(&alert).await {
+                    match delivery {
+                        Delivery::Sent => tracing::debug!("Alert delivered to {name}"),
+                        Delivery::Filtered => {}
+                        Delivery::RateLimited => {
+                            tracing::info!("Alert to {name} dropped by rate limit")
+                        }
+                        Delivery::Failed(e) => {
+                            tracing::warn!("Failed to deliver alert to {name}: {e}")
+                        }
+                    }
+                }
+            }
+        });
+        NotifierHandle { tx, task }
+    }
+}
+
+/// Queue of the background notifier
+pub struct NotifierHandle {
+    tx: mpsc::UnboundedSender<Alert>,
+    task: JoinHandle<()>,
+}
+
+impl NotifierHandle {
+    pub fn send(&self, alerts: &[Alert]) {
+        for alert in alerts {
+            if self.tx.send(alert.clone()).is_err() {
+                tracing::warn!("Alert notifier stopped, dropping alert");
+                return;
+            }
+        }
+    }
+
+    /// Wait for queued alerts to be delivered
+    pub async fn finish(self) {
+        drop(self.tx);
+        if timeout(DRAIN_TIMEOUT, self.task).await.is_err() {
+            tracing::warn!("Gave up delivering queued alerts");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn alert(kind: AlertKind) -> Alert {
+        Alert {
+            timestamp: Utc::now(),
+            kind,
+            severity: Severity::Warning,
+            state: AlertState::Firing,
+            rule: None,
+            message: "bitaxe-1 went offline".to_string(),
+            device_ip: "192.168.1.10".to_string(),
+        }
+    }
+
+    fn parse_sink(toml: &str) -> Result<Sink> {
+        let config = NotifyConfig::parse(&format!("[[sinks]]\n{toml}"))?;
+        Sink::new(config.sinks[0].clone())
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_sinks() {
+        assert!(NotifyConfig::parse("").is_err());
+        assert!(NotifyConfig::parse("[[sinks]]\ntype = \"webhook\"\nurl = \"not a url\"").is_err());
+        assert!(NotifyConfig::parse("[[sinks]]\ntype = \"pager\"\nurl = \"http://x\"").is_err());
+        assert!(
+            NotifyConfig::parse(
+                "[[sinks]]\ntype = \"smtp\"\nhost = \"mail\"\nfrom = \"axectl\"\nto = [\"me@example.com\"]"
+            )
+            .is_err()
+        );
+        assert!(
+            NotifyConfig::parse(
+                "[[sinks]]\ntype = \"webhook\"\nurl = \"http://x\"\nalerts = [\"weather\"]"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_misspelled_keys() -> Result<()> {
+        let sink = "[[sinks]]\ntype = \"ntfy\"\nurl = \"https://ntfy.sh/miners\"";
+        let config = NotifyConfig::parse(&format!("{sink}\nrate_limit = 5\npriority = 4"))?;
+        assert_eq!(config.sinks[0].rate_limit, Some(5));
+
+        for typo in ["rate_limt = 5", "min_severty = \"critical\"", "priorty = 4"] {
+            let error = match NotifyConfig::parse(&format!("{sink}\n{typo}")) {
+                Ok(_) => bail!("'{typo}' was accepted"),
+                Err(e) => format!("{e:#}"),
+            };
+            let key = typo.split(' ').next().unwrap_or_default();
+            assert!(error.contains(key), "{error}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_webhook_filters_and_retries() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let failure = server
+            .mock("POST", "/hook")
+            .with_status(502)
+            .expect(1)
+            .create_async()
+            .await;
+        let success = server
+            .mock("POST", "/hook")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "kind": "offline",
+                "device_ip": "192.168.1.10",
+            })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut sink = parse_sink(&format!(
+            "type = \"webhook\"\nurl = \"{url}/hook\"\nalerts = [\"offline\"]\nretry_delay = \"0s\"\nheaders = {{ Authorization = \"Bearer secret\" }}",
+            url = server.url()
+        ))?;
+
+        assert_eq!(
+            sink.offer(&alert(AlertKind::Temperature)).await,
+            Delivery::Filtered
+        );
+        assert_eq!(sink.offer(&alert(AlertKind::Offline)).await, Delivery::Sent);
+
+        failure.assert_async().await;
+        success.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_and_push_services() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let ntfy = server
+            .mock("POST", "/miners")
+            .match_header("title", "axectl offline alert")
+            .match_header("priority", "4")
+            .match_body("bitaxe-1 went offline")
+            .with_status(200)
+            .expect(2)
+            .create_async()
+            .await;
+        let gotify = server
+            .mock("POST", "/message")
+            .match_header("x-gotify-key", "app-token")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "title": "axectl offline alert",
+                "priority": 5,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let mut ntfy_sink = parse_sink(&format!(
+            "type = \"ntfy\"\nurl = \"{url}/miners\"\npriority = 4\nrate_limit = 2\nrate_window = \"1h\"",
+            url = server.url()
+        ))?;
+        assert_eq!(
+            ntfy_sink.offer(&alert(AlertKind::Offline)).await,
+            Delivery::Sent
+        );
+        assert_eq!(
+            ntfy_sink.offer(&alert(AlertKind::Offline)).await,
+            Delivery::Sent
+        );
+        assert_eq!(
+            ntfy_sink.offer(&alert(AlertKind::Offline)).await,
+            Delivery::RateLimited
+        );
+
+        let mut gotify_sink = parse_sink(&format!(
+            "type = \"gotify\"\nurl = \"{url}/\"\ntoken = \"app-token\"",
+            url = server.url()
+        ))?;
+        assert_eq!(
+            gotify_sink.offer(&alert(AlertKind::Offline)).await,
+            Delivery::Sent
+        );
+
+        ntfy.assert_async().await;
+        gotify.assert_async().await;
+        Ok(())
+    }
+
+    /// Minimal SMTP server that accepts one message and returns its data
+    async fn smtp_server() -> Result<(u16, JoinHandle<Result<String>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let task = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 localhost ESMTP\r\n").await?;
+
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await? {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        writer.write_all(b"250 OK\r\n").await?;
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+
+                let reply: &[u8] = match line.get(..4).unwrap_or_default().to_uppercase().as_str() {
+                    "EHLO" | "HELO" => b"250 localhost\r\n",
+                    "DATA" => {
+                        in_data = true;
+                        b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 Bye\r\n").await?;
+                        break;
+                    }
+                    _ => b"250 OK\r\n",
+                };
+                writer.write_all(reply).await?;
+            }
+            Ok(data)
+        });
+
+        Ok((port, task))
+    }
+
+    #[tokio::test]
+    async fn test_smtp_sink_sends_email() -> Result<()> {
+        let (port, server) = smtp_server().await?;
+        let mut sink = parse_sink(&format!(
+            "type = \"smtp\"\nhost = \"127.0.0.1\"\nport = {port}\nsecurity = \"none\"\nfrom = \"axectl@example.com\"\nto = [\"ops@example.com\"]"
+        ))?;
+
+        assert_eq!(sink.offer(&alert(AlertKind::Offline)).await, Delivery::Sent);
+        drop(sink);
+
+        let data = server.await??;
+        assert!(data.contains("Subject: axectl offline alert: bitaxe-1 went offline"));
+        assert!(data.contains("To: ops@example.com"));
+        assert!(data.contains("Device: 192.168.1.10"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_sink_passes_alert_on_stdin() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output = temp_dir.path().join("alert.json");
+        let mut sink = parse_sink(&format!(
+            "type = \"exec\"\ncommand = \"sh\"\nargs = [\"-c\", \"cat > {path}\"]",
+            path = output.display()
+        ))?;
+
+        assert_eq!(sink.offer(&alert(AlertKind::Offline)).await, Delivery::Sent);
+        let delivered: Alert = serde_json::from_str(&std::fs::read_to_string(&output)?)?;
+        assert_eq!(delivered.kind, AlertKind::Offline);
+
+        let mut failing = parse_sink("type = \"exec\"\ncommand = \"false\"\nretries = 0")?;
+        assert!(matches!(
+            failing.offer(&alert(AlertKind::Offline)).await,
+            Delivery::Failed(_)
+        ));
+        Ok(())
+    }
+}
@@ -23,9 +23,23 @@ pub struct BitaxeInfoResponse {
     pub pool_port: u16,
     #[serde(rename = "stratumUser")]
     pub pool_user: String,
+    #[serde(rename = "fallbackStratumURL")]
+    pub fallback_pool_url: Option<String>,
+    #[serde(rename = "fallbackStratumPort")]
+    pub fallback_pool_port: Option<u16>,
+    #[serde(rename = "fallbackStratumUser")]
+    pub fallback_pool_user: Option<String>,
+    #[serde(
+        rename = "isUsingFallbackStratum",
+        default,
+        deserialize_with = "super::deserialize_flag"
+    )]
+    pub using_fallback_pool: Option<bool>,
     pub frequency: u32,
     pub voltage: f64,
     pub fanspeed: u32,
+    #[serde(rename = "autofanspeed")]
+    pub auto_fan_speed: Option<u8>,
     pub temp: f64,
     pub power: f64,
     #[serde(rename = "uptimeSeconds")]
@@ -36,8 +50,18 @@ pub struct BitaxeInfoResponse {
     pub shares_accepted: u64,
     #[serde(rename = "sharesRejected")]
     pub shares_rejected: u64,
-    #[serde(rename = "bestDiff")]
+    #[serde(
+        rename = "bestDiff",
+        default,
+        deserialize_with = "super::deserialize_difficulty"
+    )]
     pub best_difficulty: Option<String>,
+    #[serde(
+        rename = "bestSessionDiff",
+        default,
+        deserialize_with = "super::deserialize_difficulty"
+    )]
+    pub best_session_difficulty: Option<String>,
 }
 
 impl BitaxeInfoResponse {
@@ -50,6 +74,7 @@ impl BitaxeInfoResponse {
                 .as_deref()
                 .unwrap_or("unknown")
                 .to_string(),
+            device_model: None,
             firmware_version: self.firmware_version.clone(),
             mac_address: self.mac_address.clone(),
             hostname: self.hostname.clone(),
@@ -59,9 +84,14 @@ impl BitaxeInfoResponse {
             pool_url: self.pool_url.clone(),
             pool_port: self.pool_port,
             pool_user: self.pool_user.clone(),
+            fallback_pool_url: self.fallback_pool_url.clone(),
+            fallback_pool_port: self.fallback_pool_port,
+            fallback_pool_user: self.fallback_pool_user.clone(),
+            using_fallback_pool: self.using_fallback_pool,
             frequency: self.frequency,
             voltage: self.voltage,
             fanspeed: self.fanspeed,
+            auto_fan_speed: self.auto_fan_speed.map(|mode| mode != 0),
             temp: self.temp,
             power: self.power,
             running_time: self.uptime_seconds,
@@ -79,6 +109,7 @@ impl BitaxeInfoResponse {
             shares_rejected: self.shares_rejected,
             uptime: self.uptime_seconds,
             best_difficulty: self.best_difficulty.clone(),
+            best_session_difficulty: self.best_session_difficulty.clone(),
             session_id: Some(self.firmware_version.clone()),
         }
     }
@@ -197,6 +228,44 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bitaxe_numeric_best_difficulty() -> Result<()> {
+        let mut json: serde_json::Value = serde_json::from_str(SAMPLE_BITAXE_RESPONSE)?;
+        json["bestDiff"] = serde_json::json!(4_294_967_296u64);
+        json["bestSessionDiff"] = serde_json::json!("12.3M");
+
+        let response: BitaxeInfoResponse = serde_json::from_value(json)?;
+        let stats = response.to_unified_stats();
+        assert_eq!(stats.best_difficulty, Some("4294967296".to_string()));
+        assert_eq!(stats.best_session_difficulty, Some("12.3M".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitaxe_fallback_pool() -> Result<()> {
+        let mut json: serde_json::Value = serde_json::from_str(SAMPLE_BITAXE_RESPONSE)?;
+        json["fallbackStratumURL"] = serde_json::json!("solo.ckpool.org");
+        json["fallbackStratumPort"] = serde_json::json!(3333);
+        json["fallbackStratumUser"] = serde_json::json!("bc1qbackup");
+        json["isUsingFallbackStratum"] = serde_json::json!(1);
+
+        let unified = serde_json::from_value::<BitaxeInfoResponse>(json)?.to_unified_info();
+        assert_eq!(
+            unified.fallback_pool_url.as_deref(),
+            Some("solo.ckpool.org")
+        );
+        assert_eq!(unified.fallback_pool_port, Some(3333));
+        assert_eq!(unified.fallback_pool_user.as_deref(), Some("bc1qbackup"));
+        assert_eq!(unified.using_fallback_pool, Some(true));
+        assert_eq!(unified.active_pool(), "solo.ckpool.org:3333");
+
+        let primary: BitaxeInfoResponse = serde_json::from_str(SAMPLE_BITAXE_RESPONSE)?;
+        let unified = primary.to_unified_info();
+        assert_eq!(unified.using_fallback_pool, None);
+        assert_eq!(unified.active_pool(), "stratum+tcp://test.pool.com:4334");
+        Ok(())
+    }
+
     #[test]
     fn test_bitaxe_invalid_json() -> Result<()> {
         let invalid_json = r#"{"invalid": "json"}"#;
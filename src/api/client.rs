@@ -4,6 +4,7 @@ use std::time::Duration;
 use url::Url;
 
 use super::models::*;
+use crate::hardware::HardwareDb;
 
 #[derive(Debug, Clone)]
 pub struct AxeOsClient {
@@ -78,6 +79,36 @@ impl AxeOsClient {
         Ok(device_response.to_unified_info())
     }
 
+    // Get system information together with the raw JSON reported by the device
+    pub async fn get_system_info_with_raw(
+        &self,
+    ) -> Result<(SystemInfoResponse, serde_json::Value)> {
+        let url = format!("{}/api/system/info", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to send request to device")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("HTTP error: {}", response.status()));
+        }
+
+        let json_text = response
+            .text()
+            .await
+            .context("Failed to get response text")?;
+
+        let raw: serde_json::Value =
+            serde_json::from_str(&json_text).context("Failed to parse device response")?;
+        let device_response =
+            DeviceResponse::from_json(&json_text).context("Failed to parse device response")?;
+
+        Ok((device_response.to_unified_info(), raw))
+    }
+
     /// Get complete device info with proper device type detection
     pub async fn get_complete_device_info(&self) -> Result<(SystemInfoResponse, DeviceType)> {
         let url = format!("{}/api/system/info", self.base_url);
@@ -250,6 +281,40 @@ impl AxeOsClient {
         Ok(result)
     }
 
+    // Make the device identify itself (blinks its LED and shows a message on its display)
+    pub async fn identify(&self) -> Result<CommandResult> {
+        let url = format!("{}/api/system/identify", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .send()
+            .await
+            .context("Failed to send identify request to device")?;
+
+        let result = if response.status().is_success() {
+            CommandResult {
+                success: true,
+                message: "Device identification started".to_string(),
+                data: None,
+                timestamp: chrono::Utc::now(),
+            }
+        } else {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            CommandResult {
+                success: false,
+                message: format!("Failed to identify device: {error_text}"),
+                data: None,
+                timestamp: chrono::Utc::now(),
+            }
+        };
+
+        Ok(result)
+    }
+
     // Scan for WiFi networks
     pub async fn scan_wifi(&self) -> Result<WifiScanResponse> {
         let url = format!("{}/api/system/wifi/scan", self.base_url);
@@ -378,6 +443,22 @@ impl AxeOsClient {
         Ok((info_result, stats_result))
     }
 
+    // Get device stats with the hashrate expected from its ASIC info or hardware profile
+    pub async fn get_device_stats(&self) -> Result<DeviceStats> {
+        let (complete, asic) = tokio::join!(self.get_complete_info(), self.get_asic_info());
+        let (info, stats) = complete?;
+        // Not every firmware serves /api/system/asic, the hardware profile covers those
+        let asic = asic
+            .inspect_err(|e| {
+                tracing::debug!("No ASIC info from {url}: {e:#}", url = self.base_url);
+            })
+            .ok();
+        let expected = HardwareDb::shared()
+            .and_then(|hardware| hardware.expected_hashrate(&info, asic.as_ref()));
+
+        Ok(DeviceStats::from_api_responses(&info, &stats).with_expected_hashrate(expected))
+    }
+
     // Get the base URL for this client
     pub fn base_url(&self) -> &str {
         &self.base_url
@@ -1,4 +1,4 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail, ensure};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -6,6 +6,8 @@ use std::fmt;
 use std::str::FromStr;
 use strum::{Display, EnumString, IntoStaticStr, VariantNames};
 
+use crate::hardware::HardwareDb;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
     pub name: String,
@@ -45,6 +47,9 @@ pub enum DeviceType {
     #[serde(rename = "bitaxe_max")]
     #[strum(serialize = "bitaxe-max")]
     BitaxeMax,
+    #[serde(rename = "bitaxe_supra")]
+    #[strum(serialize = "bitaxe-supra")]
+    BitaxeSupra,
     #[serde(rename = "bitaxe_gamma")]
     #[strum(serialize = "bitaxe-gamma")]
     BitaxeGamma,
@@ -61,6 +66,7 @@ impl DeviceType {
         match self {
             DeviceType::BitaxeUltra => "Bitaxe Ultra",
             DeviceType::BitaxeMax => "Bitaxe Max",
+            DeviceType::BitaxeSupra => "Bitaxe Supra",
             DeviceType::BitaxeGamma => "Bitaxe Gamma",
             DeviceType::NerdqaxePlus => "NerdQaxe++",
             DeviceType::Unknown => "Unknown",
@@ -72,6 +78,7 @@ impl DeviceType {
         match self {
             DeviceType::BitaxeUltra => "bitaxe-ultra",
             DeviceType::BitaxeMax => "bitaxe-max",
+            DeviceType::BitaxeSupra => "bitaxe-supra",
             DeviceType::BitaxeGamma => "bitaxe-gamma",
             DeviceType::NerdqaxePlus => "nerdqaxe",
             DeviceType::Unknown => "unknown",
@@ -83,6 +90,7 @@ impl DeviceType {
         match name.to_lowercase().as_str() {
             "bitaxe-ultra" | "bitaxe_ultra" => Some(DeviceType::BitaxeUltra),
             "bitaxe-max" | "bitaxe_max" => Some(DeviceType::BitaxeMax),
+            "bitaxe-supra" | "bitaxe_supra" => Some(DeviceType::BitaxeSupra),
             "bitaxe-gamma" | "bitaxe_gamma" => Some(DeviceType::BitaxeGamma),
             "nerdqaxe" | "nerdqaxe-plus" | "nerdqaxe_plus" => Some(DeviceType::NerdqaxePlus),
             "unknown" => Some(DeviceType::Unknown),
@@ -96,6 +104,7 @@ impl DeviceType {
         vec![
             DeviceType::BitaxeUltra,
             DeviceType::BitaxeMax,
+            DeviceType::BitaxeSupra,
             DeviceType::BitaxeGamma,
             DeviceType::NerdqaxePlus,
             DeviceType::Unknown,
@@ -106,7 +115,10 @@ impl DeviceType {
     pub fn is_bitaxe(&self) -> bool {
         matches!(
             self,
-            DeviceType::BitaxeUltra | DeviceType::BitaxeMax | DeviceType::BitaxeGamma
+            DeviceType::BitaxeUltra
+                | DeviceType::BitaxeMax
+                | DeviceType::BitaxeSupra
+                | DeviceType::BitaxeGamma
         )
     }
 
@@ -200,6 +212,24 @@ pub struct DeviceStats {
     pub wifi_rssi: Option<i32>,
     pub voltage: Option<f64>,
     pub frequency: Option<u32>,
+    /// Energy per terahash in J/TH
+    #[serde(default)]
+    pub efficiency_jth: Option<f64>,
+    /// Highest share difficulty the device has ever found
+    #[serde(default)]
+    pub best_difficulty: Option<f64>,
+    /// Highest share difficulty since the device booted
+    #[serde(default)]
+    pub best_session_difficulty: Option<f64>,
+    /// Whether the device is mining on its fallback pool (None if the firmware doesn't report it)
+    #[serde(default)]
+    pub using_fallback_pool: Option<bool>,
+    /// Theoretical hashrate from frequency, small cores and ASIC count, in the unit of `hashrate_mhs`
+    #[serde(default)]
+    pub expected_hashrate_mhs: Option<f64>,
+    /// Actual hashrate as a percentage of the expected one
+    #[serde(default)]
+    pub hashrate_health_percent: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -211,6 +241,9 @@ pub struct SwarmSummary {
     pub total_power_watts: f64,
     pub average_temperature: f64,
     pub average_efficiency: f64,
+    /// Fleet energy per terahash in J/TH
+    #[serde(default)]
+    pub efficiency_jth: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -223,6 +256,101 @@ pub struct TypeSummary {
     pub total_hashrate_mhs: f64,
     pub total_power_watts: f64,
     pub average_temperature: f64,
+    /// Energy per terahash of the online devices in J/TH
+    #[serde(default)]
+    pub efficiency_jth: Option<f64>,
+}
+
+/// Energy per terahash in J/TH for a hashrate in GH/s and a power draw in watts
+pub fn joules_per_terahash(hashrate_ghs: f64, power_watts: f64) -> Option<f64> {
+    if hashrate_ghs > 0.0 && power_watts > 0.0 {
+        Some(power_watts / (hashrate_ghs / 1000.0))
+    } else {
+        None
+    }
+}
+
+/// Theoretical hashrate in GH/s of `asic_count` ASICs with `small_core_count` cores each at `frequency_mhz`
+pub fn expected_hashrate_ghs(frequency_mhz: u32, small_core_count: u32, asic_count: u32) -> f64 {
+    f64::from(frequency_mhz) * f64::from(small_core_count) * f64::from(asic_count) / 1000.0
+}
+
+/// Actual hashrate as a percentage of the expected one
+pub fn hashrate_health_percent(hashrate_ghs: f64, expected_ghs: Option<f64>) -> Option<f64> {
+    expected_ghs
+        .filter(|expected| *expected > 0.0)
+        .map(|expected| hashrate_ghs / expected * 100.0)
+}
+
+/// Parse a difficulty as reported by AxeOS, e.g. "123.45K", "4.2 G" or "987654"
+pub fn parse_difficulty(value: &str) -> Result<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let (number, suffix) = value.split_at(split);
+
+    let number: f64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid difficulty '{value}'"))?;
+    let multiplier = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" => 1.0,
+        "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        other => bail!("Unknown difficulty suffix '{other}' in '{value}'"),
+    };
+    ensure!(
+        number.is_finite() && number >= 0.0,
+        "Invalid difficulty '{value}'"
+    );
+
+    Ok(number * multiplier)
+}
+
+/// Parse an optional difficulty from a device response, logging unparseable values
+fn parse_reported_difficulty(value: Option<&str>) -> Option<f64> {
+    let value = value?;
+    match parse_difficulty(value) {
+        Ok(difficulty) => Some(difficulty),
+        Err(e) => {
+            tracing::debug!("Ignoring reported difficulty: {e}");
+            None
+        }
+    }
+}
+
+/// Accept a difficulty given either as a string ("123.45K") or as a plain
+/// number, which newer firmware versions send
+pub(crate) fn deserialize_difficulty<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text)),
+        Some(Value::Number(number)) => Ok(Some(number.to_string())),
+        Some(other) => Err(serde::de::Error::custom(format!(
+            "invalid difficulty: {other}"
+        ))),
+    }
+}
+
+/// Accept a flag given either as a boolean or as 0/1, which older firmware versions send
+pub(crate) fn deserialize_flag<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(flag)) => Ok(Some(flag)),
+        Some(Value::Number(number)) => Ok(Some(number.as_f64() != Some(0.0))),
+        Some(other) => Err(serde::de::Error::custom(format!("invalid flag: {other}"))),
+    }
 }
 
 // API Response Models (matches AxeOS API)
@@ -232,6 +360,9 @@ pub struct TypeSummary {
 pub struct SystemInfoResponse {
     pub asic_model: String,
     pub board_version: String,
+    /// Product name the firmware reports, only NerdQAxe firmware has one
+    #[serde(default)]
+    pub device_model: Option<String>,
     pub firmware_version: String,
     pub mac_address: String,
     pub hostname: String,
@@ -241,9 +372,21 @@ pub struct SystemInfoResponse {
     pub pool_url: String,
     pub pool_port: u16,
     pub pool_user: String,
+    #[serde(default)]
+    pub fallback_pool_url: Option<String>,
+    #[serde(default)]
+    pub fallback_pool_port: Option<u16>,
+    #[serde(default)]
+    pub fallback_pool_user: Option<String>,
+    /// Whether the device is mining on its fallback pool (None if the firmware doesn't report it)
+    #[serde(default)]
+    pub using_fallback_pool: Option<bool>,
     pub frequency: u32,
     pub voltage: f64,
     pub fanspeed: u32,
+    /// Whether automatic fan control is enabled (None if the firmware doesn't report it)
+    #[serde(default)]
+    pub auto_fan_speed: Option<bool>,
     pub temp: f64,
     pub power: f64,
     pub running_time: u64,
@@ -260,6 +403,7 @@ pub struct SystemStatsResponse {
     pub shares_rejected: u64,
     pub uptime: u64,
     pub best_difficulty: Option<String>,
+    pub best_session_difficulty: Option<String>,
     pub session_id: Option<String>,
 }
 
@@ -278,6 +422,7 @@ pub struct AsicResponse {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct SystemUpdateRequest {
     #[serde(rename = "ssid", skip_serializing_if = "Option::is_none")]
     pub ssid: Option<String>,
@@ -291,12 +436,40 @@ pub struct SystemUpdateRequest {
     pub pool_port: Option<u16>,
     #[serde(rename = "pooluser", skip_serializing_if = "Option::is_none")]
     pub pool_user: Option<String>,
+    #[serde(rename = "poolpassword", skip_serializing_if = "Option::is_none")]
+    pub pool_password: Option<String>,
+    #[serde(rename = "fallbackStratumURL", skip_serializing_if = "Option::is_none")]
+    pub fallback_pool_url: Option<String>,
+    #[serde(
+        rename = "fallbackStratumPort",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fallback_pool_port: Option<u16>,
+    #[serde(
+        rename = "fallbackStratumUser",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fallback_pool_user: Option<String>,
+    #[serde(
+        rename = "fallbackStratumPassword",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fallback_pool_password: Option<String>,
     #[serde(rename = "frequencyvalue", skip_serializing_if = "Option::is_none")]
     pub frequency_value: Option<u32>,
     #[serde(rename = "voltagevalue", skip_serializing_if = "Option::is_none")]
     pub voltage_value: Option<f64>,
     #[serde(rename = "fanspeed", skip_serializing_if = "Option::is_none")]
     pub fan_speed: Option<u32>,
+    #[serde(rename = "autofanspeed", skip_serializing_if = "Option::is_none")]
+    pub auto_fan_speed: Option<u8>,
+    #[serde(rename = "flipscreen", skip_serializing_if = "Option::is_none")]
+    pub flip_screen: Option<u8>,
+    #[serde(rename = "invertscreen", skip_serializing_if = "Option::is_none")]
+    pub invert_screen: Option<u8>,
+    /// Minutes before the display turns off, -1 to keep it on
+    #[serde(rename = "displayTimeout", skip_serializing_if = "Option::is_none")]
+    pub display_timeout: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -322,15 +495,37 @@ pub struct WifiNetwork {
 
 // Helper functions for conversions
 
+impl SystemInfoResponse {
+    /// Whether the device runs NerdQAxe firmware, the only one that reports a device model
+    pub fn is_nerdqaxe(&self) -> bool {
+        self.device_model.is_some()
+    }
+
+    /// `url:port` of the pool the device is mining on, the fallback pool while it is in use
+    pub fn active_pool(&self) -> String {
+        match (
+            self.using_fallback_pool,
+            &self.fallback_pool_url,
+            self.fallback_pool_port,
+        ) {
+            (Some(true), Some(url), Some(port)) if !url.is_empty() => format!("{url}:{port}"),
+            _ => format!("{url}:{port}", url = self.pool_url, port = self.pool_port),
+        }
+    }
+}
+
 impl From<&SystemInfoResponse> for DeviceType {
+    /// Device type from the hardware profile matching the device
     fn from(info: &SystemInfoResponse) -> Self {
-        match info.asic_model.to_lowercase().as_str() {
-            s if s.contains("bm1366") => DeviceType::BitaxeUltra,
-            s if s.contains("bm1368") => DeviceType::BitaxeMax,
-            s if s.contains("bm1370") => DeviceType::BitaxeGamma,
-            s if s.contains("s21") || s.contains("nerdqaxe") => DeviceType::NerdqaxePlus,
-            _ => DeviceType::Unknown,
-        }
+        let family = if info.is_nerdqaxe() {
+            DeviceType::NerdqaxePlus
+        } else {
+            DeviceType::Unknown
+        };
+        HardwareDb::shared()
+            .and_then(|hardware| hardware.lookup(info))
+            .and_then(|profile| profile.device_type)
+            .unwrap_or(family)
     }
 }
 
@@ -345,32 +540,52 @@ impl DeviceStats {
             shares_accepted: stats.shares_accepted,
             shares_rejected: stats.shares_rejected,
             uptime_seconds: stats.uptime,
-            pool_url: Some(format!(
-                "{url}:{port}",
-                url = info.pool_url,
-                port = info.pool_port
-            )),
+            pool_url: Some(info.active_pool()),
             wifi_rssi: info.wifi_rssi,
             voltage: Some(info.voltage),
             frequency: Some(info.frequency),
+            efficiency_jth: joules_per_terahash(stats.hashrate, stats.power),
+            best_difficulty: parse_reported_difficulty(stats.best_difficulty.as_deref()),
+            best_session_difficulty: parse_reported_difficulty(
+                stats.best_session_difficulty.as_deref(),
+            ),
+            using_fallback_pool: info.using_fallback_pool,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
         }
     }
+
+    /// Record the theoretical hashrate and how much of it the device delivers
+    pub fn with_expected_hashrate(mut self, expected_ghs: Option<f64>) -> Self {
+        self.expected_hashrate_mhs = expected_ghs.filter(|expected| *expected > 0.0);
+        self.hashrate_health_percent =
+            hashrate_health_percent(self.hashrate_mhs, self.expected_hashrate_mhs);
+        self
+    }
 }
 
 impl SwarmSummary {
     pub fn from_devices(devices: &[Device]) -> Self {
-        let devices_online = devices
-            .iter()
-            .filter(|d| matches!(d.status, DeviceStatus::Online))
-            .count();
-        let devices_offline = devices.len() - devices_online;
-
         let online_with_stats: Vec<&DeviceStats> = devices
             .iter()
             .filter(|d| matches!(d.status, DeviceStatus::Online))
             .filter_map(|d| d.stats.as_ref())
             .collect();
 
+        let devices_online = devices
+            .iter()
+            .filter(|d| matches!(d.status, DeviceStatus::Online))
+            .count();
+
+        Self {
+            devices_online,
+            devices_offline: devices.len() - devices_online,
+            ..Self::from_stats(devices.len(), &online_with_stats)
+        }
+    }
+
+    /// Summarize the stats collected from the online subset of `total_devices` devices
+    pub fn from_stats(total_devices: usize, online_with_stats: &[&DeviceStats]) -> Self {
         let total_hashrate_mhs = online_with_stats.iter().map(|s| s.hashrate_mhs).sum();
         let total_power_watts = online_with_stats.iter().map(|s| s.power_watts).sum();
         let average_temperature = if !online_with_stats.is_empty() {
@@ -389,13 +604,14 @@ impl SwarmSummary {
         };
 
         Self {
-            total_devices: devices.len(),
-            devices_online,
-            devices_offline,
+            total_devices,
+            devices_online: online_with_stats.len(),
+            devices_offline: total_devices.saturating_sub(online_with_stats.len()),
             total_hashrate_mhs,
             total_power_watts,
             average_temperature,
             average_efficiency,
+            efficiency_jth: joules_per_terahash(total_hashrate_mhs, total_power_watts),
         }
     }
 }
@@ -441,6 +657,7 @@ impl TypeSummary {
             total_hashrate_mhs,
             total_power_watts,
             average_temperature,
+            efficiency_jth: joules_per_terahash(total_hashrate_mhs, total_power_watts),
         }
     }
 
@@ -508,13 +725,8 @@ impl DeviceResponse {
     /// Get the correct DeviceType for this device
     pub fn get_device_type(&self) -> DeviceType {
         match self {
-            DeviceResponse::Bitaxe(bitaxe) => match bitaxe.asic_model.to_lowercase().as_str() {
-                s if s.contains("bm1366") => DeviceType::BitaxeUltra,
-                s if s.contains("bm1368") => DeviceType::BitaxeMax,
-                s if s.contains("bm1370") => DeviceType::BitaxeGamma,
-                _ => DeviceType::Unknown,
-            },
-            DeviceResponse::NerdQaxe(_) => DeviceType::NerdqaxePlus,
+            DeviceResponse::Bitaxe(bitaxe) => DeviceType::from(&bitaxe.to_unified_info()),
+            DeviceResponse::NerdQaxe(nerdqaxe) => DeviceType::from(&nerdqaxe.to_unified_info()),
         }
     }
 
@@ -757,6 +969,7 @@ mod tests {
         let system_info = SystemInfoResponse {
             asic_model: "BM1368".to_string(),
             board_version: "204".to_string(),
+            device_model: None,
             firmware_version: "2.0.0".to_string(),
             mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
             hostname: "bitaxe-test".to_string(),
@@ -766,16 +979,34 @@ mod tests {
             pool_url: "stratum+tcp://test.pool.com".to_string(),
             pool_port: 4334,
             pool_user: "bc1qtest123".to_string(),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
             frequency: 485,
             voltage: 1200.0,
             fanspeed: 75,
+            auto_fan_speed: None,
             temp: 65.5,
             power: 15.8,
             running_time: 3600,
         };
 
-        let device_type = DeviceType::from(&system_info);
-        assert!(matches!(device_type, DeviceType::BitaxeMax));
+        // The type comes from the hardware profile table, BM1368 boards are Supras
+        assert_eq!(DeviceType::from(&system_info), DeviceType::BitaxeSupra);
+
+        let max = SystemInfoResponse {
+            asic_model: "BM1397".to_string(),
+            ..system_info.clone()
+        };
+        assert_eq!(DeviceType::from(&max), DeviceType::BitaxeMax);
+
+        let nerdqaxe = SystemInfoResponse {
+            board_version: "unknown".to_string(),
+            device_model: Some("NerdQAxe+".to_string()),
+            ..system_info
+        };
+        assert_eq!(DeviceType::from(&nerdqaxe), DeviceType::NerdqaxePlus);
     }
 
     #[test]
@@ -783,6 +1014,7 @@ mod tests {
         let system_info = SystemInfoResponse {
             asic_model: "BM1368".to_string(),
             board_version: "204".to_string(),
+            device_model: None,
             firmware_version: "2.0.0".to_string(),
             mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
             hostname: "bitaxe-test".to_string(),
@@ -792,9 +1024,14 @@ mod tests {
             pool_url: "stratum+tcp://test.pool.com".to_string(),
             pool_port: 4334,
             pool_user: "bc1qtest123".to_string(),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
             frequency: 485,
             voltage: 1200.0,
             fanspeed: 75,
+            auto_fan_speed: None,
             temp: 65.5,
             power: 15.8,
             running_time: 3600,
@@ -809,6 +1046,7 @@ mod tests {
             shares_rejected: 2,
             uptime: 3600,
             best_difficulty: Some("123.45K".to_string()),
+            best_session_difficulty: Some("4.5k".to_string()),
             session_id: Some("session123".to_string()),
         };
 
@@ -827,6 +1065,38 @@ mod tests {
             device_stats.pool_url,
             Some("stratum+tcp://test.pool.com:4334".to_string())
         );
+        assert_eq!(device_stats.best_difficulty, Some(123_450.0));
+        assert_eq!(device_stats.best_session_difficulty, Some(4_500.0));
+        assert_eq!(device_stats.hashrate_health_percent, None);
+
+        let expected = expected_hashrate_ghs(485, 1000, 1);
+        assert_eq!(expected, 485.0);
+        let device_stats = device_stats.with_expected_hashrate(Some(expected));
+        assert_eq!(device_stats.expected_hashrate_mhs, Some(485.0));
+        assert!(
+            device_stats
+                .hashrate_health_percent
+                .is_some_and(|h| (h - 100.041).abs() < 0.001)
+        );
+        assert_eq!(
+            device_stats
+                .with_expected_hashrate(Some(0.0))
+                .hashrate_health_percent,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_difficulty() -> Result<()> {
+        assert_eq!(parse_difficulty("123.45K")?, 123_450.0);
+        assert_eq!(parse_difficulty("4.2 G")?, 4.2e9);
+        assert_eq!(parse_difficulty("1.5m")?, 1.5e6);
+        assert_eq!(parse_difficulty("987654")?, 987_654.0);
+        assert_eq!(parse_difficulty(" 2T ")?, 2e12);
+        assert!(parse_difficulty("12Q").is_err());
+        assert!(parse_difficulty("K").is_err());
+        assert!(parse_difficulty("").is_err());
+        Ok(())
     }
 
     #[test]
@@ -868,6 +1138,12 @@ mod tests {
             wifi_rssi: None,
             voltage: None,
             frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
         });
 
         let summary = SwarmSummary::from_devices(&devices);
@@ -984,6 +1260,12 @@ mod tests {
             wifi_rssi: None,
             voltage: None,
             frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
         });
 
         devices[2].stats = Some(DeviceStats {
@@ -999,6 +1281,12 @@ mod tests {
             wifi_rssi: None,
             voltage: None,
             frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
         });
 
         // Filter and create summary for BitaxeMax devices
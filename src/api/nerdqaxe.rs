@@ -25,9 +25,23 @@ pub struct NerdQaxeInfoResponse {
     pub pool_port: u16,
     #[serde(rename = "stratumUser")]
     pub pool_user: String,
+    #[serde(rename = "fallbackStratumURL")]
+    pub fallback_pool_url: Option<String>,
+    #[serde(rename = "fallbackStratumPort")]
+    pub fallback_pool_port: Option<u16>,
+    #[serde(rename = "fallbackStratumUser")]
+    pub fallback_pool_user: Option<String>,
+    #[serde(
+        rename = "isUsingFallbackStratum",
+        default,
+        deserialize_with = "super::deserialize_flag"
+    )]
+    pub using_fallback_pool: Option<bool>,
     pub frequency: u32,
     pub voltage: f64,
     pub fanspeed: u32,
+    #[serde(rename = "autofanspeed")]
+    pub auto_fan_speed: Option<u8>,
     pub temp: f64,
     pub power: f64,
     #[serde(rename = "uptimeSeconds")]
@@ -38,8 +52,18 @@ pub struct NerdQaxeInfoResponse {
     pub shares_accepted: u64,
     #[serde(rename = "sharesRejected")]
     pub shares_rejected: u64,
-    #[serde(rename = "bestDiff")]
+    #[serde(
+        rename = "bestDiff",
+        default,
+        deserialize_with = "super::deserialize_difficulty"
+    )]
     pub best_difficulty: Option<String>,
+    #[serde(
+        rename = "bestSessionDiff",
+        default,
+        deserialize_with = "super::deserialize_difficulty"
+    )]
+    pub best_session_difficulty: Option<String>,
     #[serde(rename = "runningPartition")]
     pub running_partition: Option<String>,
 }
@@ -50,6 +74,7 @@ impl NerdQaxeInfoResponse {
         super::SystemInfoResponse {
             asic_model: self.asic_model.clone(),
             board_version: "unknown".to_string(), // NerdQAxe doesn't provide board version
+            device_model: Some(self.device_model.clone()),
             firmware_version: self.version.as_deref().unwrap_or("unknown").to_string(),
             mac_address: self.mac_address.clone(),
             hostname: self.hostname.clone(),
@@ -59,9 +84,14 @@ impl NerdQaxeInfoResponse {
             pool_url: self.pool_url.clone(),
             pool_port: self.pool_port,
             pool_user: self.pool_user.clone(),
+            fallback_pool_url: self.fallback_pool_url.clone(),
+            fallback_pool_port: self.fallback_pool_port,
+            fallback_pool_user: self.fallback_pool_user.clone(),
+            using_fallback_pool: self.using_fallback_pool,
             frequency: self.frequency,
             voltage: self.voltage,
             fanspeed: self.fanspeed,
+            auto_fan_speed: self.auto_fan_speed.map(|mode| mode != 0),
             temp: self.temp,
             power: self.power,
             running_time: self.uptime_seconds,
@@ -79,6 +109,7 @@ impl NerdQaxeInfoResponse {
             shares_rejected: self.shares_rejected,
             uptime: self.uptime_seconds,
             best_difficulty: self.best_difficulty.clone(),
+            best_session_difficulty: self.best_session_difficulty.clone(),
             session_id: self.running_partition.clone(),
         }
     }
@@ -201,6 +232,24 @@ mod tests {
         assert_eq!(stats.session_id, Some("firmware_a".to_string()));
     }
 
+    #[test]
+    fn test_nerdqaxe_fallback_pool() -> Result<()> {
+        let mut json: serde_json::Value = serde_json::from_str(SAMPLE_NERDQAXE_RESPONSE)?;
+        json["fallbackStratumURL"] = serde_json::json!("solo.ckpool.org");
+        json["fallbackStratumPort"] = serde_json::json!(3333);
+        json["fallbackStratumUser"] = serde_json::json!("bc1qbackup");
+        json["isUsingFallbackStratum"] = serde_json::json!(false);
+
+        let unified = serde_json::from_value::<NerdQaxeInfoResponse>(json)?.to_unified_info();
+        assert_eq!(
+            unified.fallback_pool_url.as_deref(),
+            Some("solo.ckpool.org")
+        );
+        assert_eq!(unified.using_fallback_pool, Some(false));
+        assert_eq!(unified.active_pool(), "stratum+tcp://test.pool.com:4334");
+        Ok(())
+    }
+
     #[test]
     fn test_nerdqaxe_invalid_json() -> Result<()> {
         let invalid_json = r#"{"invalid": "json"}"#;
@@ -0,0 +1,349 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::api::{Device, DeviceType, SystemInfoResponse, SystemUpdateRequest};
+use crate::history::device_key;
+
+/// Name of the backups directory inside the cache directory
+pub const BACKUP_DIR_NAME: &str = "backups";
+
+/// Snapshot IDs are their UTC creation time to the millisecond, so they sort
+/// chronologically and two backups in the same second don't collide
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// Saved configuration of a single device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub device_name: String,
+    pub ip_address: String,
+    pub device_type: DeviceType,
+    pub settings: SystemInfoResponse,
+    /// Full `/api/system/info` response as reported by the device
+    pub raw: serde_json::Value,
+}
+
+impl ConfigSnapshot {
+    pub fn new(
+        device: &Device,
+        settings: SystemInfoResponse,
+        raw: serde_json::Value,
+        created_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: created_at.format(SNAPSHOT_ID_FORMAT).to_string(),
+            created_at,
+            device_name: device.name.clone(),
+            ip_address: device.ip_address.clone(),
+            device_type: device.device_type,
+            settings,
+            raw,
+        }
+    }
+
+    /// Build the update request that restores the saved settings
+    pub fn to_update_request(&self) -> SystemUpdateRequest {
+        let settings = &self.settings;
+
+        // A manual fan speed only sticks when automatic fan control is off
+        let fan_speed = match settings.auto_fan_speed {
+            Some(true) => None,
+            _ => Some(settings.fanspeed),
+        };
+
+        SystemUpdateRequest {
+            hostname: Some(settings.hostname.clone()),
+            pool_url: Some(settings.pool_url.clone()),
+            pool_port: Some(settings.pool_port),
+            pool_user: Some(settings.pool_user.clone()),
+            fallback_pool_url: settings.fallback_pool_url.clone(),
+            fallback_pool_port: settings.fallback_pool_port,
+            fallback_pool_user: settings.fallback_pool_user.clone(),
+            frequency_value: Some(settings.frequency),
+            voltage_value: Some(settings.voltage),
+            fan_speed,
+            auto_fan_speed: settings.auto_fan_speed.map(u8::from),
+            ..Default::default()
+        }
+    }
+}
+
+/// Configuration snapshots stored as `<cache_dir>/backups/<device>/<id>.json`
+#[derive(Debug, Clone)]
+pub struct BackupStore {
+    root: PathBuf,
+}
+
+impl BackupStore {
+    /// Open the backup store inside the given cache directory
+    pub fn open(cache_dir: &Path) -> Self {
+        Self {
+            root: cache_dir.join(BACKUP_DIR_NAME),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write a snapshot to disk, returning its path
+    ///
+    /// An existing snapshot with the same ID is never overwritten.
+    pub fn save(&self, snapshot: &ConfigSnapshot) -> Result<PathBuf> {
+        let dir = self.device_dir(&snapshot.ip_address);
+        fs::create_dir_all(&dir).with_context(|| {
+            format!(
+                "Failed to create backup directory {path}",
+                path = dir.display()
+            )
+        })?;
+
+        let path = dir.join(format!("{id}.json", id = snapshot.id));
+        let content =
+            serde_json::to_string_pretty(snapshot).context("Failed to serialize snapshot")?;
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                bail!(
+                    "Snapshot {id} of {ip} already exists",
+                    id = snapshot.id,
+                    ip = snapshot.ip_address
+                );
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to create snapshot {path}", path = path.display())
+                });
+            }
+        };
+        file.write_all(content.as_bytes())
+            .with_context(|| format!("Failed to write snapshot {path}", path = path.display()))?;
+
+        Ok(path)
+    }
+
+    /// All snapshots of a device, oldest first
+    pub fn list(&self, device_ip: &str) -> Result<Vec<ConfigSnapshot>> {
+        let dir = self.device_dir(device_ip);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory {path}", path = dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            match read_snapshot(&path) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(e) => tracing::warn!("Skipping unreadable snapshot: {e:#}"),
+            }
+        }
+
+        // Older IDs lack milliseconds, so order by creation time first
+        snapshots.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        Ok(snapshots)
+    }
+
+    /// Load a snapshot by ID
+    pub fn load(&self, device_ip: &str, id: &str) -> Result<ConfigSnapshot> {
+        let path = self.device_dir(device_ip).join(format!("{id}.json"));
+        if !path.exists() {
+            bail!("Snapshot {id} not found for {device_ip}");
+        }
+        read_snapshot(&path)
+    }
+
+    /// Most recent snapshot of a device
+    pub fn latest(&self, device_ip: &str) -> Result<Option<ConfigSnapshot>> {
+        Ok(self.list(device_ip)?.pop())
+    }
+
+    fn device_dir(&self, device_ip: &str) -> PathBuf {
+        self.root.join(device_key(device_ip))
+    }
+}
+
+fn read_snapshot(path: &Path) -> Result<ConfigSnapshot> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read snapshot {path}", path = path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse snapshot {path}", path = path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceStatus;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn device() -> Device {
+        Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn settings(frequency: u32, auto_fan_speed: Option<bool>) -> SystemInfoResponse {
+        SystemInfoResponse {
+            asic_model: "BM1370".to_string(),
+            board_version: "601".to_string(),
+            device_model: None,
+            firmware_version: "2.4.0".to_string(),
+            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            hostname: "bitaxe-1".to_string(),
+            wifi_ssid: None,
+            wifi_status: None,
+            wifi_rssi: None,
+            pool_url: "public-pool.io".to_string(),
+            pool_port: 21496,
+            pool_user: "bc1qexample.bitaxe-1".to_string(),
+            fallback_pool_url: Some("solo.ckpool.org".to_string()),
+            fallback_pool_port: Some(3333),
+            fallback_pool_user: Some("bc1qexample.bitaxe-1".to_string()),
+            using_fallback_pool: Some(false),
+            frequency,
+            voltage: 1150.0,
+            fanspeed: 70,
+            auto_fan_speed,
+            temp: 55.0,
+            power: 15.0,
+            running_time: 3600,
+        }
+    }
+
+    #[test]
+    fn test_save_list_and_load() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = BackupStore::open(temp_dir.path());
+
+        let first_time = Utc
+            .with_ymd_and_hms(2025, 1, 1, 12, 0, 0)
+            .single()
+            .context("time")?;
+        let second_time = Utc
+            .with_ymd_and_hms(2025, 1, 2, 8, 30, 0)
+            .single()
+            .context("time")?;
+        let first = ConfigSnapshot::new(
+            &device(),
+            settings(500, None),
+            serde_json::json!({}),
+            first_time,
+        );
+        let second = ConfigSnapshot::new(
+            &device(),
+            settings(525, None),
+            serde_json::json!({}),
+            second_time,
+        );
+
+        store.save(&second)?;
+        let path = store.save(&first)?;
+        assert!(path.ends_with("192.168.1.10/20250101T120000000Z.json"));
+
+        let snapshots = store.list("192.168.1.10")?;
+        let ids: Vec<_> = snapshots.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["20250101T120000000Z", "20250102T083000000Z"]);
+
+        let latest = store.latest("192.168.1.10")?.context("latest snapshot")?;
+        assert_eq!(latest.settings.frequency, 525);
+
+        let loaded = store.load("192.168.1.10", "20250101T120000000Z")?;
+        assert_eq!(loaded.settings.frequency, 500);
+
+        assert!(store.load("192.168.1.10", "20240101T000000Z").is_err());
+        assert!(store.list("192.168.1.99")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshots_in_the_same_second_are_kept() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = BackupStore::open(temp_dir.path());
+        let time = Utc
+            .with_ymd_and_hms(2025, 1, 1, 12, 0, 0)
+            .single()
+            .context("time")?;
+
+        let first =
+            ConfigSnapshot::new(&device(), settings(500, None), serde_json::json!({}), time);
+        let second = ConfigSnapshot::new(
+            &device(),
+            settings(525, None),
+            serde_json::json!({}),
+            time + chrono::Duration::milliseconds(250),
+        );
+        store.save(&first)?;
+        store.save(&second)?;
+        assert_eq!(second.id, "20250101T120000250Z");
+
+        let frequencies: Vec<_> = store
+            .list("192.168.1.10")?
+            .iter()
+            .map(|s| s.settings.frequency)
+            .collect();
+        assert_eq!(frequencies, vec![500, 525]);
+
+        // Saving the same ID again leaves the first snapshot alone
+        let clash =
+            ConfigSnapshot::new(&device(), settings(600, None), serde_json::json!({}), time);
+        assert!(store.save(&clash).is_err());
+        assert_eq!(
+            store.load("192.168.1.10", &first.id)?.settings.frequency,
+            500
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_update_request() -> Result<()> {
+        let manual_fan = ConfigSnapshot::new(
+            &device(),
+            settings(525, Some(false)),
+            serde_json::json!({}),
+            Utc::now(),
+        );
+        let request = manual_fan.to_update_request();
+        assert_eq!(request.hostname.as_deref(), Some("bitaxe-1"));
+        assert_eq!(request.pool_url.as_deref(), Some("public-pool.io"));
+        assert_eq!(request.pool_port, Some(21496));
+        assert_eq!(request.pool_user.as_deref(), Some("bc1qexample.bitaxe-1"));
+        assert_eq!(
+            request.fallback_pool_url.as_deref(),
+            Some("solo.ckpool.org")
+        );
+        assert_eq!(request.fallback_pool_port, Some(3333));
+        assert_eq!(request.frequency_value, Some(525));
+        assert_eq!(request.voltage_value, Some(1150.0));
+        assert_eq!(request.fan_speed, Some(70));
+        assert_eq!(request.auto_fan_speed, Some(0));
+
+        let auto_fan = ConfigSnapshot::new(
+            &device(),
+            settings(525, Some(true)),
+            serde_json::json!({}),
+            Utc::now(),
+        );
+        let request = auto_fan.to_update_request();
+        assert_eq!(request.fan_speed, None);
+        assert_eq!(request.auto_fan_speed, Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::alerts::{Alert, AlertKind, AlertState, Severity};
+use crate::api::{Device, DeviceStats};
+use crate::output::format_difficulty;
+
+/// Name of the best difficulty records file inside the cache directory
+pub const BEST_DIFFICULTY_FILE_NAME: &str = "best-difficulty.json";
+
+/// Best share difficulties seen for one device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyRecord {
+    pub name: String,
+    /// All-time best difficulty
+    pub best: f64,
+    /// When the all-time best was first seen
+    pub achieved_at: DateTime<Utc>,
+    /// Best difficulty of the device's current session (since boot)
+    #[serde(default)]
+    pub session_best: Option<f64>,
+    #[serde(default)]
+    pub session_achieved_at: Option<DateTime<Utc>>,
+}
+
+/// When to report a share that comes close to the network difficulty
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NearBlockConfig {
+    pub network_difficulty: f64,
+    /// Share difficulty as a percentage of the network difficulty
+    pub percent: f64,
+}
+
+impl NearBlockConfig {
+    /// Share difficulty from which a share counts as near a block
+    pub fn threshold(&self) -> f64 {
+        self.network_difficulty * self.percent / 100.0
+    }
+}
+
+/// Something worth celebrating about a device's share difficulty
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DifficultyEvent {
+    /// The device beat its all-time best
+    PersonalBest { previous: f64, best: f64 },
+    /// The device found a share close to the network difficulty
+    NearBlock {
+        difficulty: f64,
+        network_difficulty: f64,
+    },
+}
+
+impl DifficultyEvent {
+    pub fn message(&self, device_name: &str) -> String {
+        match *self {
+            DifficultyEvent::PersonalBest { previous, best } => format!(
+                "🏆 {device_name} new personal best: {best} (was {previous})",
+                best = format_difficulty(best),
+                previous = format_difficulty(previous)
+            ),
+            DifficultyEvent::NearBlock {
+                difficulty,
+                network_difficulty,
+            } => format!(
+                "🎯 {device_name} found a share of {difficulty}, {percent:.2}% of the network difficulty",
+                difficulty = format_difficulty(difficulty),
+                percent = difficulty / network_difficulty * 100.0
+            ),
+        }
+    }
+
+    /// Report the event as a one-off monitor alert
+    pub fn to_alert(&self, device: &Device, now: DateTime<Utc>) -> Alert {
+        let (kind, severity) = match self {
+            DifficultyEvent::PersonalBest { .. } => (AlertKind::PersonalBest, Severity::Info),
+            DifficultyEvent::NearBlock { .. } => (AlertKind::NearBlock, Severity::Warning),
+        };
+        Alert {
+            timestamp: now,
+            kind,
+            severity,
+            state: AlertState::Firing,
+            rule: None,
+            message: self.message(&device.name),
+            device_ip: device.ip_address.clone(),
+        }
+    }
+}
+
+/// All-time and session best difficulties per device, kept in the cache directory
+///
+/// Devices report their own all-time best, but it is lost when their settings
+/// are erased; the records keep the highest value ever seen and when it was found.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BestDifficultyStore {
+    #[serde(default)]
+    pub devices: BTreeMap<String, DifficultyRecord>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl BestDifficultyStore {
+    /// Load the records from the cache directory, starting empty if there are none
+    pub fn load(cache_dir: &Path) -> Result<Self> {
+        let path = cache_dir.join(BEST_DIFFICULTY_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Failed to read best difficulty file: {path}",
+                path = path.display()
+            )
+        })?;
+        serde_json::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse best difficulty file: {path}",
+                path = path.display()
+            )
+        })
+    }
+
+    /// Write the records back if anything changed since they were loaded or saved
+    pub fn save_if_changed(&mut self, cache_dir: &Path) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        fs::create_dir_all(cache_dir).with_context(|| {
+            format!(
+                "Failed to create cache directory: {path}",
+                path = cache_dir.display()
+            )
+        })?;
+        let path = cache_dir.join(BEST_DIFFICULTY_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize best difficulty records")?;
+        fs::write(&path, content).with_context(|| {
+            format!(
+                "Failed to write best difficulty file: {path}",
+                path = path.display()
+            )
+        })?;
+
+        self.changed = false;
+        Ok(())
+    }
+
+    /// Record a device's reported difficulties and return what is new
+    ///
+    /// The first observation of a device only sets its baseline.
+    pub fn observe(
+        &mut self,
+        device: &Device,
+        stats: &DeviceStats,
+        near_block: Option<&NearBlockConfig>,
+        now: DateTime<Utc>,
+    ) -> Vec<DifficultyEvent> {
+        let session = stats.best_session_difficulty;
+        let Some(observed) = stats
+            .best_difficulty
+            .into_iter()
+            .chain(session)
+            .reduce(f64::max)
+        else {
+            return Vec::new();
+        };
+
+        let Some(record) = self.devices.get_mut(&device.ip_address) else {
+            self.devices.insert(
+                device.ip_address.clone(),
+                DifficultyRecord {
+                    name: device.name.clone(),
+                    best: observed,
+                    achieved_at: now,
+                    session_best: session,
+                    session_achieved_at: session.map(|_| now),
+                },
+            );
+            self.changed = true;
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        if record.name != device.name {
+            record.name = device.name.clone();
+            self.changed = true;
+        }
+
+        // A share better than anything seen before in this session (or at all,
+        // for firmware that only reports the all-time best)
+        let mut new_share = None;
+
+        if observed > record.best {
+            events.push(DifficultyEvent::PersonalBest {
+                previous: record.best,
+                best: observed,
+            });
+            record.best = observed;
+            record.achieved_at = now;
+            self.changed = true;
+            if session.is_none() {
+                new_share = Some(observed);
+            }
+        }
+
+        if let Some(session) = session {
+            // The session best only decreases when the device restarted
+            let improved = record
+                .session_best
+                .is_none_or(|previous| session > previous);
+            let restarted = record
+                .session_best
+                .is_some_and(|previous| session < previous);
+            if improved || restarted {
+                record.session_best = Some(session);
+                record.session_achieved_at = Some(now);
+                self.changed = true;
+                new_share = Some(session);
+            }
+        }
+
+        if let (Some(difficulty), Some(near_block)) = (new_share, near_block)
+            && difficulty >= near_block.threshold()
+        {
+            events.push(DifficultyEvent::NearBlock {
+                difficulty,
+                network_difficulty: near_block.network_difficulty,
+            });
+        }
+
+        events
+    }
+
+    /// Records ordered from the highest all-time best down, with the device IP
+    pub fn leaderboard(&self) -> Vec<(&str, &DifficultyRecord)> {
+        let mut entries: Vec<_> = self
+            .devices
+            .iter()
+            .map(|(ip, record)| (ip.as_str(), record))
+            .collect();
+        entries.sort_by(|(_, a), (_, b)| b.best.total_cmp(&a.best));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStatus, DeviceType};
+    use chrono::Duration;
+
+    fn device() -> Device {
+        Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn stats(best: Option<f64>, session: Option<f64>) -> DeviceStats {
+        DeviceStats {
+            timestamp: Utc::now(),
+            hashrate_mhs: 1000.0,
+            temperature_celsius: 60.0,
+            power_watts: 15.0,
+            fan_speed_rpm: 4000,
+            shares_accepted: 0,
+            shares_rejected: 0,
+            uptime_seconds: 0,
+            pool_url: None,
+            wifi_rssi: None,
+            voltage: None,
+            frequency: None,
+            efficiency_jth: None,
+            best_difficulty: best,
+            best_session_difficulty: session,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    #[test]
+    fn reports_personal_bests_and_near_block_shares() -> Result<()> {
+        let device = device();
+        let near_block = NearBlockConfig {
+            network_difficulty: 100e12,
+            percent: 1.0,
+        };
+        let now = Utc::now();
+        let mut store = BestDifficultyStore::default();
+
+        // The first observation sets the baseline
+        let events = store.observe(
+            &device,
+            &stats(Some(5e9), Some(1e6)),
+            Some(&near_block),
+            now,
+        );
+        assert!(events.is_empty());
+
+        // A better session share that is not an all-time best
+        let events = store.observe(
+            &device,
+            &stats(Some(5e9), Some(2e9)),
+            Some(&near_block),
+            now,
+        );
+        assert!(events.is_empty());
+        assert_eq!(store.devices[&device.ip_address].session_best, Some(2e9));
+
+        // A new record close to the network difficulty
+        let later = now + Duration::minutes(5);
+        let events = store.observe(
+            &device,
+            &stats(Some(2e12), Some(2e12)),
+            Some(&near_block),
+            later,
+        );
+        assert_eq!(
+            events,
+            vec![
+                DifficultyEvent::PersonalBest {
+                    previous: 5e9,
+                    best: 2e12
+                },
+                DifficultyEvent::NearBlock {
+                    difficulty: 2e12,
+                    network_difficulty: 100e12
+                },
+            ]
+        );
+        assert_eq!(
+            events[1].message("bitaxe-1"),
+            "🎯 bitaxe-1 found a share of 2.00T, 2.00% of the network difficulty"
+        );
+        assert_eq!(store.devices[&device.ip_address].achieved_at, later);
+
+        // After a restart the session best starts over without an event
+        let events = store.observe(
+            &device,
+            &stats(Some(2e12), Some(3e3)),
+            Some(&near_block),
+            later,
+        );
+        assert!(events.is_empty());
+        assert_eq!(store.devices[&device.ip_address].session_best, Some(3e3));
+        Ok(())
+    }
+
+    #[test]
+    fn near_block_threshold_is_inclusive_and_reported_once() -> Result<()> {
+        let device = device();
+        let near_block = NearBlockConfig {
+            network_difficulty: 100e12,
+            percent: 1.0,
+        };
+        let threshold = near_block.threshold();
+        assert_eq!(threshold, 1e12);
+        let now = Utc::now();
+        let mut store = BestDifficultyStore::default();
+        store.observe(
+            &device,
+            &stats(Some(5e12), Some(1e6)),
+            Some(&near_block),
+            now,
+        );
+
+        // Just short of the threshold
+        let events = store.observe(
+            &device,
+            &stats(Some(5e12), Some(threshold * 0.999)),
+            Some(&near_block),
+            now,
+        );
+        assert!(events.is_empty());
+
+        // Exactly at the threshold, without beating the all-time best
+        let events = store.observe(
+            &device,
+            &stats(Some(5e12), Some(threshold)),
+            Some(&near_block),
+            now,
+        );
+        assert_eq!(
+            events,
+            vec![DifficultyEvent::NearBlock {
+                difficulty: threshold,
+                network_difficulty: 100e12
+            }]
+        );
+
+        // The same share on the next poll is not reported again
+        let events = store.observe(
+            &device,
+            &stats(Some(5e12), Some(threshold)),
+            Some(&near_block),
+            now,
+        );
+        assert!(events.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn near_block_needs_a_share_found_while_watching() -> Result<()> {
+        let device = device();
+        let near_block = NearBlockConfig {
+            network_difficulty: 100e12,
+            percent: 1.0,
+        };
+        let now = Utc::now();
+
+        // A share above the threshold found before monitoring started
+        let mut store = BestDifficultyStore::default();
+        let events = store.observe(
+            &device,
+            &stats(Some(3e12), Some(3e12)),
+            Some(&near_block),
+            now,
+        );
+        assert!(events.is_empty());
+
+        // After a restart, a lower session best that is still near a block is new
+        let events = store.observe(
+            &device,
+            &stats(Some(3e12), Some(2e12)),
+            Some(&near_block),
+            now,
+        );
+        assert_eq!(
+            events,
+            vec![DifficultyEvent::NearBlock {
+                difficulty: 2e12,
+                network_difficulty: 100e12
+            }]
+        );
+
+        // Without near-block reporting only the personal best is reported
+        let events = store.observe(&device, &stats(Some(4e12), Some(4e12)), None, now);
+        assert_eq!(
+            events,
+            vec![DifficultyEvent::PersonalBest {
+                previous: 3e12,
+                best: 4e12
+            }]
+        );
+
+        // Firmware without a session best: only a new all-time best is a new share
+        let mut store = BestDifficultyStore::default();
+        store.observe(&device, &stats(Some(2e12), None), Some(&near_block), now);
+        let events = store.observe(&device, &stats(Some(2e12), None), Some(&near_block), now);
+        assert!(events.is_empty());
+        let events = store.observe(&device, &stats(Some(2.5e12), None), Some(&near_block), now);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1],
+            DifficultyEvent::NearBlock { difficulty, .. } if difficulty == 2.5e12
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn records_survive_a_reload_and_rank_devices() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let now = Utc::now();
+        let mut store = BestDifficultyStore::default();
+
+        let first = device();
+        let mut second = device();
+        second.name = "bitaxe-2".to_string();
+        second.ip_address = "192.168.1.11".to_string();
+        store.observe(&first, &stats(Some(1e9), None), None, now);
+        store.observe(&second, &stats(Some(4e9), None), None, now);
+        store.save_if_changed(dir.path())?;
+
+        let mut reloaded = BestDifficultyStore::load(dir.path())?;
+        let ranking: Vec<_> = reloaded.leaderboard().iter().map(|(ip, _)| *ip).collect();
+        assert_eq!(ranking, vec!["192.168.1.11", "192.168.1.10"]);
+
+        // Firmware that resets its best does not lower the record
+        let events = reloaded.observe(&first, &stats(Some(1e3), None), None, now);
+        assert!(events.is_empty());
+        assert_eq!(reloaded.devices[&first.ip_address].best, 1e9);
+        Ok(())
+    }
+}
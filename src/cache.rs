@@ -54,7 +54,7 @@ pub fn get_cache_dir(cache_dir: Option<&Path>) -> Result<std::borrow::Cow<'_, Pa
 pub struct CachedDevice {
     /// Device with optional embedded stats
     pub device: Device,
-    /// Recent statistics history (last 10 entries)
+    /// Recent statistics history (last 10 entries, see `HistoryStore` for long-term history)
     pub stats_history: Vec<DeviceStats>,
     /// Last time device was probed (even if failed)
     pub last_probed: DateTime<Utc>,
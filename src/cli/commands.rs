@@ -1,9 +1,21 @@
-use crate::api::{DeviceFilter, DeviceType};
-use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use crate::alerts::{AlertRule, AlertRules, NotifyConfig, RulesConfig};
+use crate::api::{DeviceFilter, DeviceType, SystemUpdateRequest};
+use crate::best_difficulty::NearBlockConfig;
+use crate::daemon::{DEFAULT_DAEMON_LISTEN, DaemonClient};
+use crate::energy::Tariff;
+use crate::history::HistoryMetric;
+use crate::mqtt::{Broker, DEFAULT_BASE_TOPIC, DEFAULT_DISCOVERY_PREFIX};
+use crate::pool::DEFAULT_POOL_PASSWORD;
+use crate::profit::MarketInputs;
+use crate::thermal::ThermalGuardConfig;
+use crate::tune::TuneGoal;
+use anyhow::{Result, ensure};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "axectl")]
@@ -28,6 +40,10 @@ pub struct Cli {
     /// Cache directory for device data (defaults to ~/.cache/axectl/devices)
     #[arg(long, global = true)]
     pub cache_dir: Option<PathBuf>,
+
+    /// Get devices and send control actions through a running `axectl daemon` (e.g. http://127.0.0.1:9185)
+    #[arg(long, global = true, value_name = "URL")]
+    pub daemon: Option<String>,
 }
 
 #[derive(Clone, Copy, ValueEnum, PartialEq)]
@@ -56,6 +72,156 @@ impl std::fmt::Display for DeviceFilterArg {
     }
 }
 
+/// Fallback stratum pool the device switches to when its primary pool is down
+#[derive(Args, Debug, Clone)]
+#[group(required = true, multiple = true)]
+pub struct FallbackPoolArgs {
+    /// Fallback pool URL (e.g., stratum+tcp://solo.ckpool.org)
+    #[arg(long)]
+    pub url: Option<String>,
+
+    /// Fallback pool port
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Fallback pool user, usually <bitcoin address>.<worker>
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Fallback pool password
+    #[arg(long)]
+    pub password: Option<String>,
+}
+
+/// Primary stratum pool the device mines on
+#[derive(Args, Debug, Clone)]
+#[group(required = true, multiple = true)]
+pub struct PoolArgs {
+    /// Pool URL (e.g., stratum+tcp://public-pool.io)
+    #[arg(long)]
+    pub url: Option<String>,
+
+    /// Pool port
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Pool user, usually <bitcoin address>.<worker>
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Pool password
+    #[arg(long)]
+    pub password: Option<String>,
+}
+
+/// Options of the device's display
+#[derive(Args, Debug, Clone)]
+#[group(required = true, multiple = true)]
+pub struct DisplayArgs {
+    /// Rotate the display by 180 degrees
+    #[arg(long, value_name = "BOOL")]
+    pub flip: Option<bool>,
+
+    /// Invert the display colors
+    #[arg(long, value_name = "BOOL")]
+    pub invert: Option<bool>,
+
+    /// Minutes before the display turns off, -1 to keep it on
+    #[arg(long, value_name = "MINUTES", allow_negative_numbers = true)]
+    pub timeout: Option<i32>,
+}
+
+/// A typed settings change
+#[derive(Subcommand, Debug, Clone)]
+pub enum SetAction {
+    /// Set the hostname
+    Hostname { hostname: String },
+
+    /// Configure the primary stratum pool
+    Pool {
+        #[command(flatten)]
+        pool: PoolArgs,
+    },
+
+    /// Set the ASIC frequency in MHz
+    Frequency { mhz: u32 },
+
+    /// Set the ASIC core voltage in mV
+    Voltage { millivolts: u32 },
+
+    /// Set a fixed fan speed percentage or hand the fan to automatic control
+    Fan {
+        /// Fan speed percentage (0-100)
+        #[arg(
+            value_parser = clap::value_parser!(u32).range(0..=100),
+            required_unless_present = "auto",
+            conflicts_with = "auto"
+        )]
+        speed: Option<u32>,
+
+        /// Let the device control the fan based on temperature
+        #[arg(long)]
+        auto: bool,
+    },
+
+    /// Configure the fallback stratum pool
+    FallbackPool {
+        #[command(flatten)]
+        pool: FallbackPoolArgs,
+    },
+
+    /// Configure the display
+    Display {
+        #[command(flatten)]
+        display: DisplayArgs,
+    },
+}
+
+impl SetAction {
+    /// Update request changing only this setting
+    pub fn to_update_request(&self) -> SystemUpdateRequest {
+        match self {
+            SetAction::Hostname { hostname } => SystemUpdateRequest {
+                hostname: Some(hostname.clone()),
+                ..Default::default()
+            },
+            SetAction::Pool { pool } => SystemUpdateRequest {
+                pool_url: pool.url.clone(),
+                pool_port: pool.port,
+                pool_user: pool.user.clone(),
+                pool_password: pool.password.clone(),
+                ..Default::default()
+            },
+            SetAction::Frequency { mhz } => SystemUpdateRequest {
+                frequency_value: Some(*mhz),
+                ..Default::default()
+            },
+            SetAction::Voltage { millivolts } => SystemUpdateRequest {
+                voltage_value: Some(f64::from(*millivolts)),
+                ..Default::default()
+            },
+            SetAction::Fan { speed, auto } => SystemUpdateRequest {
+                fan_speed: *speed,
+                auto_fan_speed: Some(u8::from(*auto)),
+                ..Default::default()
+            },
+            SetAction::FallbackPool { pool } => SystemUpdateRequest {
+                fallback_pool_url: pool.url.clone(),
+                fallback_pool_port: pool.port,
+                fallback_pool_user: pool.user.clone(),
+                fallback_pool_password: pool.password.clone(),
+                ..Default::default()
+            },
+            SetAction::Display { display } => SystemUpdateRequest {
+                flip_screen: display.flip.map(u8::from),
+                invert_screen: display.invert.map(u8::from),
+                display_timeout: display.timeout,
+                ..Default::default()
+            },
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Discover miners on the network
@@ -107,7 +273,7 @@ pub enum Commands {
         #[arg(long)]
         no_mdns: bool,
 
-        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-max, nerdqaxe, bitaxe, all)
+        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-supra, nerdqaxe, bitaxe, all)
         #[arg(long, value_name = "TYPE")]
         device_type: Option<DeviceFilterArg>,
 
@@ -122,6 +288,14 @@ pub enum Commands {
         /// Show per-type summaries
         #[arg(long)]
         type_summary: bool,
+
+        /// Don't record statistics to the history store (only with --watch)
+        #[arg(long)]
+        no_history: bool,
+
+        /// Electricity price per kWh, or a tariff TOML file with time-of-use periods
+        #[arg(long, value_name = "PRICE|FILE")]
+        tariff: Option<String>,
     },
 
     /// Control a device
@@ -140,14 +314,30 @@ pub enum Commands {
         #[arg(long, default_value = "1")]
         interval: u64,
 
-        /// Alert on high temperature (celsius)
+        /// Full-screen dashboard with sorting, device details, alert log and device control
+        #[arg(long)]
+        tui: bool,
+
+        /// Print stats updates, online/offline changes, discovered devices and alerts as NDJSON events
+        #[arg(long, conflicts_with = "tui")]
+        stream: bool,
+
+        /// Alert on high temperature (celsius), shorthand for a temperature rule
         #[arg(long)]
         temp_alert: Option<f64>,
 
-        /// Alert on low hashrate (percentage drop)
+        /// Alert on a hashrate drop (percent below the 10 minute average), shorthand for a hashrate rule
         #[arg(long)]
         hashrate_alert: Option<f64>,
 
+        /// Alert when hashrate stays below this percentage of the expected hashrate for 5 minutes
+        #[arg(long, value_name = "PERCENT")]
+        health_alert: Option<f64>,
+
+        /// Load alert rules from this TOML file
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+
         /// Monitor only devices of a specific type
         #[arg(long, value_name = "TYPE")]
         device_type: Option<DeviceFilterArg>,
@@ -179,6 +369,440 @@ pub enum Commands {
         /// Skip mDNS discovery (only with --discover)
         #[arg(long)]
         no_mdns: bool,
+
+        /// Don't record statistics to the history store
+        #[arg(long)]
+        no_history: bool,
+
+        /// Raise fans and step down frequency when a device reaches this temperature (celsius)
+        #[arg(long, value_name = "TEMP")]
+        thermal_guard: Option<f64>,
+
+        /// Restore original settings once cooled to this temperature (default: guard temperature - 5)
+        #[arg(long, value_name = "TEMP", requires = "thermal_guard")]
+        guard_clear_temp: Option<f64>,
+
+        /// Fan speed percentage the thermal guard switches to
+        #[arg(long, default_value = "100", value_parser = clap::value_parser!(u8).range(0..=100))]
+        guard_fan_speed: u8,
+
+        /// Frequency reduction per thermal guard step in MHz
+        #[arg(long, default_value = "25")]
+        guard_freq_step: u32,
+
+        /// Lowest frequency the thermal guard steps down to in MHz
+        #[arg(long, default_value = "400")]
+        guard_min_freq: u32,
+
+        /// Seconds to wait between thermal guard actions on a device
+        #[arg(long, default_value = "60")]
+        guard_cooldown: u64,
+
+        /// Deliver alerts to the sinks listed in this TOML file (webhook, ntfy, gotify, smtp, exec)
+        #[arg(long, value_name = "FILE")]
+        notify: Option<PathBuf>,
+
+        /// Electricity price per kWh, or a tariff TOML file with time-of-use periods
+        #[arg(long, value_name = "PRICE|FILE")]
+        tariff: Option<String>,
+
+        /// Network difficulty, for alerts on shares close to finding a block
+        #[arg(long, value_name = "DIFFICULTY", requires = "near_block")]
+        network_difficulty: Option<f64>,
+
+        /// Alert when a share reaches this percentage of the network difficulty
+        #[arg(long, value_name = "PERCENT", requires = "network_difficulty")]
+        near_block: Option<f64>,
+    },
+
+    /// Show the hardware profiles settings are checked against
+    Hardware,
+
+    /// Rank devices by the best share difficulty they have found
+    Leaderboard {
+        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-supra, nerdqaxe, bitaxe, all)
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Network difficulty, to show each best as a percentage of it
+        #[arg(long, value_name = "DIFFICULTY")]
+        network_difficulty: Option<f64>,
+    },
+
+    /// Query recorded statistics history
+    History {
+        /// Device name or IP (all devices with history if omitted)
+        device: Option<String>,
+
+        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-supra, nerdqaxe, bitaxe, all)
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Show history covering this span up to --to or now (e.g., 30m, 6h, 7d)
+        #[arg(long, conflicts_with = "from")]
+        since: Option<String>,
+
+        /// Start of the time range (RFC 3339 timestamp or relative, e.g. 2d)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// End of the time range (RFC 3339 timestamp or relative, defaults to now)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Metric to report (hashrate, temperature, power, fan, shares-accepted,
+        /// shares-rejected, voltage, frequency, rssi, hashrate-health)
+        #[arg(long, default_value = "hashrate")]
+        metric: HistoryMetric,
+    },
+
+    /// Report energy use and electricity cost per day from the recorded history
+    Energy {
+        /// Device name or IP (all devices with history if omitted)
+        device: Option<String>,
+
+        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-supra, nerdqaxe, bitaxe, all)
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Report over this span up to --to or now (e.g., 24h, 7d)
+        #[arg(long, conflicts_with = "from")]
+        since: Option<String>,
+
+        /// Start of the time range (RFC 3339 timestamp or relative, e.g. 2d)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// End of the time range (RFC 3339 timestamp or relative, defaults to now)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Electricity price per kWh, or a tariff TOML file with time-of-use periods
+        #[arg(long, value_name = "PRICE|FILE")]
+        tariff: Option<String>,
+    },
+
+    /// Estimate mining revenue, power cost and solo odds per device and fleet
+    Profit {
+        /// Device name or IP (all online devices if omitted)
+        device: Option<String>,
+
+        /// Filter devices by type (e.g., bitaxe-ultra, bitaxe-supra, nerdqaxe, bitaxe, all)
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Network difficulty
+        #[arg(long)]
+        difficulty: Option<f64>,
+
+        /// Block subsidy in BTC [default: 3.125]
+        #[arg(long)]
+        block_reward: Option<f64>,
+
+        /// Average transaction fees per block in BTC [default: 0]
+        #[arg(long)]
+        fees: Option<f64>,
+
+        /// BTC price, in the currency of the tariff
+        #[arg(long)]
+        price: Option<f64>,
+
+        /// TOML file with static values and HTTP sources for difficulty, fees and price
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Don't fetch market data from the sources in the config file
+        #[arg(long)]
+        offline: bool,
+
+        /// Electricity price per kWh, or a tariff TOML file with time-of-use periods
+        #[arg(long, value_name = "PRICE|FILE")]
+        tariff: Option<String>,
+    },
+
+    /// Poll devices continuously and serve their state over a local HTTP API
+    Daemon {
+        /// Address to serve the API on
+        #[arg(long, default_value = DEFAULT_DAEMON_LISTEN)]
+        listen: SocketAddr,
+
+        /// Polling interval in seconds
+        #[arg(long, default_value = "15")]
+        interval: u64,
+
+        /// Poll only devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Keep polling offline devices
+        #[arg(long)]
+        all: bool,
+
+        /// Enable background network discovery
+        #[arg(long)]
+        discover: bool,
+
+        /// Discovery interval in seconds (with --discover)
+        #[arg(long, default_value = "300")]
+        discover_interval: u64,
+
+        /// Network range to scan (auto-detected if not specified, only with --discover)
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Skip mDNS discovery (only with --discover)
+        #[arg(long)]
+        no_mdns: bool,
+
+        /// Don't record statistics to the history store
+        #[arg(long)]
+        no_history: bool,
+
+        /// Load alert rules from this TOML file
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+
+        /// Deliver alerts to the sinks listed in this TOML file (webhook, ntfy, gotify, smtp, exec)
+        #[arg(long, value_name = "FILE")]
+        notify: Option<PathBuf>,
+
+        /// Disable the control endpoints
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Publish device statistics to an MQTT broker, with Home Assistant discovery
+    Mqtt {
+        /// Broker address: host[:port], mqtt://host[:port] or mqtts://host[:port]
+        #[arg(long)]
+        broker: Broker,
+
+        /// Broker username
+        #[arg(long)]
+        username: Option<String>,
+
+        /// Broker password
+        #[arg(long, requires = "username")]
+        password: Option<String>,
+
+        /// MQTT client ID
+        #[arg(long, default_value = "axectl")]
+        client_id: String,
+
+        /// Topic prefix for device state and commands
+        #[arg(long, default_value = DEFAULT_BASE_TOPIC)]
+        base_topic: String,
+
+        /// Home Assistant discovery prefix
+        #[arg(long, default_value = DEFAULT_DISCOVERY_PREFIX)]
+        discovery_prefix: String,
+
+        /// Don't publish Home Assistant discovery configs
+        #[arg(long)]
+        no_discovery: bool,
+
+        /// Ignore command topics (fan speed, frequency, restart)
+        #[arg(long)]
+        read_only: bool,
+
+        /// Polling interval in seconds
+        #[arg(long, default_value = "15")]
+        interval: u64,
+
+        /// Publish only devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Keep polling offline devices
+        #[arg(long)]
+        all: bool,
+
+        /// Enable background network discovery
+        #[arg(long)]
+        discover: bool,
+
+        /// Discovery interval in seconds (with --discover)
+        #[arg(long, default_value = "300")]
+        discover_interval: u64,
+
+        /// Network range to scan (auto-detected if not specified, only with --discover)
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Skip mDNS discovery (only with --discover)
+        #[arg(long)]
+        no_mdns: bool,
+
+        /// Don't record statistics to the history store
+        #[arg(long)]
+        no_history: bool,
+    },
+
+    /// Serve device and fleet metrics for Prometheus
+    Exporter {
+        /// Address to serve /metrics on
+        #[arg(long, default_value = "0.0.0.0:9184")]
+        listen: SocketAddr,
+
+        /// Polling interval in seconds
+        #[arg(long, default_value = "15")]
+        interval: u64,
+
+        /// Export only devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Include offline devices from the cache
+        #[arg(long)]
+        all: bool,
+
+        /// Enable background network discovery
+        #[arg(long)]
+        discover: bool,
+
+        /// Discovery interval in seconds (with --discover)
+        #[arg(long, default_value = "60")]
+        discover_interval: u64,
+
+        /// Network range to scan (auto-detected if not specified, only with --discover)
+        #[arg(long)]
+        network: Option<String>,
+
+        /// Skip mDNS discovery (only with --discover)
+        #[arg(long)]
+        no_mdns: bool,
+
+        /// Don't record statistics to the history store
+        #[arg(long)]
+        no_history: bool,
+    },
+
+    /// Show how live device settings drift from a fleet file
+    Diff {
+        /// Fleet file (TOML) declaring the desired settings
+        file: PathBuf,
+
+        /// Only compare devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+    },
+
+    /// Update devices so their settings match a fleet file
+    Apply {
+        /// Fleet file (TOML) declaring the desired settings
+        file: PathBuf,
+
+        /// Only update devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Show the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Skip confirmation prompt
+        #[arg(long)]
+        force: bool,
+
+        /// Allow frequency and voltage outside each device's safe range
+        #[arg(long = "unsafe")]
+        allow_unsafe: bool,
+    },
+
+    /// Save device configuration snapshots to the cache directory
+    Backup {
+        /// Device name or IP (all online devices if omitted)
+        device: Option<String>,
+
+        /// Only back up devices of a specific type
+        #[arg(long, value_name = "TYPE", conflicts_with = "device")]
+        device_type: Option<DeviceFilterArg>,
+    },
+
+    /// Restore a device configuration from a snapshot
+    Restore {
+        /// Device name or IP
+        device: String,
+
+        /// Snapshot ID to restore (latest if omitted)
+        #[arg(long)]
+        snapshot: Option<String>,
+
+        /// List available snapshots instead of restoring
+        #[arg(long, conflicts_with = "snapshot")]
+        list: bool,
+
+        /// Skip confirmation prompt
+        #[arg(long)]
+        force: bool,
+
+        /// Allow frequency and voltage outside the device's safe range
+        #[arg(long = "unsafe")]
+        allow_unsafe: bool,
+    },
+
+    /// Find the best frequency/voltage pair for a device by sweeping a grid
+    Tune {
+        /// Device name or IP
+        device: String,
+
+        /// Frequencies to try in MHz, as start:end:step or a comma separated list
+        #[arg(long)]
+        frequencies: String,
+
+        /// Core voltages to try in mV, as start:end:step or a comma separated list
+        #[arg(long)]
+        voltages: String,
+
+        /// Seconds to let the device stabilize after changing settings
+        #[arg(long, default_value = "120")]
+        settle: u64,
+
+        /// Number of stats samples averaged per point
+        #[arg(long, default_value = "5")]
+        samples: usize,
+
+        /// Seconds between stats samples
+        #[arg(long, default_value = "10")]
+        sample_interval: u64,
+
+        /// Reject points that reach this ASIC temperature (celsius)
+        #[arg(long, default_value = "65")]
+        max_temp: f64,
+
+        /// Reject points that reach this power draw (watts)
+        #[arg(long)]
+        max_power: Option<f64>,
+
+        /// Optimize for efficiency (lowest J/TH) or hashrate
+        #[arg(long, default_value = "efficiency")]
+        goal: TuneGoal,
+
+        /// Apply the best point when done instead of restoring the original settings
+        #[arg(long)]
+        apply: bool,
+
+        /// Restart the device after each settings change
+        #[arg(long)]
+        restart: bool,
+
+        /// Discard a saved session instead of resuming it
+        #[arg(long)]
+        fresh: bool,
+
+        /// Skip confirmation prompt
+        #[arg(long)]
+        force: bool,
+
+        /// Allow grid points outside the device's safe frequency and voltage range
+        #[arg(long = "unsafe")]
+        allow_unsafe: bool,
+    },
+
+    /// Check the stratum pools the devices mine on
+    Pool {
+        #[command(subcommand)]
+        action: PoolAction,
     },
 
     /// Bulk operations on groups of devices
@@ -200,10 +824,25 @@ pub enum ControlAction {
     /// Restart the device
     Restart,
 
+    /// Make the device identify itself (blinks its LED and shows a message on its display)
+    Identify,
+
     /// Update system settings
     UpdateSettings {
         /// JSON string with settings to update
         settings: String,
+        /// Allow frequency and voltage outside the device's safe range
+        #[arg(long = "unsafe")]
+        allow_unsafe: bool,
+    },
+
+    /// Change one setting, validated against the device's hardware profile
+    Set {
+        #[command(subcommand)]
+        setting: SetAction,
+        /// Allow frequency and voltage outside the device's safe range
+        #[arg(long = "unsafe", global = true)]
+        allow_unsafe: bool,
     },
 
     /// Scan for WiFi networks
@@ -211,20 +850,44 @@ pub enum ControlAction {
 
     /// Update firmware via OTA
     UpdateFirmware {
-        /// Firmware URL or file path
+        /// Firmware URL or file path (local files are served to the device over HTTP)
         firmware: String,
+        /// Flash even if the image fails validation or does not match the device
+        #[arg(long)]
+        force: bool,
     },
 
     /// Update AxeOS web interface
     UpdateAxeOs {
-        /// AxeOS update URL or file path
+        /// AxeOS update URL or file path (local files are served to the device over HTTP)
         axeos: String,
+        /// Flash even if the image fails validation or does not match the device
+        #[arg(long)]
+        force: bool,
     },
 
     /// Show current device configuration
     ShowConfig,
 }
 
+#[derive(Subcommand)]
+pub enum PoolAction {
+    /// Connect to each distinct pool, subscribe and authorize every device's worker
+    Check {
+        /// Only check the pools of devices of a specific type
+        #[arg(long, value_name = "TYPE")]
+        device_type: Option<DeviceFilterArg>,
+
+        /// Worker password sent with mining.authorize
+        #[arg(long, default_value = DEFAULT_POOL_PASSWORD)]
+        password: String,
+
+        /// Seconds allowed for each pool check
+        #[arg(long, default_value = "10")]
+        timeout: u64,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum BulkAction {
     /// Restart selected devices
@@ -277,6 +940,36 @@ pub enum BulkAction {
         /// Skip confirmation prompt
         #[arg(long)]
         force: bool,
+        /// Check that a new pool is reachable and accepts each worker before pushing it
+        #[arg(long)]
+        check_pool: bool,
+        /// Allow frequency and voltage outside each device's safe range
+        #[arg(long = "unsafe")]
+        allow_unsafe: bool,
+    },
+
+    /// Change one setting on selected devices, validated against each device's hardware profile
+    Set {
+        #[command(subcommand)]
+        setting: SetAction,
+        /// Filter by device type (can be specified multiple times)
+        #[arg(long = "device-type", value_name = "TYPE", global = true)]
+        device_types: Vec<DeviceType>,
+        /// Target specific IP addresses (can be specified multiple times)
+        #[arg(long = "ip-address", value_name = "IP", global = true)]
+        ip_addresses: Vec<String>,
+        /// Target all devices
+        #[arg(long, global = true)]
+        all: bool,
+        /// Skip confirmation prompt
+        #[arg(long, global = true)]
+        force: bool,
+        /// Check that a new pool is reachable and accepts each worker before pushing it
+        #[arg(long, global = true)]
+        check_pool: bool,
+        /// Allow frequency and voltage outside each device's safe range
+        #[arg(long = "unsafe", global = true)]
+        allow_unsafe: bool,
     },
 
     /// Scan WiFi on selected devices
@@ -311,6 +1004,45 @@ pub enum BulkAction {
         /// Maximum parallel operations
         #[arg(long, default_value = "5")]
         parallel: usize,
+        /// Flash even if the image fails validation or does not match a device
+        #[arg(long)]
+        force_image: bool,
+    },
+
+    /// Update firmware in stages: a canary first, then waves gated on device health
+    RolloutFirmware {
+        /// Firmware URL or file path
+        firmware: String,
+        /// Filter by device type (can be specified multiple times)
+        #[arg(long = "device-type", value_name = "TYPE")]
+        device_types: Vec<DeviceType>,
+        /// Target specific IP addresses (can be specified multiple times)
+        #[arg(long = "ip-address", value_name = "IP")]
+        ip_addresses: Vec<String>,
+        /// Target all devices
+        #[arg(long)]
+        all: bool,
+        /// Skip confirmation prompt
+        #[arg(long)]
+        force: bool,
+        /// Number of devices updated first as canaries
+        #[arg(long, default_value = "1")]
+        canary: usize,
+        /// Number of devices updated in parallel in each following wave
+        #[arg(long, default_value = "5")]
+        wave_size: usize,
+        /// Seconds to wait for a device to reboot into the new firmware
+        #[arg(long, default_value = "300")]
+        reboot_timeout: u64,
+        /// Seconds to wait for the hashrate to recover after the reboot
+        #[arg(long, default_value = "600")]
+        recovery_timeout: u64,
+        /// Minimum hashrate after the update, as a percentage of the hashrate before it
+        #[arg(long, default_value = "80")]
+        min_hashrate_percent: f64,
+        /// Flash even if the image fails validation or does not match a device
+        #[arg(long)]
+        force_image: bool,
     },
 
     /// Update AxeOS on selected devices
@@ -332,6 +1064,9 @@ pub enum BulkAction {
         /// Maximum parallel operations
         #[arg(long, default_value = "5")]
         parallel: usize,
+        /// Flash even if the image fails validation or does not match a device
+        #[arg(long)]
+        force_image: bool,
     },
 
     /// Show configuration for selected devices
@@ -363,6 +1098,9 @@ pub enum BulkAction {
         /// Skip confirmation prompt
         #[arg(long)]
         force: bool,
+        /// Check that each device's pool accepts the new worker before pushing it
+        #[arg(long)]
+        check_pool: bool,
     },
 }
 
@@ -405,7 +1143,18 @@ impl Cli {
                 temp_alert,
                 hashrate_alert,
                 type_summary,
+                no_history,
+                tariff,
             } => {
+                let mut alert_rules = AlertRules::default();
+                let shorthands = [
+                    temp_alert.map(AlertRule::temperature_above),
+                    hashrate_alert.map(AlertRule::hashrate_drop),
+                ];
+                for rule in shorthands.into_iter().flatten() {
+                    alert_rules.add(rule)?;
+                }
+
                 let args = handlers::ListArgs {
                     all,
                     no_stats,
@@ -416,9 +1165,11 @@ impl Cli {
                     timeout,
                     no_mdns,
                     device_type,
-                    temp_alert,
-                    hashrate_alert,
+                    alert_rules,
                     type_summary,
+                    no_history,
+                    tariff: tariff.as_deref().map(Tariff::from_arg).transpose()?,
+                    daemon: self.daemon.as_deref().map(DaemonClient::new).transpose()?,
                     format: self.format,
                     color: !self.no_color,
                     cache_dir: self.cache_dir.as_deref(),
@@ -432,13 +1183,18 @@ impl Cli {
                     self.format,
                     !self.no_color,
                     self.cache_dir.as_deref(),
+                    self.daemon.as_deref(),
                 )
                 .await
             }
             Commands::Monitor {
                 interval,
+                tui,
+                stream,
                 temp_alert,
                 hashrate_alert,
+                health_alert,
+                rules,
                 device_type,
                 type_summary,
                 all,
@@ -447,11 +1203,66 @@ impl Cli {
                 discover_interval,
                 network,
                 no_mdns,
+                no_history,
+                thermal_guard,
+                guard_clear_temp,
+                guard_fan_speed,
+                guard_freq_step,
+                guard_min_freq,
+                guard_cooldown,
+                notify,
+                tariff,
+                network_difficulty,
+                near_block,
             } => {
-                handlers::monitor_async(handlers::monitor_async::AsyncMonitorConfig {
+                let near_block = match (network_difficulty, near_block) {
+                    (Some(network_difficulty), Some(percent)) => {
+                        ensure!(
+                            network_difficulty > 0.0,
+                            "--network-difficulty must be positive"
+                        );
+                        ensure!(
+                            percent > 0.0 && percent <= 100.0,
+                            "--near-block must be a percentage between 0 and 100"
+                        );
+                        Some(NearBlockConfig {
+                            network_difficulty,
+                            percent,
+                        })
+                    }
+                    _ => None,
+                };
+                let thermal_guard = thermal_guard.map(|trigger_temp| ThermalGuardConfig {
+                    trigger_temp,
+                    clear_temp: guard_clear_temp.unwrap_or(trigger_temp - 5.0),
+                    fan_speed: guard_fan_speed,
+                    frequency_step: guard_freq_step,
+                    min_frequency: guard_min_freq,
+                    cooldown: Duration::from_secs(guard_cooldown),
+                });
+                if let Some(ref guard) = thermal_guard {
+                    ensure!(
+                        guard.clear_temp < guard.trigger_temp,
+                        "--guard-clear-temp must be below the --thermal-guard temperature"
+                    );
+                }
+
+                let mut alert_rules = match rules {
+                    Some(ref path) => RulesConfig::load(path)?,
+                    None => AlertRules::default(),
+                };
+                let shorthands = [
+                    temp_alert.map(AlertRule::temperature_above),
+                    hashrate_alert.map(AlertRule::hashrate_drop),
+                    health_alert.map(AlertRule::hashrate_health_below),
+                ];
+                for rule in shorthands.into_iter().flatten() {
+                    alert_rules.add(rule)?;
+                }
+
+                let config = handlers::monitor_async::AsyncMonitorConfig {
                     interval,
-                    temp_alert,
-                    hashrate_alert,
+                    alert_rules,
                     type_filter: device_type,
                     type_summary,
                     format: self.format,
@@ -463,6 +1274,321 @@ impl Cli {
                     discover_interval,
                     network,
                     no_mdns,
+                    no_history,
+                    thermal_guard,
+                    notify: notify.as_deref().map(NotifyConfig::load).transpose()?,
+                    tariff: tariff.as_deref().map(Tariff::from_arg).transpose()?,
+                    near_block,
+                };
+                if tui {
+                    handlers::monitor_tui(config).await
+                } else if stream {
+                    handlers::monitor_stream(config).await
+                } else {
+                    handlers::monitor_async(config).await
+                }
+            }
+            Commands::Hardware => handlers::hardware(self.format, !self.no_color).await,
+            Commands::Leaderboard {
+                device_type,
+                network_difficulty,
+            } => {
+                handlers::leaderboard(handlers::LeaderboardArgs {
+                    device_type,
+                    network_difficulty,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::History {
+                device,
+                device_type,
+                since,
+                from,
+                to,
+                metric,
+            } => {
+                handlers::history(handlers::HistoryArgs {
+                    device,
+                    device_type,
+                    since,
+                    from,
+                    to,
+                    metric,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Energy {
+                device,
+                device_type,
+                since,
+                from,
+                to,
+                tariff,
+            } => {
+                handlers::energy(handlers::EnergyArgs {
+                    device,
+                    device_type,
+                    since,
+                    from,
+                    to,
+                    tariff: tariff.as_deref().map(Tariff::from_arg).transpose()?,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Profit {
+                device,
+                device_type,
+                difficulty,
+                block_reward,
+                fees,
+                price,
+                config,
+                offline,
+                tariff,
+            } => {
+                handlers::profit(handlers::ProfitArgs {
+                    device,
+                    device_type,
+                    inputs: MarketInputs {
+                        difficulty,
+                        block_reward,
+                        fees,
+                        price,
+                    },
+                    config,
+                    offline,
+                    tariff: tariff.as_deref().map(Tariff::from_arg).transpose()?,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Daemon {
+                listen,
+                interval,
+                device_type,
+                all,
+                discover,
+                discover_interval,
+                network,
+                no_mdns,
+                no_history,
+                rules,
+                notify,
+                read_only,
+            } => {
+                handlers::daemon(handlers::DaemonArgs {
+                    listen,
+                    interval,
+                    type_filter: device_type,
+                    all,
+                    discover,
+                    discover_interval,
+                    network,
+                    no_mdns,
+                    no_history,
+                    alert_rules: match rules {
+                        Some(ref path) => RulesConfig::load(path)?,
+                        None => AlertRules::default(),
+                    },
+                    notify: notify.as_deref().map(NotifyConfig::load).transpose()?,
+                    read_only,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Mqtt {
+                broker,
+                username,
+                password,
+                client_id,
+                base_topic,
+                discovery_prefix,
+                no_discovery,
+                read_only,
+                interval,
+                device_type,
+                all,
+                discover,
+                discover_interval,
+                network,
+                no_mdns,
+                no_history,
+            } => {
+                handlers::mqtt(handlers::MqttArgs {
+                    broker,
+                    username,
+                    password,
+                    client_id,
+                    base_topic,
+                    discovery_prefix,
+                    no_discovery,
+                    read_only,
+                    interval,
+                    type_filter: device_type,
+                    all,
+                    discover,
+                    discover_interval,
+                    network,
+                    no_mdns,
+                    no_history,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Exporter {
+                listen,
+                interval,
+                device_type,
+                all,
+                discover,
+                discover_interval,
+                network,
+                no_mdns,
+                no_history,
+            } => {
+                handlers::exporter(handlers::ExporterArgs {
+                    listen,
+                    interval,
+                    type_filter: device_type,
+                    all,
+                    discover,
+                    discover_interval,
+                    network,
+                    no_mdns,
+                    no_history,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Diff { file, device_type } => {
+                handlers::diff(handlers::FleetArgs {
+                    file,
+                    device_type,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Apply {
+                file,
+                device_type,
+                dry_run,
+                force,
+                allow_unsafe,
+            } => {
+                handlers::apply(
+                    handlers::FleetArgs {
+                        file,
+                        device_type,
+                        format: self.format,
+                        color: !self.no_color,
+                        cache_dir: self.cache_dir.as_deref(),
+                    },
+                    dry_run,
+                    force,
+                    allow_unsafe,
+                )
+                .await
+            }
+            Commands::Backup {
+                device,
+                device_type,
+            } => {
+                handlers::backup(handlers::BackupArgs {
+                    device,
+                    device_type,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Tune {
+                device,
+                frequencies,
+                voltages,
+                settle,
+                samples,
+                sample_interval,
+                max_temp,
+                max_power,
+                goal,
+                apply,
+                restart,
+                fresh,
+                force,
+                allow_unsafe,
+            } => {
+                handlers::tune(handlers::TuneArgs {
+                    device,
+                    frequencies,
+                    voltages,
+                    settle,
+                    samples,
+                    sample_interval,
+                    max_temp,
+                    max_power,
+                    goal,
+                    apply,
+                    restart,
+                    fresh,
+                    force,
+                    allow_unsafe,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Restore {
+                device,
+                snapshot,
+                list,
+                force,
+                allow_unsafe,
+            } => {
+                handlers::restore(handlers::RestoreArgs {
+                    device,
+                    snapshot,
+                    list,
+                    force,
+                    allow_unsafe,
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
+                })
+                .await
+            }
+            Commands::Pool {
+                action:
+                    PoolAction::Check {
+                        device_type,
+                        password,
+                        timeout,
+                    },
+            } => {
+                handlers::pool_check(handlers::PoolCheckArgs {
+                    device_type,
+                    password,
+                    timeout: Duration::from_secs(timeout),
+                    format: self.format,
+                    color: !self.no_color,
+                    cache_dir: self.cache_dir.as_deref(),
                 })
                 .await
             }
@@ -0,0 +1,581 @@
+use crate::api::{AxeOsClient, Device, DeviceStatus};
+use crate::backup::{BackupStore, ConfigSnapshot};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::output::{
+    format_table, print_error, print_info, print_json, print_success, print_warning,
+};
+use crate::settings::SettingsPolicy;
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use futures::future::join_all;
+use std::io::{self, Write};
+use std::path::Path;
+use tabled::Tabled;
+
+/// Arguments for the backup command
+pub struct BackupArgs<'a> {
+    pub device: Option<String>,
+    pub device_type: Option<DeviceFilterArg>,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+/// Arguments for the restore command
+pub struct RestoreArgs<'a> {
+    pub device: String,
+    pub snapshot: Option<String>,
+    pub list: bool,
+    pub force: bool,
+    /// Allow frequency and voltage outside the device's safe range
+    pub allow_unsafe: bool,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct SnapshotTableRow {
+    #[tabled(rename = "Snapshot")]
+    id: String,
+    #[tabled(rename = "Created")]
+    created_at: String,
+    #[tabled(rename = "Hostname")]
+    hostname: String,
+    #[tabled(rename = "Pool")]
+    pool: String,
+    #[tabled(rename = "Frequency")]
+    frequency: String,
+    #[tabled(rename = "Voltage")]
+    voltage: String,
+    #[tabled(rename = "Fan")]
+    fan: String,
+}
+
+pub async fn backup(args: BackupArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+    let cache = DeviceCache::load(cache_path)?;
+    let store = BackupStore::open(cache_path);
+
+    let mut devices = match args.device {
+        Some(ref identifier) => vec![
+            cache
+                .find_device(identifier)
+                .with_context(|| format!("Device not found: {identifier}"))?,
+        ],
+        None => match args.device_type {
+            Some(filter) => cache.get_online_devices_by_filter(filter.0),
+            None => cache.get_devices_by_status(DeviceStatus::Online),
+        },
+    };
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    if devices.is_empty() {
+        match args.format {
+            OutputFormat::Json => {
+                let output = serde_json::json!({
+                    "success": false,
+                    "error": "No devices to back up",
+                    "message": "Run 'axectl discover' first to find devices",
+                    "timestamp": Utc::now()
+                });
+                print_json(&output, true)?;
+            }
+            OutputFormat::Text => {
+                print_warning(
+                    "No online devices to back up. Run 'axectl discover' first to find devices.",
+                    args.color,
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let snapshots = join_all(devices.iter().map(|device| take_snapshot(device, now))).await;
+
+    let mut results = Vec::new();
+    for (device, snapshot) in devices.iter().zip(snapshots) {
+        let saved = snapshot.and_then(|snapshot| {
+            let path = store.save(&snapshot)?;
+            Ok((snapshot.id, path))
+        });
+
+        match saved {
+            Ok((id, path)) => {
+                if args.format == OutputFormat::Text {
+                    print_success(
+                        &format!("✓ {name} saved as snapshot {id}", name = device.name),
+                        args.color,
+                    );
+                }
+                results.push(serde_json::json!({
+                    "device": device.name,
+                    "ip": device.ip_address,
+                    "success": true,
+                    "snapshot": id,
+                    "path": path,
+                    "error": null,
+                }));
+            }
+            Err(e) => {
+                if args.format == OutputFormat::Text {
+                    print_error(
+                        &format!("✗ {name} failed: {e:#}", name = device.name),
+                        args.color,
+                    );
+                }
+                results.push(serde_json::json!({
+                    "device": device.name,
+                    "ip": device.ip_address,
+                    "success": false,
+                    "snapshot": null,
+                    "path": null,
+                    "error": format!("{e:#}"),
+                }));
+            }
+        }
+    }
+
+    match args.format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "action": "backup",
+                "total_devices": devices.len(),
+                "results": results,
+                "timestamp": now,
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            print_info(
+                &format!(
+                    "Snapshots are stored in {path}",
+                    path = store.root().display()
+                ),
+                args.color,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn restore(args: RestoreArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+    let cache = DeviceCache::load(cache_path)?;
+    let store = BackupStore::open(cache_path);
+
+    // Devices that dropped out of the cache can still be restored by IP
+    let (name, ip_address) = match cache.find_device(&args.device) {
+        Some(device) => (device.name, device.ip_address),
+        None if !store.list(&args.device)?.is_empty() => (args.device.clone(), args.device.clone()),
+        None => bail!("Device not found: {device}", device = args.device),
+    };
+
+    if args.list {
+        return print_snapshots(&store.list(&ip_address)?, &name, args.format, args.color);
+    }
+
+    let snapshot = match args.snapshot {
+        Some(ref id) => store.load(&ip_address, id)?,
+        None => store
+            .latest(&ip_address)?
+            .with_context(|| format!("No snapshots found for {name}, run 'axectl backup' first"))?,
+    };
+
+    // A snapshot may predate the hardware profile or come from another board
+    let client = AxeOsClient::new(&ip_address)?;
+    let request = snapshot.to_update_request();
+    SettingsPolicy::load(args.allow_unsafe)?
+        .ensure_supported(&client, &request)
+        .await
+        .with_context(|| format!("Refusing to restore snapshot {id}", id = snapshot.id))?;
+
+    if !args.force && args.format == OutputFormat::Text {
+        print_info(
+            &format!(
+                "About to restore snapshot {id} ({created}) to {name} at {ip_address}:",
+                id = snapshot.id,
+                created = snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            args.color,
+        );
+        println!(
+            "{}",
+            format_table(vec![snapshot_row(&snapshot)], args.color)
+        );
+
+        eprint!("Continue? [y/N]: ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            print_info("Operation cancelled.", args.color);
+            return Ok(());
+        }
+    }
+
+    let result = client.update_system(request).await?;
+
+    match args.format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "action": "restore",
+                "device": name,
+                "ip": ip_address,
+                "snapshot": snapshot.id,
+                "success": result.success,
+                "message": result.message,
+                "timestamp": Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if result.success {
+                print_success(
+                    &format!("✓ {name} restored from snapshot {id}", id = snapshot.id),
+                    args.color,
+                );
+                print_info(
+                    "Restart the device to apply pool and ASIC settings",
+                    args.color,
+                );
+            } else {
+                print_error(
+                    &format!("✗ {name} failed: {msg}", msg = result.message),
+                    args.color,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the full configuration of a device
+async fn take_snapshot(
+    device: &Device,
+    created_at: chrono::DateTime<Utc>,
+) -> Result<ConfigSnapshot> {
+    let client = AxeOsClient::new(&device.ip_address)?;
+    let (settings, raw) = client.get_system_info_with_raw().await?;
+    Ok(ConfigSnapshot::new(device, settings, raw, created_at))
+}
+
+fn print_snapshots(
+    snapshots: &[ConfigSnapshot],
+    name: &str,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "device": name,
+                "snapshots": snapshots,
+                "timestamp": Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if snapshots.is_empty() {
+                print_warning(&format!("No snapshots found for {name}"), color);
+                return Ok(());
+            }
+
+            // Newest first
+            let rows: Vec<_> = snapshots.iter().rev().map(snapshot_row).collect();
+            println!("{}", format_table(rows, color));
+        }
+    }
+
+    Ok(())
+}
+
+fn snapshot_row(snapshot: &ConfigSnapshot) -> SnapshotTableRow {
+    let settings = &snapshot.settings;
+    SnapshotTableRow {
+        id: snapshot.id.clone(),
+        created_at: snapshot.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        hostname: settings.hostname.clone(),
+        pool: format!(
+            "{url}:{port} ({user})",
+            url = settings.pool_url,
+            port = settings.pool_port,
+            user = settings.pool_user
+        ),
+        frequency: format!("{freq} MHz", freq = settings.frequency),
+        voltage: format!("{volt} mV", volt = settings.voltage),
+        fan: match settings.auto_fan_speed {
+            Some(true) => "auto".to_string(),
+            _ => format!("{speed}%", speed = settings.fanspeed),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceType;
+    use mockito::Matcher;
+    use std::sync::{Arc, Mutex};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_backup_then_restore_latest_snapshot() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let info_mock = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "ASICModel": "BM1370",
+                "boardVersion": "601",
+                "version": "2.4.0",
+                "macAddr": "AA:BB:CC:DD:EE:FF",
+                "hostname": "bitaxe-1",
+                "stratumURL": "public-pool.io",
+                "stratumPort": 21496,
+                "stratumUser": "bc1qexample.bitaxe-1",
+                "frequency": 525,
+                "voltage": 1150,
+                "fanspeed": 100,
+                "autofanspeed": 1,
+                "temp": 55.0,
+                "power": 15.0,
+                "hashRate": 1100.0,
+                "uptimeSeconds": 3600,
+                "sharesAccepted": 10,
+                "sharesRejected": 0
+            }"#,
+            )
+            .create_async()
+            .await;
+        let patch_mock = server
+            .mock("PATCH", "/api/system")
+            .match_body(Matcher::Json(serde_json::json!({
+                "hostname": "bitaxe-1",
+                "poolurl": "public-pool.io",
+                "poolport": 21496,
+                "pooluser": "bc1qexample.bitaxe-1",
+                "frequencyvalue": 525,
+                "voltagevalue": 1150.0,
+                "autofanspeed": 1,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let tempdir = tempdir()?;
+        let device_addr = server.host_with_port();
+        let mut cache = DeviceCache::new();
+        cache.add_device(Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: device_addr.clone(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        });
+        cache.save(tempdir.path())?;
+
+        backup(BackupArgs {
+            device: None,
+            device_type: None,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        })
+        .await?;
+        info_mock.assert_async().await;
+
+        let snapshots = BackupStore::open(tempdir.path()).list(&device_addr)?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].raw["autofanspeed"], 1);
+
+        restore(RestoreArgs {
+            device: "bitaxe-1".to_string(),
+            snapshot: None,
+            list: false,
+            force: true,
+            allow_unsafe: false,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        })
+        .await?;
+        patch_mock.assert_async().await;
+        Ok(())
+    }
+
+    /// Mock device whose `/api/system/info` reflects the settings patched into it
+    async fn mock_device(
+        server: &mut mockito::ServerGuard,
+        config: Arc<Mutex<serde_json::Value>>,
+    ) -> Vec<mockito::Mock> {
+        let patched = config.clone();
+        let patch = server
+            .mock("PATCH", "/api/system")
+            .with_status(200)
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value = request
+                    .body()
+                    .ok()
+                    .and_then(|body| serde_json::from_slice(body).ok())
+                    .unwrap_or_default();
+                if let (Some(update), Ok(mut config)) = (body.as_object(), patched.lock()) {
+                    for (key, value) in update {
+                        let field = match key.as_str() {
+                            "poolurl" => "stratumURL",
+                            "poolport" => "stratumPort",
+                            "pooluser" => "stratumUser",
+                            "frequencyvalue" => "frequency",
+                            "voltagevalue" => "voltage",
+                            other => other,
+                        };
+                        config[field] = value.clone();
+                    }
+                }
+                Vec::new()
+            })
+            .create_async()
+            .await;
+        let info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                config
+                    .lock()
+                    .map(|config| config.to_string().into_bytes())
+                    .unwrap_or_default()
+            })
+            .create_async()
+            .await;
+        vec![patch, info]
+    }
+
+    #[tokio::test]
+    async fn test_restore_undoes_changes_since_the_snapshot() -> Result<()> {
+        let config = Arc::new(Mutex::new(serde_json::json!({
+            "ASICModel": "BM1370",
+            "boardVersion": "601",
+            "version": "2.4.0",
+            "macAddr": "AA:BB:CC:DD:EE:FF",
+            "hostname": "bitaxe-1",
+            "stratumURL": "public-pool.io",
+            "stratumPort": 21496,
+            "stratumUser": "bc1qexample.bitaxe-1",
+            "fallbackStratumURL": "solo.ckpool.org",
+            "fallbackStratumPort": 3333,
+            "fallbackStratumUser": "bc1qexample.bitaxe-1",
+            "frequency": 525,
+            "voltage": 1150,
+            "fanspeed": 70,
+            "autofanspeed": 0,
+            "temp": 55.0,
+            "power": 15.0,
+            "hashRate": 1100.0,
+            "uptimeSeconds": 3600,
+            "sharesAccepted": 10,
+            "sharesRejected": 0
+        })));
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server, config.clone()).await;
+
+        let tempdir = tempdir()?;
+        let device_addr = server.host_with_port();
+        let mut cache = DeviceCache::new();
+        cache.add_device(Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: device_addr.clone(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        });
+        cache.save(tempdir.path())?;
+        let backup_args = || BackupArgs {
+            device: None,
+            device_type: None,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        };
+        let store = BackupStore::open(tempdir.path());
+        let restorable = |snapshot: &ConfigSnapshot| {
+            serde_json::to_value(snapshot.to_update_request()).unwrap_or_default()
+        };
+
+        backup(backup_args()).await?;
+        let original = store.latest(&device_addr)?.context("first snapshot")?;
+
+        // Someone retunes the device and moves it to another pool
+        if let Ok(mut config) = config.lock() {
+            config["stratumURL"] = "solo.ckpool.org".into();
+            config["stratumUser"] = "bc1qother.bitaxe-1".into();
+            config["frequency"] = 600.into();
+            config["voltage"] = 1200.into();
+            config["autofanspeed"] = 1.into();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        backup(backup_args()).await?;
+        let changed = store.latest(&device_addr)?.context("second snapshot")?;
+        assert_ne!(restorable(&changed), restorable(&original));
+
+        restore(RestoreArgs {
+            device: "bitaxe-1".to_string(),
+            snapshot: Some(original.id.clone()),
+            list: false,
+            force: true,
+            allow_unsafe: false,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        })
+        .await?;
+
+        // A fresh backup no longer differs from the restored snapshot
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        backup(backup_args()).await?;
+        let restored = store.latest(&device_addr)?.context("third snapshot")?;
+        assert_eq!(store.list(&device_addr)?.len(), 3);
+        assert_eq!(restorable(&restored), restorable(&original));
+        assert_eq!(restored.settings.fanspeed, 70);
+        assert_eq!(restored.settings.auto_fan_speed, Some(false));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_without_snapshots_fails() -> Result<()> {
+        let tempdir = tempdir()?;
+        DeviceCache::new().save(tempdir.path())?;
+
+        let result = restore(RestoreArgs {
+            device: "192.168.1.10".to_string(),
+            snapshot: None,
+            list: false,
+            force: true,
+            allow_unsafe: false,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        })
+        .await;
+
+        assert!(result.is_err());
+        Ok(())
+    }
+}
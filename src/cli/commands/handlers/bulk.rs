@@ -1,13 +1,25 @@
 use crate::api::{
-    AxeOsClient, Device, DeviceFilter, DeviceStatus, DeviceType, SystemUpdateRequest,
+    AxeOsClient, Device, DeviceFilter, DeviceStatus, DeviceType, SystemInfoResponse,
+    SystemUpdateRequest,
 };
 use crate::cache::DeviceCache;
+use crate::cli::commands::handlers::pool::pool_targets;
 use crate::cli::commands::{BulkAction, OutputFormat};
+use crate::firmware::{
+    DOWNLOAD_TIMEOUT, FirmwareImage, FirmwareServer, FirmwareSource, ImageKind, read_image,
+};
 use crate::output::{print_error, print_info, print_json, print_success, print_warning};
-use anyhow::{Context, Result};
+use crate::pool::{
+    DEFAULT_POOL_CHECK_TIMEOUT, DEFAULT_POOL_PASSWORD, PoolEndpoint, ensure_pools_reachable,
+};
+use crate::rollout::{RolloutConfig, RolloutEvent, RolloutStatus, run_rollout};
+use crate::settings::{SettingsPolicy, parse_update_request, validate_settings};
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result, bail};
 use futures::future::join_all;
 use std::io::{self, Write};
 use std::path::Path;
+use std::time::Duration;
 
 pub async fn bulk(
     action: BulkAction,
@@ -15,6 +27,26 @@ pub async fn bulk(
     color: bool,
     cache_dir: Option<&Path>,
 ) -> Result<()> {
+    // Settings changes are checked before any device is looked at
+    let update_request = match &action {
+        BulkAction::UpdateSettings { settings, .. } => Some(parse_update_request(settings)?),
+        BulkAction::Set { setting, .. } => Some(setting.to_update_request()),
+        _ => None,
+    };
+    if let Some(request) = &update_request {
+        validate_settings(request)?;
+    }
+    let allow_unsafe = matches!(
+        action,
+        BulkAction::UpdateSettings {
+            allow_unsafe: true,
+            ..
+        } | BulkAction::Set {
+            allow_unsafe: true,
+            ..
+        }
+    );
+
     // Get cache directory, using default if not provided
     let cache_path = crate::cache::get_cache_dir(cache_dir)?;
     let cache_path_ref = cache_path.as_ref();
@@ -63,6 +95,12 @@ pub async fn bulk(
             all,
             ..
         }
+        | BulkAction::Set {
+            device_types,
+            ip_addresses,
+            all,
+            ..
+        }
         | BulkAction::WifiScan {
             device_types,
             ip_addresses,
@@ -74,6 +112,12 @@ pub async fn bulk(
             all,
             ..
         }
+        | BulkAction::RolloutFirmware {
+            device_types,
+            ip_addresses,
+            all,
+            ..
+        }
         | BulkAction::UpdateAxeOs {
             device_types,
             ip_addresses,
@@ -114,12 +158,21 @@ pub async fn bulk(
         return Ok(());
     }
 
+    if let Some(request) = &update_request {
+        let policy = SettingsPolicy::load(allow_unsafe)?;
+        if policy.needs_device(request) {
+            verify_settings(&target_devices, request, &policy, format, color).await?;
+        }
+    }
+
     // Get confirmation if not forced
     let force = match &action {
         BulkAction::Restart { force, .. }
         | BulkAction::SetFanSpeed { force, .. }
         | BulkAction::UpdateSettings { force, .. }
+        | BulkAction::Set { force, .. }
         | BulkAction::UpdateFirmware { force, .. }
+        | BulkAction::RolloutFirmware { force, .. }
         | BulkAction::UpdateAxeOs { force, .. }
         | BulkAction::UpdateBitcoinAddress { force, .. } => *force,
         BulkAction::WifiScan { .. } | BulkAction::ShowConfig { .. } => true, // WifiScan and ShowConfig don't need confirmation
@@ -161,20 +214,119 @@ pub async fn bulk(
         BulkAction::SetFanSpeed { speed, .. } => {
             execute_set_fan_speed(&target_devices, speed, format, color).await
         }
-        BulkAction::UpdateSettings { settings, .. } => {
-            execute_update_settings(&target_devices, &settings, format, color).await
+        BulkAction::UpdateSettings { check_pool, .. } | BulkAction::Set { check_pool, .. } => {
+            let request = update_request.context("Settings update without a request")?;
+            if check_pool {
+                if request.pool_url.is_none()
+                    && request.pool_port.is_none()
+                    && request.pool_user.is_none()
+                {
+                    if format == OutputFormat::Text {
+                        print_info("Settings don't change the pool, skipping pool check", color);
+                    }
+                } else {
+                    verify_pools(
+                        &target_devices,
+                        |info| {
+                            let url = request.pool_url.as_deref().unwrap_or(&info.pool_url);
+                            let port = request.pool_port.unwrap_or(info.pool_port);
+                            let user = request.pool_user.as_ref().unwrap_or(&info.pool_user);
+                            Ok((PoolEndpoint::parse(url, port)?, user.clone()))
+                        },
+                        format,
+                        color,
+                    )
+                    .await?;
+                }
+            }
+            execute_update_settings(&target_devices, &request, format, color).await
         }
         BulkAction::WifiScan { .. } => execute_wifi_scan(&target_devices, format, color).await,
         BulkAction::UpdateFirmware {
-            firmware, parallel, ..
-        } => execute_update_firmware(&target_devices, &firmware, parallel, format, color).await,
+            firmware,
+            parallel,
+            force_image,
+            ..
+        } => {
+            verify_image(
+                &firmware,
+                ImageKind::Firmware,
+                &target_devices,
+                force_image,
+                format,
+                color,
+            )
+            .await?;
+            execute_update_firmware(&target_devices, &firmware, parallel, format, color).await
+        }
+        BulkAction::RolloutFirmware {
+            firmware,
+            canary,
+            wave_size,
+            reboot_timeout,
+            recovery_timeout,
+            min_hashrate_percent,
+            force_image,
+            ..
+        } => {
+            verify_image(
+                &firmware,
+                ImageKind::Firmware,
+                &target_devices,
+                force_image,
+                format,
+                color,
+            )
+            .await?;
+            let config = RolloutConfig {
+                canary,
+                wave_size,
+                reboot_timeout: Duration::from_secs(reboot_timeout),
+                recovery_timeout: Duration::from_secs(recovery_timeout),
+                min_hashrate_percent,
+                ..Default::default()
+            };
+            execute_rollout_firmware(target_devices, &firmware, &config, format, color).await
+        }
         BulkAction::UpdateAxeOs {
-            axeos, parallel, ..
-        } => execute_update_axeos(&target_devices, &axeos, parallel, format, color).await,
+            axeos,
+            parallel,
+            force_image,
+            ..
+        } => {
+            verify_image(
+                &axeos,
+                ImageKind::Www,
+                &target_devices,
+                force_image,
+                format,
+                color,
+            )
+            .await?;
+            execute_update_axeos(&target_devices, &axeos, parallel, format, color).await
+        }
         BulkAction::ShowConfig { .. } => execute_show_config(&target_devices, format, color).await,
         BulkAction::UpdateBitcoinAddress {
-            bitcoin_address, ..
-        } => execute_update_bitcoin_address(&target_devices, &bitcoin_address, format, color).await,
+            bitcoin_address,
+            check_pool,
+            ..
+        } => {
+            if check_pool {
+                verify_pools(
+                    &target_devices,
+                    |info| {
+                        Ok((
+                            PoolEndpoint::parse(&info.pool_url, info.pool_port)?,
+                            format!("{bitcoin_address}.{hostname}", hostname = info.hostname),
+                        ))
+                    },
+                    format,
+                    color,
+                )
+                .await?;
+            }
+            execute_update_bitcoin_address(&target_devices, &bitcoin_address, format, color).await
+        }
     }
 }
 
@@ -344,14 +496,10 @@ async fn execute_set_fan_speed(
 /// Execute update settings on all target devices
 async fn execute_update_settings(
     devices: &[Device],
-    settings: &str,
+    update_request: &SystemUpdateRequest,
     format: OutputFormat,
     color: bool,
 ) -> Result<()> {
-    // Parse settings JSON
-    let settings_json: serde_json::Value =
-        serde_json::from_str(settings).context("Failed to parse settings JSON")?;
-
     if format == OutputFormat::Text {
         print_info(
             &format!(
@@ -366,10 +514,7 @@ async fn execute_update_settings(
 
     for device in devices {
         let client = AxeOsClient::new(&device.ip_address)?;
-        // Parse settings into SystemUpdateRequest
-        let update_request: SystemUpdateRequest = serde_json::from_value(settings_json.clone())
-            .context("Failed to parse settings into SystemUpdateRequest")?;
-        let result = client.update_system(update_request).await;
+        let result = client.update_system(update_request.clone()).await;
 
         let success = result.is_ok();
         let message = result.as_ref().err().map(|e| e.to_string());
@@ -403,7 +548,7 @@ async fn execute_update_settings(
     if format == OutputFormat::Json {
         let output = serde_json::json!({
             "action": "update_settings",
-            "settings": settings_json,
+            "settings": update_request,
             "total_devices": devices.len(),
             "results": results,
             "timestamp": chrono::Utc::now()
@@ -509,7 +654,13 @@ async fn execute_update_firmware(
         );
     }
 
+    let server = serve_local_file(firmware, devices, format, color).await?;
+    let url = server
+        .as_ref()
+        .map_or_else(|| firmware.to_string(), |server| server.url().to_string());
+
     let mut results = Vec::new();
+    let mut started = 0;
 
     // Process in batches for parallel updates
     for batch in devices.chunks(parallel) {
@@ -517,7 +668,7 @@ async fn execute_update_firmware(
 
         for device in batch {
             let client = AxeOsClient::new(&device.ip_address)?;
-            let fw = firmware.to_string();
+            let fw = url.clone();
             let name = device.name.clone();
             let ip = device.ip_address.clone();
 
@@ -530,20 +681,23 @@ async fn execute_update_firmware(
         let batch_results = join_all(batch_futures).await;
 
         for (name, ip, result) in batch_results {
-            let success = result.is_ok();
-            let message = result.as_ref().err().map(|e| e.to_string());
+            let message = match result {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.message),
+                Err(e) => Some(e.to_string()),
+            };
+            let success = message.is_none();
 
             if format == OutputFormat::Text {
-                if success {
-                    print_success(&format!("✓ {name} firmware update started"), color);
-                } else {
-                    print_error(
-                        &format!("✗ {name} failed: {msg}", msg = message.as_ref().unwrap()),
-                        color,
-                    );
+                match message {
+                    None => print_success(&format!("✓ {name} firmware update started"), color),
+                    Some(ref msg) => print_error(&format!("✗ {name} failed: {msg}"), color),
                 }
             }
 
+            if success {
+                started += 1;
+            }
             results.push(serde_json::json!({
                 "device": name,
                 "ip": ip,
@@ -551,6 +705,15 @@ async fn execute_update_firmware(
                 "error": message
             }));
         }
+
+        // Keep the next batch waiting until this one has fetched the served image
+        if let Some(ref server) = server {
+            wait_for_downloads(server, started, format, color).await;
+        }
+    }
+
+    if let Some(server) = server {
+        server.shutdown().await?;
     }
 
     if format == OutputFormat::Json {
@@ -568,6 +731,322 @@ async fn execute_update_firmware(
     Ok(())
 }
 
+/// Execute a staged firmware rollout on all target devices
+async fn execute_rollout_firmware(
+    mut devices: Vec<Device>,
+    firmware: &str,
+    config: &RolloutConfig,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    if format == OutputFormat::Text {
+        print_info(
+            &format!(
+                "Rolling out firmware to {count} device(s): {canary} canary, then waves of {wave_size}",
+                count = devices.len(),
+                canary = config.canary.min(devices.len()),
+                wave_size = config.wave_size
+            ),
+            color,
+        );
+    }
+
+    let server = serve_local_file(firmware, &devices, format, color).await?;
+    let url = server.as_ref().map_or(firmware, |server| server.url());
+
+    let results = run_rollout(&devices, url, config, |event| {
+        if format != OutputFormat::Text {
+            return;
+        }
+        match event {
+            RolloutEvent::WaveStarted { wave, devices } => {
+                let names: Vec<_> = devices.iter().map(|d| d.name.as_str()).collect();
+                print_info(
+                    &format!("Wave {wave}: updating {names}", names = names.join(", ")),
+                    color,
+                );
+            }
+            RolloutEvent::DeviceFinished(result) => {
+                let before = result.version_before.as_deref().unwrap_or("unknown");
+                let after = result.version_after.as_deref().unwrap_or("unknown");
+                match result.status {
+                    RolloutStatus::Updated => print_success(
+                        &format!("✓ {name} updated {before} → {after}", name = result.device),
+                        color,
+                    ),
+                    _ => print_error(
+                        &format!(
+                            "✗ {name} failed ({before} → {after}): {msg}",
+                            name = result.device,
+                            msg = result.error.as_deref().unwrap_or("unknown error")
+                        ),
+                        color,
+                    ),
+                }
+            }
+            RolloutEvent::Halted { wave } => print_warning(
+                &format!("Rollout halted after wave {wave}, remaining devices were not updated"),
+                color,
+            ),
+        }
+    })
+    .await;
+
+    if let Some(server) = server {
+        server.shutdown().await?;
+    }
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let (updated, failed, skipped) = (
+        count(RolloutStatus::Updated),
+        count(RolloutStatus::Failed),
+        count(RolloutStatus::Skipped),
+    );
+
+    match format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "action": "rollout_firmware",
+                "firmware": firmware,
+                "total_devices": devices.len(),
+                "halted": failed > 0,
+                "updated": updated,
+                "failed": failed,
+                "skipped": skipped,
+                "results": results,
+                "timestamp": chrono::Utc::now()
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if failed == 0 {
+                print_success(
+                    &format!("Rollout complete: {updated} device(s) updated"),
+                    color,
+                );
+            } else {
+                print_error(
+                    &format!(
+                        "Rollout halted: {updated} updated, {failed} failed, {skipped} skipped"
+                    ),
+                    color,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the image and that it fits every target device, refusing mismatches unless forced
+async fn verify_image(
+    source: &str,
+    kind: ImageKind,
+    devices: &[Device],
+    force: bool,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    let data = read_image(&FirmwareSource::parse(source)?).await?;
+    let image = match FirmwareImage::parse(&data, kind) {
+        Ok(image) => image,
+        Err(e) if force => {
+            if format == OutputFormat::Text {
+                print_warning(&format!("Flashing anyway: {e:#}"), color);
+            }
+            return Ok(());
+        }
+        Err(e) => bail!("{e:#}. Use --force-image to flash it anyway"),
+    };
+    if format == OutputFormat::Text {
+        print_info(
+            &format!("Image: {summary}", summary = image.summary()),
+            color,
+        );
+    }
+
+    let checks = join_all(devices.iter().map(|device| {
+        let image = &image;
+        async move {
+            let issues = async {
+                let client = AxeOsClient::new(&device.ip_address)?;
+                let (info, device_type) = client.get_complete_device_info().await?;
+                Ok::<_, anyhow::Error>(image.compatibility_issues(&info, device_type))
+            }
+            .await
+            .unwrap_or_else(|e| vec![format!("Failed to read device info: {e:#}")]);
+            (device, issues)
+        }
+    }))
+    .await;
+
+    let mismatches: Vec<String> = checks
+        .iter()
+        .flat_map(|(device, issues)| {
+            issues
+                .iter()
+                .map(|issue| format!("{name}: {issue}", name = device.name))
+        })
+        .collect();
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    if force {
+        if format == OutputFormat::Text {
+            for mismatch in &mismatches {
+                print_warning(&format!("Flashing anyway: {mismatch}"), color);
+            }
+        }
+        Ok(())
+    } else {
+        bail!(
+            "Image does not match the target devices:\n  - {list}\nUse --force-image to flash it anyway",
+            list = mismatches.join("\n  - ")
+        )
+    }
+}
+
+/// Check that the pool each device would mine on accepts its worker, refusing
+/// to continue otherwise
+async fn verify_pools<F>(
+    devices: &[Device],
+    pool: F,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()>
+where
+    F: Fn(&SystemInfoResponse) -> Result<(PoolEndpoint, String)>,
+{
+    let (targets, errors) = pool_targets(devices, pool).await;
+    if !errors.is_empty() {
+        bail!(
+            "Cannot check the pools of every device:\n  - {list}",
+            list = errors
+                .iter()
+                .map(|(name, error)| format!("{name}: {error}"))
+                .collect::<Vec<_>>()
+                .join("\n  - ")
+        );
+    }
+
+    let checks =
+        ensure_pools_reachable(&targets, DEFAULT_POOL_PASSWORD, DEFAULT_POOL_CHECK_TIMEOUT).await?;
+    if format == OutputFormat::Text {
+        for check in &checks {
+            print_success(
+                &format!(
+                    "✓ {pool} accepted {count} worker(s) (connect {connect:.0} ms)",
+                    pool = check.pool,
+                    count = check.authorizations.len(),
+                    connect = check.connect_ms.unwrap_or_default()
+                ),
+                color,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check the update against every device's hardware profile, failing with each device that can't take it
+async fn verify_settings(
+    devices: &[Device],
+    request: &SystemUpdateRequest,
+    policy: &SettingsPolicy,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    let results = join_all(devices.iter().map(|device| async move {
+        let info = async {
+            let client = AxeOsClient::new(&device.ip_address)?;
+            client.get_system_info().await
+        }
+        .await;
+        (device, info)
+    }))
+    .await;
+
+    let mut problems = Vec::new();
+    for (device, info) in results {
+        let name = &device.name;
+        match info {
+            Ok(info) => problems.extend(
+                policy
+                    .problems(request, &info)
+                    .into_iter()
+                    .map(|problem| format!("{name}: {problem}")),
+            ),
+            Err(e) => problems.push(format!("{name}: Failed to read the ASIC model: {e:#}")),
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Settings rejected for {count} device(s):\n  - {list}",
+            count = problems.len(),
+            list = problems.join("\n  - ")
+        );
+    }
+
+    if format == OutputFormat::Text {
+        print_success(
+            &format!(
+                "✓ Settings are within the limits of all {count} device(s)",
+                count = devices.len()
+            ),
+            color,
+        );
+    }
+    Ok(())
+}
+
+/// Start serving the image to the devices if it is a local file
+async fn serve_local_file(
+    source: &str,
+    devices: &[Device],
+    format: OutputFormat,
+    color: bool,
+) -> Result<Option<FirmwareServer>> {
+    let path = match FirmwareSource::parse(source)? {
+        FirmwareSource::Url(_) => return Ok(None),
+        FirmwareSource::Local(path) => path,
+    };
+    let first = devices
+        .first()
+        .context("No devices to serve the image to")?;
+
+    let server = FirmwareServer::start(&path, &first.ip_address).await?;
+    if format == OutputFormat::Text {
+        print_info(
+            &format!(
+                "Serving {path} at {url}",
+                path = path.display(),
+                url = server.url()
+            ),
+            color,
+        );
+    }
+    Ok(Some(server))
+}
+
+/// Wait until the served image has been downloaded `count` times
+async fn wait_for_downloads(
+    server: &FirmwareServer,
+    count: usize,
+    format: OutputFormat,
+    color: bool,
+) {
+    match server.wait_for_downloads(count, DOWNLOAD_TIMEOUT).await {
+        Ok(()) if format == OutputFormat::Text => print_info(
+            &format!("{count} device(s) finished downloading the image"),
+            color,
+        ),
+        Ok(()) => {}
+        Err(e) => tracing::warn!("{e:#}"),
+    }
+}
+
 /// Execute AxeOS update on all target devices
 async fn execute_update_axeos(
     devices: &[Device],
@@ -586,7 +1065,13 @@ async fn execute_update_axeos(
         );
     }
 
+    let server = serve_local_file(axeos, devices, format, color).await?;
+    let url = server
+        .as_ref()
+        .map_or_else(|| axeos.to_string(), |server| server.url().to_string());
+
     let mut results = Vec::new();
+    let mut started = 0;
 
     // Process in batches for parallel updates
     for batch in devices.chunks(parallel) {
@@ -594,7 +1079,7 @@ async fn execute_update_axeos(
 
         for device in batch {
             let client = AxeOsClient::new(&device.ip_address)?;
-            let axe = axeos.to_string();
+            let axe = url.clone();
             let name = device.name.clone();
             let ip = device.ip_address.clone();
 
@@ -607,20 +1092,23 @@ async fn execute_update_axeos(
         let batch_results = join_all(batch_futures).await;
 
         for (name, ip, result) in batch_results {
-            let success = result.is_ok();
-            let message = result.as_ref().err().map(|e| e.to_string());
+            let message = match result {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.message),
+                Err(e) => Some(e.to_string()),
+            };
+            let success = message.is_none();
 
             if format == OutputFormat::Text {
-                if success {
-                    print_success(&format!("✓ {name} AxeOS update started"), color);
-                } else {
-                    print_error(
-                        &format!("✗ {name} failed: {msg}", msg = message.as_ref().unwrap()),
-                        color,
-                    );
+                match message {
+                    None => print_success(&format!("✓ {name} AxeOS update started"), color),
+                    Some(ref msg) => print_error(&format!("✗ {name} failed: {msg}"), color),
                 }
             }
 
+            if success {
+                started += 1;
+            }
             results.push(serde_json::json!({
                 "device": name,
                 "ip": ip,
@@ -628,6 +1116,15 @@ async fn execute_update_axeos(
                 "error": message
             }));
         }
+
+        // Keep the next batch waiting until this one has fetched the served image
+        if let Some(ref server) = server {
+            wait_for_downloads(server, started, format, color).await;
+        }
+    }
+
+    if let Some(server) = server {
+        server.shutdown().await?;
     }
 
     if format == OutputFormat::Json {
@@ -700,6 +1197,19 @@ async fn execute_show_config(devices: &[Device], format: OutputFormat, color: bo
                     println!("  Pool URL:    {}", config.pool_url);
                     println!("  Pool Port:   {}", config.pool_port);
                     println!("  Pool User:   {}", config.pool_user);
+                    println!(
+                        "  Fallback:    {}",
+                        match (&config.fallback_pool_url, config.fallback_pool_port) {
+                            (Some(url), Some(port)) if !url.is_empty() => format!(
+                                "{url}:{port} ({user})",
+                                user = config.fallback_pool_user.as_deref().unwrap_or("-")
+                            ),
+                            _ => "-".to_string(),
+                        }
+                    );
+                    if config.using_fallback_pool == Some(true) {
+                        println!("  Active Pool: fallback");
+                    }
 
                     // Hardware
                     println!("  Frequency:   {} MHz", config.frequency);
@@ -1,5 +1,12 @@
+use crate::api::{AxeOsClient, CommandResult, SystemUpdateRequest};
 use crate::cli::commands::{ControlAction, OutputFormat};
-use anyhow::{Context, Result};
+use crate::daemon::{DaemonClient, DaemonCommand};
+use crate::firmware::{
+    DOWNLOAD_TIMEOUT, FirmwareImage, FirmwareServer, FirmwareSource, ImageKind, read_image,
+};
+use crate::output::{print_info, print_success, print_warning};
+use crate::settings::{SettingsPolicy, parse_update_request, validate_settings};
+use anyhow::{Context, Result, bail};
 use std::path::Path;
 use std::time::Duration;
 
@@ -9,10 +16,25 @@ pub async fn control(
     format: OutputFormat,
     color: bool,
     cache_dir: Option<&Path>,
+    daemon: Option<&str>,
 ) -> Result<()> {
-    use crate::api::{AxeOsClient, SystemUpdateRequest};
     use crate::cache::{DeviceCache, get_cache_dir};
-    use crate::output::{print_error, print_info, print_json, print_success};
+    use crate::output::{print_error, print_json};
+
+    // Actions the daemon can proxy go through it when one is configured
+    if let Some(url) = daemon
+        && let Some(command) = daemon_command(&action)?
+    {
+        print_info(
+            &format!(
+                "Sending {action} to {device} through the daemon",
+                action = command.endpoint()
+            ),
+            color,
+        );
+        let result = DaemonClient::new(url)?.control(&device, &command).await;
+        return print_command_result(result, &device, format, color);
+    }
 
     // Get cache directory, using default if not provided
     let cache_path = get_cache_dir(cache_dir)?;
@@ -62,21 +84,41 @@ pub async fn control(
             );
             client.restart_system().await
         }
-        ControlAction::UpdateSettings { settings } => {
+        ControlAction::Identify => {
+            print_info(
+                &format!("Identifying device {name}", name = device_info.name),
+                color,
+            );
+            client.identify().await
+        }
+        ControlAction::UpdateSettings {
+            settings,
+            allow_unsafe,
+        } => {
             print_info(
                 &format!("Updating settings on {name}", name = device_info.name),
                 color,
             );
-            match serde_json::from_str::<SystemUpdateRequest>(&settings) {
-                Ok(update_request) => client.update_system(update_request).await,
+            match parse_update_request(&settings) {
+                Ok(update_request) => update_settings(&client, update_request, allow_unsafe).await,
                 Err(e) => Ok(crate::api::CommandResult {
                     success: false,
-                    message: format!("Invalid settings JSON: {e}"),
+                    message: format!("{e:#}"),
                     data: None,
                     timestamp: chrono::Utc::now(),
                 }),
             }
         }
+        ControlAction::Set {
+            setting,
+            allow_unsafe,
+        } => {
+            print_info(
+                &format!("Updating settings on {name}", name = device_info.name),
+                color,
+            );
+            update_settings(&client, setting.to_update_request(), allow_unsafe).await
+        }
         ControlAction::WifiScan => {
             print_info(
                 &format!("Scanning WiFi networks on {name}", name = device_info.name),
@@ -100,7 +142,7 @@ pub async fn control(
                 }),
             }
         }
-        ControlAction::UpdateFirmware { firmware } => {
+        ControlAction::UpdateFirmware { firmware, force } => {
             print_info(
                 &format!(
                     "Updating firmware on {} from {}",
@@ -108,9 +150,17 @@ pub async fn control(
                 ),
                 color,
             );
-            client.update_firmware(&firmware).await
+            run_ota(
+                &client,
+                &device_info.ip_address,
+                &firmware,
+                ImageKind::Firmware,
+                force,
+                color,
+            )
+            .await
         }
-        ControlAction::UpdateAxeOs { axeos } => {
+        ControlAction::UpdateAxeOs { axeos, force } => {
             print_info(
                 &format!(
                     "Updating AxeOS on {name} from {axeos}",
@@ -118,7 +168,15 @@ pub async fn control(
                 ),
                 color,
             );
-            client.update_axeos(&axeos).await
+            run_ota(
+                &client,
+                &device_info.ip_address,
+                &axeos,
+                ImageKind::Www,
+                force,
+                color,
+            )
+            .await
         }
         ControlAction::ShowConfig => {
             // Get the full system info which contains all configuration
@@ -136,6 +194,62 @@ pub async fn control(
         }
     };
 
+    print_command_result(result, &device_info.name, format, color)
+}
+
+/// The daemon equivalent of a control action, if the daemon can proxy it
+fn daemon_command(action: &ControlAction) -> Result<Option<DaemonCommand>> {
+    Ok(match action {
+        ControlAction::SetFanSpeed { speed } => Some(DaemonCommand::SetFanSpeed(*speed)),
+        ControlAction::Restart => Some(DaemonCommand::Restart),
+        ControlAction::Identify => Some(DaemonCommand::Identify),
+        ControlAction::UpdateSettings {
+            settings,
+            allow_unsafe,
+        } => Some(update_command(
+            parse_update_request(settings)?,
+            *allow_unsafe,
+        )?),
+        ControlAction::Set {
+            setting,
+            allow_unsafe,
+        } => Some(update_command(setting.to_update_request(), *allow_unsafe)?),
+        _ => None,
+    })
+}
+
+/// Send a settings update after checking it against the device's hardware profile
+async fn update_settings(
+    client: &AxeOsClient,
+    request: SystemUpdateRequest,
+    allow_unsafe: bool,
+) -> Result<CommandResult> {
+    SettingsPolicy::load(allow_unsafe)?
+        .ensure_supported(client, &request)
+        .await?;
+    client.update_system(request).await
+}
+
+/// Settings update for the daemon, after the checks that don't need the device
+///
+/// The daemon validates the update against the device's hardware profile before sending it.
+fn update_command(request: SystemUpdateRequest, allow_unsafe: bool) -> Result<DaemonCommand> {
+    validate_settings(&request)?;
+    Ok(DaemonCommand::UpdateSettings {
+        settings: Box::new(request),
+        allow_unsafe,
+    })
+}
+
+/// Print the outcome of a control action
+fn print_command_result(
+    result: Result<CommandResult>,
+    device_name: &str,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    use crate::output::{print_error, print_json};
+
     match result {
         Ok(command_result) => match format {
             OutputFormat::Json => {
@@ -185,6 +299,28 @@ pub async fn control(
                                 println!("  User:        {}", user.as_str().unwrap_or("-"));
                             }
 
+                            println!("\n🛟 Fallback Pool:");
+                            if let Some(url) =
+                                data.get("fallback_pool_url").and_then(|v| v.as_str())
+                            {
+                                println!("  URL:         {url}");
+                            }
+                            if let Some(port) =
+                                data.get("fallback_pool_port").and_then(|v| v.as_u64())
+                            {
+                                println!("  Port:        {port}");
+                            }
+                            if let Some(user) =
+                                data.get("fallback_pool_user").and_then(|v| v.as_str())
+                            {
+                                println!("  User:        {user}");
+                            }
+                            if let Some(active) =
+                                data.get("using_fallback_pool").and_then(|v| v.as_bool())
+                            {
+                                println!("  In use:      {}", if active { "yes" } else { "no" });
+                            }
+
                             // Hardware Settings
                             println!("\n⚙️  Hardware Settings:");
                             if let Some(freq) = data.get("frequency") {
@@ -196,6 +332,14 @@ pub async fn control(
                             if let Some(fan) = data.get("fanspeed") {
                                 println!("  Fan Speed:   {} RPM", fan);
                             }
+                            if let Some(auto_fan) =
+                                data.get("auto_fan_speed").and_then(|v| v.as_bool())
+                            {
+                                println!(
+                                    "  Fan Mode:    {}",
+                                    if auto_fan { "auto" } else { "manual" }
+                                );
+                            }
                             println!("────────────────────────");
                         }
                         // WiFi scan results
@@ -226,7 +370,7 @@ pub async fn control(
                 let output = serde_json::json!({
                     "success": false,
                     "error": e.to_string(),
-                    "device": device_info.name,
+                    "device": device_name,
                     "timestamp": chrono::Utc::now()
                 });
                 print_json(&output, true)?;
@@ -239,3 +383,109 @@ pub async fn control(
 
     Ok(())
 }
+
+/// Start an OTA update, serving local files until the device has downloaded them
+async fn run_ota(
+    client: &AxeOsClient,
+    device_ip: &str,
+    source: &str,
+    image: ImageKind,
+    force: bool,
+    color: bool,
+) -> Result<CommandResult> {
+    let source = FirmwareSource::parse(source)?;
+    verify_image(client, &source, image, force, color).await?;
+
+    let path = match source {
+        FirmwareSource::Url(url) => return start_ota(client, &url, image).await,
+        FirmwareSource::Local(path) => path,
+    };
+
+    let server = FirmwareServer::start(&path, device_ip).await?;
+    print_info(
+        &format!(
+            "Serving {path} at {url}",
+            path = path.display(),
+            url = server.url()
+        ),
+        color,
+    );
+
+    let mut result = start_ota(client, server.url(), image).await;
+    if let Ok(ref mut started) = result
+        && started.success
+    {
+        match server.wait_for_downloads(1, DOWNLOAD_TIMEOUT).await {
+            Ok(()) => {
+                let progress = server.progress();
+                print_success(
+                    &format!(
+                        "Device downloaded {bytes} bytes",
+                        bytes = progress.total_bytes
+                    ),
+                    color,
+                );
+                started.data = Some(serde_json::json!({
+                    "file": path,
+                    "served_url": server.url(),
+                    "bytes": progress.total_bytes,
+                }));
+            }
+            Err(e) => {
+                started.success = false;
+                started.message = format!("{e:#}");
+            }
+        }
+    }
+
+    server.shutdown().await?;
+    result
+}
+
+/// Check the image and that it fits the device, refusing mismatches unless forced
+async fn verify_image(
+    client: &AxeOsClient,
+    source: &FirmwareSource,
+    image: ImageKind,
+    force: bool,
+    color: bool,
+) -> Result<()> {
+    let data = read_image(source).await?;
+    let parsed = match FirmwareImage::parse(&data, image) {
+        Ok(parsed) => parsed,
+        Err(e) if force => {
+            print_warning(&format!("Flashing anyway: {e:#}"), color);
+            return Ok(());
+        }
+        Err(e) => bail!("{e:#}. Use --force to flash it anyway"),
+    };
+    print_info(
+        &format!("Image: {summary}", summary = parsed.summary()),
+        color,
+    );
+
+    let (info, device_type) = client.get_complete_device_info().await?;
+    let issues = parsed.compatibility_issues(&info, device_type);
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    if force {
+        for issue in &issues {
+            print_warning(&format!("Flashing anyway: {issue}"), color);
+        }
+        Ok(())
+    } else {
+        bail!(
+            "Image does not match the device: {issues}. Use --force to flash it anyway",
+            issues = issues.join("; ")
+        )
+    }
+}
+
+async fn start_ota(client: &AxeOsClient, url: &str, image: ImageKind) -> Result<CommandResult> {
+    match image {
+        ImageKind::Firmware => client.update_firmware(url).await,
+        ImageKind::Www => client.update_axeos(url).await,
+    }
+}
@@ -0,0 +1,673 @@
+use super::history::resolve_time_range;
+use crate::alerts::{AlertRules, NotifyConfig};
+use crate::api::{
+    AxeOsClient, Device, DeviceFilter, SwarmSummary, SystemUpdateRequest, TypeSummary,
+};
+use crate::cache::get_cache_dir;
+use crate::cli::commands::handlers::monitor_async::{
+    AsyncMonitorConfig, EVENT_CHANNEL_CAPACITY, MonitorOutput, MonitorState, monitored_devices,
+    run_monitor_loop,
+};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::daemon::{
+    DaemonCommand, DevicesResponse, ErrorResponse, FanSpeedRequest, SettingsQuery,
+};
+use crate::history::{HistoryMetric, HistoryStore, SeriesSummary, extract_series};
+use crate::output::print_success;
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result};
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use futures::Stream;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{RwLock, broadcast, oneshot};
+
+/// Timeout for control actions proxied to a device
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Alerts returned by `GET /api/alerts` unless a limit is given
+const DEFAULT_ALERT_LIMIT: usize = 100;
+
+/// Arguments for the daemon
+pub struct DaemonArgs<'a> {
+    pub listen: SocketAddr,
+    pub interval: u64,
+    pub type_filter: Option<DeviceFilterArg>,
+    pub all: bool,
+    pub discover: bool,
+    pub discover_interval: u64,
+    pub network: Option<String>,
+    pub no_mdns: bool,
+    pub no_history: bool,
+    pub alert_rules: AlertRules,
+    pub notify: Option<NotifyConfig>,
+    pub read_only: bool,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+/// State shared with the HTTP handlers
+#[derive(Clone)]
+struct DaemonState {
+    monitor: Arc<RwLock<MonitorState>>,
+    history: Option<HistoryStore>,
+    read_only: bool,
+}
+
+/// Error answered as `{"error": "..."}`
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn device_not_found(identifier: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            format!("Device not found: {identifier}"),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(ErrorResponse {
+                error: self.message,
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicesQuery {
+    /// Device type filter (e.g. bitaxe, nerdqaxe, bitaxe-gamma)
+    #[serde(rename = "type")]
+    device_type: Option<String>,
+    /// Include offline devices
+    #[serde(default)]
+    all: bool,
+}
+
+impl DevicesQuery {
+    fn filter(&self) -> Result<Option<DeviceFilterArg>, ApiError> {
+        self.device_type
+            .as_deref()
+            .map(|t| {
+                DeviceFilter::from_str(t)
+                    .map(DeviceFilterArg)
+                    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    since: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    metric: Option<HistoryMetric>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertsQuery {
+    limit: Option<usize>,
+}
+
+pub async fn daemon(args: DaemonArgs<'_>) -> Result<()> {
+    let listener = TcpListener::bind(args.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {listen}", listen = args.listen))?;
+    let local_addr = listener
+        .local_addr()
+        .context("Failed to get listener address")?;
+
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let state = Arc::new(RwLock::new(MonitorState {
+        events: Some(events),
+        ..MonitorState::default()
+    }));
+    let router = api_router(DaemonState {
+        monitor: state.clone(),
+        history: (!args.no_history).then(|| HistoryStore::open(cache_path.as_ref())),
+        read_only: args.read_only,
+    });
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+    });
+
+    print_success(
+        &format!("🛰️ Serving the axectl API on http://{local_addr}/api"),
+        args.color,
+    );
+
+    // Poll with the monitor loop, keeping everything in memory for the API
+    let config = AsyncMonitorConfig {
+        interval: args.interval,
+        alert_rules: args.alert_rules,
+        type_filter: args.type_filter,
+        type_summary: false,
+        format: OutputFormat::Text,
+        color: args.color,
+        cache_dir: args.cache_dir,
+        all: args.all,
+        no_stats: false,
+        discover: args.discover,
+        discover_interval: args.discover_interval,
+        network: args.network,
+        no_mdns: args.no_mdns,
+        no_history: args.no_history,
+        thermal_guard: None,
+        notify: args.notify,
+        tariff: None,
+        near_block: None,
+    };
+    let result = run_monitor_loop(
+        &config,
+        state,
+        Arc::new(AtomicBool::new(false)),
+        MonitorOutput::StateOnly,
+    )
+    .await;
+
+    let _ = shutdown_tx.send(());
+    server
+        .await
+        .context("API server task failed")?
+        .context("API server failed")?;
+
+    result
+}
+
+fn api_router(state: DaemonState) -> Router {
+    Router::new()
+        .route("/api/devices", get(list_devices))
+        .route("/api/devices/{device}", get(get_device))
+        .route("/api/devices/{device}/stats", get(get_stats))
+        .route("/api/devices/{device}/history", get(get_history))
+        .route("/api/devices/{device}/restart", post(restart))
+        .route("/api/devices/{device}/identify", post(identify))
+        .route("/api/devices/{device}/fan-speed", post(set_fan_speed))
+        .route("/api/devices/{device}/settings", post(update_settings))
+        .route("/api/summary", get(summary))
+        .route("/api/alerts", get(alerts))
+        .route("/api/events", get(events))
+        .with_state(state)
+}
+
+/// Find a device by IP address or name
+fn find_device(monitor: &MonitorState, identifier: &str) -> Result<Device, ApiError> {
+    monitor
+        .devices
+        .get(identifier)
+        .or_else(|| monitor.devices.values().find(|d| d.name == identifier))
+        .cloned()
+        .ok_or_else(|| ApiError::device_not_found(identifier))
+}
+
+async fn list_devices(
+    State(state): State<DaemonState>,
+    Query(query): Query<DevicesQuery>,
+) -> Result<Json<DevicesResponse>, ApiError> {
+    let filter = query.filter()?;
+    let mut devices = {
+        let monitor = state.monitor.read().await;
+        monitored_devices(&monitor, filter, query.all)
+    };
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    Ok(Json(DevicesResponse {
+        total: devices.len(),
+        devices,
+        timestamp: Utc::now(),
+    }))
+}
+
+async fn get_device(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+) -> Result<Json<Device>, ApiError> {
+    let monitor = state.monitor.read().await;
+    find_device(&monitor, &identifier).map(Json)
+}
+
+async fn get_stats(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+) -> Result<Response, ApiError> {
+    let monitor = state.monitor.read().await;
+    let device = find_device(&monitor, &identifier)?;
+    match device.stats {
+        Some(stats) => Ok(Json(stats).into_response()),
+        None => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("No statistics for {name} yet", name = device.name),
+        )),
+    }
+}
+
+async fn get_history(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let Some(ref store) = state.history else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "History is not recorded (daemon started with --no-history)",
+        ));
+    };
+    let device = {
+        let monitor = state.monitor.read().await;
+        find_device(&monitor, &identifier)?
+    };
+
+    let now = Utc::now();
+    let (from, to) = resolve_time_range(
+        query.since.as_deref(),
+        query.from.as_deref(),
+        query.to.as_deref(),
+        chrono::Duration::hours(1),
+        now,
+    )
+    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("{e:#}")))?;
+
+    let samples = store
+        .query(&device.ip_address, from, to)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+
+    let output = match query.metric {
+        Some(metric) => {
+            let series = extract_series(&samples, metric);
+            let values: Vec<f64> = series.iter().map(|p| p.value).collect();
+            serde_json::json!({
+                "name": device.name,
+                "ip_address": device.ip_address,
+                "metric": metric,
+                "from": from,
+                "to": to,
+                "summary": SeriesSummary::from_values(&values),
+                "series": series,
+            })
+        }
+        None => serde_json::json!({
+            "name": device.name,
+            "ip_address": device.ip_address,
+            "from": from,
+            "to": to,
+            "samples": samples,
+        }),
+    };
+    Ok(Json(output))
+}
+
+async fn summary(
+    State(state): State<DaemonState>,
+    Query(query): Query<DevicesQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let filter = query.filter()?;
+    // Offline devices count towards the summary
+    let devices = {
+        let monitor = state.monitor.read().await;
+        monitored_devices(&monitor, filter, true)
+    };
+
+    Ok(Json(serde_json::json!({
+        "summary": SwarmSummary::from_devices(&devices),
+        "type_summaries": TypeSummary::from_all_devices(&devices),
+        "timestamp": Utc::now(),
+    })))
+}
+
+async fn alerts(
+    State(state): State<DaemonState>,
+    Query(query): Query<AlertsQuery>,
+) -> Json<serde_json::Value> {
+    let monitor = state.monitor.read().await;
+    let limit = query.limit.unwrap_or(DEFAULT_ALERT_LIMIT);
+    let recent: Vec<_> = monitor.alerts.iter().rev().take(limit).collect();
+
+    Json(serde_json::json!({
+        "alerts": recent,
+        "active": monitor.alert_engine.active(),
+        "total": monitor.alert_count,
+        "timestamp": Utc::now(),
+    }))
+}
+
+/// Server-sent events stream with one `MonitorEvent` per message
+async fn events(
+    State(state): State<DaemonState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    let receiver = state
+        .monitor
+        .read()
+        .await
+        .events
+        .as_ref()
+        .map(broadcast::Sender::subscribe)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Event stream is not enabled",
+            )
+        })?;
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((Event::default().json_data(&event), receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Event subscriber fell behind, {skipped} event(s) dropped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn restart(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+) -> Result<Response, ApiError> {
+    control(&state, &identifier, DaemonCommand::Restart).await
+}
+
+async fn identify(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+) -> Result<Response, ApiError> {
+    control(&state, &identifier, DaemonCommand::Identify).await
+}
+
+async fn set_fan_speed(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+    Json(request): Json<FanSpeedRequest>,
+) -> Result<Response, ApiError> {
+    control(
+        &state,
+        &identifier,
+        DaemonCommand::SetFanSpeed(request.speed),
+    )
+    .await
+}
+
+async fn update_settings(
+    State(state): State<DaemonState>,
+    UrlPath(identifier): UrlPath<String>,
+    Query(query): Query<SettingsQuery>,
+    Json(settings): Json<SystemUpdateRequest>,
+) -> Result<Response, ApiError> {
+    control(
+        &state,
+        &identifier,
+        DaemonCommand::UpdateSettings {
+            settings: Box::new(settings),
+            allow_unsafe: query.allow_unsafe,
+        },
+    )
+    .await
+}
+
+/// Proxy a control action to the device, answering with its `CommandResult`
+async fn control(
+    state: &DaemonState,
+    identifier: &str,
+    command: DaemonCommand,
+) -> Result<Response, ApiError> {
+    if state.read_only {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Device control is disabled (daemon started with --read-only)",
+        ));
+    }
+
+    let device = {
+        let monitor = state.monitor.read().await;
+        find_device(&monitor, identifier)?
+    };
+    tracing::info!(
+        "Sending {action} to {name} ({ip})",
+        action = command.endpoint(),
+        name = device.name,
+        ip = device.ip_address
+    );
+
+    let client = AxeOsClient::with_timeout(&device.ip_address, CONTROL_TIMEOUT)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    let result = command.send(&client).await.map_err(|e| {
+        ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("{name}: {e:#}", name = device.name),
+        )
+    })?;
+
+    let status = if result.success {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    Ok((status, Json(result)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStats, DeviceStatus, DeviceType};
+    use crate::daemon::DaemonClient;
+    use crate::events::{DeviceRef, EVENT_SCHEMA_VERSION, EventKind, MonitorEvent};
+
+    fn test_device(name: &str, ip_address: &str, device_type: DeviceType) -> Device {
+        Device {
+            name: name.to_string(),
+            ip_address: ip_address.to_string(),
+            device_type,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: Some(DeviceStats {
+                timestamp: Utc::now(),
+                hashrate_mhs: 1150.0,
+                temperature_celsius: 58.5,
+                power_watts: 17.5,
+                fan_speed_rpm: 4200,
+                shares_accepted: 420,
+                shares_rejected: 3,
+                uptime_seconds: 7200,
+                pool_url: None,
+                wifi_rssi: None,
+                voltage: None,
+                frequency: None,
+                efficiency_jth: None,
+                best_difficulty: None,
+                best_session_difficulty: None,
+                using_fallback_pool: None,
+                expected_hashrate_mhs: None,
+                hashrate_health_percent: None,
+            }),
+        }
+    }
+
+    async fn serve(devices: Vec<Device>, read_only: bool) -> Result<(SocketAddr, DaemonState)> {
+        let (events, _) = broadcast::channel(16);
+        let mut monitor = MonitorState {
+            events: Some(events),
+            ..MonitorState::default()
+        };
+        for device in devices {
+            monitor.devices.insert(device.ip_address.clone(), device);
+        }
+        let state = DaemonState {
+            monitor: Arc::new(RwLock::new(monitor)),
+            history: None,
+            read_only,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let router = api_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Ok((addr, state))
+    }
+
+    #[tokio::test]
+    async fn serves_devices_and_summaries_from_memory() -> Result<()> {
+        let mut offline = test_device("nerd-1", "192.168.1.20", DeviceType::NerdqaxePlus);
+        offline.status = DeviceStatus::Offline;
+        let (addr, _state) = serve(
+            vec![
+                test_device("gamma-1", "192.168.1.10", DeviceType::BitaxeGamma),
+                offline,
+            ],
+            false,
+        )
+        .await?;
+
+        let client = DaemonClient::new(&format!("http://{addr}"))?;
+        let online = client.devices(None, false).await?;
+        assert_eq!(online.len(), 1);
+        assert_eq!(online[0].name, "gamma-1");
+        assert_eq!(client.devices(None, true).await?.len(), 2);
+        assert!(
+            client
+                .devices(Some(DeviceFilter::AnyBitaxe), true)
+                .await?
+                .iter()
+                .all(|d| d.device_type.is_bitaxe())
+        );
+
+        let summary: serde_json::Value = reqwest::get(format!("http://{addr}/api/summary"))
+            .await?
+            .json()
+            .await?;
+        assert_eq!(summary["summary"]["devices_online"], 1);
+        assert_eq!(summary["summary"]["devices_offline"], 1);
+
+        let response = reqwest::get(format!("http://{addr}/api/devices/gamma-1/stats")).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let stats: DeviceStats = response.json().await?;
+        assert_eq!(stats.fan_speed_rpm, 4200);
+
+        let response = reqwest::get(format!("http://{addr}/api/devices/missing")).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let error: ErrorResponse = response.json().await?;
+        assert_eq!(error.error, "Device not found: missing");
+
+        let response = reqwest::get(format!("http://{addr}/api/devices?type=bogus")).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn proxies_control_actions_to_devices() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let restart = server
+            .mock("POST", "/api/system/restart")
+            .with_status(200)
+            .create_async()
+            .await;
+        let fan = server
+            .mock("PATCH", "/api/system")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "fanspeed": 80 }),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let device = test_device("gamma-1", &server.host_with_port(), DeviceType::BitaxeGamma);
+        let (addr, _state) = serve(vec![device], false).await?;
+        let client = DaemonClient::new(&format!("http://{addr}/"))?;
+
+        let result = client.control("gamma-1", &DaemonCommand::Restart).await?;
+        assert!(result.success);
+        let result = client
+            .control("gamma-1", &DaemonCommand::SetFanSpeed(80))
+            .await?;
+        assert!(result.success);
+        restart.assert_async().await;
+        fan.assert_async().await;
+
+        let error = client
+            .control("missing", &DaemonCommand::Identify)
+            .await
+            .expect_err("unknown device");
+        assert!(error.to_string().contains("Device not found: missing"));
+
+        let (addr, _state) = serve(Vec::new(), true).await?;
+        let client = DaemonClient::new(&format!("http://{addr}"))?;
+        let error = client
+            .control("gamma-1", &DaemonCommand::Restart)
+            .await
+            .expect_err("read-only daemon");
+        assert!(error.to_string().contains("--read-only"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn streams_monitor_events() -> Result<()> {
+        let device = test_device("gamma-1", "192.168.1.10", DeviceType::BitaxeGamma);
+        let (addr, state) = serve(vec![device.clone()], false).await?;
+
+        let mut response = reqwest::get(format!("http://{addr}/api/events")).await?;
+        assert_eq!(
+            response
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok()),
+            Some("text/event-stream")
+        );
+
+        state.monitor.read().await.emit(EventKind::DeviceOffline {
+            device: DeviceRef::from(&device),
+        });
+        let chunk = response.chunk().await?.context("stream ended")?;
+        let message = String::from_utf8(chunk.to_vec())?;
+        let data = message
+            .strip_prefix("data: ")
+            .context("not an SSE data line")?;
+        let event: MonitorEvent = serde_json::from_str(data.trim())?;
+        assert_eq!(event.version, EVENT_SCHEMA_VERSION);
+        assert!(
+            matches!(event.kind, EventKind::DeviceOffline { device: ref d } if d.name == "gamma-1")
+        );
+        Ok(())
+    }
+}
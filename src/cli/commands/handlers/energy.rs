@@ -0,0 +1,201 @@
+use super::history::{resolve_targets, resolve_time_range};
+use super::list::format_cost;
+use crate::api::DeviceType;
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::energy::{DailyEnergy, Tariff, daily_energy};
+use crate::history::HistoryStore;
+use crate::output::{format_table, print_info, print_json, print_warning};
+use alphanumeric_sort::compare_str;
+use anyhow::Result;
+use chrono::{Local, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tabled::Tabled;
+
+/// Arguments for the energy command
+pub struct EnergyArgs<'a> {
+    pub device: Option<String>,
+    pub device_type: Option<DeviceFilterArg>,
+    pub since: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub tariff: Option<Tariff>,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct EnergyTableRow {
+    #[tabled(rename = "Date")]
+    date: String,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "IP Address")]
+    ip_address: String,
+    #[tabled(rename = "Hours")]
+    hours: String,
+    #[tabled(rename = "kWh")]
+    kwh: String,
+    #[tabled(rename = "Cost")]
+    cost: String,
+}
+
+/// Energy use of one device per day
+#[derive(Debug, Serialize)]
+struct DeviceEnergy {
+    name: String,
+    ip_address: String,
+    device_type: Option<DeviceType>,
+    total_kwh: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_cost: Option<f64>,
+    days: Vec<DailyEnergy>,
+}
+
+pub async fn energy(args: EnergyArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+
+    let cache = DeviceCache::load(cache_path)?;
+    let store = HistoryStore::open(cache_path);
+
+    let now = Utc::now();
+    let (from, to) = resolve_time_range(
+        args.since.as_deref(),
+        args.from.as_deref(),
+        args.to.as_deref(),
+        chrono::Duration::days(7),
+        now,
+    )?;
+
+    let targets = resolve_targets(args.device.as_deref(), args.device_type, &cache, &store)?;
+    let tariff = args.tariff.as_ref();
+
+    let mut devices = Vec::new();
+    for (ip_address, device) in targets {
+        let samples = store.query(&ip_address, from, to)?;
+        let days = daily_energy(&samples, tariff, &Local);
+
+        devices.push(DeviceEnergy {
+            name: device
+                .as_ref()
+                .map(|d| d.name.clone())
+                .unwrap_or_else(|| ip_address.clone()),
+            ip_address,
+            device_type: device.as_ref().map(|d| d.device_type),
+            total_kwh: days.iter().map(|d| d.kwh).sum(),
+            total_cost: tariff.map(|_| days.iter().filter_map(|d| d.cost).sum()),
+            days,
+        });
+    }
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    // Fleet totals per day
+    let mut fleet_days: BTreeMap<chrono::NaiveDate, (f64, Option<f64>)> = BTreeMap::new();
+    for day in devices.iter().flat_map(|d| d.days.iter()) {
+        let entry = fleet_days.entry(day.date).or_default();
+        entry.0 += day.kwh;
+        if let Some(cost) = day.cost {
+            entry.1 = Some(entry.1.unwrap_or_default() + cost);
+        }
+    }
+    let total_kwh: f64 = devices.iter().map(|d| d.total_kwh).sum();
+    let total_cost = tariff.map(|_| devices.iter().filter_map(|d| d.total_cost).sum::<f64>());
+
+    match args.format {
+        OutputFormat::Json => {
+            let fleet: Vec<_> = fleet_days
+                .iter()
+                .map(|(date, (kwh, cost))| {
+                    serde_json::json!({ "date": date, "kwh": kwh, "cost": cost })
+                })
+                .collect();
+            let output = serde_json::json!({
+                "from": from,
+                "to": to,
+                "currency": tariff.and_then(|t| t.currency.clone()),
+                "devices": devices,
+                "fleet": {
+                    "total_kwh": total_kwh,
+                    "total_cost": total_cost,
+                    "days": fleet,
+                },
+                "timestamp": now,
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if devices.iter().all(|d| d.days.is_empty()) {
+                print_warning(
+                    &format!(
+                        "No power history between {from} and {to}",
+                        from = from.format("%Y-%m-%d %H:%M"),
+                        to = to.format("%Y-%m-%d %H:%M")
+                    ),
+                    args.color,
+                );
+                print_info(
+                    "History is recorded by 'axectl monitor' and 'axectl list --watch'",
+                    args.color,
+                );
+                return Ok(());
+            }
+
+            let format_cost = |cost: Option<f64>| format_cost(tariff, cost);
+
+            // Each day lists its devices followed by the fleet total
+            let mut table_rows = Vec::new();
+            for (date, (kwh, cost)) in &fleet_days {
+                for device in &devices {
+                    for day in device.days.iter().filter(|d| d.date == *date) {
+                        table_rows.push(EnergyTableRow {
+                            date: date.to_string(),
+                            name: device.name.clone(),
+                            ip_address: device.ip_address.clone(),
+                            hours: format!("{hours:.1}", hours = day.hours),
+                            kwh: format!("{kwh:.3}", kwh = day.kwh),
+                            cost: format_cost(day.cost),
+                        });
+                    }
+                }
+                if devices.len() > 1 {
+                    table_rows.push(EnergyTableRow {
+                        date: date.to_string(),
+                        name: "Fleet".to_string(),
+                        ip_address: "-".to_string(),
+                        hours: "-".to_string(),
+                        kwh: format!("{kwh:.3}"),
+                        cost: format_cost(*cost),
+                    });
+                }
+            }
+
+            print_info(
+                &format!(
+                    "Energy from {from} to {to}",
+                    from = from.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                    to = to.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                ),
+                args.color,
+            );
+            println!("{}", format_table(table_rows, args.color));
+            print_info(
+                &format!(
+                    "Total: {total_kwh:.3} kWh{cost}",
+                    cost = total_cost
+                        .map(|c| format!(", {cost}", cost = format_cost(Some(c))))
+                        .unwrap_or_default()
+                ),
+                args.color,
+            );
+            if tariff.is_none() {
+                print_info("Pass --tariff to include electricity cost", args.color);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,309 @@
+use crate::alerts::AlertRules;
+use crate::api::{SwarmSummary, TypeSummary};
+use crate::cli::commands::handlers::monitor_async::{
+    AsyncMonitorConfig, MonitorOutput, MonitorState, monitored_devices, run_monitor_loop,
+};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::output::{PROMETHEUS_CONTENT_TYPE, print_success, render_prometheus_metrics};
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use tokio::net::TcpListener;
+use tokio::sync::{RwLock, oneshot};
+
+/// Arguments for the Prometheus exporter
+pub struct ExporterArgs<'a> {
+    pub listen: SocketAddr,
+    pub interval: u64,
+    pub type_filter: Option<DeviceFilterArg>,
+    pub all: bool,
+    pub discover: bool,
+    pub discover_interval: u64,
+    pub network: Option<String>,
+    pub no_mdns: bool,
+    pub no_history: bool,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+/// State shared with the HTTP handlers
+#[derive(Clone)]
+struct ExporterState {
+    monitor: Arc<RwLock<MonitorState>>,
+    type_filter: Option<DeviceFilterArg>,
+}
+
+pub async fn exporter(args: ExporterArgs<'_>) -> Result<()> {
+    let listener = TcpListener::bind(args.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {listen}", listen = args.listen))?;
+    let local_addr = listener
+        .local_addr()
+        .context("Failed to get listener address")?;
+
+    let state = Arc::new(RwLock::new(MonitorState::default()));
+    let router = metrics_router(ExporterState {
+        monitor: state.clone(),
+        type_filter: args.type_filter,
+    });
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+    });
+
+    print_success(
+        &format!("📈 Serving Prometheus metrics on http://{local_addr}/metrics"),
+        args.color,
+    );
+
+    // Reuse the monitor polling loop without rendering its display
+    let config = AsyncMonitorConfig {
+        interval: args.interval,
+        alert_rules: AlertRules::default(),
+        type_filter: args.type_filter,
+        type_summary: false,
+        format: OutputFormat::Text,
+        color: args.color,
+        cache_dir: args.cache_dir,
+        all: args.all,
+        no_stats: false,
+        discover: args.discover,
+        discover_interval: args.discover_interval,
+        network: args.network,
+        no_mdns: args.no_mdns,
+        no_history: args.no_history,
+        thermal_guard: None,
+        notify: None,
+        tariff: None,
+        near_block: None,
+    };
+    let result = run_monitor_loop(
+        &config,
+        state,
+        Arc::new(AtomicBool::new(false)),
+        MonitorOutput::StateOnly,
+    )
+    .await;
+
+    let _ = shutdown_tx.send(());
+    server
+        .await
+        .context("Metrics server task failed")?
+        .context("Metrics server failed")?;
+
+    result
+}
+
+fn metrics_router(state: ExporterState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+async fn metrics(State(state): State<ExporterState>) -> impl IntoResponse {
+    // Offline devices stay in the output so their up metric drops to 0
+    let devices = {
+        let monitor = state.monitor.read().await;
+        monitored_devices(&monitor, state.type_filter, true)
+    };
+
+    let swarm_summary = SwarmSummary::from_devices(&devices);
+    let type_summaries = TypeSummary::from_all_devices(&devices);
+
+    (
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        render_prometheus_metrics(&devices, &swarm_summary, &type_summaries),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Device, DeviceStatus, DeviceType};
+    use crate::cache::DeviceCache;
+    use chrono::Utc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    const DEVICE_INFO: &str = r#"{
+        "ASICModel": "BM1370",
+        "boardVersion": "601",
+        "version": "2.4.0",
+        "macAddr": "AA:BB:CC:DD:EE:FF",
+        "hostname": "bitaxe-gamma",
+        "ssid": "TestNetwork",
+        "wifiStatus": "Connected",
+        "wifiRSSI": -52,
+        "stratumURL": "public-pool.io",
+        "stratumPort": 21496,
+        "stratumUser": "bc1qtest.bitaxe-gamma",
+        "frequency": 525,
+        "voltage": 5100,
+        "fanspeed": 60,
+        "temp": 58.5,
+        "power": 17.5,
+        "hashRate": 1150.0,
+        "uptimeSeconds": 7200,
+        "sharesAccepted": 420,
+        "sharesRejected": 3,
+        "bestDiff": "1.2M"
+    }"#;
+
+    fn test_device(ip_address: &str, device_type: DeviceType) -> Device {
+        Device {
+            name: "bitaxe-gamma".to_string(),
+            ip_address: ip_address.to_string(),
+            device_type,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    async fn scrape(addr: SocketAddr) -> Result<(String, String)> {
+        let response = reqwest::get(format!("http://{addr}/metrics")).await?;
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .context("Missing content type")?
+            .to_str()?
+            .to_string();
+        Ok((content_type, response.text().await?))
+    }
+
+    #[tokio::test]
+    async fn test_exporter_serves_metrics_from_monitor_loop() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            .expect_at_least(1)
+            .create_async()
+            .await;
+
+        let device_addr = server.host_with_port();
+        let tempdir = tempdir()?;
+        let mut cache = DeviceCache::new();
+        cache.add_device(test_device(&device_addr, DeviceType::BitaxeGamma));
+        cache.save(tempdir.path())?;
+
+        let state = Arc::new(RwLock::new(MonitorState::default()));
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let router = metrics_router(ExporterState {
+            monitor: state.clone(),
+            type_filter: None,
+        });
+        let server_handle = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let cache_path = tempdir.path().to_path_buf();
+        let monitor_handle = tokio::spawn(async move {
+            let config = AsyncMonitorConfig {
+                interval: 60,
+                alert_rules: AlertRules::default(),
+                type_filter: None,
+                type_summary: false,
+                format: OutputFormat::Json,
+                color: false,
+                cache_dir: Some(cache_path.as_path()),
+                all: false,
+                no_stats: false,
+                discover: false,
+                discover_interval: 60,
+                network: None,
+                no_mdns: true,
+                no_history: true,
+                thermal_guard: None,
+                notify: None,
+                tariff: None,
+                near_block: None,
+            };
+            run_monitor_loop(
+                &config,
+                state,
+                Arc::new(AtomicBool::new(false)),
+                MonitorOutput::StateOnly,
+            )
+            .await
+        });
+
+        // The first poll happens immediately; wait for it to land in the metrics
+        let mut body = String::new();
+        let mut content_type = String::new();
+        for _ in 0..50 {
+            (content_type, body) = scrape(addr).await?;
+            if body.contains("axectl_device_hashrate_hashes_per_second") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        monitor_handle.abort();
+        server_handle.abort();
+
+        let labels = format!(
+            r#"hostname="bitaxe-gamma",ip="{device_addr}",device_type="bitaxe-gamma",pool="public-pool.io:21496""#
+        );
+        assert_eq!(content_type, PROMETHEUS_CONTENT_TYPE);
+        assert!(body.contains(&format!("axectl_device_up{{{labels}}} 1\n")));
+        assert!(body.contains(&format!(
+            "axectl_device_hashrate_hashes_per_second{{{labels}}} 1150000000000\n"
+        )));
+        assert!(body.contains(&format!(
+            "axectl_device_shares_accepted_total{{{labels}}} 420\n"
+        )));
+        assert!(body.contains(&format!(
+            "axectl_device_temperature_celsius{{{labels}}} 58.5\n"
+        )));
+        assert!(body.contains("axectl_swarm_devices{status=\"online\"} 1\n"));
+        assert!(body.contains("axectl_type_power_watts{device_type=\"bitaxe-gamma\"} 17.5\n"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metrics_respect_type_filter() -> Result<()> {
+        let state = Arc::new(RwLock::new(MonitorState::default()));
+        {
+            let mut guard = state.write().await;
+            for (ip, device_type) in [
+                ("192.168.1.10", DeviceType::BitaxeGamma),
+                ("192.168.1.11", DeviceType::NerdqaxePlus),
+            ] {
+                guard
+                    .devices
+                    .insert(ip.to_string(), test_device(ip, device_type));
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let router = metrics_router(ExporterState {
+            monitor: state,
+            type_filter: Some("nerdqaxe".parse().map_err(anyhow::Error::msg)?),
+        });
+        let server_handle = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let (_, body) = scrape(addr).await?;
+        server_handle.abort();
+
+        assert!(body.contains(r#"ip="192.168.1.11""#));
+        assert!(!body.contains(r#"ip="192.168.1.10""#));
+        Ok(())
+    }
+}
@@ -0,0 +1,459 @@
+use crate::api::{AxeOsClient, Device, DeviceStatus};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::fleet::{DevicePlan, FleetConfig, SettingChange, plan_changes};
+use crate::output::{
+    format_table, print_error, print_info, print_json, print_success, print_warning,
+};
+use crate::settings::SettingsPolicy;
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tabled::Tabled;
+
+/// Arguments for the diff and apply commands
+pub struct FleetArgs<'a> {
+    pub file: PathBuf,
+    pub device_type: Option<DeviceFilterArg>,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct DriftTableRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "IP Address")]
+    ip_address: String,
+    #[tabled(rename = "Setting")]
+    field: String,
+    #[tabled(rename = "Current")]
+    current: String,
+    #[tabled(rename = "Desired")]
+    desired: String,
+}
+
+/// Drift between a device and the fleet file
+struct DeviceDrift {
+    device: Device,
+    plan: Result<DevicePlan>,
+}
+
+pub async fn diff(args: FleetArgs<'_>) -> Result<()> {
+    let drifts = collect_drift(&args).await?;
+
+    match args.format {
+        OutputFormat::Json => {
+            let devices: Vec<_> = drifts.iter().map(drift_json).collect();
+            let output = serde_json::json!({
+                "file": args.file,
+                "devices": devices,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => print_drift(&drifts, args.color),
+    }
+
+    Ok(())
+}
+
+pub async fn apply(
+    args: FleetArgs<'_>,
+    dry_run: bool,
+    force: bool,
+    allow_unsafe: bool,
+) -> Result<()> {
+    let policy = SettingsPolicy::load(allow_unsafe)?;
+    let drifts = collect_drift(&args).await?;
+
+    let pending: Vec<(&Device, &DevicePlan)> = drifts
+        .iter()
+        .filter_map(|d| match d.plan {
+            Ok(ref plan) if !plan.is_in_sync() => Some((&d.device, plan)),
+            _ => None,
+        })
+        .collect();
+
+    if args.format == OutputFormat::Text {
+        print_drift(&drifts, args.color);
+    }
+
+    if pending.is_empty() || dry_run {
+        if args.format == OutputFormat::Json {
+            let devices: Vec<_> = drifts.iter().map(drift_json).collect();
+            let output = serde_json::json!({
+                "action": "apply",
+                "dry_run": dry_run,
+                "file": args.file,
+                "devices": devices,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        } else if dry_run && !pending.is_empty() {
+            print_info("Dry run, no changes were applied.", args.color);
+        }
+        return Ok(());
+    }
+
+    if !force && args.format == OutputFormat::Text {
+        eprint!(
+            "Apply changes to {count} device(s)? [y/N]: ",
+            count = pending.len()
+        );
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            print_info("Operation cancelled.", args.color);
+            return Ok(());
+        }
+    }
+
+    let mut results = Vec::new();
+
+    for (device, plan) in pending {
+        let client = AxeOsClient::new(&device.ip_address)?;
+        let error = match policy.ensure_supported(&client, &plan.request).await {
+            Err(e) => Some(format!("{e:#}")),
+            Ok(()) => match client.update_system(plan.request.clone()).await {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.message),
+                Err(e) => Some(e.to_string()),
+            },
+        };
+
+        if args.format == OutputFormat::Text {
+            match error {
+                None => print_success(
+                    &format!(
+                        "✓ {name} updated ({count} setting(s))",
+                        name = device.name,
+                        count = plan.changes.len()
+                    ),
+                    args.color,
+                ),
+                Some(ref msg) => print_error(
+                    &format!("✗ {name} failed: {msg}", name = device.name),
+                    args.color,
+                ),
+            }
+        }
+
+        results.push(serde_json::json!({
+            "device": device.name,
+            "ip": device.ip_address,
+            "success": error.is_none(),
+            "changes": plan.changes,
+            "error": error,
+        }));
+    }
+
+    if args.format == OutputFormat::Json {
+        let output = serde_json::json!({
+            "action": "apply",
+            "dry_run": false,
+            "file": args.file,
+            "total_devices": results.len(),
+            "results": results,
+            "timestamp": chrono::Utc::now(),
+        });
+        print_json(&output, true)?;
+    }
+
+    Ok(())
+}
+
+/// Load the fleet file and compare it with the live configuration of every matching device
+async fn collect_drift(args: &FleetArgs<'_>) -> Result<Vec<DeviceDrift>> {
+    let config = FleetConfig::load(&args.file)?;
+
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache = DeviceCache::load(cache_path.as_ref())?;
+
+    let mut devices = match args.device_type {
+        Some(filter) => cache.get_online_devices_by_filter(filter.0),
+        None => cache.get_devices_by_status(DeviceStatus::Online),
+    };
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    if args.format == OutputFormat::Text {
+        for identifier in config.unmatched_identifiers(&devices) {
+            print_warning(
+                &format!("'{identifier}' in the fleet file matches no online device"),
+                args.color,
+            );
+        }
+    }
+
+    let futures = devices
+        .into_iter()
+        .filter_map(|device| {
+            let desired = config.resolve(&device);
+            (!desired.is_empty()).then_some((device, desired))
+        })
+        .map(|(device, desired)| async move {
+            let plan = async {
+                let client = AxeOsClient::new(&device.ip_address)?;
+                let info = client.get_system_info().await.with_context(|| {
+                    format!("Failed to read settings from {name}", name = device.name)
+                })?;
+                Ok(plan_changes(&desired, &info))
+            }
+            .await;
+            DeviceDrift { device, plan }
+        });
+
+    Ok(join_all(futures).await)
+}
+
+fn print_drift(drifts: &[DeviceDrift], color: bool) {
+    if drifts.is_empty() {
+        print_warning("No online devices are covered by the fleet file", color);
+        return;
+    }
+
+    let mut rows = Vec::new();
+    let mut in_sync = 0;
+
+    for drift in drifts {
+        match drift.plan {
+            Ok(ref plan) if plan.is_in_sync() => in_sync += 1,
+            Ok(ref plan) => rows.extend(plan.changes.iter().map(|change| DriftTableRow {
+                name: drift.device.name.clone(),
+                ip_address: drift.device.ip_address.clone(),
+                field: change.field.to_string(),
+                current: change.current.clone(),
+                desired: change.desired.clone(),
+            })),
+            Err(ref e) => print_error(&format!("✗ {name}: {e:#}", name = drift.device.name), color),
+        }
+    }
+
+    if !rows.is_empty() {
+        println!("{}", format_table(rows, color));
+    }
+
+    let drifted = drifts
+        .iter()
+        .filter(|d| matches!(d.plan, Ok(ref plan) if !plan.is_in_sync()))
+        .count();
+    if drifted == 0 {
+        print_success(
+            &format!("All {in_sync} device(s) match the fleet file"),
+            color,
+        );
+    } else {
+        print_info(
+            &format!("{drifted} device(s) differ from the fleet file, {in_sync} in sync"),
+            color,
+        );
+    }
+}
+
+fn drift_json(drift: &DeviceDrift) -> serde_json::Value {
+    let (changes, error): (&[SettingChange], Option<String>) = match drift.plan {
+        Ok(ref plan) => (&plan.changes, None),
+        Err(ref e) => (&[], Some(format!("{e:#}"))),
+    };
+
+    serde_json::json!({
+        "device": drift.device.name,
+        "ip": drift.device.ip_address,
+        "in_sync": error.is_none() && changes.is_empty(),
+        "changes": changes,
+        "error": error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceType;
+    use chrono::Utc;
+    use mockito::Matcher;
+    use tempfile::tempdir;
+
+    const DEVICE_INFO: &str = r#"{
+        "ASICModel": "BM1370",
+        "boardVersion": "601",
+        "version": "2.4.0",
+        "macAddr": "AA:BB:CC:DD:EE:FF",
+        "hostname": "bitaxe-1",
+        "stratumURL": "public-pool.io",
+        "stratumPort": 21496,
+        "stratumUser": "bc1qold.bitaxe-1",
+        "frequency": 525,
+        "voltage": 1150,
+        "fanspeed": 80,
+        "temp": 55.0,
+        "power": 15.0,
+        "hashRate": 1100.0,
+        "uptimeSeconds": 3600,
+        "sharesAccepted": 10,
+        "sharesRejected": 0
+    }"#;
+
+    #[tokio::test]
+    async fn test_apply_patches_only_differing_fields() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let info_mock = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            // Once for the drift, once for the ASIC model the frequency is checked against
+            .expect(2)
+            .create_async()
+            .await;
+        let patch_mock = server
+            .mock("PATCH", "/api/system")
+            .match_body(Matcher::Json(serde_json::json!({
+                "pooluser": "bc1qnew.bitaxe-1",
+                "frequencyvalue": 550,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let tempdir = tempdir()?;
+        let mut cache = DeviceCache::new();
+        cache.add_device(Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: server.host_with_port(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        });
+        cache.save(tempdir.path())?;
+
+        let fleet_file = tempdir.path().join("fleet.toml");
+        std::fs::write(
+            &fleet_file,
+            r#"
+            [defaults]
+            pool_url = "public-pool.io"
+            pool_port = 21496
+            pool_user = "bc1qnew.{hostname}"
+
+            [types.bitaxe-gamma]
+            frequency = 550
+            voltage = 1150
+            "#,
+        )?;
+
+        let args = FleetArgs {
+            file: fleet_file,
+            device_type: None,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        };
+        apply(args, false, true, false).await?;
+
+        info_mock.assert_async().await;
+        patch_mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apply_refuses_unsafe_frequency() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _info_mock = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        let patch_mock = server
+            .mock("PATCH", "/api/system")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let tempdir = tempdir()?;
+        let mut cache = DeviceCache::new();
+        cache.add_device(Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: server.host_with_port(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        });
+        cache.save(tempdir.path())?;
+
+        let fleet_file = tempdir.path().join("fleet.toml");
+        std::fs::write(&fleet_file, "[devices.bitaxe-1]\nfrequency = 800\n")?;
+
+        let args = FleetArgs {
+            file: fleet_file,
+            device_type: None,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        };
+        apply(args, false, true, false).await?;
+
+        patch_mock.assert_async().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_patch() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _info_mock = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            .create_async()
+            .await;
+        let patch_mock = server
+            .mock("PATCH", "/api/system")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let tempdir = tempdir()?;
+        let mut cache = DeviceCache::new();
+        cache.add_device(Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: server.host_with_port(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        });
+        cache.save(tempdir.path())?;
+
+        let fleet_file = tempdir.path().join("fleet.toml");
+        std::fs::write(&fleet_file, "[devices.bitaxe-1]\nfan_speed = 100\n")?;
+
+        let args = FleetArgs {
+            file: fleet_file,
+            device_type: None,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+        };
+        apply(args, true, true, false).await?;
+
+        patch_mock.assert_async().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::cli::commands::OutputFormat;
+use crate::hardware::HardwareDb;
+use crate::output::{format_table, print_info, print_json};
+use anyhow::Result;
+use tabled::Tabled;
+
+#[derive(Tabled)]
+struct HardwareTableRow {
+    #[tabled(rename = "Product")]
+    product: String,
+    #[tabled(rename = "ASIC")]
+    asic_model: String,
+    #[tabled(rename = "Boards")]
+    boards: String,
+    #[tabled(rename = "ASICs")]
+    asic_count: u32,
+    #[tabled(rename = "Cores")]
+    small_core_count: u32,
+    #[tabled(rename = "Stock")]
+    stock: String,
+    #[tabled(rename = "Frequency")]
+    frequency: String,
+    #[tabled(rename = "Voltage")]
+    voltage: String,
+    #[tabled(rename = "Max Temp")]
+    max_temperature: String,
+    #[tabled(rename = "Nominal")]
+    nominal_hashrate: String,
+}
+
+/// Show the hardware profiles settings are validated against
+pub async fn hardware(format: OutputFormat, color: bool) -> Result<()> {
+    let db = HardwareDb::load_user()?;
+    let user_file = HardwareDb::user_path().filter(|path| path.exists());
+
+    match format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "profiles": db.profiles,
+                "user_file": user_file,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            let rows: Vec<HardwareTableRow> = db
+                .profiles
+                .iter()
+                .map(|p| HardwareTableRow {
+                    product: p.product.clone(),
+                    asic_model: p.asic_model.clone(),
+                    boards: if p.device_type.is_some_and(|t| t.is_nerdqaxe()) {
+                        "NerdQAxe".to_string()
+                    } else if p.boards.is_empty() {
+                        "any".to_string()
+                    } else {
+                        p.boards.join(", ")
+                    },
+                    asic_count: p.asic_count,
+                    small_core_count: p.small_core_count,
+                    stock: format!(
+                        "{freq} MHz / {volt:.0} mV",
+                        freq = p.stock_frequency,
+                        volt = p.stock_voltage
+                    ),
+                    frequency: format!(
+                        "{min}-{max} MHz",
+                        min = p.min_frequency,
+                        max = p.max_frequency
+                    ),
+                    voltage: format!(
+                        "{min:.0}-{max:.0} mV",
+                        min = p.min_voltage,
+                        max = p.max_voltage
+                    ),
+                    max_temperature: format!("{temp:.0}°C", temp = p.max_temperature),
+                    nominal_hashrate: format!("{rate:.1} GH/s", rate = p.nominal_hashrate_ghs),
+                })
+                .collect();
+            println!("{}", format_table(rows, color));
+
+            match (user_file, HardwareDb::user_path()) {
+                (Some(path), _) => print_info(
+                    &format!(
+                        "Profiles from {path} take precedence over the built-in ones",
+                        path = path.display()
+                    ),
+                    color,
+                ),
+                (None, Some(path)) => print_info(
+                    &format!(
+                        "Add profiles to {path} to override the built-in ones",
+                        path = path.display()
+                    ),
+                    color,
+                ),
+                (None, None) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use crate::api::{Device, DeviceType};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::history::{
+    HistoryMetric, HistoryStore, SeriesPoint, SeriesSummary, extract_series, parse_duration,
+    parse_time,
+};
+use crate::output::{
+    format_hashrate, format_power, format_sparkline, format_table, print_info, print_json,
+    print_warning,
+};
+use alphanumeric_sort::compare_str;
+use anyhow::{Result, bail, ensure};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use tabled::Tabled;
+
+/// Width of the trend sparkline in the text table
+const SPARKLINE_WIDTH: usize = 40;
+
+/// Arguments for the history command
+pub struct HistoryArgs<'a> {
+    pub device: Option<String>,
+    pub device_type: Option<DeviceFilterArg>,
+    pub since: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub metric: HistoryMetric,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct HistoryTableRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "IP Address")]
+    ip_address: String,
+    #[tabled(rename = "Samples")]
+    samples: usize,
+    #[tabled(rename = "Min")]
+    min: String,
+    #[tabled(rename = "Avg")]
+    avg: String,
+    #[tabled(rename = "Max")]
+    max: String,
+    #[tabled(rename = "P50")]
+    p50: String,
+    #[tabled(rename = "P90")]
+    p90: String,
+    #[tabled(rename = "P99")]
+    p99: String,
+    #[tabled(rename = "Trend")]
+    trend: String,
+}
+
+/// History of one device for the selected metric
+#[derive(Debug, Serialize)]
+struct DeviceHistory {
+    name: String,
+    ip_address: String,
+    device_type: Option<DeviceType>,
+    summary: Option<SeriesSummary>,
+    series: Vec<SeriesPoint>,
+}
+
+pub async fn history(args: HistoryArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+
+    let cache = DeviceCache::load(cache_path)?;
+    let store = HistoryStore::open(cache_path);
+
+    let now = Utc::now();
+    let (from, to) = resolve_time_range(
+        args.since.as_deref(),
+        args.from.as_deref(),
+        args.to.as_deref(),
+        chrono::Duration::hours(24),
+        now,
+    )?;
+
+    let targets = resolve_targets(args.device.as_deref(), args.device_type, &cache, &store)?;
+
+    let mut histories = Vec::new();
+    for (ip_address, device) in targets {
+        let samples = store.query(&ip_address, from, to)?;
+        let series = extract_series(&samples, args.metric);
+        let values: Vec<f64> = series.iter().map(|p| p.value).collect();
+
+        histories.push(DeviceHistory {
+            name: device
+                .as_ref()
+                .map(|d| d.name.clone())
+                .unwrap_or_else(|| ip_address.clone()),
+            ip_address,
+            device_type: device.as_ref().map(|d| d.device_type),
+            summary: SeriesSummary::from_values(&values),
+            series,
+        });
+    }
+    histories.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    match args.format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "metric": args.metric,
+                "from": from,
+                "to": to,
+                "devices": histories,
+                "timestamp": now,
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if histories.iter().all(|h| h.series.is_empty()) {
+                print_warning(
+                    &format!(
+                        "No {metric} history between {from} and {to}",
+                        metric = args.metric,
+                        from = from.format("%Y-%m-%d %H:%M"),
+                        to = to.format("%Y-%m-%d %H:%M")
+                    ),
+                    args.color,
+                );
+                print_info(
+                    "History is recorded by 'axectl monitor' and 'axectl list --watch'",
+                    args.color,
+                );
+                return Ok(());
+            }
+
+            let table_rows: Vec<HistoryTableRow> = histories
+                .iter()
+                .map(|h| {
+                    let values: Vec<f64> = h.series.iter().map(|p| p.value).collect();
+                    let format = |value: f64| format_metric(args.metric, value);
+                    match h.summary {
+                        Some(summary) => HistoryTableRow {
+                            name: h.name.clone(),
+                            ip_address: h.ip_address.clone(),
+                            samples: summary.count,
+                            min: format(summary.min),
+                            avg: format(summary.avg),
+                            max: format(summary.max),
+                            p50: format(summary.p50),
+                            p90: format(summary.p90),
+                            p99: format(summary.p99),
+                            trend: format_sparkline(&values, SPARKLINE_WIDTH),
+                        },
+                        None => HistoryTableRow {
+                            name: h.name.clone(),
+                            ip_address: h.ip_address.clone(),
+                            samples: 0,
+                            min: "-".to_string(),
+                            avg: "-".to_string(),
+                            max: "-".to_string(),
+                            p50: "-".to_string(),
+                            p90: "-".to_string(),
+                            p99: "-".to_string(),
+                            trend: "-".to_string(),
+                        },
+                    }
+                })
+                .collect();
+
+            print_info(
+                &format!(
+                    "{metric} from {from} to {to}",
+                    metric = args.metric,
+                    from = from.format("%Y-%m-%d %H:%M"),
+                    to = to.format("%Y-%m-%d %H:%M")
+                ),
+                args.color,
+            );
+            println!("{}", format_table(table_rows, args.color));
+        }
+    }
+
+    Ok(())
+}
+
+/// Work out the `[from, to]` range from `--since` or `--from`/`--to`
+/// (default: `default_span` until `--to`); `--since` counts back from `--to`
+pub(crate) fn resolve_time_range(
+    since: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    default_span: chrono::Duration,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let to = match to {
+        Some(to) => parse_time(to, now)?,
+        None => now,
+    };
+
+    let from = match (since, from) {
+        (Some(_), Some(_)) => bail!("--since and --from cannot be used together"),
+        (Some(since), None) => to - parse_duration(since)?,
+        (None, Some(from)) => parse_time(from, now)?,
+        (None, None) => to - default_span,
+    };
+
+    ensure!(from < to, "Start of the time range must be before its end");
+    Ok((from, to))
+}
+
+/// Resolve which devices to report on, as (IP address, cached device) pairs
+pub(crate) fn resolve_targets(
+    device: Option<&str>,
+    device_type: Option<DeviceFilterArg>,
+    cache: &DeviceCache,
+    store: &HistoryStore,
+) -> Result<Vec<(String, Option<Device>)>> {
+    if let Some(identifier) = device {
+        if let Some(device) = cache.find_device(identifier) {
+            return Ok(vec![(device.ip_address.clone(), Some(device))]);
+        }
+
+        // The device may have been pruned from the cache while its history remains
+        if store.devices()?.iter().any(|ip| ip == identifier) {
+            return Ok(vec![(identifier.to_string(), None)]);
+        }
+
+        bail!("Device not found: {identifier}");
+    }
+
+    let targets = store
+        .devices()?
+        .into_iter()
+        .map(|ip| {
+            let device = cache.get_device(&ip).map(|cached| cached.device.clone());
+            (ip, device)
+        })
+        .filter(|(_, device)| match (device_type, device) {
+            (Some(filter), Some(device)) => filter.0.matches(device.device_type),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
+
+    Ok(targets)
+}
+
+/// Format a metric value with its unit
+fn format_metric(metric: HistoryMetric, value: f64) -> String {
+    match metric {
+        HistoryMetric::Hashrate => format_hashrate(value),
+        HistoryMetric::Temperature => format!("{value:.1}°C"),
+        HistoryMetric::Power => format_power(value),
+        HistoryMetric::Fan => format!("{value:.0}"),
+        HistoryMetric::SharesAccepted | HistoryMetric::SharesRejected => format!("{value:.0}"),
+        HistoryMetric::Voltage => format!("{value:.0} mV"),
+        HistoryMetric::Frequency => format!("{value:.0} MHz"),
+        HistoryMetric::Rssi => format!("{value:.0} dBm"),
+        HistoryMetric::HashrateHealth => format!("{value:.1}%"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since_counts_back_from_to() -> Result<()> {
+        let now = DateTime::parse_from_rfc3339("2025-01-10T12:00:00Z")?.with_timezone(&Utc);
+        let day = chrono::Duration::days(1);
+
+        let (from, to) = resolve_time_range(Some("6h"), None, None, day, now)?;
+        assert_eq!((from, to), (now - chrono::Duration::hours(6), now));
+
+        let (from, to) = resolve_time_range(Some("6h"), None, Some("2d"), day, now)?;
+        assert_eq!(to, now - chrono::Duration::days(2));
+        assert_eq!(from, to - chrono::Duration::hours(6));
+        Ok(())
+    }
+}
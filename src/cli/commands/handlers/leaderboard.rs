@@ -0,0 +1,127 @@
+use crate::best_difficulty::BestDifficultyStore;
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::output::{
+    format_difficulty, format_table, format_table_without, print_info, print_json, print_warning,
+};
+use anyhow::Result;
+use chrono::Local;
+use std::collections::HashSet;
+use std::path::Path;
+use tabled::Tabled;
+
+/// Arguments for the leaderboard command
+pub struct LeaderboardArgs<'a> {
+    pub device_type: Option<DeviceFilterArg>,
+    pub network_difficulty: Option<f64>,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct LeaderboardTableRow {
+    #[tabled(rename = "Rank")]
+    rank: usize,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "IP Address")]
+    ip_address: String,
+    #[tabled(rename = "Best")]
+    best: String,
+    #[tabled(rename = "% of network")]
+    network_percent: String,
+    #[tabled(rename = "Achieved")]
+    achieved_at: String,
+    #[tabled(rename = "Session best")]
+    session_best: String,
+}
+
+pub async fn leaderboard(args: LeaderboardArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+
+    let store = BestDifficultyStore::load(cache_path)?;
+    let mut entries = store.leaderboard();
+
+    if let Some(filter) = args.device_type {
+        let cache = DeviceCache::load(cache_path)?;
+        let matching: HashSet<String> = cache
+            .get_devices_by_filter(filter.0)
+            .into_iter()
+            .map(|d| d.ip_address)
+            .collect();
+        entries.retain(|(ip, _)| matching.contains(*ip));
+    }
+
+    let network_percent = |best: f64| args.network_difficulty.map(|n| best / n * 100.0);
+
+    match args.format {
+        OutputFormat::Json => {
+            let devices: Vec<_> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, (ip, record))| {
+                    serde_json::json!({
+                        "rank": i + 1,
+                        "ip_address": ip,
+                        "name": record.name,
+                        "best": record.best,
+                        "achieved_at": record.achieved_at,
+                        "session_best": record.session_best,
+                        "session_achieved_at": record.session_achieved_at,
+                        "network_percent": network_percent(record.best),
+                    })
+                })
+                .collect();
+            let output = serde_json::json!({
+                "network_difficulty": args.network_difficulty,
+                "devices": devices,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if entries.is_empty() {
+                print_warning("No best difficulty records yet", args.color);
+                print_info(
+                    "Records are kept by 'axectl list' and 'axectl monitor'",
+                    args.color,
+                );
+                return Ok(());
+            }
+
+            let table_rows: Vec<LeaderboardTableRow> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, (ip, record))| LeaderboardTableRow {
+                    rank: i + 1,
+                    name: record.name.clone(),
+                    ip_address: ip.to_string(),
+                    best: format_difficulty(record.best),
+                    network_percent: network_percent(record.best)
+                        .map(|p| format!("{p:.4}%"))
+                        .unwrap_or_else(|| "-".to_string()),
+                    achieved_at: record
+                        .achieved_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                    session_best: record
+                        .session_best
+                        .map(format_difficulty)
+                        .unwrap_or_else(|| "-".to_string()),
+                })
+                .collect();
+
+            let table = if args.network_difficulty.is_some() {
+                format_table(table_rows, args.color)
+            } else {
+                format_table_without(table_rows, &["% of network"], args.color)
+            };
+            println!("{table}");
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,10 @@
+use crate::alerts::{AlertEngine, AlertRules};
+use crate::best_difficulty::BestDifficultyStore;
 use crate::cache::get_cache_dir;
 use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::daemon::DaemonClient;
+use crate::energy::{EnergyEstimate, Tariff};
+use crate::history::HistoryStore;
 use alphanumeric_sort::compare_str;
 use anyhow::{Context, Result};
 use crossterm::{
@@ -14,6 +19,9 @@ use std::time::Duration;
 use tabled::Tabled;
 use tokio::time::{sleep, timeout};
 
+/// How often the history store applies its retention policy in watch mode
+const HISTORY_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
 /// Arguments for the list command
 pub struct ListArgs<'a> {
     pub all: bool,
@@ -25,9 +33,13 @@ pub struct ListArgs<'a> {
     pub timeout: u64,
     pub no_mdns: bool,
     pub device_type: Option<DeviceFilterArg>,
-    pub temp_alert: Option<f64>,
-    pub hashrate_alert: Option<f64>,
+    /// Rules evaluated on every refresh (only with --watch)
+    pub alert_rules: AlertRules,
     pub type_summary: bool,
+    pub no_history: bool,
+    pub tariff: Option<Tariff>,
+    /// Take devices and statistics from a running daemon instead of polling
+    pub daemon: Option<DaemonClient>,
     pub format: OutputFormat,
     pub color: bool,
     pub cache_dir: Option<&'a Path>,
@@ -45,18 +57,35 @@ struct DeviceTableRow {
     status: String,
     #[tabled(rename = "Hashrate")]
     hashrate: String,
+    #[tabled(rename = "Health")]
+    health: String,
     #[tabled(rename = "Temp")]
     temperature: String,
     #[tabled(rename = "Power")]
     power: String,
+    #[tabled(rename = "J/TH")]
+    efficiency: String,
+    #[tabled(rename = "kWh/day")]
+    energy: String,
+    #[tabled(rename = "Cost/day")]
+    cost: String,
     #[tabled(rename = "Fan")]
     fan_speed: String,
     #[tabled(rename = "Uptime")]
     uptime: String,
+    #[tabled(rename = "Best")]
+    best_difficulty: String,
+    #[tabled(rename = "Session")]
+    session_difficulty: String,
     #[tabled(rename = "Pool")]
     pool: String,
+    #[tabled(rename = "Fallback")]
+    fallback: String,
 }
 
+/// Columns that are only shown when an electricity tariff is configured
+pub(crate) const ENERGY_COLUMNS: [&str; 2] = ["kWh/day", "Cost/day"];
+
 #[derive(Tabled)]
 struct BasicDeviceTableRow {
     #[tabled(rename = "Name")]
@@ -72,13 +101,13 @@ struct BasicDeviceTableRow {
 }
 
 pub async fn list(args: ListArgs<'_>) -> Result<()> {
-    use crate::api::{DeviceStatus, SwarmSummary};
+    use crate::api::{DeviceStatus, SwarmSummary, joules_per_terahash};
     use crate::cache::DeviceCache;
     use crate::output::{
-        ColoredTemperature, format_hashrate, format_power, format_table, format_uptime, print_info,
-        print_json, print_success, print_warning,
+        ColoredTemperature, format_efficiency, format_hashrate, format_health, format_power,
+        format_table, format_table_without, format_uptime, print_info, print_json, print_success,
+        print_warning,
     };
-    use std::collections::HashMap;
 
     // Set up alternate screen for watch mode to prevent flicker.
     // The alternate screen is a separate buffer that doesn't affect the main terminal scrollback.
@@ -127,15 +156,37 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
         use_alternate_screen,
     };
 
-    // Track previous hashrates for drop detection
-    let mut previous_hashrates: HashMap<String, f64> = HashMap::new();
+    // Alerts fire and resolve on state changes across refreshes
+    let mut alert_engine = AlertEngine::default();
     let mut alert_count = 0;
 
     // Get cache directory, using default if not provided
     let cache_path = get_cache_dir(args.cache_dir)?;
     let cache_path = cache_path.as_ref();
 
+    // Long-term statistics history is only recorded while watching
+    let history = if args.watch && !args.no_history {
+        Some(HistoryStore::open(cache_path))
+    } else {
+        None
+    };
+    let mut last_maintenance: Option<std::time::Instant> = None;
+
+    let mut best_difficulty = BestDifficultyStore::load(cache_path).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load best difficulty records: {e:#}");
+        BestDifficultyStore::default()
+    });
+
     loop {
+        if let Some(ref history) = history
+            && last_maintenance.is_none_or(|t| t.elapsed() >= HISTORY_MAINTENANCE_INTERVAL)
+        {
+            if let Err(e) = history.maintain(chrono::Utc::now()) {
+                tracing::warn!("Failed to maintain history store: {e}");
+            }
+            last_maintenance = Some(std::time::Instant::now());
+        }
+
         // Perform discovery if requested
         if args.discover {
             eprintln!(); // Add spacing
@@ -165,8 +216,20 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
         // Load from cache
         let mut cache = DeviceCache::load(cache_path)?;
 
+        // The daemon already polled its devices, so its answer includes their statistics
+        let daemon_devices = match args.daemon {
+            Some(ref daemon) => Some(
+                daemon
+                    .devices(args.device_type.map(|f| f.0), args.all)
+                    .await?,
+            ),
+            None => None,
+        };
+
         // Apply type filtering if specified
-        let devices = if let Some(ref device_filter_arg) = args.device_type {
+        let devices = if let Some(ref daemon_devices) = daemon_devices {
+            daemon_devices.clone()
+        } else if let Some(ref device_filter_arg) = args.device_type {
             let filter = device_filter_arg.0;
             if args.all {
                 cache.get_devices_by_filter(filter)
@@ -180,7 +243,11 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
         };
 
         let cache_age_minutes = cache.age_seconds() / 60;
-        if !devices.is_empty() && args.format == OutputFormat::Text && !args.watch {
+        if !devices.is_empty()
+            && args.format == OutputFormat::Text
+            && !args.watch
+            && daemon_devices.is_none()
+        {
             print_warning(
                 &format!(
                     "📦 Showing cached devices ({} minutes old)",
@@ -226,98 +293,34 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
         let mut device_stats = Vec::new();
         let mut alerts = Vec::new();
 
-        if !args.no_stats {
+        if let Some(ref daemon_devices) = daemon_devices {
+            if !args.no_stats {
+                device_stats = daemon_devices
+                    .iter()
+                    .map(|d| match d.status {
+                        DeviceStatus::Online => d.stats.clone(),
+                        _ => None,
+                    })
+                    .collect();
+            }
+        } else if !args.no_stats {
             // Create futures for parallel stats collection
             let stats_futures: Vec<_> = devices
                 .iter()
                 .map(|device| {
                     let device_clone = device.clone();
-                    let watch = args.watch;
-                    let temp_alert = args.temp_alert;
-                    let hashrate_alert = args.hashrate_alert;
-                    let prev_hashrates = previous_hashrates.clone();
 
                     async move {
                         if device_clone.status != DeviceStatus::Online {
-                            return (device_clone, None, Vec::new());
+                            return (device_clone, None);
                         }
 
                         // Use timeout to prevent indefinite waiting (60s for patient timeout)
                         let result =
                             timeout(Duration::from_secs(60), collect_device_stats(&device_clone))
-                                .await;
-
-                        let mut local_alerts = Vec::new();
-
-                        match result {
-                            Ok(Ok(stats)) => {
-                                // Check for alerts if in watch mode
-                                if watch {
-                                    // Temperature alert
-                                    if let Some(temp_threshold) = temp_alert
-                                        && stats.temperature_celsius > temp_threshold
-                                    {
-                                        local_alerts.push(format!(
-                                            "🌡️ {} temperature alert: {:.1}°C > {:.1}°C",
-                                            device_clone.name,
-                                            stats.temperature_celsius,
-                                            temp_threshold
-                                        ));
-                                    }
-
-                                    // Hashrate drop alert
-                                    if let Some(hashrate_threshold) = hashrate_alert
-                                        && let Some(previous_hashrate) =
-                                            prev_hashrates.get(&device_clone.ip_address)
-                                    {
-                                        let drop_percent = ((previous_hashrate
-                                            - stats.hashrate_mhs)
-                                            / previous_hashrate)
-                                            * 100.0;
-                                        if drop_percent > hashrate_threshold {
-                                            local_alerts.push(format!(
-                                                "📉 {} hashrate drop: {:.1}% ({} -> {})",
-                                                device_clone.name,
-                                                drop_percent,
-                                                format_hashrate(*previous_hashrate),
-                                                format_hashrate(stats.hashrate_mhs)
-                                            ));
-                                        }
-                                    }
-                                }
-
-                                (device_clone, Some(stats), local_alerts)
-                            }
-                            Ok(Err(e)) => {
-                                tracing::warn!(
-                                    "Failed to collect stats from {}: {}",
-                                    device_clone.ip_address,
-                                    e
-                                );
-
-                                // Add offline alert if in watch mode
-                                if watch {
-                                    local_alerts
-                                        .push(format!("🔌 {} went offline", device_clone.name));
-                                }
-
-                                (device_clone, None, local_alerts)
-                            }
-                            Err(_) => {
-                                tracing::warn!(
-                                    "Timeout collecting stats from {} (60s)",
-                                    device_clone.ip_address
-                                );
-
-                                // Add timeout alert if in watch mode
-                                if watch {
-                                    local_alerts
-                                        .push(format!("⏱️ {} timeout (60s)", device_clone.name));
-                                }
-
-                                (device_clone, None, local_alerts)
-                            }
-                        }
+                                .await
+                                .unwrap_or_else(|_| Err(anyhow::anyhow!("timeout (60s)")));
+                        (device_clone, Some(result))
                     }
                 })
                 .collect();
@@ -326,22 +329,62 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
             let results = join_all(stats_futures).await;
 
             // Process results
-            for (device, stats_opt, device_alerts) in results {
-                if let Some(stats) = &stats_opt {
-                    // Update stats in cache
-                    cache.update_device_stats(&device.ip_address, stats.clone());
-
-                    // Update previous hashrates for next iteration
-                    if args.watch && args.hashrate_alert.is_some() {
-                        previous_hashrates.insert(device.ip_address.clone(), stats.hashrate_mhs);
+            for (device, result) in results {
+                let now = chrono::Utc::now();
+                let stats_opt = match result {
+                    Some(Ok(stats)) => {
+                        // Update stats in cache
+                        cache.update_device_stats(&device.ip_address, stats.clone());
+
+                        let events = best_difficulty.observe(&device, &stats, None, now);
+                        if args.watch {
+                            alerts.extend(
+                                alert_engine
+                                    .evaluate(&args.alert_rules, &device, &stats, now)
+                                    .into_iter()
+                                    .map(|alert| alert.message),
+                            );
+                            alerts.extend(events.iter().map(|e| e.message(&device.name)));
+                        }
+
+                        if let Some(ref history) = history
+                            && let Err(e) = history.append(&device.ip_address, &stats)
+                        {
+                            tracing::warn!(
+                                "Failed to record history for {ip}: {e}",
+                                ip = device.ip_address
+                            );
+                        }
+                        Some(stats)
                     }
-                } else {
-                    // Mark device as offline in cache if failed
-                    cache.mark_device_probed(&device.ip_address, false);
-                }
+                    Some(Err(e)) => {
+                        tracing::warn!(
+                            "Failed to collect stats from {ip}: {e}",
+                            ip = device.ip_address
+                        );
+
+                        // Mark device as offline in cache if failed
+                        cache.mark_device_probed(&device.ip_address, false);
+                        if args.watch {
+                            alerts.extend(
+                                alert_engine
+                                    .device_offline(&device, now)
+                                    .map(|alert| alert.message),
+                            );
+                        }
+                        None
+                    }
+                    None => {
+                        cache.mark_device_probed(&device.ip_address, false);
+                        None
+                    }
+                };
 
                 device_stats.push(stats_opt);
-                alerts.extend(device_alerts);
+            }
+
+            if let Err(e) = best_difficulty.save_if_changed(cache_path) {
+                tracing::warn!("Failed to save best difficulty records: {e:#}");
             }
         }
 
@@ -382,6 +425,13 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                                             name = device.name
                                         )
                                     })?;
+                                if args.tariff.is_some() {
+                                    device_json["energy"] =
+                                        serde_json::to_value(EnergyEstimate::from_power(
+                                            stats.power_watts,
+                                            args.tariff.as_ref(),
+                                        ))?;
+                                }
                             }
                             Ok(device_json)
                         })
@@ -397,41 +447,8 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                     let swarm_summary = if online_devices.is_empty() {
                         SwarmSummary::default()
                     } else {
-                        SwarmSummary {
-                            total_devices: devices.len(),
-                            devices_online: online_devices.len(),
-                            devices_offline: devices.len() - online_devices.len(),
-                            total_hashrate_mhs: online_devices
-                                .iter()
-                                .map(|(_, s)| s.hashrate_mhs)
-                                .sum(),
-                            total_power_watts: online_devices
-                                .iter()
-                                .map(|(_, s)| s.power_watts)
-                                .sum(),
-                            average_temperature: online_devices
-                                .iter()
-                                .map(|(_, s)| s.temperature_celsius)
-                                .sum::<f64>()
-                                / online_devices.len() as f64,
-                            average_efficiency: if online_devices
-                                .iter()
-                                .map(|(_, s)| s.power_watts)
-                                .sum::<f64>()
-                                > 0.0
-                            {
-                                online_devices
-                                    .iter()
-                                    .map(|(_, s)| s.hashrate_mhs)
-                                    .sum::<f64>()
-                                    / online_devices
-                                        .iter()
-                                        .map(|(_, s)| s.power_watts)
-                                        .sum::<f64>()
-                            } else {
-                                0.0
-                            },
-                        }
+                        let online_stats: Vec<_> = online_devices.iter().map(|(_, s)| *s).collect();
+                        SwarmSummary::from_stats(devices.len(), &online_stats)
                     };
                     let mut output = serde_json::json!({
                         "devices": devices_with_stats,
@@ -446,6 +463,18 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                         output["type_filter"] = serde_json::json!(type_filter);
                     }
 
+                    // Add projected fleet energy use and cost if a tariff is configured
+                    if let Some(ref tariff) = args.tariff {
+                        output["energy"] = serde_json::json!({
+                            "fleet": EnergyEstimate::from_power(
+                                swarm_summary.total_power_watts,
+                                Some(tariff),
+                            ),
+                            "currency": tariff.currency,
+                            "average_price": tariff.average_price(),
+                        });
+                    }
+
                     // Add alerts if in watch mode
                     if args.watch && !alerts.is_empty() {
                         output["alerts"] = serde_json::json!(alerts);
@@ -515,21 +544,40 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                         .iter()
                         .map(|(device, stats)| {
                             if let Some(stats) = stats {
+                                let energy = EnergyEstimate::from_power(
+                                    stats.power_watts,
+                                    args.tariff.as_ref(),
+                                );
                                 DeviceTableRow {
                                     name: device.name.clone(),
                                     ip_address: device.ip_address.clone(),
                                     device_type: device.device_type.as_str().to_string(),
                                     status: format!("{:?}", device.status),
                                     hashrate: format_hashrate(stats.hashrate_mhs),
+                                    health: format_health(stats.hashrate_health_percent),
                                     temperature: ColoredTemperature::new(
                                         stats.temperature_celsius,
                                         args.color,
                                     )
                                     .to_string(),
                                     power: format_power(stats.power_watts),
+                                    efficiency: format_efficiency(stats.efficiency_jth),
+                                    energy: format!("{kwh:.2}", kwh = energy.kwh_per_day),
+                                    cost: format_cost(args.tariff.as_ref(), energy.cost_per_day),
                                     fan_speed: format!("{rpm}", rpm = stats.fan_speed_rpm),
                                     uptime: format_uptime(stats.uptime_seconds),
+                                    best_difficulty: format_optional_difficulty(
+                                        stats.best_difficulty,
+                                    ),
+                                    session_difficulty: format_optional_difficulty(
+                                        stats.best_session_difficulty,
+                                    ),
                                     pool: stats.pool_url.as_deref().unwrap_or("-").to_string(),
+                                    fallback: match stats.using_fallback_pool {
+                                        Some(true) => "yes".to_string(),
+                                        Some(false) => "no".to_string(),
+                                        None => "-".to_string(),
+                                    },
                                 }
                             } else {
                                 DeviceTableRow {
@@ -538,20 +586,33 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                                     device_type: device.device_type.as_str().to_string(),
                                     status: format!("{:?}", device.status),
                                     hashrate: "-".to_string(),
+                                    health: "-".to_string(),
                                     temperature: "-".to_string(),
                                     power: "-".to_string(),
+                                    efficiency: "-".to_string(),
+                                    energy: "-".to_string(),
+                                    cost: "-".to_string(),
                                     fan_speed: "-".to_string(),
                                     uptime: "-".to_string(),
+                                    best_difficulty: "-".to_string(),
+                                    session_difficulty: "-".to_string(),
                                     pool: "-".to_string(),
+                                    fallback: "-".to_string(),
                                 }
                             }
                         })
                         .collect();
 
+                    let hidden_columns: &[&str] = if args.tariff.is_some() {
+                        &[]
+                    } else {
+                        &ENERGY_COLUMNS
+                    };
+                    let table = format_table_without(table_rows, hidden_columns, args.color);
                     if let Some(ref mut buffer) = output_buffer {
-                        writeln!(buffer, "{}", format_table(table_rows, args.color))?;
+                        writeln!(buffer, "{table}")?;
                     } else {
-                        println!("{}", format_table(table_rows, args.color));
+                        println!("{table}");
                     }
 
                     // Show summary if we have stats for multiple devices
@@ -565,29 +626,22 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                             .map(|s| s.temperature_celsius)
                             .sum::<f64>()
                             / online_stats.len() as f64;
+                        let summary = format!(
+                            "Summary: {} devices, {} total, {:.1}W total, {:.1}°C avg, {}{}",
+                            online_stats.len(),
+                            format_hashrate(total_hashrate),
+                            total_power,
+                            avg_temp,
+                            format_efficiency(joules_per_terahash(total_hashrate, total_power)),
+                            format_energy_summary(total_power, args.tariff.as_ref())
+                        );
 
                         if let Some(ref mut buffer) = output_buffer {
                             writeln!(buffer)?;
-                            writeln!(
-                                buffer,
-                                "ℹ Summary: {} devices, {} total, {:.1}W total, {:.1}°C avg",
-                                online_stats.len(),
-                                format_hashrate(total_hashrate),
-                                total_power,
-                                avg_temp
-                            )?;
+                            writeln!(buffer, "ℹ {summary}")?;
                         } else {
                             println!();
-                            print_info(
-                                &format!(
-                                    "Summary: {} devices, {} total, {:.1}W total, {:.1}°C avg",
-                                    online_stats.len(),
-                                    format_hashrate(total_hashrate),
-                                    total_power,
-                                    avg_temp
-                                ),
-                                args.color,
-                            );
+                            print_info(&summary, args.color);
                         }
                     }
                 }
@@ -646,13 +700,14 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
 
                                 writeln!(
                                     buffer,
-                                    "{} {} ({}/{} online) | {} | {:.1}W | Avg: {:.1}°C",
+                                    "{} {} ({}/{} online) | {} | {:.1}W | {} | Avg: {:.1}°C",
                                     status_indicator,
                                     summary.type_name,
                                     summary.devices_online,
                                     summary.total_devices,
                                     format_hashrate(summary.total_hashrate_mhs),
                                     summary.total_power_watts,
+                                    format_efficiency(summary.efficiency_jth),
                                     summary.average_temperature
                                 )?;
                             }
@@ -676,13 +731,14 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
                                 };
 
                                 println!(
-                                    "{} {} ({}/{} online) | {} | {:.1}W | Avg: {:.1}°C",
+                                    "{} {} ({}/{} online) | {} | {:.1}W | {} | Avg: {:.1}°C",
                                     status_indicator,
                                     summary.type_name,
                                     summary.devices_online,
                                     summary.total_devices,
                                     format_hashrate(summary.total_hashrate_mhs),
                                     summary.total_power_watts,
+                                    format_efficiency(summary.efficiency_jth),
                                     summary.average_temperature
                                 );
                             }
@@ -759,11 +815,41 @@ pub async fn list(args: ListArgs<'_>) -> Result<()> {
     Ok(())
 }
 
-async fn collect_device_stats(device: &crate::api::DeviceInfo) -> Result<crate::api::DeviceStats> {
+pub(crate) async fn collect_device_stats(
+    device: &crate::api::DeviceInfo,
+) -> Result<crate::api::DeviceStats> {
     let client =
         crate::api::AxeOsClient::with_timeout(&device.ip_address, Duration::from_secs(60))?;
 
-    let (info, stats) = client.get_complete_info().await?;
+    client.get_device_stats().await
+}
 
-    Ok(crate::api::DeviceStats::from_api_responses(&info, &stats))
+/// Format a best share difficulty, or "-" when the device doesn't report it
+pub(crate) fn format_optional_difficulty(difficulty: Option<f64>) -> String {
+    difficulty
+        .map(crate::output::format_difficulty)
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Format a projected cost per day, or "-" without a tariff
+pub(crate) fn format_cost(tariff: Option<&Tariff>, cost: Option<f64>) -> String {
+    match (tariff, cost) {
+        (Some(tariff), Some(cost)) => tariff.format_cost(cost),
+        _ => "-".to_string(),
+    }
+}
+
+/// Projected fleet energy use and cost for the summary line
+pub(crate) fn format_energy_summary(total_power_watts: f64, tariff: Option<&Tariff>) -> String {
+    match tariff {
+        Some(tariff) => {
+            let energy = EnergyEstimate::from_power(total_power_watts, Some(tariff));
+            format!(
+                ", {kwh:.2} kWh/day, {cost}/day",
+                kwh = energy.kwh_per_day,
+                cost = format_cost(Some(tariff), energy.cost_per_day)
+            )
+        }
+        None => String::new(),
+    }
 }
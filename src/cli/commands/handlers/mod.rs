@@ -1,13 +1,39 @@
+pub mod backup;
 pub mod bulk;
 pub mod control;
+pub mod daemon;
 pub mod discovery;
+pub mod energy;
+pub mod exporter;
+pub mod fleet;
+pub mod hardware;
+pub mod history;
+pub mod leaderboard;
 pub mod list;
 pub mod monitor;
 pub mod monitor_async;
+pub mod mqtt;
+pub mod pool;
+pub mod profit;
+pub mod tui;
+pub mod tune;
 
+pub use backup::{BackupArgs, RestoreArgs, backup, restore};
 pub use bulk::bulk;
 pub use control::control;
+pub use daemon::{DaemonArgs, daemon};
 pub use discovery::discover;
+pub use energy::{EnergyArgs, energy};
+pub use exporter::{ExporterArgs, exporter};
+pub use fleet::{FleetArgs, apply, diff};
+pub use hardware::hardware;
+pub use history::{HistoryArgs, history};
+pub use leaderboard::{LeaderboardArgs, leaderboard};
 pub use list::{ListArgs, list};
 pub use monitor::monitor;
-pub use monitor_async::monitor_async;
+pub use monitor_async::{monitor_async, monitor_stream};
+pub use mqtt::{MqttArgs, mqtt};
+pub use pool::{PoolCheckArgs, pool_check};
+pub use profit::{ProfitArgs, profit};
+pub use tui::monitor_tui;
+pub use tune::{TuneArgs, tune};
@@ -1,3 +1,4 @@
+use crate::alerts::{AlertEngine, AlertRules};
 use crate::cli::commands::{DeviceFilterArg, OutputFormat};
 use anyhow::{Context, Result};
 use crossterm::{
@@ -5,7 +6,6 @@ use crossterm::{
     execute,
     terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::collections::HashMap;
 use std::io::{Write as IoWrite, stdout};
 use std::path::Path;
 use std::time::Duration;
@@ -13,8 +13,7 @@ use tokio::time::sleep;
 
 pub struct MonitorConfig<'a> {
     pub interval: u64,
-    pub temp_alert: Option<f64>,
-    pub hashrate_alert: Option<f64>,
+    pub alert_rules: AlertRules,
     pub type_filter: Option<DeviceFilterArg>,
     pub type_summary: bool,
     pub format: OutputFormat,
@@ -99,8 +98,8 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
         }
     };
 
-    // Track previous hashrates for drop detection
-    let mut previous_hashrates: HashMap<String, f64> = HashMap::new();
+    // Alerts fire and resolve on state changes across refreshes
+    let mut alert_engine = AlertEngine::default();
     let mut alert_count = 0;
 
     // Load cache
@@ -150,34 +149,12 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
         for device in &devices {
             match collect_device_stats(device).await {
                 Ok(stats) => {
-                    // Check for alerts
-                    if let Some(temp_threshold) = config.temp_alert
-                        && stats.temperature_celsius > temp_threshold
-                    {
-                        alerts.push(format!(
-                            "🌡️ {} temperature alert: {:.1}°C > {:.1}°C",
-                            device.name, stats.temperature_celsius, temp_threshold
-                        ));
-                    }
-
-                    if let Some(hashrate_threshold) = config.hashrate_alert {
-                        if let Some(previous_hashrate) = previous_hashrates.get(&device.ip_address)
-                        {
-                            let drop_percent = ((previous_hashrate - stats.hashrate_mhs)
-                                / previous_hashrate)
-                                * 100.0;
-                            if drop_percent > hashrate_threshold {
-                                alerts.push(format!(
-                                    "📉 {} hashrate drop: {:.1}% ({} -> {})",
-                                    device.name,
-                                    drop_percent,
-                                    crate::output::format_hashrate(*previous_hashrate),
-                                    crate::output::format_hashrate(stats.hashrate_mhs)
-                                ));
-                            }
-                        }
-                        previous_hashrates.insert(device.ip_address.clone(), stats.hashrate_mhs);
-                    }
+                    alerts.extend(
+                        alert_engine
+                            .evaluate(&config.alert_rules, device, &stats, chrono::Utc::now())
+                            .into_iter()
+                            .map(|alert| alert.message),
+                    );
 
                     // Update stats in cache
                     cache.update_device_stats(&device.ip_address, stats.clone());
@@ -191,8 +168,11 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
                     cache.mark_device_probed(&device.ip_address, false);
                     device_stats.push(None);
 
-                    // Add offline alert
-                    alerts.push(format!("🔌 {} went offline", device.name));
+                    alerts.extend(
+                        alert_engine
+                            .device_offline(device, chrono::Utc::now())
+                            .map(|alert| alert.message),
+                    );
                 }
             }
         }
@@ -238,38 +218,8 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
                 let swarm_summary = if online_devices.is_empty() {
                     SwarmSummary::default()
                 } else {
-                    SwarmSummary {
-                        total_devices: devices.len(),
-                        devices_online: online_devices.len(),
-                        devices_offline: devices.len() - online_devices.len(),
-                        total_hashrate_mhs: online_devices
-                            .iter()
-                            .map(|(_, s)| s.hashrate_mhs)
-                            .sum(),
-                        total_power_watts: online_devices.iter().map(|(_, s)| s.power_watts).sum(),
-                        average_temperature: online_devices
-                            .iter()
-                            .map(|(_, s)| s.temperature_celsius)
-                            .sum::<f64>()
-                            / online_devices.len() as f64,
-                        average_efficiency: if online_devices
-                            .iter()
-                            .map(|(_, s)| s.power_watts)
-                            .sum::<f64>()
-                            > 0.0
-                        {
-                            online_devices
-                                .iter()
-                                .map(|(_, s)| s.hashrate_mhs)
-                                .sum::<f64>()
-                                / online_devices
-                                    .iter()
-                                    .map(|(_, s)| s.power_watts)
-                                    .sum::<f64>()
-                        } else {
-                            0.0
-                        },
-                    }
+                    let online_stats: Vec<_> = online_devices.iter().map(|(_, s)| *s).collect();
+                    SwarmSummary::from_stats(devices.len(), &online_stats)
                 };
 
                 let mut output = serde_json::json!({
@@ -292,7 +242,8 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
             }
             OutputFormat::Text => {
                 use crate::output::{
-                    ColoredTemperature, format_hashrate, format_power, format_table, format_uptime,
+                    ColoredTemperature, format_hashrate, format_health, format_power, format_table,
+                    format_uptime,
                 };
                 use std::fmt::Write as FmtWrite;
                 use tabled::Tabled;
@@ -310,6 +261,8 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
                     device_type: String,
                     #[tabled(rename = "Hashrate")]
                     hashrate: String,
+                    #[tabled(rename = "Health")]
+                    health: String,
                     #[tabled(rename = "Temp")]
                     temperature: String,
                     #[tabled(rename = "Power")]
@@ -328,6 +281,7 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
                                 ip_address: device.ip_address.clone(),
                                 device_type: device.device_type.as_str().to_string(),
                                 hashrate: format_hashrate(stats.hashrate_mhs),
+                                health: format_health(stats.hashrate_health_percent),
                                 temperature: ColoredTemperature::new(
                                     stats.temperature_celsius,
                                     config.color,
@@ -342,6 +296,7 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
                                 ip_address: device.ip_address.clone(),
                                 device_type: device.device_type.as_str().to_string(),
                                 hashrate: "-".to_string(),
+                                health: "-".to_string(),
                                 temperature: "-".to_string(),
                                 power: "-".to_string(),
                                 uptime: "-".to_string(),
@@ -448,6 +403,5 @@ pub async fn monitor(config: MonitorConfig<'_>) -> Result<()> {
 async fn collect_device_stats(device: &crate::api::Device) -> Result<crate::api::DeviceStats> {
     let client =
         crate::api::AxeOsClient::with_timeout(&device.ip_address, Duration::from_secs(60))?;
-    let (info, stats) = client.get_complete_info().await?;
-    Ok(crate::api::DeviceStats::from_api_responses(&info, &stats))
+    client.get_device_stats().await
 }
@@ -1,11 +1,26 @@
-use crate::api::{Device, DeviceStats, DeviceStatus, SwarmSummary};
+use crate::alerts::{
+    Alert, AlertEngine, AlertKind, AlertRules, AlertState, Notifier, NotifierHandle, NotifyConfig,
+    Severity,
+};
+use crate::api::{
+    AxeOsClient, Device, DeviceStats, DeviceStatus, SwarmSummary, joules_per_terahash,
+};
+use crate::best_difficulty::{BestDifficultyStore, NearBlockConfig};
 use crate::cache::{DeviceCache, get_cache_dir};
 use crate::cli::commands::handlers::discovery::perform_discovery;
+use crate::cli::commands::handlers::list::{
+    ENERGY_COLUMNS, format_cost, format_energy_summary, format_optional_difficulty,
+};
 use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::energy::{EnergyEstimate, Tariff};
+use crate::events::{DeviceRef, EventKind, MonitorEvent};
+use crate::history::HistoryStore;
 use crate::output::{
-    ColoredTemperature, format_hashrate, format_power, format_table, format_uptime, print_info,
-    print_json, print_success, print_warning,
+    ColoredTemperature, format_efficiency, format_hashrate, format_health, format_power,
+    format_table, format_table_without, format_uptime, print_info, print_json, print_success,
+    print_warning,
 };
+use crate::thermal::{GuardAction, ThermalGuard, ThermalGuardConfig, ThermalLog, apply_action};
 use alphanumeric_sort::compare_str;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
@@ -15,7 +30,7 @@ use crossterm::{
     terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use futures::future::join_all;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::io::{Write as IoWrite, stdout};
 use std::path::{Path, PathBuf};
@@ -23,32 +38,48 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tabled::Tabled;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{RwLock, broadcast, mpsc};
 use tokio::time::{interval, timeout};
 
 /// Shared state for the monitor
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct MonitorState {
     pub devices: HashMap<String, Device>,
-    pub alerts: Vec<Alert>,
+    /// Most recent alerts, oldest first, capped at `ALERT_HISTORY_SIZE`
+    pub alerts: VecDeque<Alert>,
     pub discovery_active: bool,
     pub last_discovery: Option<DateTime<Utc>>,
     pub alert_count: usize,
-    pub previous_hashrates: HashMap<String, f64>,
+    pub alert_engine: AlertEngine,
+    pub thermal_guard: ThermalGuard,
+    pub best_difficulty: BestDifficultyStore,
+    /// Subscribers to fleet changes (`monitor --stream`, the daemon's event stream)
+    pub events: Option<broadcast::Sender<MonitorEvent>>,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct Alert {
-    pub timestamp: DateTime<Utc>,
-    pub message: String,
-    pub device_ip: String,
+impl MonitorState {
+    /// Publish an event to the stream subscribers, if streaming is enabled
+    pub fn emit(&self, kind: EventKind) {
+        if let Some(ref events) = self.events {
+            // Sending only fails while nobody is subscribed
+            let _ = events.send(MonitorEvent::new(kind));
+        }
+    }
+
+    /// Keep alerts for the alert list, dropping the oldest beyond the cap
+    pub fn record_alerts(&mut self, alerts: &[Alert]) {
+        self.alert_count += alerts.len();
+        self.alerts.extend(alerts.iter().cloned());
+        let excess = self.alerts.len().saturating_sub(ALERT_HISTORY_SIZE);
+        self.alerts.drain(..excess);
+    }
 }
 
 /// Configuration for the async monitor
 pub struct AsyncMonitorConfig<'a> {
     pub interval: u64,
-    pub temp_alert: Option<f64>,
-    pub hashrate_alert: Option<f64>,
+    pub alert_rules: AlertRules,
     pub type_filter: Option<DeviceFilterArg>,
     pub type_summary: bool,
     pub format: OutputFormat,
@@ -60,11 +91,54 @@ pub struct AsyncMonitorConfig<'a> {
     pub discover_interval: u64,
     pub network: Option<String>,
     pub no_mdns: bool,
+    pub no_history: bool,
+    pub thermal_guard: Option<ThermalGuardConfig>,
+    pub notify: Option<NotifyConfig>,
+    pub tariff: Option<Tariff>,
+    pub near_block: Option<NearBlockConfig>,
+}
+
+/// How often the history store applies its retention policy
+const HISTORY_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// How often the shutdown flag is checked between polling rounds
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Alerts kept in memory for the daemon API and the TUI alert log
+pub(crate) const ALERT_HISTORY_SIZE: usize = 1000;
+
+/// Events buffered per stream subscriber before a slow one starts missing some
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// What the monitor loop does with the results of each polling round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MonitorOutput {
+    /// Render the device table or JSON to stdout
+    Display,
+    /// Only update the shared state (used by the exporter)
+    StateOnly,
+    /// Only update the shared state, without printing progress (used by the TUI)
+    Quiet,
+}
+
+impl MonitorOutput {
+    /// Whether progress messages are printed to the terminal
+    fn prints_status(self, format: OutputFormat) -> bool {
+        self != MonitorOutput::Quiet && matches!(format, OutputFormat::Text)
+    }
+}
+
+/// Results of one polling round
+struct PollResult {
+    devices: Vec<Device>,
+    device_stats: Vec<Option<DeviceStats>>,
+    alerts: Vec<Alert>,
 }
 
 /// Message types for communication between tasks
 #[derive(Debug)]
 enum MonitorMessage {
+    /// Devices found by discovery that are not monitored or were offline
     NewDevices(Vec<Device>),
     DiscoveryComplete(usize),
 }
@@ -94,14 +168,26 @@ struct MonitorTableRow {
     status: String,
     #[tabled(rename = "Hashrate")]
     hashrate: String,
+    #[tabled(rename = "Health")]
+    health: String,
     #[tabled(rename = "Temp")]
     temperature: String,
     #[tabled(rename = "Power")]
     power: String,
+    #[tabled(rename = "J/TH")]
+    efficiency: String,
+    #[tabled(rename = "kWh/day")]
+    energy: String,
+    #[tabled(rename = "Cost/day")]
+    cost: String,
     #[tabled(rename = "Fan")]
     fan_speed: String,
     #[tabled(rename = "Uptime")]
     uptime: String,
+    #[tabled(rename = "Best")]
+    best_difficulty: String,
+    #[tabled(rename = "Session")]
+    session_difficulty: String,
     #[tabled(rename = "Pool")]
     pool: String,
 }
@@ -182,29 +268,63 @@ impl Drop for CleanupGuard {
     }
 }
 
+/// Print fleet changes to stdout as NDJSON, one [`MonitorEvent`] per line
+pub async fn monitor_stream(config: AsyncMonitorConfig<'_>) -> Result<()> {
+    let (events, receiver) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let state = Arc::new(RwLock::new(MonitorState {
+        events: Some(events),
+        ..MonitorState::default()
+    }));
+    let shutdown = Arc::new(AtomicBool::new(false));
+
+    tokio::select! {
+        result = run_monitor_loop(&config, state, shutdown, MonitorOutput::Quiet) => result,
+        result = write_events(receiver) => result,
+    }
+}
+
+async fn write_events(mut receiver: broadcast::Receiver<MonitorEvent>) -> Result<()> {
+    let mut stdout_handle = stdout();
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                let line = serde_json::to_string(&event).context("Failed to serialize event")?;
+                writeln!(stdout_handle, "{line}")
+                    .and_then(|()| stdout_handle.flush())
+                    .context("Failed to write event")?;
+            }
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!("Event stream fell behind, {skipped} event(s) dropped");
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
 async fn monitor_async_impl(
     config: AsyncMonitorConfig<'_>,
     shutdown: Arc<AtomicBool>,
+) -> Result<()> {
+    let state = Arc::new(RwLock::new(MonitorState::default()));
+    run_monitor_loop(&config, state, shutdown, MonitorOutput::Display).await
+}
+
+/// Poll devices on the configured interval, keeping `state` up to date until Ctrl+C
+pub(crate) async fn run_monitor_loop(
+    config: &AsyncMonitorConfig<'_>,
+    state: Arc<RwLock<MonitorState>>,
+    shutdown: Arc<AtomicBool>,
+    output: MonitorOutput,
 ) -> Result<()> {
     // Get cache directory, using default if not provided
     let cache_path = get_cache_dir(config.cache_dir)?;
     let cache_path = cache_path.as_ref();
 
-    // Initialize shared state
-    let state = Arc::new(RwLock::new(MonitorState {
-        devices: HashMap::new(),
-        alerts: Vec::new(),
-        discovery_active: false,
-        last_discovery: None,
-        alert_count: 0,
-        previous_hashrates: HashMap::new(),
-    }));
-
     // Load initial cache
     let cache = Arc::new(RwLock::new(DeviceCache::load(cache_path)?));
     {
         let cache_guard = cache.read().await;
-        if !cache_guard.is_empty() && matches!(config.format, OutputFormat::Text) {
+        if !cache_guard.is_empty() && output.prints_status(config.format) {
             print_info(
                 &format!(
                     "📦 Loaded {count} device(s) from cache",
@@ -233,8 +353,26 @@ async fn monitor_async_impl(
                 .devices
                 .insert(device.ip_address.clone(), device);
         }
+
+        state_guard.best_difficulty = BestDifficultyStore::load(cache_path).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load best difficulty records: {e:#}");
+            BestDifficultyStore::default()
+        });
     }
 
+    // Long-term statistics history
+    let history = if config.no_history {
+        None
+    } else {
+        Some(HistoryStore::open(cache_path))
+    };
+
+    // Background delivery of alerts to notification sinks
+    let notifier = match config.notify {
+        Some(ref notify) => Some(Notifier::new(notify)?.spawn()),
+        None => None,
+    };
+
     // Create communication channel
     let (tx, mut rx) = mpsc::channel::<MonitorMessage>(100);
 
@@ -263,12 +401,14 @@ async fn monitor_async_impl(
 
     // Main monitoring loop
     let mut monitor_timer = interval(Duration::from_secs(config.interval));
+    let mut maintenance_timer = interval(HISTORY_MAINTENANCE_INTERVAL);
+    let mut shutdown_timer = interval(SHUTDOWN_CHECK_INTERVAL);
 
     loop {
         tokio::select! {
             // Check for shutdown signal with high priority
             _ = tokio::signal::ctrl_c() => {
-                if matches!(config.format, OutputFormat::Text) {
+                if output.prints_status(config.format) {
                     print_info("\n✅ Shutting down gracefully...", config.color);
                 }
                 break;
@@ -277,7 +417,7 @@ async fn monitor_async_impl(
             _ = monitor_timer.tick() => {
                 // Check shutdown flag set by signal handler
                 if shutdown.load(Ordering::SeqCst) {
-                    if matches!(config.format, OutputFormat::Text) {
+                    if output.prints_status(config.format) {
                         print_info("\n✅ Shutting down gracefully...", config.color);
                     }
                     break;
@@ -288,19 +428,56 @@ async fn monitor_async_impl(
                     &state,
                     &cache,
                     cache_path,
-                    &config,
-                    &tx,
+                    history.as_ref(),
+                    notifier.as_ref(),
+                    config,
+                    output,
                 ).await?;
             }
 
+            // Don't wait for the next polling round when asked to stop
+            _ = shutdown_timer.tick() => {
+                if shutdown.load(Ordering::SeqCst) {
+                    if output.prints_status(config.format) {
+                        print_info("\n✅ Shutting down gracefully...", config.color);
+                    }
+                    break;
+                }
+            }
+
+            _ = maintenance_timer.tick(), if history.is_some() => {
+                if let Some(ref history) = history
+                    && let Err(e) = history.maintain(Utc::now())
+                {
+                    tracing::warn!("Failed to maintain history store: {e}");
+                }
+            }
+
             Some(msg) = rx.recv() => {
-                if handle_monitor_message(&state, &config, msg).await {
-                    update_and_display(&state, &cache, cache_path, &config, &tx).await?;
+                if handle_monitor_message(&state, config, output, msg).await {
+                    update_and_display(
+                        &state,
+                        &cache,
+                        cache_path,
+                        history.as_ref(),
+                        notifier.as_ref(),
+                        config,
+                        output,
+                    )
+                    .await?;
                 }
             }
         }
     }
 
+    if config.thermal_guard.is_some() {
+        restore_guarded_devices(&state, config, output).await;
+    }
+
+    if let Some(notifier) = notifier {
+        notifier.finish().await;
+    }
+
     Ok(())
 }
 
@@ -340,7 +517,11 @@ where
                     let state_guard = ctx.state.read().await;
 
                     for device in discovered {
-                        if !state_guard.devices.contains_key(&device.ip_address) {
+                        let known_online = state_guard
+                            .devices
+                            .get(&device.ip_address)
+                            .is_some_and(|d| d.status == DeviceStatus::Online);
+                        if !known_online {
                             new_devices.push(device.clone());
                         }
                         cache_guard.update_device(device);
@@ -381,21 +562,33 @@ where
 async fn handle_monitor_message(
     state: &Arc<RwLock<MonitorState>>,
     config: &AsyncMonitorConfig<'_>,
+    output: MonitorOutput,
     msg: MonitorMessage,
 ) -> bool {
     match msg {
         MonitorMessage::NewDevices(devices) => {
             let mut state_guard = state.write().await;
             for device in devices {
-                if matches!(config.format, OutputFormat::Text) {
-                    print_success(
-                        &format!(
-                            "🆕 New device discovered: {name} ({ip})",
-                            name = device.name,
-                            ip = device.ip_address
-                        ),
-                        config.color,
-                    );
+                if let Some(known) = state_guard.devices.get(&device.ip_address) {
+                    if known.status == DeviceStatus::Offline {
+                        state_guard.emit(EventKind::DeviceOnline {
+                            device: DeviceRef::from(&device),
+                        });
+                    }
+                } else {
+                    if output.prints_status(config.format) {
+                        print_success(
+                            &format!(
+                                "🆕 New device discovered: {name} ({ip})",
+                                name = device.name,
+                                ip = device.ip_address
+                            ),
+                            config.color,
+                        );
+                    }
+                    state_guard.emit(EventKind::DeviceDiscovered {
+                        device: device.clone(),
+                    });
                 }
                 state_guard
                     .devices
@@ -404,7 +597,11 @@ async fn handle_monitor_message(
             true
         }
         MonitorMessage::DiscoveryComplete(count) => {
-            if matches!(config.format, OutputFormat::Text) {
+            state
+                .read()
+                .await
+                .emit(EventKind::DiscoveryComplete { total: count });
+            if output.prints_status(config.format) {
                 print_info(
                     &format!("✓ Background discovery complete, {count} total devices found"),
                     config.color,
@@ -419,27 +616,22 @@ async fn update_and_display(
     state: &Arc<RwLock<MonitorState>>,
     cache: &Arc<RwLock<DeviceCache>>,
     cache_path: &Path,
+    history: Option<&HistoryStore>,
+    notifier: Option<&NotifierHandle>,
     config: &AsyncMonitorConfig<'_>,
-    _tx: &mpsc::Sender<MonitorMessage>,
+    output: MonitorOutput,
 ) -> Result<()> {
-    // Get current devices based on filter
-    let devices: Vec<Device> = {
-        let state_guard = state.read().await;
-        let mut devices: Vec<_> = state_guard.devices.values().cloned().collect();
+    let poll = poll_devices(state, cache, cache_path, history, config).await?;
 
-        // Apply filters
-        if let Some(ref filter_arg) = config.type_filter {
-            devices.retain(|d| filter_arg.0.matches(d.device_type));
-        }
-
-        if !config.all {
-            devices.retain(|d| d.status == DeviceStatus::Online);
-        }
+    if let Some(notifier) = notifier {
+        notifier.send(&poll.alerts);
+    }
 
-        devices
-    };
+    if output != MonitorOutput::Display {
+        return Ok(());
+    }
 
-    if devices.is_empty() {
+    if poll.devices.is_empty() {
         if matches!(config.format, OutputFormat::Text) {
             if let Some(ref type_filter) = config.type_filter {
                 print_warning(&format!("No {type_filter} devices found"), config.color);
@@ -461,15 +653,65 @@ async fn update_and_display(
         return Ok(());
     }
 
+    // Display results
+    display_results(
+        state,
+        cache,
+        &poll.devices,
+        &poll.device_stats,
+        &poll.alerts,
+        config,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Devices in the monitor state that match the type filter (and are online unless `all`)
+pub(crate) fn monitored_devices(
+    state: &MonitorState,
+    type_filter: Option<DeviceFilterArg>,
+    all: bool,
+) -> Vec<Device> {
+    let mut devices: Vec<_> = state.devices.values().cloned().collect();
+
+    // Apply filters
+    if let Some(filter_arg) = type_filter {
+        devices.retain(|d| filter_arg.0.matches(d.device_type));
+    }
+
+    if !all {
+        devices.retain(|d| d.status == DeviceStatus::Online);
+    }
+
+    devices
+}
+
+/// Collect stats from all monitored devices, run alert checks and update state, cache and history
+async fn poll_devices(
+    state: &Arc<RwLock<MonitorState>>,
+    cache: &Arc<RwLock<DeviceCache>>,
+    cache_path: &Path,
+    history: Option<&HistoryStore>,
+    config: &AsyncMonitorConfig<'_>,
+) -> Result<PollResult> {
+    // Get current devices based on filter
+    let devices = {
+        let state_guard = state.read().await;
+        monitored_devices(&state_guard, config.type_filter, config.all)
+    };
+
     // Collect stats asynchronously if not in no-stats mode
     let mut device_stats = Vec::new();
     let mut alerts = Vec::new();
+    let mut temperatures = Vec::new();
+    let mut events = Vec::new();
 
-    if !config.no_stats {
-        // Create futures for all device stats collection
+    if !devices.is_empty() && !config.no_stats {
+        // Create futures for all device stats collection, offline devices included so
+        // `--all` notices when they come back
         let stats_futures: Vec<_> = devices
             .iter()
-            .filter(|d| d.status == DeviceStatus::Online)
             .map(|device| {
                 let device_clone = device.clone();
                 async move {
@@ -491,71 +733,64 @@ async fn update_and_display(
         for (ip, result) in results {
             match result {
                 Ok(Ok(stats)) => {
-                    // Check for alerts
-                    if let Some(temp_threshold) = config.temp_alert
-                        && stats.temperature_celsius > temp_threshold
-                        && let Some(device) = state_guard.devices.get(&ip)
-                    {
-                        alerts.push(Alert {
-                            timestamp: Utc::now(),
-                            message: format!(
-                                "🌡️ {name} temperature alert: {temp:.1}°C > {threshold:.1}°C",
-                                name = device.name,
-                                temp = stats.temperature_celsius,
-                                threshold = temp_threshold
-                            ),
-                            device_ip: ip.clone(),
-                        });
-                    }
-
-                    if let Some(hashrate_threshold) = config.hashrate_alert {
-                        if let Some(previous_hashrate) = state_guard.previous_hashrates.get(&ip) {
-                            let drop_percent = ((previous_hashrate - stats.hashrate_mhs)
-                                / previous_hashrate)
-                                * 100.0;
-                            if drop_percent > hashrate_threshold
-                                && let Some(device) = state_guard.devices.get(&ip)
-                            {
-                                alerts.push(Alert {
-                                    timestamp: Utc::now(),
-                                    message: format!(
-                                        "📉 {name} hashrate drop: {drop:.1}% ({prev} -> {curr})",
-                                        name = device.name,
-                                        drop = drop_percent,
-                                        prev = format_hashrate(*previous_hashrate),
-                                        curr = format_hashrate(stats.hashrate_mhs)
-                                    ),
-                                    device_ip: ip.clone(),
-                                });
-                            }
-                        }
-                        state_guard
-                            .previous_hashrates
-                            .insert(ip.clone(), stats.hashrate_mhs);
+                    // Evaluate alert rules and best difficulty records
+                    let MonitorState {
+                        devices,
+                        alert_engine,
+                        best_difficulty,
+                        ..
+                    } = &mut *state_guard;
+                    if let Some(device) = devices.get(&ip) {
+                        let now = Utc::now();
+                        alerts.extend(alert_engine.evaluate(
+                            &config.alert_rules,
+                            device,
+                            &stats,
+                            now,
+                        ));
+                        alerts.extend(
+                            best_difficulty
+                                .observe(device, &stats, config.near_block.as_ref(), now)
+                                .iter()
+                                .map(|event| event.to_alert(device, now)),
+                        );
                     }
 
                     // Update device with stats
                     if let Some(device) = state_guard.devices.get_mut(&ip) {
                         device.stats = Some(stats.clone());
-                        device.status = DeviceStatus::Online;
+                        events.extend(set_reachable(device, true));
                         device.last_seen = Utc::now();
+                        temperatures.push((device.clone(), stats.temperature_celsius));
+                        events.push(EventKind::Stats {
+                            device: DeviceRef::from(&*device),
+                            stats: stats.clone(),
+                        });
                     }
 
                     // Update cache
                     cache_guard.update_device_stats(&ip, stats.clone());
+
+                    if let Some(history) = history
+                        && let Err(e) = history.append(&ip, &stats)
+                    {
+                        tracing::warn!("Failed to record history for {ip}: {e}");
+                    }
+
                     device_stats.push(Some(stats));
                 }
                 Ok(Err(e)) => {
                     tracing::warn!("Failed to collect stats from {ip}: {e}");
 
                     // Mark device as offline
-                    if let Some(device) = state_guard.devices.get_mut(&ip) {
-                        device.status = DeviceStatus::Offline;
-                        alerts.push(Alert {
-                            timestamp: Utc::now(),
-                            message: format!("🔌 {name} went offline", name = device.name),
-                            device_ip: ip.clone(),
-                        });
+                    let MonitorState {
+                        devices,
+                        alert_engine,
+                        ..
+                    } = &mut *state_guard;
+                    if let Some(device) = devices.get_mut(&ip) {
+                        events.extend(set_reachable(device, false));
+                        alerts.extend(alert_engine.device_offline(device, Utc::now()));
                     }
 
                     cache_guard.mark_device_probed(&ip, false);
@@ -565,13 +800,14 @@ async fn update_and_display(
                     tracing::warn!("Failed to collect stats from {ip} (timeout)");
 
                     // Mark device as offline
-                    if let Some(device) = state_guard.devices.get_mut(&ip) {
-                        device.status = DeviceStatus::Offline;
-                        alerts.push(Alert {
-                            timestamp: Utc::now(),
-                            message: format!("🔌 {name} went offline", name = device.name),
-                            device_ip: ip.clone(),
-                        });
+                    let MonitorState {
+                        devices,
+                        alert_engine,
+                        ..
+                    } = &mut *state_guard;
+                    if let Some(device) = devices.get_mut(&ip) {
+                        events.extend(set_reachable(device, false));
+                        alerts.extend(alert_engine.device_offline(device, Utc::now()));
                     }
 
                     cache_guard.mark_device_probed(&ip, false);
@@ -581,19 +817,139 @@ async fn update_and_display(
         }
 
         // Add alerts to state
-        state_guard.alerts.extend(alerts.clone());
-        state_guard.alert_count += alerts.len();
+        state_guard.record_alerts(&alerts);
 
         // Save cache
         if let Err(e) = cache_guard.save(cache_path) {
             tracing::warn!("Failed to save cache: {e}");
         }
+        if let Err(e) = state_guard.best_difficulty.save_if_changed(cache_path) {
+            tracing::warn!("Failed to save best difficulty records: {e:#}");
+        }
     }
 
-    // Display results
-    display_results(state, cache, &devices, &device_stats, &alerts, config).await?;
+    if let Some(ref guard_config) = config.thermal_guard
+        && !temperatures.is_empty()
+    {
+        let guard_alerts = run_thermal_guard(state, guard_config, &temperatures, cache_path).await;
+        let mut state_guard = state.write().await;
+        state_guard.record_alerts(&guard_alerts);
+        alerts.extend(guard_alerts);
+    }
 
-    Ok(())
+    {
+        let state_guard = state.read().await;
+        let alert_events = alerts.iter().map(|alert| EventKind::Alert {
+            alert: alert.clone(),
+        });
+        for kind in events.into_iter().chain(alert_events) {
+            state_guard.emit(kind);
+        }
+    }
+
+    Ok(PollResult {
+        devices,
+        device_stats,
+        alerts,
+    })
+}
+
+/// Record whether a poll reached a device, returning the event for an online/offline transition
+fn set_reachable(device: &mut Device, reachable: bool) -> Option<EventKind> {
+    let previous = device.status.clone();
+    device.status = if reachable {
+        DeviceStatus::Online
+    } else {
+        DeviceStatus::Offline
+    };
+
+    let device_ref = DeviceRef::from(&*device);
+    match (previous, reachable) {
+        (DeviceStatus::Offline, true) => Some(EventKind::DeviceOnline { device: device_ref }),
+        (DeviceStatus::Online, false) => Some(EventKind::DeviceOffline { device: device_ref }),
+        _ => None,
+    }
+}
+
+/// Let the thermal guard act on hot devices, returning an alert per action taken
+async fn run_thermal_guard(
+    state: &Arc<RwLock<MonitorState>>,
+    config: &ThermalGuardConfig,
+    temperatures: &[(Device, f64)],
+    cache_path: &Path,
+) -> Vec<Alert> {
+    // Device updates are slow, so work on a copy instead of holding the state lock
+    let mut guard = state.read().await.thermal_guard.clone();
+    let log = ThermalLog::open(cache_path);
+    let mut alerts = Vec::new();
+
+    for (device, temperature) in temperatures {
+        match guard.check_device(config, device, *temperature).await {
+            Ok(Some(event)) => {
+                tracing::info!(
+                    "Thermal guard on {name} ({ip}) at {temperature:.1}°C: {before} -> {after}",
+                    name = event.device,
+                    ip = event.ip,
+                    before = event.before,
+                    after = event.after
+                );
+                if let Err(e) = log.append(&event) {
+                    tracing::warn!("Failed to write thermal guard log: {e}");
+                }
+                alerts.push(Alert {
+                    timestamp: event.timestamp,
+                    kind: AlertKind::ThermalGuard,
+                    severity: Severity::Info,
+                    state: AlertState::Firing,
+                    rule: None,
+                    message: event.message(),
+                    device_ip: event.ip.clone(),
+                });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("{e:#}"),
+        }
+    }
+
+    state.write().await.thermal_guard = guard;
+    alerts
+}
+
+/// Put back the settings of every device the thermal guard still holds
+async fn restore_guarded_devices(
+    state: &Arc<RwLock<MonitorState>>,
+    config: &AsyncMonitorConfig<'_>,
+    output: MonitorOutput,
+) {
+    let guarded: Vec<_> = {
+        let state_guard = state.read().await;
+        state_guard
+            .thermal_guard
+            .guarded()
+            .map(|(ip, settings)| (ip.to_string(), settings))
+            .collect()
+    };
+
+    for (ip, settings) in guarded {
+        let result = match AxeOsClient::new(&ip) {
+            Ok(client) => apply_action(&client, &GuardAction::Restore { settings }).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                if output.prints_status(config.format) {
+                    print_info(
+                        &format!(
+                            "🧊 Restored {freq} MHz and fan settings on {ip}",
+                            freq = settings.frequency
+                        ),
+                        config.color,
+                    );
+                }
+            }
+            Err(e) => tracing::warn!("Failed to restore thermal guard settings on {ip}: {e:#}"),
+        }
+    }
 }
 
 async fn display_results(
@@ -630,6 +986,13 @@ async fn display_results(
                                 name = device.name
                             )
                         })?;
+                        if config.tariff.is_some() {
+                            device_json["energy"] =
+                                serde_json::to_value(EnergyEstimate::from_power(
+                                    stats.power_watts,
+                                    config.tariff.as_ref(),
+                                ))?;
+                        }
                     }
                     serialized_devices.push(device_json);
                 }
@@ -645,44 +1008,11 @@ async fn display_results(
             let swarm_summary = if online_devices.is_empty() {
                 SwarmSummary::default()
             } else {
-                SwarmSummary {
-                    total_devices: devices.len(),
-                    devices_online: online_devices.len(),
-                    devices_offline: devices.len() - online_devices.len(),
-                    total_hashrate_mhs: online_devices
-                        .iter()
-                        .filter_map(|d| d.stats.as_ref())
-                        .map(|s| s.hashrate_mhs)
-                        .sum(),
-                    total_power_watts: online_devices
-                        .iter()
-                        .filter_map(|d| d.stats.as_ref())
-                        .map(|s| s.power_watts)
-                        .sum(),
-                    average_temperature: online_devices
-                        .iter()
-                        .filter_map(|d| d.stats.as_ref())
-                        .map(|s| s.temperature_celsius)
-                        .sum::<f64>()
-                        / online_devices.len() as f64,
-                    average_efficiency: {
-                        let total_power: f64 = online_devices
-                            .iter()
-                            .filter_map(|d| d.stats.as_ref())
-                            .map(|s| s.power_watts)
-                            .sum();
-                        if total_power > 0.0 {
-                            let total_hashrate: f64 = online_devices
-                                .iter()
-                                .filter_map(|d| d.stats.as_ref())
-                                .map(|s| s.hashrate_mhs)
-                                .sum();
-                            total_hashrate / total_power
-                        } else {
-                            0.0
-                        }
-                    },
-                }
+                let online_stats: Vec<_> = online_devices
+                    .iter()
+                    .filter_map(|d| d.stats.as_ref())
+                    .collect();
+                SwarmSummary::from_stats(devices.len(), &online_stats)
             };
 
             let mut output = serde_json::json!({
@@ -692,11 +1022,27 @@ async fn display_results(
                 "discovery_active": state_guard.discovery_active,
             });
 
+            if let Some(ref tariff) = config.tariff {
+                output["energy"] = serde_json::json!({
+                    "fleet": EnergyEstimate::from_power(
+                        swarm_summary.total_power_watts,
+                        Some(tariff),
+                    ),
+                    "currency": tariff.currency,
+                    "average_price": tariff.average_price(),
+                });
+            }
+
             if !alerts.is_empty() {
                 output["alerts"] = serde_json::json!(alerts);
                 output["alert_count"] = serde_json::json!(state_guard.alert_count);
             }
 
+            let active = state_guard.alert_engine.active();
+            if !active.is_empty() {
+                output["active_alerts"] = serde_json::json!(active);
+            }
+
             if config.type_summary {
                 let cache_guard = cache.read().await;
                 let type_summaries = cache_guard.get_type_summaries();
@@ -746,20 +1092,32 @@ async fn display_results(
                     .iter()
                     .map(|device| {
                         if let Some(ref stats) = device.stats {
+                            let energy = EnergyEstimate::from_power(
+                                stats.power_watts,
+                                config.tariff.as_ref(),
+                            );
                             MonitorTableRow {
                                 name: device.name.clone(),
                                 ip_address: device.ip_address.clone(),
                                 device_type: device.device_type.as_str().to_string(),
                                 status: format!("{status:?}", status = device.status),
                                 hashrate: format_hashrate(stats.hashrate_mhs),
+                                health: format_health(stats.hashrate_health_percent),
                                 temperature: ColoredTemperature::new(
                                     stats.temperature_celsius,
                                     config.color,
                                 )
                                 .to_string(),
                                 power: format_power(stats.power_watts),
+                                efficiency: format_efficiency(stats.efficiency_jth),
+                                energy: format!("{kwh:.2}", kwh = energy.kwh_per_day),
+                                cost: format_cost(config.tariff.as_ref(), energy.cost_per_day),
                                 fan_speed: format!("{rpm}", rpm = stats.fan_speed_rpm),
                                 uptime: format_uptime(stats.uptime_seconds),
+                                best_difficulty: format_optional_difficulty(stats.best_difficulty),
+                                session_difficulty: format_optional_difficulty(
+                                    stats.best_session_difficulty,
+                                ),
                                 pool: stats.pool_url.as_deref().unwrap_or("-").to_string(),
                             }
                         } else {
@@ -769,20 +1127,31 @@ async fn display_results(
                                 device_type: device.device_type.as_str().to_string(),
                                 status: format!("{status:?}", status = device.status),
                                 hashrate: "-".to_string(),
+                                health: "-".to_string(),
                                 temperature: "-".to_string(),
                                 power: "-".to_string(),
+                                efficiency: "-".to_string(),
+                                energy: "-".to_string(),
+                                cost: "-".to_string(),
                                 fan_speed: "-".to_string(),
                                 uptime: "-".to_string(),
+                                best_difficulty: "-".to_string(),
+                                session_difficulty: "-".to_string(),
                                 pool: "-".to_string(),
                             }
                         }
                     })
                     .collect();
 
+                let hidden_columns: &[&str] = if config.tariff.is_some() {
+                    &[]
+                } else {
+                    &ENERGY_COLUMNS
+                };
                 writeln!(
                     &mut output_buffer,
                     "{}",
-                    format_table(table_rows, config.color)
+                    format_table_without(table_rows, hidden_columns, config.color)
                 )?;
 
                 // Show summary
@@ -801,21 +1170,40 @@ async fn display_results(
                     writeln!(&mut output_buffer)?;
                     writeln!(
                         &mut output_buffer,
-                        "ℹ Summary: {count} devices, {hashrate} total, {power:.1}W total, {temp:.1}°C avg",
+                        "ℹ Summary: {count} devices, {hashrate} total, {power:.1}W total, {temp:.1}°C avg, {efficiency}{energy}",
                         count = online_stats.len(),
                         hashrate = format_hashrate(total_hashrate),
                         power = total_power,
-                        temp = avg_temp
+                        temp = avg_temp,
+                        efficiency =
+                            format_efficiency(joules_per_terahash(total_hashrate, total_power)),
+                        energy = format_energy_summary(total_power, config.tariff.as_ref())
                     )?;
                 }
             }
 
-            // Show alerts if any
-            if !alerts.is_empty() {
+            // Show firing alerts, plus what resolved or happened this round
+            let active = state_guard.alert_engine.active();
+            let events: Vec<_> = alerts
+                .iter()
+                .filter(|a| a.state == AlertState::Resolved || a.kind.is_event())
+                .collect();
+            if !active.is_empty() || !events.is_empty() {
                 writeln!(&mut output_buffer)?;
                 writeln!(&mut output_buffer, "🚨 ALERTS:")?;
-                for alert in alerts {
-                    writeln!(&mut output_buffer, "⚠️ {}", alert.message)?;
+                for alert in active {
+                    writeln!(
+                        &mut output_buffer,
+                        "{message} (since {since})",
+                        message = alert.message,
+                        since = alert
+                            .timestamp
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M:%S")
+                    )?;
+                }
+                for alert in events {
+                    writeln!(&mut output_buffer, "{message}", message = alert.message)?;
                 }
             }
 
@@ -841,13 +1229,14 @@ async fn display_results(
                         };
                         writeln!(
                             &mut output_buffer,
-                            "{} {} ({}/{} online) | {} | {:.1}W | Avg: {:.1}°C",
+                            "{} {} ({}/{} online) | {} | {:.1}W | {} | Avg: {:.1}°C",
                             status_indicator,
                             summary.type_name,
                             summary.devices_online,
                             summary.total_devices,
                             format_hashrate(summary.total_hashrate_mhs),
                             summary.total_power_watts,
+                            format_efficiency(summary.efficiency_jth),
                             summary.average_temperature
                         )?;
                     }
@@ -890,8 +1279,7 @@ async fn display_results(
 async fn collect_device_stats(device: &Device) -> Result<DeviceStats> {
     let client =
         crate::api::AxeOsClient::with_timeout(&device.ip_address, Duration::from_secs(60))?;
-    let (info, stats) = client.get_complete_info().await?;
-    Ok(DeviceStats::from_api_responses(&info, &stats))
+    client.get_device_stats().await
 }
 
 fn format_last_seen(last_seen: DateTime<Utc>) -> String {
@@ -929,14 +1317,47 @@ mod tests {
     fn test_state() -> Arc<RwLock<MonitorState>> {
         Arc::new(RwLock::new(MonitorState {
             devices: HashMap::new(),
-            alerts: Vec::new(),
+            alerts: VecDeque::new(),
             discovery_active: false,
             last_discovery: None,
             alert_count: 0,
-            previous_hashrates: HashMap::new(),
+            alert_engine: AlertEngine::default(),
+            thermal_guard: ThermalGuard::default(),
+            best_difficulty: BestDifficultyStore::default(),
+            events: None,
         }))
     }
 
+    #[test]
+    fn stored_alerts_are_capped() {
+        let mut state = MonitorState::default();
+        let alert = |index: usize| Alert {
+            timestamp: Utc::now(),
+            kind: AlertKind::Offline,
+            severity: crate::alerts::Severity::Critical,
+            state: AlertState::Firing,
+            rule: None,
+            message: format!("alert {index}"),
+            device_ip: "192.168.1.10".to_string(),
+        };
+
+        let first: Vec<_> = (0..ALERT_HISTORY_SIZE).map(alert).collect();
+        state.record_alerts(&first);
+        state.record_alerts(&[alert(ALERT_HISTORY_SIZE), alert(ALERT_HISTORY_SIZE + 1)]);
+
+        assert_eq!(state.alerts.len(), ALERT_HISTORY_SIZE);
+        assert_eq!(state.alert_count, ALERT_HISTORY_SIZE + 2);
+        assert_eq!(
+            state.alerts.front().map(|a| a.message.as_str()),
+            Some("alert 2")
+        );
+        let last = format!("alert {count}", count = ALERT_HISTORY_SIZE + 1);
+        assert_eq!(
+            state.alerts.back().map(|a| a.message.as_str()),
+            Some(last.as_str())
+        );
+    }
+
     #[tokio::test]
     async fn discovery_loop_starts_without_waiting_for_discover_interval() {
         let tempdir = tempdir().expect("tempdir");
@@ -1001,8 +1422,7 @@ mod tests {
         let device = test_device("192.168.1.11");
         let config = AsyncMonitorConfig {
             interval: 1,
-            temp_alert: None,
-            hashrate_alert: None,
+            alert_rules: AlertRules::default(),
             type_filter: None,
             type_summary: false,
             format: OutputFormat::Json,
@@ -1014,13 +1434,189 @@ mod tests {
             discover_interval: 60,
             network: None,
             no_mdns: false,
+            no_history: true,
+            thermal_guard: None,
+            notify: None,
+            tariff: None,
+            near_block: None,
         };
 
-        let refresh_requested =
-            handle_monitor_message(&state, &config, MonitorMessage::NewDevices(vec![device])).await;
+        let refresh_requested = handle_monitor_message(
+            &state,
+            &config,
+            MonitorOutput::Display,
+            MonitorMessage::NewDevices(vec![device]),
+        )
+        .await;
 
         assert!(refresh_requested);
         let state_guard = state.read().await;
         assert!(state_guard.devices.contains_key("192.168.1.11"));
     }
+
+    #[tokio::test]
+    async fn online_and_offline_events_fire_once_per_transition() -> Result<()> {
+        const DEVICE_INFO: &str = r#"{
+            "ASICModel": "BM1366",
+            "boardVersion": "204",
+            "version": "2.4.0",
+            "macAddr": "AA:BB:CC:DD:EE:FF",
+            "hostname": "test-device",
+            "stratumURL": "public-pool.io",
+            "stratumPort": 21496,
+            "stratumUser": "bc1qtest.test-device",
+            "frequency": 485,
+            "voltage": 1200,
+            "fanspeed": 60,
+            "temp": 58.5,
+            "power": 12.5,
+            "hashRate": 420.0,
+            "uptimeSeconds": 7200,
+            "sharesAccepted": 42,
+            "sharesRejected": 0
+        }"#;
+
+        let mut server = mockito::Server::new_async().await;
+        let tempdir = tempdir()?;
+        let state = test_state();
+        let cache = Arc::new(RwLock::new(DeviceCache::new()));
+        let (events, mut receiver) = broadcast::channel(64);
+        {
+            let mut state_guard = state.write().await;
+            state_guard.events = Some(events);
+            let mut device = test_device(&server.host_with_port());
+            device.status = DeviceStatus::Offline;
+            state_guard
+                .devices
+                .insert(device.ip_address.clone(), device);
+        }
+        let config = AsyncMonitorConfig {
+            interval: 1,
+            alert_rules: AlertRules::default(),
+            type_filter: None,
+            type_summary: false,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+            all: true,
+            no_stats: false,
+            discover: false,
+            discover_interval: 60,
+            network: None,
+            no_mdns: false,
+            no_history: true,
+            thermal_guard: None,
+            notify: None,
+            tariff: None,
+            near_block: None,
+        };
+
+        // The device comes back and stays up for two polls
+        let healthy = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        for _ in 0..2 {
+            poll_devices(&state, &cache, tempdir.path(), None, &config).await?;
+        }
+
+        // Then it stops responding for two polls
+        healthy.remove_async().await;
+        let _failing = server
+            .mock("GET", "/api/system/info")
+            .with_status(500)
+            .create_async()
+            .await;
+        for _ in 0..2 {
+            poll_devices(&state, &cache, tempdir.path(), None, &config).await?;
+        }
+
+        let mut transitions = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            match event.kind {
+                EventKind::DeviceOnline { .. } => transitions.push("online"),
+                EventKind::DeviceOffline { .. } => transitions.push("offline"),
+                _ => {}
+            }
+        }
+        assert_eq!(transitions, vec!["online", "offline"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn discovered_devices_are_streamed_as_events() -> Result<()> {
+        let tempdir = tempdir()?;
+        let state = test_state();
+        let (events, mut receiver) = broadcast::channel(16);
+        {
+            let mut state_guard = state.write().await;
+            state_guard.events = Some(events);
+            let mut returning = test_device("192.168.1.12");
+            returning.status = DeviceStatus::Offline;
+            state_guard
+                .devices
+                .insert(returning.ip_address.clone(), returning);
+        }
+        let config = AsyncMonitorConfig {
+            interval: 1,
+            alert_rules: AlertRules::default(),
+            type_filter: None,
+            type_summary: false,
+            format: OutputFormat::Json,
+            color: false,
+            cache_dir: Some(tempdir.path()),
+            all: false,
+            no_stats: true,
+            discover: false,
+            discover_interval: 60,
+            network: None,
+            no_mdns: false,
+            no_history: true,
+            thermal_guard: None,
+            notify: None,
+            tariff: None,
+            near_block: None,
+        };
+
+        handle_monitor_message(
+            &state,
+            &config,
+            MonitorOutput::Quiet,
+            MonitorMessage::NewDevices(vec![
+                test_device("192.168.1.11"),
+                test_device("192.168.1.12"),
+            ]),
+        )
+        .await;
+        handle_monitor_message(
+            &state,
+            &config,
+            MonitorOutput::Quiet,
+            MonitorMessage::DiscoveryComplete(2),
+        )
+        .await;
+
+        let event = receiver.try_recv()?;
+        assert!(
+            matches!(event.kind, EventKind::DeviceDiscovered { ref device } if device.ip_address == "192.168.1.11")
+        );
+        let event = receiver.try_recv()?;
+        assert!(
+            matches!(event.kind, EventKind::DeviceOnline { ref device } if device.ip_address == "192.168.1.12")
+        );
+        let event = receiver.try_recv()?;
+        assert!(matches!(
+            event.kind,
+            EventKind::DiscoveryComplete { total: 2 }
+        ));
+        assert_eq!(
+            state.read().await.devices["192.168.1.12"].status,
+            DeviceStatus::Online
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,332 @@
+use crate::alerts::AlertRules;
+use crate::api::{AxeOsClient, CommandResult, Device};
+use crate::cli::commands::handlers::monitor_async::{
+    AsyncMonitorConfig, EVENT_CHANNEL_CAPACITY, MonitorOutput, MonitorState, run_monitor_loop,
+};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::daemon::DaemonCommand;
+use crate::events::{EventKind, MonitorEvent};
+use crate::mqtt::{Broker, MqttTopics, tls_client_config};
+use crate::output::print_success;
+use anyhow::{Context, Result};
+use rumqttc::{
+    AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, QoS, TlsConfiguration,
+    Transport,
+};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tokio::time::timeout;
+
+/// Delay before reconnecting after the broker connection failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Time allowed to deliver the final messages when shutting down
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout for commands sent to a device
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Arguments for the MQTT bridge
+pub struct MqttArgs<'a> {
+    pub broker: Broker,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: String,
+    pub base_topic: String,
+    pub discovery_prefix: String,
+    pub no_discovery: bool,
+    pub read_only: bool,
+    pub interval: u64,
+    pub type_filter: Option<DeviceFilterArg>,
+    pub all: bool,
+    pub discover: bool,
+    pub discover_interval: u64,
+    pub network: Option<String>,
+    pub no_mdns: bool,
+    pub no_history: bool,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+/// A message received on a command topic
+struct CommandMessage {
+    topic: String,
+    payload: Vec<u8>,
+}
+
+pub async fn mqtt(args: MqttArgs<'_>) -> Result<()> {
+    let topics = MqttTopics::new(&args.base_topic, &args.discovery_prefix);
+
+    let mut options = MqttOptions::new(&args.client_id, &args.broker.host, args.broker.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        topics.status(),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(ref username) = args.username {
+        options.set_credentials(username, args.password.clone().unwrap_or_default());
+    }
+    if args.broker.tls {
+        options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+            tls_client_config()?,
+        )));
+    }
+
+    let (client, eventloop) = AsyncClient::new(options, 100);
+    let (command_tx, command_rx) = mpsc::channel(16);
+    let connection = tokio::spawn(run_connection(
+        eventloop,
+        client.clone(),
+        topics.clone(),
+        (!args.read_only).then_some(command_tx),
+    ));
+
+    print_success(
+        &format!(
+            "📡 Publishing to mqtt{tls}://{host}:{port} under {base}/",
+            tls = if args.broker.tls { "s" } else { "" },
+            host = args.broker.host,
+            port = args.broker.port,
+            base = args.base_topic.trim_end_matches('/')
+        ),
+        args.color,
+    );
+
+    let (events, receiver) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let state = Arc::new(RwLock::new(MonitorState {
+        events: Some(events),
+        ..MonitorState::default()
+    }));
+
+    // Poll with the monitor loop and publish what changes
+    let config = AsyncMonitorConfig {
+        interval: args.interval,
+        alert_rules: AlertRules::default(),
+        type_filter: args.type_filter,
+        type_summary: false,
+        format: OutputFormat::Text,
+        color: args.color,
+        cache_dir: args.cache_dir,
+        all: args.all,
+        no_stats: false,
+        discover: args.discover,
+        discover_interval: args.discover_interval,
+        network: args.network,
+        no_mdns: args.no_mdns,
+        no_history: args.no_history,
+        thermal_guard: None,
+        notify: None,
+        tariff: None,
+        near_block: None,
+    };
+    let bridge = Bridge {
+        client: client.clone(),
+        topics: topics.clone(),
+        discovery: !args.no_discovery,
+        controls: !args.read_only,
+        announced: HashSet::new(),
+    };
+
+    let result = tokio::select! {
+        result = run_monitor_loop(
+            &config,
+            state.clone(),
+            Arc::new(AtomicBool::new(false)),
+            MonitorOutput::StateOnly,
+        ) => result,
+        result = bridge.run(state, receiver, command_rx) => result,
+    };
+
+    // A clean disconnect does not trigger the last will
+    let _ = client
+        .publish(topics.status(), QoS::AtLeastOnce, true, "offline")
+        .await;
+    let _ = client.disconnect().await;
+    let _ = timeout(DISCONNECT_TIMEOUT, connection).await;
+
+    result
+}
+
+/// Drive the MQTT connection, (re)subscribing to command topics on every connect
+async fn run_connection(
+    mut eventloop: EventLoop,
+    client: AsyncClient,
+    topics: MqttTopics,
+    commands: Option<mpsc::Sender<CommandMessage>>,
+) {
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                tracing::info!("Connected to MQTT broker");
+                // The event loop must keep polling, so only queue requests here
+                if let Err(e) =
+                    client.try_publish(topics.status(), QoS::AtLeastOnce, true, "online")
+                {
+                    tracing::warn!("Failed to publish MQTT status: {e}");
+                }
+                if commands.is_some() {
+                    for filter in topics.command_filters() {
+                        if let Err(e) = client.try_subscribe(filter, QoS::AtLeastOnce) {
+                            tracing::warn!("Failed to subscribe to MQTT commands: {e}");
+                        }
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                if let Some(ref commands) = commands {
+                    let message = CommandMessage {
+                        topic: publish.topic,
+                        payload: publish.payload.to_vec(),
+                    };
+                    if commands.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("MQTT connection failed: {e}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Publishes monitor events and runs commands received from the broker
+struct Bridge {
+    client: AsyncClient,
+    topics: MqttTopics,
+    discovery: bool,
+    controls: bool,
+    /// Devices whose Home Assistant discovery configs were published
+    announced: HashSet<String>,
+}
+
+impl Bridge {
+    async fn run(
+        mut self,
+        state: Arc<RwLock<MonitorState>>,
+        mut events: broadcast::Receiver<MonitorEvent>,
+        mut commands: mpsc::Receiver<CommandMessage>,
+    ) -> Result<()> {
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => self.publish_event(&state, event).await?,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("MQTT publisher fell behind, {skipped} event(s) dropped");
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                Some(message) = commands.recv() => {
+                    self.handle_command(&state, message).await;
+                }
+            }
+        }
+    }
+
+    async fn publish_event(
+        &mut self,
+        state: &Arc<RwLock<MonitorState>>,
+        event: MonitorEvent,
+    ) -> Result<()> {
+        match event.kind {
+            EventKind::Stats { device, stats } => {
+                let Some(device) = state.read().await.devices.get(&device.ip_address).cloned()
+                else {
+                    return Ok(());
+                };
+                let device_id = MqttTopics::device_id(&device.name);
+
+                if self.discovery && self.announced.insert(device_id.clone()) {
+                    for (topic, config) in self.topics.discovery_messages(&device, self.controls) {
+                        self.publish(topic, config.to_string()).await?;
+                    }
+                }
+                self.publish(self.topics.availability(&device_id), "online".to_string())
+                    .await?;
+                for (topic, payload) in self.topics.stats_messages(&device, &stats)? {
+                    self.publish(topic, payload).await?;
+                }
+            }
+            EventKind::DeviceOffline { device } => {
+                let device_id = MqttTopics::device_id(&device.name);
+                self.publish(self.topics.availability(&device_id), "offline".to_string())
+                    .await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, topic: String, payload: String) -> Result<()> {
+        self.client
+            .publish(topic, QoS::AtLeastOnce, true, payload)
+            .await
+            .context("Failed to queue MQTT message")
+    }
+
+    /// Run a command on the device it addresses, logging the outcome
+    async fn handle_command(&self, state: &Arc<RwLock<MonitorState>>, message: CommandMessage) {
+        let (device_id, command) = match self.topics.parse_command(&message.topic, &message.payload)
+        {
+            Ok(Some(command)) => command,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(
+                    "Ignoring MQTT command on {topic}: {e:#}",
+                    topic = message.topic
+                );
+                return;
+            }
+        };
+
+        let device = state
+            .read()
+            .await
+            .devices
+            .values()
+            .find(|d| MqttTopics::device_id(&d.name) == device_id)
+            .cloned();
+        let Some(device) = device else {
+            tracing::warn!("Ignoring MQTT command for unknown device: {device_id}");
+            return;
+        };
+
+        // Device requests are slow, so don't hold up publishing
+        tokio::spawn(async move {
+            match run_command(&device, &command).await {
+                Ok(result) if result.success => tracing::info!(
+                    "MQTT {action} on {name}: {message}",
+                    action = command.endpoint(),
+                    name = device.name,
+                    message = result.message
+                ),
+                Ok(result) => tracing::warn!(
+                    "MQTT {action} on {name} failed: {message}",
+                    action = command.endpoint(),
+                    name = device.name,
+                    message = result.message
+                ),
+                Err(e) => tracing::warn!(
+                    "MQTT {action} on {name} failed: {e:#}",
+                    action = command.endpoint(),
+                    name = device.name
+                ),
+            }
+        });
+    }
+}
+
+async fn run_command(device: &Device, command: &DaemonCommand) -> Result<CommandResult> {
+    let client = AxeOsClient::with_timeout(&device.ip_address, COMMAND_TIMEOUT)?;
+    command.send(&client).await
+}
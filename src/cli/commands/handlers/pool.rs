@@ -0,0 +1,200 @@
+use crate::api::{AxeOsClient, Device, DeviceStatus, SystemInfoResponse};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::output::{
+    format_difficulty, format_table, print_error, print_info, print_json, print_success,
+    print_warning,
+};
+use crate::pool::{PoolCheck, PoolEndpoint, PoolTarget, check_pools};
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use std::path::Path;
+use std::time::Duration;
+use tabled::Tabled;
+
+/// Arguments for the pool check command
+pub struct PoolCheckArgs<'a> {
+    pub device_type: Option<DeviceFilterArg>,
+    pub password: String,
+    pub timeout: Duration,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct PoolTableRow {
+    #[tabled(rename = "Pool")]
+    pool: String,
+    #[tabled(rename = "Worker")]
+    user: String,
+    #[tabled(rename = "Devices")]
+    devices: String,
+    #[tabled(rename = "Connect")]
+    connect: String,
+    #[tabled(rename = "Subscribe")]
+    subscribe: String,
+    #[tabled(rename = "Difficulty")]
+    difficulty: String,
+    #[tabled(rename = "Status")]
+    status: String,
+}
+
+pub async fn pool_check(args: PoolCheckArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache = DeviceCache::load(cache_path.as_ref())?;
+
+    let mut devices = match args.device_type {
+        Some(filter) => cache.get_online_devices_by_filter(filter.0),
+        None => cache.get_devices_by_status(DeviceStatus::Online),
+    };
+    devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    if devices.is_empty() {
+        match args.format {
+            OutputFormat::Json => {
+                let output = serde_json::json!({
+                    "pools": [],
+                    "errors": [],
+                    "timestamp": chrono::Utc::now(),
+                });
+                print_json(&output, true)?;
+            }
+            OutputFormat::Text => print_warning(
+                "No online devices. Run 'axectl discover' first to find devices.",
+                args.color,
+            ),
+        }
+        return Ok(());
+    }
+
+    let (targets, errors) = pool_targets(&devices, |info| {
+        Ok((
+            PoolEndpoint::parse(&info.pool_url, info.pool_port)?,
+            info.pool_user.clone(),
+        ))
+    })
+    .await;
+
+    if args.format == OutputFormat::Text {
+        for (name, error) in &errors {
+            print_error(&format!("✗ {name}: {error}"), args.color);
+        }
+        print_info(
+            &format!(
+                "Checking the pools of {count} device(s)...",
+                count = targets.len()
+            ),
+            args.color,
+        );
+    }
+
+    let checks = check_pools(&targets, &args.password, args.timeout).await;
+
+    match args.format {
+        OutputFormat::Json => {
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|(device, error)| serde_json::json!({ "device": device, "error": error }))
+                .collect();
+            let output = serde_json::json!({
+                "pools": checks,
+                "errors": errors,
+                "success": checks.iter().all(PoolCheck::is_ok),
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => print_checks(&checks, args.color),
+    }
+
+    Ok(())
+}
+
+fn print_checks(checks: &[PoolCheck], color: bool) {
+    if checks.is_empty() {
+        return;
+    }
+
+    let milliseconds = |ms: Option<f64>| {
+        ms.map(|ms| format!("{ms:.0} ms"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let rows: Vec<PoolTableRow> = checks
+        .iter()
+        .flat_map(|check| {
+            check.authorizations.iter().map(move |auth| PoolTableRow {
+                pool: check.pool.clone(),
+                user: auth.user.clone(),
+                devices: auth.devices.join(", "),
+                connect: milliseconds(check.connect_ms),
+                subscribe: milliseconds(check.subscribe_ms),
+                difficulty: check
+                    .difficulty
+                    .map(format_difficulty)
+                    .unwrap_or_else(|| "-".to_string()),
+                status: match (&check.error, auth.authorized) {
+                    (Some(_), _) => "unreachable".to_string(),
+                    (None, Some(true)) => "authorized".to_string(),
+                    (None, Some(false)) => "rejected".to_string(),
+                    (None, None) => "no answer".to_string(),
+                },
+            })
+        })
+        .collect();
+    println!("{}", format_table(rows, color));
+
+    let problems: Vec<String> = checks.iter().flat_map(PoolCheck::problems).collect();
+    if problems.is_empty() {
+        print_success(
+            &format!(
+                "All {count} pool(s) reachable and accepting their workers",
+                count = checks.len()
+            ),
+            color,
+        );
+    } else {
+        for problem in problems {
+            print_error(&problem, color);
+        }
+    }
+}
+
+/// Read each device's settings and derive the pool and worker to check from them
+///
+/// Devices whose settings could not be read are returned by name with the error.
+pub(crate) async fn pool_targets<F>(
+    devices: &[Device],
+    pool: F,
+) -> (Vec<PoolTarget>, Vec<(String, String)>)
+where
+    F: Fn(&SystemInfoResponse) -> Result<(PoolEndpoint, String)>,
+{
+    let results = join_all(devices.iter().map(|device| async move {
+        let info = async {
+            let client = AxeOsClient::new(&device.ip_address)?;
+            client
+                .get_system_info()
+                .await
+                .with_context(|| format!("Failed to read settings from {name}", name = device.name))
+        }
+        .await;
+        (device, info)
+    }))
+    .await;
+
+    let mut targets = Vec::new();
+    let mut errors = Vec::new();
+    for (device, info) in results {
+        match info.and_then(|info| pool(&info)) {
+            Ok((endpoint, user)) => targets.push(PoolTarget {
+                device: device.name.clone(),
+                endpoint,
+                user,
+            }),
+            Err(e) => errors.push((device.name.clone(), format!("{e:#}"))),
+        }
+    }
+    (targets, errors)
+}
@@ -0,0 +1,230 @@
+use super::list::collect_device_stats;
+use crate::api::{Device, DeviceStats, DeviceStatus};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::{DeviceFilterArg, OutputFormat};
+use crate::energy::{Tariff, format_money};
+use crate::output::{
+    format_difficulty, format_hashrate, format_power, format_table, print_info, print_json,
+    print_warning,
+};
+use crate::profit::{Estimate, MarketData, MarketInputs, ProfitConfig};
+use alphanumeric_sort::compare_str;
+use anyhow::{Result, bail};
+use futures::future::join_all;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tabled::Tabled;
+use tokio::time::timeout;
+
+/// Arguments for the profit command
+pub struct ProfitArgs<'a> {
+    pub device: Option<String>,
+    pub device_type: Option<DeviceFilterArg>,
+    pub inputs: MarketInputs,
+    pub config: Option<PathBuf>,
+    pub offline: bool,
+    pub tariff: Option<Tariff>,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct ProfitTableRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Hashrate")]
+    hashrate: String,
+    #[tabled(rename = "Power")]
+    power: String,
+    #[tabled(rename = "Sats/day")]
+    sats: String,
+    #[tabled(rename = "Revenue/day")]
+    revenue: String,
+    #[tabled(rename = "Cost/day")]
+    cost: String,
+    #[tabled(rename = "Profit/day")]
+    profit: String,
+    #[tabled(rename = "Solo/day")]
+    solo_day: String,
+    #[tabled(rename = "Solo/year")]
+    solo_year: String,
+    #[tabled(rename = "Expected block")]
+    expected_block: String,
+}
+
+/// Estimate for one device
+#[derive(Debug, Serialize)]
+struct DeviceProfit {
+    name: String,
+    ip_address: String,
+    #[serde(flatten)]
+    estimate: Estimate,
+}
+
+pub async fn profit(args: ProfitArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache = DeviceCache::load(cache_path.as_ref())?;
+
+    let config = match args.config {
+        Some(ref path) => ProfitConfig::load(path)?,
+        None => ProfitConfig::default(),
+    };
+    let fetched = if args.offline {
+        MarketInputs::default()
+    } else {
+        config.fetch().await?
+    };
+
+    let tariff_currency = args.tariff.as_ref().and_then(|t| t.currency.clone());
+    let currency = match (config.currency.clone(), tariff_currency) {
+        (Some(price), Some(tariff)) if price != tariff => {
+            bail!("BTC price is in {price} but the tariff is in {tariff}")
+        }
+        (price, tariff) => price.or(tariff),
+    };
+    let market = MarketData::from_inputs(args.inputs.or(fetched).or(config.inputs()), currency)?;
+
+    let devices: Vec<Device> = match args.device {
+        Some(ref identifier) => match cache.find_device(identifier) {
+            Some(device) => vec![device],
+            None => bail!("Device not found: {identifier}"),
+        },
+        None => match args.device_type {
+            Some(filter) => cache.get_online_devices_by_filter(filter.0),
+            None => cache.get_devices_by_status(DeviceStatus::Online),
+        },
+    };
+
+    let results = join_all(devices.into_iter().map(|device| async move {
+        let stats = timeout(Duration::from_secs(30), collect_device_stats(&device)).await;
+        (device, stats)
+    }))
+    .await;
+
+    let tariff = args.tariff.as_ref();
+    let mut estimates = Vec::new();
+    for (device, stats) in results {
+        let stats: DeviceStats = match stats {
+            Ok(Ok(stats)) => stats,
+            _ => match device.stats {
+                Some(ref cached) => {
+                    tracing::warn!(
+                        "Using cached statistics for {name}, the device did not respond",
+                        name = device.name
+                    );
+                    cached.clone()
+                }
+                None => {
+                    tracing::warn!("No statistics for {name}, skipping", name = device.name);
+                    continue;
+                }
+            },
+        };
+
+        estimates.push(DeviceProfit {
+            name: device.name.clone(),
+            ip_address: device.ip_address.clone(),
+            estimate: market.estimate(stats.hashrate_mhs, stats.power_watts, tariff),
+        });
+    }
+    estimates.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    let fleet = market.estimate(
+        estimates.iter().map(|e| e.estimate.hashrate_ghs).sum(),
+        estimates.iter().map(|e| e.estimate.power_watts).sum(),
+        tariff,
+    );
+
+    match args.format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "market": market,
+                "devices": estimates,
+                "fleet": fleet,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            if estimates.is_empty() {
+                print_warning("No devices with statistics found", args.color);
+                return Ok(());
+            }
+
+            let money = |amount: Option<f64>| {
+                amount
+                    .map(|a| format_money(a, market.currency.as_deref()))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let row = |name: &str, estimate: &Estimate| ProfitTableRow {
+                name: name.to_string(),
+                hashrate: format_hashrate(estimate.hashrate_ghs),
+                power: format_power(estimate.power_watts),
+                sats: format!("{sats:.0}", sats = estimate.sats_per_day),
+                revenue: money(estimate.revenue_per_day),
+                cost: money(estimate.energy.cost_per_day),
+                profit: money(estimate.profit_per_day),
+                solo_day: format_odds(estimate.solo_chance_day),
+                solo_year: format_odds(estimate.solo_chance_year),
+                expected_block: format_expected_time(estimate.expected_days_to_block),
+            };
+
+            let mut table_rows: Vec<ProfitTableRow> = estimates
+                .iter()
+                .map(|e| row(&e.name, &e.estimate))
+                .collect();
+            if estimates.len() > 1 {
+                table_rows.push(row("Fleet", &fleet));
+            }
+
+            print_info(
+                &format!(
+                    "Difficulty {difficulty} | Block {reward} + {fees:.4} BTC fees | Price {price}",
+                    difficulty = format_difficulty(market.difficulty),
+                    reward = market.block_reward,
+                    fees = market.fees,
+                    price = money(market.price)
+                ),
+                args.color,
+            );
+            println!("{}", format_table(table_rows, args.color));
+
+            if market.price.is_none() {
+                print_info(
+                    "Pass --price or configure a price source to estimate revenue",
+                    args.color,
+                );
+            }
+            if tariff.is_none() {
+                print_info(
+                    "Pass --tariff to subtract electricity cost from revenue",
+                    args.color,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Format the chance of an event as a percentage, or "1 in N" when it is small
+fn format_odds(probability: f64) -> String {
+    if probability <= 0.0 {
+        "-".to_string()
+    } else if probability >= 0.001 {
+        format!("{percent:.2}%", percent = probability * 100.0)
+    } else {
+        format!("1 in {odds:.0}", odds = 1.0 / probability)
+    }
+}
+
+/// Format the expected time until a block is found
+fn format_expected_time(days: Option<f64>) -> String {
+    match days {
+        Some(days) if days < 365.0 => format!("{days:.0} days"),
+        Some(days) => format!("{years:.0} years", years = days / 365.25),
+        None => "-".to_string(),
+    }
+}
@@ -0,0 +1,1342 @@
+use crate::alerts::{Alert, AlertState, Severity};
+use crate::api::{
+    AxeOsClient, CommandResult, Device, DeviceFilter, DeviceStats, DeviceStatus, SwarmSummary,
+};
+use crate::cache::get_cache_dir;
+use crate::cli::commands::OutputFormat;
+use crate::cli::commands::handlers::list::format_optional_difficulty;
+use crate::cli::commands::handlers::monitor_async::{
+    AsyncMonitorConfig, MonitorOutput, MonitorState, monitored_devices, run_monitor_loop,
+};
+use crate::history::HistoryStore;
+use crate::output::{
+    format_efficiency, format_hashrate, format_health, format_power, format_uptime,
+};
+use alphanumeric_sort::compare_str;
+use anyhow::{Context, Result, ensure};
+use chrono::{DateTime, Local, Utc};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, List, ListItem, Paragraph, Row, Sparkline, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+use tokio::time::interval;
+
+/// How often the screen is refreshed from the monitor state
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Samples kept per device for the sparklines
+const SPARKLINE_SAMPLES: usize = 300;
+
+/// How far back the sparklines are seeded from the history store, in minutes
+const SPARKLINE_HISTORY_MINUTES: i64 = 30;
+
+/// Alerts shown in the alert log
+const ALERT_LOG_SIZE: usize = 50;
+
+/// Timeout for control actions sent to a device
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Columns of the device table, all of which can be sorted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    Ip,
+    Type,
+    Status,
+    Hashrate,
+    Health,
+    Temperature,
+    Power,
+    Efficiency,
+    Fan,
+    Uptime,
+    Best,
+}
+
+const COLUMNS: [Column; 12] = [
+    Column::Name,
+    Column::Ip,
+    Column::Type,
+    Column::Status,
+    Column::Hashrate,
+    Column::Health,
+    Column::Temperature,
+    Column::Power,
+    Column::Efficiency,
+    Column::Fan,
+    Column::Uptime,
+    Column::Best,
+];
+
+impl Column {
+    fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Ip => "IP",
+            Column::Type => "Type",
+            Column::Status => "Status",
+            Column::Hashrate => "Hashrate",
+            Column::Health => "Health",
+            Column::Temperature => "Temp",
+            Column::Power => "Power",
+            Column::Efficiency => "J/TH",
+            Column::Fan => "Fan",
+            Column::Uptime => "Uptime",
+            Column::Best => "Best",
+        }
+    }
+
+    fn width(self) -> Constraint {
+        match self {
+            Column::Name => Constraint::Min(12),
+            Column::Ip => Constraint::Length(15),
+            Column::Type => Constraint::Length(13),
+            Column::Status => Constraint::Length(8),
+            Column::Hashrate => Constraint::Length(12),
+            Column::Health => Constraint::Length(7),
+            Column::Temperature => Constraint::Length(8),
+            Column::Power => Constraint::Length(8),
+            Column::Efficiency => Constraint::Length(10),
+            Column::Fan => Constraint::Length(9),
+            Column::Uptime => Constraint::Length(10),
+            Column::Best => Constraint::Length(9),
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = COLUMNS.iter().position(|c| *c == self).unwrap_or(0);
+        COLUMNS[(index + 1) % COLUMNS.len()]
+    }
+
+    /// Metrics sort from the highest value down when first selected
+    fn descending_by_default(self) -> bool {
+        !matches!(
+            self,
+            Column::Name | Column::Ip | Column::Type | Column::Status
+        )
+    }
+
+    fn compare(self, a: &Device, b: &Device) -> Ordering {
+        match self {
+            Column::Name => compare_str(&a.name, &b.name),
+            Column::Ip => compare_str(&a.ip_address, &b.ip_address),
+            Column::Type => a.device_type.as_str().cmp(b.device_type.as_str()),
+            Column::Status => status_rank(&a.status).cmp(&status_rank(&b.status)),
+            Column::Hashrate => compare_metric(a, b, |s| Some(s.hashrate_mhs)),
+            Column::Health => compare_metric(a, b, |s| s.hashrate_health_percent),
+            Column::Temperature => compare_metric(a, b, |s| Some(s.temperature_celsius)),
+            Column::Power => compare_metric(a, b, |s| Some(s.power_watts)),
+            Column::Efficiency => compare_metric(a, b, |s| s.efficiency_jth),
+            Column::Fan => compare_metric(a, b, |s| Some(f64::from(s.fan_speed_rpm))),
+            Column::Uptime => compare_metric(a, b, |s| Some(s.uptime_seconds as f64)),
+            Column::Best => compare_metric(a, b, |s| s.best_difficulty),
+        }
+    }
+
+    fn cell(self, device: &Device, color: bool) -> Cell<'static> {
+        let stats = live_stats(device);
+        let text = match (self, stats) {
+            (Column::Name, _) => device.name.clone(),
+            (Column::Ip, _) => device.ip_address.clone(),
+            (Column::Type, _) => device.device_type.as_str().to_string(),
+            (Column::Status, _) => {
+                let (text, style) = status_label(&device.status);
+                return Cell::from(text).style(paint(color, style));
+            }
+            (_, None) => "-".to_string(),
+            (Column::Hashrate, Some(s)) => format_hashrate(s.hashrate_mhs),
+            (Column::Health, Some(s)) => format_health(s.hashrate_health_percent),
+            (Column::Temperature, Some(s)) => {
+                return Cell::from(format!("{temp:.1}°C", temp = s.temperature_celsius))
+                    .style(paint(color, temperature_style(s.temperature_celsius)));
+            }
+            (Column::Power, Some(s)) => format_power(s.power_watts),
+            (Column::Efficiency, Some(s)) => format_efficiency(s.efficiency_jth),
+            (Column::Fan, Some(s)) => format!("{rpm} RPM", rpm = s.fan_speed_rpm),
+            (Column::Uptime, Some(s)) => format_uptime(s.uptime_seconds),
+            (Column::Best, Some(s)) => format_optional_difficulty(s.best_difficulty),
+        };
+        Cell::from(text)
+    }
+}
+
+/// Order devices by a statistic, devices without it first
+fn compare_metric(a: &Device, b: &Device, metric: fn(&DeviceStats) -> Option<f64>) -> Ordering {
+    let value = |d: &Device| live_stats(d).and_then(metric);
+    match (value(a), value(b)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// Statistics of an online device (offline devices only have stale ones)
+fn live_stats(device: &Device) -> Option<&DeviceStats> {
+    match device.status {
+        DeviceStatus::Online => device.stats.as_ref(),
+        _ => None,
+    }
+}
+
+fn status_rank(status: &DeviceStatus) -> u8 {
+    match status {
+        DeviceStatus::Online => 0,
+        DeviceStatus::Error => 1,
+        DeviceStatus::Offline => 2,
+    }
+}
+
+fn status_label(status: &DeviceStatus) -> (&'static str, Style) {
+    match status {
+        DeviceStatus::Online => ("online", Style::new().fg(Color::Green)),
+        DeviceStatus::Offline => ("offline", Style::new().fg(Color::Red)),
+        DeviceStatus::Error => ("error", Style::new().fg(Color::Yellow)),
+    }
+}
+
+/// Same thresholds as the monitor table
+fn temperature_style(temperature: f64) -> Style {
+    if temperature >= 80.0 {
+        Style::new().fg(Color::Red)
+    } else if temperature >= 70.0 {
+        Style::new().fg(Color::Yellow)
+    } else {
+        Style::new().fg(Color::Green)
+    }
+}
+
+fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Info => Style::new().fg(Color::Cyan),
+        Severity::Warning => Style::new().fg(Color::Yellow),
+        Severity::Critical => Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+    }
+}
+
+/// Drop colors with --no-color, keeping modifiers such as the selection highlight
+fn paint(color: bool, style: Style) -> Style {
+    if color {
+        style
+    } else {
+        Style {
+            fg: None,
+            bg: None,
+            ..style
+        }
+    }
+}
+
+/// Scale values into sparkline bars between their minimum and maximum
+///
+/// Sparklines start at zero, which would flatten metrics such as temperature
+/// that only move within a narrow band.
+fn sparkline_data(values: &[f64]) -> Vec<u64> {
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    let range = max - min;
+    values
+        .iter()
+        .map(|v| {
+            if range > f64::EPSILON {
+                ((v - min) / range * 99.0).round() as u64 + 1
+            } else {
+                1
+            }
+        })
+        .collect()
+}
+
+/// Statistic plotted in the detail pane
+#[derive(Clone, Copy)]
+struct SparklineMetric {
+    label: &'static str,
+    value: fn(&DeviceStats) -> f64,
+    format: fn(f64) -> String,
+    color: Color,
+}
+
+const SPARKLINES: [SparklineMetric; 3] = [
+    SparklineMetric {
+        label: "Hashrate",
+        value: |s| s.hashrate_mhs,
+        format: format_hashrate,
+        color: Color::Cyan,
+    },
+    SparklineMetric {
+        label: "Temp",
+        value: |s| s.temperature_celsius,
+        format: |t| format!("{t:.1}°C"),
+        color: Color::Yellow,
+    },
+    SparklineMetric {
+        label: "Power",
+        value: |s| s.power_watts,
+        format: format_power,
+        color: Color::Magenta,
+    },
+];
+
+/// A control action on one device, sent once confirmed
+#[derive(Debug, Clone, PartialEq)]
+struct ControlRequest {
+    command: ControlCommand,
+    name: String,
+    ip_address: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlCommand {
+    Restart,
+    Identify,
+    SetFanSpeed(u8),
+}
+
+impl ControlRequest {
+    fn prompt(&self) -> String {
+        let device = format!("{name} ({ip})", name = self.name, ip = self.ip_address);
+        match self.command {
+            ControlCommand::Restart => format!("Restart {device}?"),
+            ControlCommand::Identify => format!("Identify {device}?"),
+            ControlCommand::SetFanSpeed(speed) => format!("Set fan speed to {speed}% on {device}?"),
+        }
+    }
+
+    async fn send(&self) -> Result<CommandResult> {
+        let client = AxeOsClient::with_timeout(&self.ip_address, CONTROL_TIMEOUT)?;
+        match self.command {
+            ControlCommand::Restart => client.restart_system().await,
+            ControlCommand::Identify => client.identify().await,
+            ControlCommand::SetFanSpeed(speed) => client.set_fan_speed(speed).await,
+        }
+    }
+}
+
+/// Modal dialog shown over the dashboard
+#[derive(Debug, Clone, PartialEq)]
+enum Dialog {
+    /// Entering the fan speed for a device
+    FanSpeed {
+        name: String,
+        ip_address: String,
+        input: String,
+    },
+    /// Waiting for confirmation of a control action
+    Confirm(ControlRequest),
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, PartialEq)]
+enum KeyOutcome {
+    Continue,
+    Quit,
+    Control(ControlRequest),
+}
+
+/// Message in the footer, replacing the key help
+#[derive(Debug, Clone)]
+struct StatusMessage {
+    text: String,
+    style: Style,
+}
+
+/// Dashboard state, rebuilt from the monitor state on every refresh
+struct App {
+    /// All monitored devices
+    devices: Vec<Device>,
+    /// Devices matching the type filter, in display order
+    rows: Vec<Device>,
+    /// Most recent alerts first
+    alerts: Vec<Alert>,
+    sort: Column,
+    descending: bool,
+    filter: DeviceFilter,
+    table: TableState,
+    selected: Option<String>,
+    samples: HashMap<String, VecDeque<DeviceStats>>,
+    dialog: Option<Dialog>,
+    status: Option<StatusMessage>,
+    color: bool,
+}
+
+impl App {
+    fn new(color: bool) -> Self {
+        Self {
+            devices: Vec::new(),
+            rows: Vec::new(),
+            alerts: Vec::new(),
+            sort: Column::Name,
+            descending: false,
+            filter: DeviceFilter::All,
+            table: TableState::default(),
+            selected: None,
+            samples: HashMap::new(),
+            dialog: None,
+            status: None,
+            color,
+        }
+    }
+
+    /// Take in the latest devices and alerts, recording new samples for the sparklines
+    fn update(&mut self, devices: Vec<Device>, alerts: Vec<Alert>, history: Option<&HistoryStore>) {
+        for device in &devices {
+            let samples = self
+                .samples
+                .entry(device.ip_address.clone())
+                .or_insert_with(|| seed_samples(history, &device.ip_address));
+            if let Some(stats) = live_stats(device)
+                && samples
+                    .back()
+                    .is_none_or(|last| last.timestamp < stats.timestamp)
+            {
+                samples.push_back(stats.clone());
+                if samples.len() > SPARKLINE_SAMPLES {
+                    samples.pop_front();
+                }
+            }
+        }
+
+        self.devices = devices;
+        self.alerts = alerts;
+        self.arrange();
+    }
+
+    /// Filter and sort the rows, keeping the selected device selected
+    fn arrange(&mut self) {
+        let (sort, descending) = (self.sort, self.descending);
+        self.rows = self
+            .devices
+            .iter()
+            .filter(|d| self.filter.matches(d.device_type))
+            .cloned()
+            .collect();
+        self.rows.sort_by(|a, b| {
+            let ordering = sort.compare(a, b);
+            let ordering = if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            ordering.then_with(|| compare_str(&a.name, &b.name))
+        });
+
+        let index = self
+            .selected
+            .as_ref()
+            .and_then(|ip| self.rows.iter().position(|d| &d.ip_address == ip))
+            .or(if self.rows.is_empty() { None } else { Some(0) });
+        self.select(index);
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        self.table.select(index);
+        self.selected = index
+            .and_then(|i| self.rows.get(i))
+            .map(|d| d.ip_address.clone());
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let last = self.rows.len() as isize - 1;
+        self.select(Some((current + offset).clamp(0, last) as usize));
+    }
+
+    fn selected_device(&self) -> Option<&Device> {
+        let ip = self.selected.as_ref()?;
+        self.rows.iter().find(|d| &d.ip_address == ip)
+    }
+
+    /// Type filters to cycle through: all, each family, then each type present
+    fn filters(&self) -> Vec<DeviceFilter> {
+        let mut filters = vec![
+            DeviceFilter::All,
+            DeviceFilter::AnyBitaxe,
+            DeviceFilter::AnyNerdQaxe,
+        ];
+        let mut types: Vec<_> = self.devices.iter().map(|d| d.device_type).collect();
+        types.sort_by_key(|t| t.as_str());
+        types.dedup();
+        filters.extend(types.into_iter().map(DeviceFilter::Specific));
+        filters
+    }
+
+    fn set_status(&mut self, text: String, style: Style) {
+        self.status = Some(StatusMessage { text, style });
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyOutcome {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return KeyOutcome::Quit;
+        }
+
+        if let Some(dialog) = self.dialog.take() {
+            return self.handle_dialog_key(dialog, key);
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return KeyOutcome::Quit,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.descending_by_default();
+                self.arrange();
+            }
+            KeyCode::Char('S') => {
+                self.descending = !self.descending;
+                self.arrange();
+            }
+            KeyCode::Char('t') => {
+                let filters = self.filters();
+                let index = filters.iter().position(|f| *f == self.filter);
+                self.filter = index
+                    .and_then(|i| filters.get(i + 1))
+                    .copied()
+                    .unwrap_or(DeviceFilter::All);
+                self.arrange();
+            }
+            KeyCode::Char(key @ ('r' | 'i' | 'f')) => {
+                let Some(device) = self.selected_device() else {
+                    self.set_status(
+                        "No device selected".to_string(),
+                        Style::new().fg(Color::Yellow),
+                    );
+                    return KeyOutcome::Continue;
+                };
+                let (name, ip_address) = (device.name.clone(), device.ip_address.clone());
+                self.dialog = Some(match key {
+                    'f' => Dialog::FanSpeed {
+                        name,
+                        ip_address,
+                        input: String::new(),
+                    },
+                    _ => Dialog::Confirm(ControlRequest {
+                        command: if key == 'r' {
+                            ControlCommand::Restart
+                        } else {
+                            ControlCommand::Identify
+                        },
+                        name,
+                        ip_address,
+                    }),
+                });
+            }
+            _ => {}
+        }
+        KeyOutcome::Continue
+    }
+
+    fn handle_dialog_key(&mut self, dialog: Dialog, key: KeyEvent) -> KeyOutcome {
+        match dialog {
+            Dialog::Confirm(request) => match key.code {
+                KeyCode::Char('y' | 'Y') | KeyCode::Enter => return KeyOutcome::Control(request),
+                KeyCode::Char('n' | 'N') | KeyCode::Esc => {}
+                _ => self.dialog = Some(Dialog::Confirm(request)),
+            },
+            Dialog::FanSpeed {
+                name,
+                ip_address,
+                mut input,
+            } => match key.code {
+                KeyCode::Esc => {}
+                KeyCode::Enter => match input.parse::<u8>() {
+                    Ok(speed) if speed <= 100 => {
+                        self.dialog = Some(Dialog::Confirm(ControlRequest {
+                            command: ControlCommand::SetFanSpeed(speed),
+                            name,
+                            ip_address,
+                        }));
+                    }
+                    _ => {
+                        self.set_status(
+                            "Fan speed must be between 0 and 100 percent".to_string(),
+                            Style::new().fg(Color::Red),
+                        );
+                        input.clear();
+                        self.dialog = Some(Dialog::FanSpeed {
+                            name,
+                            ip_address,
+                            input,
+                        });
+                    }
+                },
+                code => {
+                    match code {
+                        KeyCode::Char(digit) if digit.is_ascii_digit() && input.len() < 3 => {
+                            input.push(digit)
+                        }
+                        KeyCode::Backspace => {
+                            input.pop();
+                        }
+                        _ => {}
+                    }
+                    self.dialog = Some(Dialog::FanSpeed {
+                        name,
+                        ip_address,
+                        input,
+                    });
+                }
+            },
+        }
+        KeyOutcome::Continue
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [header, table, bottom, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(6),
+            Constraint::Length(14),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [detail, sparklines, alerts] = Layout::horizontal([
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+        ])
+        .areas(bottom);
+
+        self.render_header(frame, header);
+        self.render_table(frame, table);
+        self.render_detail(frame, detail);
+        self.render_sparklines(frame, sparklines);
+        self.render_alerts(frame, alerts);
+        self.render_footer(frame, footer);
+        if let Some(dialog) = &self.dialog {
+            self.render_dialog(frame, dialog);
+        }
+    }
+
+    fn render_header(&self, frame: &mut Frame, area: Rect) {
+        let summary = SwarmSummary::from_devices(&self.rows);
+        let text = format!(
+            " axectl │ {online}/{total} online │ {hashrate} │ {power} │ {efficiency} │ type: {filter} │ sort: {sort} {arrow}",
+            online = summary.devices_online,
+            total = summary.total_devices,
+            hashrate = format_hashrate(summary.total_hashrate_mhs),
+            power = format_power(summary.total_power_watts),
+            efficiency = format_efficiency(summary.efficiency_jth),
+            filter = self.filter,
+            sort = self.sort.title(),
+            arrow = if self.descending { "▼" } else { "▲" },
+        );
+        frame.render_widget(
+            Paragraph::new(text).style(paint(
+                self.color,
+                Style::new().fg(Color::Black).bg(Color::Cyan),
+            )),
+            area,
+        );
+    }
+
+    fn render_table(&mut self, frame: &mut Frame, area: Rect) {
+        let header = Row::new(COLUMNS.iter().map(|column| {
+            let mut title = column.title().to_string();
+            if *column == self.sort {
+                title.push_str(if self.descending { " ▼" } else { " ▲" });
+            }
+            Cell::from(title)
+        }))
+        .style(Style::new().add_modifier(Modifier::BOLD));
+
+        let rows = self
+            .rows
+            .iter()
+            .map(|device| Row::new(COLUMNS.iter().map(|column| column.cell(device, self.color))));
+
+        let table = Table::new(rows, COLUMNS.map(Column::width))
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(" Devices "))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn render_detail(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default().borders(Borders::ALL).title(" Details ");
+        let Some(device) = self.selected_device() else {
+            frame.render_widget(Paragraph::new("No device selected").block(block), area);
+            return;
+        };
+
+        let field = |label: &str, value: String| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{label:<10}"),
+                    Style::new().add_modifier(Modifier::DIM),
+                ),
+                Span::raw(value),
+            ])
+        };
+        let (status, status_style) = status_label(&device.status);
+        let mut lines = vec![
+            Line::styled(
+                device.name.clone(),
+                Style::new().add_modifier(Modifier::BOLD),
+            ),
+            field("IP", device.ip_address.clone()),
+            field("Type", device.device_type.as_str().to_string()),
+            Line::from(vec![
+                Span::styled(
+                    format!("{label:<10}", label = "Status"),
+                    Style::new().add_modifier(Modifier::DIM),
+                ),
+                Span::styled(status, paint(self.color, status_style)),
+            ]),
+        ];
+        if let Some(stats) = live_stats(device) {
+            lines.extend([
+                field(
+                    "Frequency",
+                    stats
+                        .frequency
+                        .map(|f| format!("{f} MHz"))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                field(
+                    "Voltage",
+                    stats
+                        .voltage
+                        .map(|v| format!("{v:.0} mV"))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                field("Fan", format!("{rpm} RPM", rpm = stats.fan_speed_rpm)),
+                field(
+                    "Shares",
+                    format!(
+                        "{accepted} ({rejected} rejected)",
+                        accepted = stats.shares_accepted,
+                        rejected = stats.shares_rejected
+                    ),
+                ),
+                field(
+                    "Best",
+                    format!(
+                        "{best} (session {session})",
+                        best = format_optional_difficulty(stats.best_difficulty),
+                        session = format_optional_difficulty(stats.best_session_difficulty)
+                    ),
+                ),
+                field(
+                    "RSSI",
+                    stats
+                        .wifi_rssi
+                        .map(|r| format!("{r} dBm"))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                field(
+                    "Pool",
+                    stats.pool_url.clone().unwrap_or_else(|| "-".to_string()),
+                ),
+            ]);
+        }
+        lines.push(field(
+            "Last seen",
+            device
+                .last_seen
+                .with_timezone(&Local)
+                .format("%H:%M:%S")
+                .to_string(),
+        ));
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn render_sparklines(&self, frame: &mut Frame, area: Rect) {
+        let areas: [Rect; 3] = Layout::vertical([Constraint::Ratio(1, 3); 3]).areas(area);
+        let samples = self.selected.as_ref().and_then(|ip| self.samples.get(ip));
+
+        for (sparkline, area) in SPARKLINES.iter().zip(areas) {
+            let SparklineMetric {
+                label,
+                value,
+                format,
+                color,
+            } = *sparkline;
+            // Keep the samples that fit, newest on the right
+            let width = area.width.saturating_sub(2) as usize;
+            let values: Vec<f64> = samples
+                .map(|samples| {
+                    samples
+                        .iter()
+                        .skip(samples.len().saturating_sub(width))
+                        .map(value)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let title = match values.last() {
+                Some(current) => {
+                    let (min, max) = values
+                        .iter()
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                            (min.min(*v), max.max(*v))
+                        });
+                    format!(
+                        " {label} {current} ({min} – {max}) ",
+                        current = format(*current),
+                        min = format(min),
+                        max = format(max)
+                    )
+                }
+                None => format!(" {label} "),
+            };
+            let sparkline = Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .data(sparkline_data(&values))
+                .style(paint(self.color, Style::new().fg(color)));
+            frame.render_widget(sparkline, area);
+        }
+    }
+
+    fn render_alerts(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .alerts
+            .iter()
+            .map(|alert| {
+                let (tag, style) = match alert.state {
+                    AlertState::Resolved => ("OK  ", Style::new().fg(Color::Green)),
+                    AlertState::Firing => match alert.severity {
+                        Severity::Info => ("INFO", severity_style(alert.severity)),
+                        Severity::Warning => ("WARN", severity_style(alert.severity)),
+                        Severity::Critical => ("CRIT", severity_style(alert.severity)),
+                    },
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        alert
+                            .timestamp
+                            .with_timezone(&Local)
+                            .format("%H:%M:%S ")
+                            .to_string(),
+                        Style::new().add_modifier(Modifier::DIM),
+                    ),
+                    Span::styled(format!("{tag} "), paint(self.color, style)),
+                    Span::raw(alert.message.clone()),
+                ]))
+            })
+            .collect();
+
+        frame.render_widget(
+            List::new(items).block(Block::default().borders(Borders::ALL).title(" Alerts ")),
+            area,
+        );
+    }
+
+    fn render_footer(&self, frame: &mut Frame, area: Rect) {
+        let footer = match &self.status {
+            Some(status) => Paragraph::new(format!(" {text}", text = status.text))
+                .style(paint(self.color, status.style)),
+            None => Paragraph::new(
+                " ↑↓ select  s sort  S reverse  t type  r restart  f fan speed  i identify  q quit",
+            )
+            .style(Style::new().add_modifier(Modifier::DIM)),
+        };
+        frame.render_widget(footer, area);
+    }
+
+    fn render_dialog(&self, frame: &mut Frame, dialog: &Dialog) {
+        let (title, text, keys) = match dialog {
+            Dialog::Confirm(request) => (" Confirm ", request.prompt(), "[y] yes  [n] no"),
+            Dialog::FanSpeed { name, input, .. } => (
+                " Fan speed ",
+                format!("Fan speed for {name} (0-100%): {input}_"),
+                "[Enter] continue  [Esc] cancel",
+            ),
+        };
+
+        let width = (text.chars().count() as u16 + 6).clamp(40, frame.area().width);
+        let [area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Length(5)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::raw(text),
+                Line::raw(""),
+                Line::styled(keys, Style::new().add_modifier(Modifier::DIM)),
+            ])
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(paint(self.color, Style::new().fg(Color::Yellow))),
+            ),
+            area,
+        );
+    }
+}
+
+/// Recent samples of a device from the history store, to start its sparklines
+fn seed_samples(history: Option<&HistoryStore>, ip_address: &str) -> VecDeque<DeviceStats> {
+    let Some(history) = history else {
+        return VecDeque::new();
+    };
+    let now = Utc::now();
+    match history.query(
+        ip_address,
+        now - chrono::Duration::minutes(SPARKLINE_HISTORY_MINUTES),
+        now,
+    ) {
+        Ok(samples) => {
+            let skip = samples.len().saturating_sub(SPARKLINE_SAMPLES);
+            samples.into_iter().skip(skip).collect()
+        }
+        Err(e) => {
+            tracing::warn!("Failed to read history for {ip_address}: {e:#}");
+            VecDeque::new()
+        }
+    }
+}
+
+/// Run the monitor as a full-screen dashboard until the user quits
+pub async fn monitor_tui(config: AsyncMonitorConfig<'_>) -> Result<()> {
+    ensure!(
+        matches!(config.format, OutputFormat::Text),
+        "The TUI can't be combined with JSON output"
+    );
+
+    let cache_path = get_cache_dir(config.cache_dir)?.into_owned();
+    let history = (!config.no_history).then(|| HistoryStore::open(&cache_path));
+
+    let state = Arc::new(RwLock::new(MonitorState::default()));
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let monitor = run_monitor_loop(
+        &config,
+        state.clone(),
+        shutdown.clone(),
+        MonitorOutput::Quiet,
+    );
+    tokio::pin!(monitor);
+
+    let mut terminal = ratatui::try_init().context("Failed to initialize the terminal")?;
+    let ui_result = tokio::select! {
+        // The monitor loop only ends early on errors
+        result = &mut monitor => {
+            ratatui::restore();
+            return result;
+        }
+        result = run_ui(&mut terminal, &state, &config, history.as_ref()) => result,
+    };
+    ratatui::restore();
+
+    // Let the monitor loop finish, restoring thermal guard settings and flushing notifications
+    shutdown.store(true, AtomicOrdering::SeqCst);
+    let monitor_result = monitor.await;
+    ui_result.and(monitor_result)
+}
+
+async fn run_ui(
+    terminal: &mut DefaultTerminal,
+    state: &Arc<RwLock<MonitorState>>,
+    config: &AsyncMonitorConfig<'_>,
+    history: Option<&HistoryStore>,
+) -> Result<()> {
+    let mut app = App::new(config.color);
+    let mut events = EventStream::new();
+    let mut refresh = interval(REFRESH_INTERVAL);
+    let (results_tx, mut results_rx) =
+        mpsc::unbounded_channel::<(ControlRequest, Result<CommandResult>)>();
+    let mut last_discovery: Option<DateTime<Utc>> = None;
+
+    loop {
+        terminal
+            .draw(|frame| app.render(frame))
+            .context("Failed to draw the dashboard")?;
+
+        tokio::select! {
+            _ = refresh.tick() => {
+                let (devices, alerts, discovery) = {
+                    let state_guard = state.read().await;
+                    (
+                        monitored_devices(&state_guard, config.type_filter, config.all),
+                        state_guard.alerts.iter().rev().take(ALERT_LOG_SIZE).cloned().collect(),
+                        state_guard.last_discovery,
+                    )
+                };
+                app.update(devices, alerts, history);
+
+                // Background discovery prints its progress, so repaint the whole screen after it
+                if discovery != last_discovery {
+                    last_discovery = discovery;
+                    terminal.clear().context("Failed to clear the terminal")?;
+                }
+            }
+
+            Some(event) = events.next() => {
+                match event.context("Failed to read terminal event")? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('l') {
+                            terminal.clear().context("Failed to clear the terminal")?;
+                            continue;
+                        }
+                        match app.handle_key(key) {
+                            KeyOutcome::Continue => {}
+                            KeyOutcome::Quit => return Ok(()),
+                            KeyOutcome::Control(request) => {
+                                app.set_status(
+                                    format!("Sending to {name}...", name = request.name),
+                                    Style::new().fg(Color::Cyan),
+                                );
+                                let tx = results_tx.clone();
+                                tokio::spawn(async move {
+                                    let result = request.send().await;
+                                    let _ = tx.send((request, result));
+                                });
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            Some((request, result)) = results_rx.recv() => {
+                match result {
+                    Ok(result) if result.success => app.set_status(
+                        format!("{name}: {message}", name = request.name, message = result.message),
+                        Style::new().fg(Color::Green),
+                    ),
+                    Ok(result) => app.set_status(
+                        format!("{name}: {message}", name = request.name, message = result.message),
+                        Style::new().fg(Color::Red),
+                    ),
+                    Err(e) => app.set_status(
+                        format!("{name}: {e:#}", name = request.name),
+                        Style::new().fg(Color::Red),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceType;
+    use crossterm::event::KeyEventState;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn device(name: &str, ip_address: &str, device_type: DeviceType, hashrate: f64) -> Device {
+        Device {
+            name: name.to_string(),
+            ip_address: ip_address.to_string(),
+            device_type,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: Some(DeviceStats {
+                timestamp: Utc::now(),
+                hashrate_mhs: hashrate,
+                temperature_celsius: 60.0,
+                power_watts: 15.0,
+                fan_speed_rpm: 4000,
+                shares_accepted: 10,
+                shares_rejected: 0,
+                uptime_seconds: 3600,
+                pool_url: None,
+                wifi_rssi: None,
+                voltage: None,
+                frequency: None,
+                efficiency_jth: None,
+                best_difficulty: None,
+                best_session_difficulty: None,
+                using_fallback_pool: None,
+                expected_hashrate_mhs: None,
+                hashrate_health_percent: None,
+            }),
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    fn names(app: &App) -> Vec<&str> {
+        app.rows.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    fn app() -> App {
+        let mut app = App::new(true);
+        app.update(
+            vec![
+                device("gamma-1", "192.168.1.10", DeviceType::BitaxeGamma, 1100.0),
+                device("nerd-1", "192.168.1.20", DeviceType::NerdqaxePlus, 4800.0),
+                device("ultra-1", "192.168.1.30", DeviceType::BitaxeUltra, 500.0),
+            ],
+            Vec::new(),
+            None,
+        );
+        app
+    }
+
+    #[test]
+    fn sorts_and_filters_while_keeping_the_selection() {
+        let mut app = app();
+        assert_eq!(names(&app), vec!["gamma-1", "nerd-1", "ultra-1"]);
+
+        // Select nerd-1, then sort by hashrate (highest first)
+        app.handle_key(key(KeyCode::Down));
+        for _ in 0..4 {
+            app.handle_key(key(KeyCode::Char('s')));
+        }
+        assert_eq!(app.sort, Column::Hashrate);
+        assert_eq!(names(&app), vec!["nerd-1", "gamma-1", "ultra-1"]);
+        assert_eq!(app.table.selected(), Some(0));
+
+        app.handle_key(key(KeyCode::Char('S')));
+        assert_eq!(names(&app), vec!["ultra-1", "gamma-1", "nerd-1"]);
+        assert_eq!(app.selected.as_deref(), Some("192.168.1.20"));
+
+        // all -> bitaxe
+        app.handle_key(key(KeyCode::Char('t')));
+        assert_eq!(app.filter, DeviceFilter::AnyBitaxe);
+        assert_eq!(names(&app), vec!["ultra-1", "gamma-1"]);
+        assert_eq!(app.selected.as_deref(), Some("192.168.1.30"));
+    }
+
+    #[test]
+    fn control_actions_need_confirmation() {
+        let mut app = app();
+
+        app.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('n'))),
+            KeyOutcome::Continue
+        );
+        assert!(app.dialog.is_none());
+
+        // Out of range fan speeds are rejected
+        app.handle_key(key(KeyCode::Char('f')));
+        for digit in ['1', '5', '0'] {
+            app.handle_key(key(KeyCode::Char(digit)));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.dialog, Some(Dialog::FanSpeed { ref input, .. }) if input.is_empty()));
+
+        for digit in ['8', '0'] {
+            app.handle_key(key(KeyCode::Char(digit)));
+        }
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('y'))),
+            KeyOutcome::Control(ControlRequest {
+                command: ControlCommand::SetFanSpeed(80),
+                name: "gamma-1".to_string(),
+                ip_address: "192.168.1.10".to_string(),
+            })
+        );
+        assert!(app.dialog.is_none());
+        assert_eq!(app.handle_key(key(KeyCode::Char('q'))), KeyOutcome::Quit);
+    }
+
+    #[test]
+    fn navigation_keys_stay_within_the_table() {
+        let mut app = app();
+
+        app.handle_key(key(KeyCode::Char('j')));
+        app.handle_key(key(KeyCode::Char('j')));
+        app.handle_key(key(KeyCode::Char('j')));
+        assert_eq!(app.selected.as_deref(), Some("192.168.1.30"));
+
+        app.handle_key(key(KeyCode::PageUp));
+        assert_eq!(app.table.selected(), Some(0));
+        app.handle_key(key(KeyCode::PageDown));
+        assert_eq!(app.table.selected(), Some(2));
+        app.handle_key(key(KeyCode::Char('k')));
+        assert_eq!(app.selected.as_deref(), Some("192.168.1.20"));
+
+        // Keys inside a dialog go to the dialog, but Ctrl-C always quits
+        app.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Char('q'))),
+            KeyOutcome::Continue
+        );
+        assert!(matches!(app.dialog, Some(Dialog::Confirm(_))));
+        let ctrl_c = KeyEvent {
+            modifiers: KeyModifiers::CONTROL,
+            ..key(KeyCode::Char('c'))
+        };
+        assert_eq!(app.handle_key(ctrl_c), KeyOutcome::Quit);
+        assert_eq!(app.handle_key(key(KeyCode::Esc)), KeyOutcome::Continue);
+        assert_eq!(app.handle_key(key(KeyCode::Esc)), KeyOutcome::Quit);
+
+        // Nothing to control without devices
+        let mut empty = App::new(false);
+        empty.update(Vec::new(), Vec::new(), None);
+        empty.handle_key(key(KeyCode::Down));
+        assert_eq!(empty.table.selected(), None);
+        empty.handle_key(key(KeyCode::Char('r')));
+        assert!(empty.dialog.is_none());
+        assert!(
+            empty
+                .status
+                .as_ref()
+                .is_some_and(|status| status.text == "No device selected")
+        );
+    }
+
+    #[test]
+    fn type_filter_and_sort_cycle_back_to_the_start() {
+        let mut app = app();
+        if let Some(ultra) = app.devices.iter_mut().find(|d| d.name == "ultra-1") {
+            ultra.status = DeviceStatus::Offline;
+        }
+        app.devices.swap(0, 2);
+        app.arrange();
+
+        // Family filters first, then each type present, then back to all
+        let expected = [
+            (DeviceFilter::AnyBitaxe, vec!["gamma-1", "ultra-1"]),
+            (DeviceFilter::AnyNerdQaxe, vec!["nerd-1"]),
+            (
+                DeviceFilter::Specific(DeviceType::BitaxeGamma),
+                vec!["gamma-1"],
+            ),
+            (
+                DeviceFilter::Specific(DeviceType::BitaxeUltra),
+                vec!["ultra-1"],
+            ),
+            (
+                DeviceFilter::Specific(DeviceType::NerdqaxePlus),
+                vec!["nerd-1"],
+            ),
+            (DeviceFilter::All, vec!["gamma-1", "nerd-1", "ultra-1"]),
+        ];
+        for (filter, rows) in expected {
+            app.handle_key(key(KeyCode::Char('t')));
+            assert_eq!(app.filter, filter);
+            assert_eq!(names(&app), rows);
+        }
+
+        // Status sorts online devices first, ties by name
+        for _ in 0..3 {
+            app.handle_key(key(KeyCode::Char('s')));
+        }
+        assert_eq!((app.sort, app.descending), (Column::Status, false));
+        assert_eq!(names(&app), vec!["gamma-1", "nerd-1", "ultra-1"]);
+        app.handle_key(key(KeyCode::Char('S')));
+        assert_eq!(names(&app), vec!["ultra-1", "gamma-1", "nerd-1"]);
+
+        for _ in 3..COLUMNS.len() {
+            app.handle_key(key(KeyCode::Char('s')));
+        }
+        assert_eq!((app.sort, app.descending), (Column::Name, false));
+        assert_eq!(names(&app), vec!["gamma-1", "nerd-1", "ultra-1"]);
+    }
+
+    #[test]
+    fn control_dialogs_can_be_cancelled_or_confirmed() {
+        let mut app = app();
+
+        // Enter confirms a restart
+        app.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(
+            app.handle_key(key(KeyCode::Enter)),
+            KeyOutcome::Control(ControlRequest {
+                command: ControlCommand::Restart,
+                name: "gamma-1".to_string(),
+                ip_address: "192.168.1.10".to_string(),
+            })
+        );
+
+        // Other keys keep asking; Esc cancels without quitting
+        app.handle_key(key(KeyCode::Char('i')));
+        app.handle_key(key(KeyCode::Char('x')));
+        assert!(matches!(
+            app.dialog,
+            Some(Dialog::Confirm(ControlRequest {
+                command: ControlCommand::Identify,
+                ..
+            }))
+        ));
+        assert_eq!(app.handle_key(key(KeyCode::Esc)), KeyOutcome::Continue);
+        assert!(app.dialog.is_none());
+
+        // Fan speed input takes at most three digits and nothing else
+        app.handle_key(key(KeyCode::Char('f')));
+        for code in ['a', '1', '0', '0', '0'].map(KeyCode::Char) {
+            app.handle_key(key(code));
+        }
+        assert!(matches!(app.dialog, Some(Dialog::FanSpeed { ref input, .. }) if input == "100"));
+        app.handle_key(key(KeyCode::Backspace));
+        assert!(matches!(app.dialog, Some(Dialog::FanSpeed { ref input, .. }) if input == "10"));
+        assert_eq!(app.handle_key(key(KeyCode::Esc)), KeyOutcome::Continue);
+        assert!(app.dialog.is_none());
+
+        // An empty fan speed is rejected, and confirming still needs a second key
+        app.handle_key(key(KeyCode::Char('f')));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.dialog, Some(Dialog::FanSpeed { .. })));
+        app.handle_key(key(KeyCode::Char('0')));
+        assert_eq!(app.handle_key(key(KeyCode::Enter)), KeyOutcome::Continue);
+        assert!(matches!(
+            app.dialog,
+            Some(Dialog::Confirm(ControlRequest {
+                command: ControlCommand::SetFanSpeed(0),
+                ..
+            }))
+        ));
+        app.handle_key(key(KeyCode::Char('n')));
+        assert!(app.dialog.is_none());
+    }
+
+    #[test]
+    fn renders_devices_sparklines_and_dialogs() -> Result<()> {
+        let mut app = app();
+        let mut later = app.devices.clone();
+        for device in &mut later {
+            if let Some(stats) = device.stats.as_mut() {
+                stats.timestamp += chrono::Duration::seconds(5);
+                stats.hashrate_mhs *= 1.1;
+            }
+        }
+        app.update(later, Vec::new(), None);
+        assert_eq!(app.samples["192.168.1.10"].len(), 2);
+        assert_eq!(sparkline_data(&[60.0, 61.0, 62.0]), vec![1, 51, 100]);
+        assert_eq!(sparkline_data(&[60.0, 60.0]), vec![1, 1]);
+
+        app.handle_key(key(KeyCode::Char('i')));
+        let mut terminal = Terminal::new(TestBackend::new(140, 40))?;
+        terminal.draw(|frame| app.render(frame))?;
+
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("nerd-1"));
+        assert!(screen.contains("Hashrate 1.21 TH/s"));
+        assert!(screen.contains("Identify gamma-1 (192.168.1.10)?"));
+        Ok(())
+    }
+}
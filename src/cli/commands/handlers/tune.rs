@@ -0,0 +1,339 @@
+use crate::api::{AxeOsClient, SystemUpdateRequest};
+use crate::cache::{DeviceCache, get_cache_dir};
+use crate::cli::commands::OutputFormat;
+use crate::output::{
+    format_hashrate, format_table, print_error, print_info, print_json, print_success,
+    print_warning,
+};
+use crate::settings::SettingsPolicy;
+use crate::tune::{
+    PointStatus, TuneConfig, TuneGoal, TunePoint, TuneSession, TuneStore, apply_point,
+    parse_grid_values, run_tune_or_restore,
+};
+use anyhow::{Context, Result};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+use tabled::Tabled;
+
+/// Arguments for the tune command
+pub struct TuneArgs<'a> {
+    pub device: String,
+    pub frequencies: String,
+    pub voltages: String,
+    pub settle: u64,
+    pub samples: usize,
+    pub sample_interval: u64,
+    pub max_temp: f64,
+    pub max_power: Option<f64>,
+    pub goal: TuneGoal,
+    pub apply: bool,
+    pub restart: bool,
+    pub fresh: bool,
+    pub force: bool,
+    /// Allow grid points outside the device's safe frequency and voltage range
+    pub allow_unsafe: bool,
+    pub format: OutputFormat,
+    pub color: bool,
+    pub cache_dir: Option<&'a Path>,
+}
+
+#[derive(Tabled)]
+struct TunePointRow {
+    #[tabled(rename = "Frequency")]
+    frequency: String,
+    #[tabled(rename = "Voltage")]
+    voltage: String,
+    #[tabled(rename = "Hashrate")]
+    hashrate: String,
+    #[tabled(rename = "Temp")]
+    temperature: String,
+    #[tabled(rename = "Power")]
+    power: String,
+    #[tabled(rename = "J/TH")]
+    efficiency: String,
+    #[tabled(rename = "Result")]
+    status: String,
+}
+
+pub async fn tune(args: TuneArgs<'_>) -> Result<()> {
+    let cache_path = get_cache_dir(args.cache_dir)?;
+    let cache_path = cache_path.as_ref();
+    let cache = DeviceCache::load(cache_path)?;
+    let device = cache
+        .find_device(&args.device)
+        .with_context(|| format!("Device not found: {device}", device = args.device))?;
+
+    let config = TuneConfig {
+        frequencies: parse_grid_values(&args.frequencies).context("Invalid --frequencies")?,
+        voltages: parse_grid_values(&args.voltages).context("Invalid --voltages")?,
+        settle: Duration::from_secs(args.settle),
+        samples: args.samples.max(1),
+        sample_interval: Duration::from_secs(args.sample_interval),
+        max_temp: args.max_temp,
+        max_power: args.max_power,
+        restart: args.restart,
+        policy: SettingsPolicy::load(args.allow_unsafe)?,
+    };
+
+    let client = AxeOsClient::with_timeout(&device.ip_address, Duration::from_secs(30))?;
+    let info = client.get_system_info().await?;
+    config.ensure_supported(&info)?;
+    let store = TuneStore::open(cache_path);
+
+    let saved = if args.fresh {
+        store.remove(&device.ip_address)?;
+        None
+    } else {
+        store.load(&device.ip_address)?
+    };
+
+    let mut session = match saved {
+        Some(session) if session.matches_grid(&config) => {
+            if args.format == OutputFormat::Text {
+                print_info(
+                    &format!(
+                        "Resuming tuning session from {started}: {done} point(s) already measured",
+                        started = session.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        done = session.points.len()
+                    ),
+                    args.color,
+                );
+            }
+            session
+        }
+        saved => {
+            if saved.is_some() && args.format == OutputFormat::Text {
+                print_warning(
+                    "Discarding the saved tuning session, it used a different grid",
+                    args.color,
+                );
+            }
+            TuneSession::new(
+                &device.name,
+                &device.ip_address,
+                info.frequency,
+                info.voltage,
+                &config,
+            )
+        }
+    };
+
+    // Tuning ends by restoring these when no point is applied, so they must pass the same checks
+    let original = SystemUpdateRequest {
+        frequency_value: Some(session.original_frequency),
+        voltage_value: Some(session.original_voltage),
+        ..Default::default()
+    };
+    config
+        .policy
+        .validate(&original, &info)
+        .context("The settings tuning would restore are outside the device's safe range")?;
+
+    let remaining = session.remaining().len();
+    if remaining > 0 && !args.force && args.format == OutputFormat::Text {
+        let per_point = config.settle + config.sample_interval * (config.samples as u32 - 1);
+        print_info(
+            &format!(
+                "About to test {remaining} frequency/voltage point(s) on {name} at {ip} (about {minutes} min)",
+                name = device.name,
+                ip = device.ip_address,
+                minutes = (per_point * remaining as u32).as_secs().div_ceil(60)
+            ),
+            args.color,
+        );
+        print_warning(
+            "Out of range settings can overheat or destabilize the device",
+            args.color,
+        );
+
+        eprint!("Continue? [y/N]: ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            print_info("Operation cancelled.", args.color);
+            return Ok(());
+        }
+    }
+
+    let text = args.format == OutputFormat::Text;
+    let color = args.color;
+    let on_point = |point: &TunePoint| {
+        if text {
+            print_point(point, color);
+        }
+    };
+    run_tune_or_restore(
+        &client,
+        &config,
+        &mut session,
+        &store,
+        on_point,
+        tokio::signal::ctrl_c(),
+    )
+    .await?;
+
+    let best = session.best(args.goal).cloned();
+    let applied = match best {
+        Some(ref best) if args.apply => {
+            apply_point(
+                &client,
+                &config.policy,
+                best.frequency,
+                f64::from(best.voltage),
+                args.restart,
+            )
+            .await?;
+            true
+        }
+        _ => {
+            // Leave the device as it was found
+            apply_point(
+                &client,
+                &config.policy,
+                session.original_frequency,
+                session.original_voltage,
+                args.restart,
+            )
+            .await?;
+            false
+        }
+    };
+
+    match args.format {
+        OutputFormat::Json => {
+            let output = serde_json::json!({
+                "device": session.device_name,
+                "ip": session.ip_address,
+                "goal": args.goal,
+                "original_frequency": session.original_frequency,
+                "original_voltage": session.original_voltage,
+                "points": session.points,
+                "best": best,
+                "applied": applied,
+                "timestamp": chrono::Utc::now(),
+            });
+            print_json(&output, true)?;
+        }
+        OutputFormat::Text => {
+            let mut points: Vec<_> = session.points.iter().collect();
+            points.sort_by_key(|p| (p.voltage, p.frequency));
+            let rows: Vec<_> = points
+                .into_iter()
+                .map(|point| {
+                    let is_best = best.as_ref() == Some(point);
+                    point_row(point, is_best)
+                })
+                .collect();
+            println!("{}", format_table(rows, args.color));
+
+            match best {
+                Some(ref best) => {
+                    let summary = format!(
+                        "{freq} MHz @ {volt} mV: {hashrate}, {efficiency}",
+                        freq = best.frequency,
+                        volt = best.voltage,
+                        hashrate = format_hashrate(best.hashrate.unwrap_or_default()),
+                        efficiency = format_efficiency(best.efficiency())
+                    );
+                    if applied {
+                        print_success(
+                            &format!("✓ Applied best {goal} point {summary}", goal = args.goal),
+                            args.color,
+                        );
+                    } else {
+                        print_info(
+                            &format!("Best {goal} point: {summary}", goal = args.goal),
+                            args.color,
+                        );
+                        print_info(
+                            "Restored the original settings, rerun with --apply to use it",
+                            args.color,
+                        );
+                    }
+                }
+                None => {
+                    print_error("No point stayed within the limits", args.color);
+                    print_info("Restored the original settings", args.color);
+                }
+            }
+            if applied && !args.restart {
+                print_info(
+                    "Restart the device if the new settings are not active yet",
+                    args.color,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_point(point: &TunePoint, color: bool) {
+    let label = format!(
+        "{freq} MHz @ {volt} mV",
+        freq = point.frequency,
+        volt = point.voltage
+    );
+    match point.status {
+        PointStatus::Accepted => print_success(
+            &format!(
+                "✓ {label}: {hashrate}, {temp:.1}°C, {power:.1} W, {efficiency}",
+                hashrate = format_hashrate(point.hashrate.unwrap_or_default()),
+                temp = point.temperature.unwrap_or_default(),
+                power = point.power.unwrap_or_default(),
+                efficiency = format_efficiency(point.efficiency())
+            ),
+            color,
+        ),
+        PointStatus::Skipped => print_info(&format!("- {label}: skipped"), color),
+        PointStatus::Rejected | PointStatus::Failed => print_warning(
+            &format!(
+                "✗ {label}: {reason}",
+                reason = point.reason.as_deref().unwrap_or("failed")
+            ),
+            color,
+        ),
+    }
+}
+
+fn point_row(point: &TunePoint, is_best: bool) -> TunePointRow {
+    let status = match point.status {
+        PointStatus::Accepted if is_best => "★ best",
+        PointStatus::Accepted => "ok",
+        PointStatus::Rejected => "over limit",
+        PointStatus::Skipped => "skipped",
+        PointStatus::Failed => "failed",
+    };
+
+    TunePointRow {
+        frequency: format!("{freq} MHz", freq = point.frequency),
+        voltage: format!("{volt} mV", volt = point.voltage),
+        hashrate: point
+            .hashrate
+            .map(format_hashrate)
+            .unwrap_or_else(|| "-".to_string()),
+        temperature: point
+            .temperature
+            .map(|t| format!("{t:.1}°C"))
+            .unwrap_or_else(|| "-".to_string()),
+        power: point
+            .power
+            .map(|p| format!("{p:.1} W"))
+            .unwrap_or_else(|| "-".to_string()),
+        efficiency: point
+            .efficiency()
+            .map(|e| format!("{e:.2}"))
+            .unwrap_or_else(|| "-".to_string()),
+        status: status.to_string(),
+    }
+}
+
+fn format_efficiency(efficiency: Option<f64>) -> String {
+    efficiency
+        .map(|e| format!("{e:.2} J/TH"))
+        .unwrap_or_else(|| "- J/TH".to_string())
+}
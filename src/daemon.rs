@@ -0,0 +1,182 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::api::{AxeOsClient, CommandResult, Device, DeviceFilter, SystemUpdateRequest};
+use crate::settings::SettingsPolicy;
+
+/// Address the daemon listens on by default (local only)
+pub const DEFAULT_DAEMON_LISTEN: &str = "127.0.0.1:9185";
+
+/// Timeout for requests to the daemon, long enough for proxied control actions
+const DAEMON_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Response of `GET /api/devices`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicesResponse {
+    pub devices: Vec<Device>,
+    pub total: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Error body returned by the daemon API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Body of `POST /api/devices/{device}/fan-speed`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FanSpeedRequest {
+    pub speed: u8,
+}
+
+/// Query of `POST /api/devices/{device}/settings`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SettingsQuery {
+    /// Allow frequency and voltage outside the device's safe range
+    #[serde(default, rename = "unsafe")]
+    pub allow_unsafe: bool,
+}
+
+/// Control action the daemon proxies to a device
+#[derive(Debug, Clone)]
+pub enum DaemonCommand {
+    Restart,
+    Identify,
+    SetFanSpeed(u8),
+    UpdateSettings {
+        settings: Box<SystemUpdateRequest>,
+        allow_unsafe: bool,
+    },
+}
+
+impl DaemonCommand {
+    /// Last path segment of the control endpoint
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            DaemonCommand::Restart => "restart",
+            DaemonCommand::Identify => "identify",
+            DaemonCommand::SetFanSpeed(_) => "fan-speed",
+            DaemonCommand::UpdateSettings { .. } => "settings",
+        }
+    }
+
+    /// Send the action straight to the device
+    pub async fn send(&self, client: &AxeOsClient) -> Result<CommandResult> {
+        match self {
+            DaemonCommand::Restart => client.restart_system().await,
+            DaemonCommand::Identify => client.identify().await,
+            DaemonCommand::SetFanSpeed(speed) => client.set_fan_speed(*speed).await,
+            DaemonCommand::UpdateSettings {
+                settings,
+                allow_unsafe,
+            } => {
+                SettingsPolicy::load(*allow_unsafe)?
+                    .ensure_supported(client, settings)
+                    .await?;
+                client.update_system(settings.as_ref().clone()).await
+            }
+        }
+    }
+}
+
+/// Client for the `axectl daemon` HTTP API
+pub struct DaemonClient {
+    base_url: Url,
+    client: reqwest::Client,
+}
+
+impl DaemonClient {
+    /// Connect to a daemon at `url`, e.g. `http://127.0.0.1:9185`
+    pub fn new(url: &str) -> Result<Self> {
+        let base_url = Url::parse(url).with_context(|| format!("Invalid daemon URL: {url}"))?;
+        if base_url.cannot_be_a_base() || !matches!(base_url.scheme(), "http" | "https") {
+            bail!("Invalid daemon URL: {url}");
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(DAEMON_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self { base_url, client })
+    }
+
+    /// Devices known to the daemon, with their latest statistics
+    pub async fn devices(&self, filter: Option<DeviceFilter>, all: bool) -> Result<Vec<Device>> {
+        let mut url = self.url(&["api", "devices"]);
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(filter) = filter {
+                query.append_pair("type", &filter.to_string());
+            }
+            if all {
+                query.append_pair("all", "true");
+            }
+        }
+
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context("Failed to connect to the daemon")?;
+        let devices: DevicesResponse = parse_response(response).await?;
+        Ok(devices.devices)
+    }
+
+    /// Have the daemon run a control action on a device (name or IP)
+    pub async fn control(&self, device: &str, command: &DaemonCommand) -> Result<CommandResult> {
+        let url = self.url(&["api", "devices", device, command.endpoint()]);
+        let request = self.client.post(url);
+        let request = match command {
+            DaemonCommand::SetFanSpeed(speed) => request.json(&FanSpeedRequest { speed: *speed }),
+            DaemonCommand::UpdateSettings {
+                settings,
+                allow_unsafe,
+            } => request
+                .query(&SettingsQuery {
+                    allow_unsafe: *allow_unsafe,
+                })
+                .json(settings),
+            DaemonCommand::Restart | DaemonCommand::Identify => request,
+        };
+
+        let response = request
+            .send()
+            .await
+            .context("Failed to connect to the daemon")?;
+        parse_response(response).await
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+}
+
+/// Parse a daemon response, turning error bodies into errors
+///
+/// Failed control actions still carry a `CommandResult`, which is returned as is.
+async fn parse_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .context("Failed to read daemon response")?;
+
+    if let Ok(value) = serde_json::from_str::<T>(&body) {
+        return Ok(value);
+    }
+    if let Ok(error) = serde_json::from_str::<ErrorResponse>(&body) {
+        bail!("Daemon error: {error}", error = error.error);
+    }
+    bail!("Unexpected daemon response ({status}): {body}")
+}
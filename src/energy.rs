@@ -0,0 +1,435 @@
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{Duration, NaiveDate, NaiveTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::api::DeviceStats;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Longest gap between two history samples that still counts as continuous
+/// operation when integrating power into energy
+pub const MAX_SAMPLE_GAP: Duration = Duration::minutes(15);
+
+/// Electricity tariff as written in a TOML file
+///
+/// ```toml
+/// currency = "EUR"
+/// price = 0.30            # per kWh outside of the periods below
+///
+/// [[periods]]
+/// start = "23:00"
+/// end = "07:00"           # periods may wrap around midnight
+/// price = 0.18
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TariffConfig {
+    /// Currency label shown next to costs
+    pub currency: Option<String>,
+    /// Price per kWh whenever no period applies
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub periods: Vec<TariffPeriodConfig>,
+}
+
+/// Time-of-use period as written in a TOML file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TariffPeriodConfig {
+    /// Local start time (HH:MM, inclusive)
+    pub start: String,
+    /// Local end time (HH:MM, exclusive)
+    pub end: String,
+    /// Price per kWh during the period
+    pub price: f64,
+}
+
+/// Time-of-use period with a price per kWh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TariffPeriod {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub price: f64,
+}
+
+impl TariffPeriod {
+    /// Whether the period covers the given local time
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            // Wraps around midnight
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Validated electricity tariff, either flat or time-of-use
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tariff {
+    pub currency: Option<String>,
+    base_price: Option<f64>,
+    periods: Vec<TariffPeriod>,
+}
+
+impl Tariff {
+    /// Tariff with the same price per kWh all day
+    pub fn flat(price: f64) -> Result<Self> {
+        ensure!(
+            price.is_finite() && price >= 0.0,
+            "Electricity price must be a non-negative number, got {price}"
+        );
+        Ok(Self {
+            currency: None,
+            base_price: Some(price),
+            periods: Vec::new(),
+        })
+    }
+
+    /// Parse a `--tariff` argument: a flat price per kWh or the path of a tariff file
+    pub fn from_arg(value: &str) -> Result<Self> {
+        match value.parse::<f64>() {
+            Ok(price) => Self::flat(price),
+            Err(_) => Self::load(Path::new(value)),
+        }
+    }
+
+    /// Load a tariff from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tariff file {path}", path = path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid tariff file {path}", path = path.display()))
+    }
+
+    /// Parse a tariff from TOML
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: TariffConfig = toml::from_str(content).context("Failed to parse TOML")?;
+        Self::try_from(config)
+    }
+
+    /// Price per kWh at the given local time
+    pub fn price_at(&self, time: NaiveTime) -> f64 {
+        self.periods
+            .iter()
+            .find(|p| p.contains(time))
+            .map(|p| p.price)
+            .or(self.base_price)
+            .unwrap_or_default()
+    }
+
+    /// Time-weighted average price per kWh over a day, which is what a
+    /// constant load pays on average
+    pub fn average_price(&self) -> f64 {
+        if self.periods.is_empty() {
+            return self.base_price.unwrap_or_default();
+        }
+        let total: f64 = (0..MINUTES_PER_DAY)
+            .map(|minute| self.price_at(minute_of_day(minute)))
+            .sum();
+        total / f64::from(MINUTES_PER_DAY)
+    }
+
+    /// Whether the price changes over the day
+    pub fn is_time_of_use(&self) -> bool {
+        !self.periods.is_empty()
+    }
+
+    /// Format an amount of money in the tariff's currency
+    pub fn format_cost(&self, cost: f64) -> String {
+        format_money(cost, self.currency.as_deref())
+    }
+}
+
+impl TryFrom<TariffConfig> for Tariff {
+    type Error = anyhow::Error;
+
+    fn try_from(config: TariffConfig) -> Result<Self> {
+        if let Some(price) = config.price {
+            ensure!(
+                price.is_finite() && price >= 0.0,
+                "Electricity price must be a non-negative number, got {price}"
+            );
+        }
+
+        let mut periods = Vec::new();
+        for period in config.periods {
+            let start = parse_time_of_day(&period.start)?;
+            let end = parse_time_of_day(&period.end)?;
+            ensure!(
+                start != end,
+                "Tariff period {start}-{end} is empty",
+                start = period.start,
+                end = period.end
+            );
+            ensure!(
+                period.price.is_finite() && period.price >= 0.0,
+                "Price of tariff period {start}-{end} must be a non-negative number",
+                start = period.start,
+                end = period.end
+            );
+            periods.push(TariffPeriod {
+                start,
+                end,
+                price: period.price,
+            });
+        }
+
+        if config.price.is_none() && periods.is_empty() {
+            bail!("Tariff needs a price or at least one period");
+        }
+
+        for minute in 0..MINUTES_PER_DAY {
+            let time = minute_of_day(minute);
+            let matching = periods.iter().filter(|p| p.contains(time)).count();
+            ensure!(
+                matching <= 1,
+                "Tariff periods overlap at {time}",
+                time = time.format("%H:%M")
+            );
+            ensure!(
+                matching == 1 || config.price.is_some(),
+                "Tariff periods leave {time} uncovered; set a default price",
+                time = time.format("%H:%M")
+            );
+        }
+
+        Ok(Self {
+            currency: config.currency,
+            base_price: config.price,
+            periods,
+        })
+    }
+}
+
+/// Projected energy use and cost of a constant load over a day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EnergyEstimate {
+    pub kwh_per_day: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_per_day: Option<f64>,
+}
+
+impl EnergyEstimate {
+    /// Project a day of running at the given power draw
+    pub fn from_power(power_watts: f64, tariff: Option<&Tariff>) -> Self {
+        let kwh_per_day = power_watts * 24.0 / 1000.0;
+        Self {
+            kwh_per_day,
+            cost_per_day: tariff.map(|t| kwh_per_day * t.average_price()),
+        }
+    }
+}
+
+/// Energy actually used on one day, integrated from history samples
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DailyEnergy {
+    pub date: NaiveDate,
+    pub kwh: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// How long the device was covered by samples
+    pub hours: f64,
+}
+
+/// Integrate the power draw of ordered history samples into energy per local day
+///
+/// Each sample's power is assumed to hold until the next sample, priced at the
+/// tariff in effect when the interval starts. Gaps longer than [`MAX_SAMPLE_GAP`]
+/// (the device or the recorder was off) are not counted.
+pub fn daily_energy<Tz: TimeZone>(
+    samples: &[DeviceStats],
+    tariff: Option<&Tariff>,
+    tz: &Tz,
+) -> Vec<DailyEnergy> {
+    let mut days: BTreeMap<NaiveDate, (f64, f64, f64)> = BTreeMap::new();
+
+    for pair in samples.windows(2) {
+        let (current, next) = (&pair[0], &pair[1]);
+        let gap = next.timestamp - current.timestamp;
+        if gap <= Duration::zero() || gap > MAX_SAMPLE_GAP {
+            continue;
+        }
+
+        let hours = gap.num_milliseconds() as f64 / 3_600_000.0;
+        let kwh = current.power_watts * hours / 1000.0;
+        let local = current.timestamp.with_timezone(tz);
+        let price = tariff.map(|t| t.price_at(local.time())).unwrap_or_default();
+
+        let day = days.entry(local.date_naive()).or_default();
+        day.0 += kwh;
+        day.1 += kwh * price;
+        day.2 += hours;
+    }
+
+    days.into_iter()
+        .map(|(date, (kwh, cost, hours))| DailyEnergy {
+            date,
+            kwh,
+            cost: tariff.map(|_| cost),
+            hours,
+        })
+        .collect()
+}
+
+/// Format an amount of money with an optional currency label
+pub fn format_money(amount: f64, currency: Option<&str>) -> String {
+    match currency {
+        Some(currency) => format!("{amount:.2} {currency}"),
+        None => format!("{amount:.2}"),
+    }
+}
+
+fn parse_time_of_day(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .with_context(|| format!("Invalid time of day '{value}', expected HH:MM"))
+}
+
+fn minute_of_day(minute: u32) -> NaiveTime {
+    NaiveTime::MIN
+        .with_hour(minute / 60)
+        .and_then(|t| t.with_minute(minute % 60))
+        .unwrap_or(NaiveTime::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn time(value: &str) -> Result<NaiveTime> {
+        parse_time_of_day(value)
+    }
+
+    fn sample(timestamp: DateTime<Utc>, power_watts: f64) -> DeviceStats {
+        DeviceStats {
+            timestamp,
+            hashrate_mhs: 1000.0,
+            temperature_celsius: 60.0,
+            power_watts,
+            fan_speed_rpm: 4000,
+            shares_accepted: 0,
+            shares_rejected: 0,
+            uptime_seconds: 0,
+            pool_url: None,
+            wifi_rssi: None,
+            voltage: None,
+            frequency: None,
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    #[test]
+    fn time_of_use_tariff_prices_periods_across_midnight() -> Result<()> {
+        let tariff = Tariff::parse(
+            r#"
+            currency = "EUR"
+            price = 0.30
+
+            [[periods]]
+            start = "23:00"
+            end = "07:00"
+            price = 0.18
+            "#,
+        )?;
+
+        assert!(tariff.is_time_of_use());
+        assert_eq!(tariff.price_at(time("23:30")?), 0.18);
+        assert_eq!(tariff.price_at(time("06:59")?), 0.18);
+        assert_eq!(tariff.price_at(time("07:00")?), 0.30);
+        // 8 cheap hours and 16 regular hours
+        assert!((tariff.average_price() - (8.0 * 0.18 + 16.0 * 0.30) / 24.0).abs() < 1e-9);
+        assert_eq!(tariff.format_cost(1.5), "1.50 EUR");
+
+        let estimate = EnergyEstimate::from_power(20.0, Some(&tariff));
+        assert!((estimate.kwh_per_day - 0.48).abs() < 1e-9);
+        assert!(
+            (estimate.cost_per_day.unwrap_or_default() - 0.48 * tariff.average_price()).abs()
+                < 1e-9
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_tariffs_are_rejected() -> Result<()> {
+        let uncovered = Tariff::parse(
+            r#"
+            [[periods]]
+            start = "00:00"
+            end = "12:00"
+            price = 0.2
+            "#,
+        );
+        assert!(uncovered.is_err());
+
+        let overlapping = Tariff::parse(
+            r#"
+            price = 0.3
+            [[periods]]
+            start = "08:00"
+            end = "12:00"
+            price = 0.2
+            [[periods]]
+            start = "11:00"
+            end = "13:00"
+            price = 0.4
+            "#,
+        );
+        assert!(overlapping.is_err());
+
+        assert!(Tariff::parse("price = -1.0").is_err());
+        assert!(Tariff::parse("currency = \"EUR\"").is_err());
+        assert!(Tariff::parse("price = 0.2\nunknown = 1").is_err());
+
+        assert_eq!(Tariff::from_arg("0.25")?, Tariff::flat(0.25)?);
+        assert!(Tariff::from_arg("/nonexistent/tariff.toml").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn daily_energy_integrates_samples_and_skips_gaps() -> Result<()> {
+        let tariff = Tariff::parse(
+            r#"
+            price = 0.30
+            [[periods]]
+            start = "00:00"
+            end = "01:00"
+            price = 0.10
+            "#,
+        )?;
+        let start: DateTime<Utc> = "2026-01-01T23:50:00Z".parse()?;
+        let minutes = |m: i64| start + Duration::minutes(m);
+
+        let samples = vec![
+            sample(minutes(0), 60.0),
+            // 10 minutes at 60 W on Jan 1 at 0.30
+            sample(minutes(10), 30.0),
+            // 10 minutes at 30 W on Jan 2 at 0.10
+            sample(minutes(20), 30.0),
+            // An hour without samples is not counted
+            sample(minutes(80), 30.0),
+        ];
+
+        let days = daily_energy(&samples, Some(&tariff), &Utc);
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0].date,
+            NaiveDate::from_ymd_opt(2026, 1, 1).context("date")?
+        );
+        assert!((days[0].kwh - 0.01).abs() < 1e-9);
+        assert!((days[0].cost.unwrap_or_default() - 0.003).abs() < 1e-9);
+        assert!((days[1].kwh - 0.005).abs() < 1e-9);
+        assert!((days[1].cost.unwrap_or_default() - 0.0005).abs() < 1e-9);
+        assert!((days[1].hours - 1.0 / 6.0).abs() < 1e-9);
+
+        assert!(daily_energy(&samples, None, &Utc)[0].cost.is_none());
+        Ok(())
+    }
+}
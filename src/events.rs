@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::Alert;
+use crate::api::{Device, DeviceStats, DeviceType};
+
+/// Version of the event schema, bumped on incompatible changes
+///
+/// Adding event types or optional fields is not an incompatible change;
+/// consumers should ignore events and fields they don't know.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// One change in the monitored fleet, as streamed by `monitor --stream` and
+/// the daemon's `/api/events` endpoint
+///
+/// Serialized as a flat JSON object, e.g.
+/// `{"version":1,"timestamp":"...","type":"device_offline","device":{...}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorEvent {
+    /// Schema version, see [`EVENT_SCHEMA_VERSION`]
+    pub version: u32,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl MonitorEvent {
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            timestamp: Utc::now(),
+            kind,
+        }
+    }
+}
+
+/// What happened, tagged by `type`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Fresh statistics polled from a device
+    Stats {
+        device: DeviceRef,
+        stats: DeviceStats,
+    },
+    /// A device started responding again
+    DeviceOnline { device: DeviceRef },
+    /// A device stopped responding
+    DeviceOffline { device: DeviceRef },
+    /// Background discovery found a device that was not monitored yet
+    DeviceDiscovered { device: Device },
+    /// A background discovery round finished
+    DiscoveryComplete { total: usize },
+    /// An alert fired or resolved
+    Alert { alert: Alert },
+}
+
+/// Identifies the device an event is about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceRef {
+    pub name: String,
+    pub ip_address: String,
+    pub device_type: DeviceType,
+}
+
+impl From<&Device> for DeviceRef {
+    fn from(device: &Device) -> Self {
+        Self {
+            name: device.name.clone(),
+            ip_address: device.ip_address.clone(),
+            device_type: device.device_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceStatus;
+    use anyhow::Result;
+
+    #[test]
+    fn events_serialize_as_flat_tagged_objects() -> Result<()> {
+        let device = Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Offline,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        };
+        let event = MonitorEvent::new(EventKind::DeviceOffline {
+            device: DeviceRef::from(&device),
+        });
+
+        let value = serde_json::to_value(&event)?;
+        assert_eq!(value["version"], EVENT_SCHEMA_VERSION);
+        assert_eq!(value["type"], "device_offline");
+        assert_eq!(value["device"]["ip_address"], "192.168.1.10");
+        assert_eq!(value["device"]["device_type"], "bitaxe_gamma");
+
+        let parsed: MonitorEvent = serde_json::from_value(value)?;
+        assert!(matches!(
+            parsed.kind,
+            EventKind::DeviceOffline { device: ref d } if *d == DeviceRef::from(&device)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,591 @@
+use anyhow::{Context, Result, bail, ensure};
+use serde::Serialize;
+
+use super::FirmwareSource;
+use crate::api::{DeviceType, SystemInfoResponse};
+
+/// First byte of every ESP32 app image
+const APP_IMAGE_MAGIC: u8 = 0xE9;
+/// Magic word of the `esp_app_desc_t` at the start of the first segment
+const APP_DESC_MAGIC: u32 = 0xABCD_5432;
+const IMAGE_HEADER_LEN: usize = 24;
+const SEGMENT_HEADER_LEN: usize = 8;
+const APP_DESC_LEN: usize = 256;
+const MAX_SEGMENTS: u8 = 16;
+const CHECKSUM_SEED: u8 = 0xEF;
+const SHA256_LEN: usize = 32;
+
+/// Chip ID of the ESP32-S3 used by all AxeOS boards
+const ESP32_S3_CHIP_ID: u16 = 9;
+
+/// Size of the OTA app partitions in the AxeOS partition table
+pub const MAX_APP_IMAGE_SIZE: usize = 0x40_0000;
+/// Size of the `www` SPIFFS partition in the AxeOS partition table
+pub const MAX_WWW_IMAGE_SIZE: usize = 0x30_0000;
+
+const SPIFFS_BLOCK_SIZE: usize = 4096;
+const SPIFFS_PAGE_SIZE: usize = 256;
+const SPIFFS_MAGIC_SEED: u32 = 0x2014_0529;
+
+/// ASIC driver names that show up as strings in firmware images
+const KNOWN_ASIC_MODELS: &[&str] = &["BM1366", "BM1368", "BM1370", "BM1397"];
+/// String only NerdQAxe firmware builds contain
+const NERDQAXE_MARKER: &[u8] = b"NerdQ";
+
+/// Which partition an OTA image is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageKind {
+    /// ESP32 application image (`esp-miner.bin`)
+    Firmware,
+    /// SPIFFS image with the web interface (`www.bin`)
+    Www,
+}
+
+/// Application description embedded in an app image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppDescription {
+    pub project_name: String,
+    pub version: String,
+    pub idf_version: String,
+    pub build_date: String,
+    pub build_time: String,
+    pub segments: u8,
+    /// ASIC models the firmware has drivers for
+    pub asic_models: Vec<&'static str>,
+    /// ASIC model a board specific build names in its project name
+    pub asic_model: Option<&'static str>,
+    /// Board version a board specific build names in its project name
+    pub board_version: Option<String>,
+    /// Whether the image is a NerdQAxe build rather than a Bitaxe one
+    pub nerdqaxe: bool,
+}
+
+/// Result of validating an OTA image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FirmwareImage {
+    pub kind: ImageKind,
+    pub size: usize,
+    /// Present for application images
+    pub app: Option<AppDescription>,
+}
+
+impl FirmwareImage {
+    /// Validate the structure of an image of the given kind
+    pub fn parse(data: &[u8], kind: ImageKind) -> Result<Self> {
+        let app = match kind {
+            ImageKind::Firmware => Some(parse_app_image(data)?),
+            ImageKind::Www => {
+                parse_www_image(data)?;
+                None
+            }
+        };
+
+        Ok(Self {
+            kind,
+            size: data.len(),
+            app,
+        })
+    }
+
+    /// Reasons the image should not be flashed onto the given device
+    pub fn compatibility_issues(
+        &self,
+        info: &SystemInfoResponse,
+        device_type: DeviceType,
+    ) -> Vec<String> {
+        let Some(ref app) = self.app else {
+            return Vec::new();
+        };
+
+        let mut issues = Vec::new();
+
+        if app.nerdqaxe && device_type.is_bitaxe() {
+            issues.push(format!(
+                "Image is a NerdQAxe build but the device is a {device}",
+                device = device_type.as_str()
+            ));
+        } else if !app.nerdqaxe && device_type.is_nerdqaxe() {
+            issues.push(format!(
+                "Image is a Bitaxe build but the device is a {device}",
+                device = device_type.as_str()
+            ));
+        }
+
+        if let Some(asic) = app.asic_model {
+            if !asic.eq_ignore_ascii_case(&info.asic_model) {
+                issues.push(format!(
+                    "Image is built for {asic} but the device uses {device_asic}",
+                    device_asic = info.asic_model
+                ));
+            }
+        } else if !app.asic_models.is_empty()
+            && !app
+                .asic_models
+                .iter()
+                .any(|model| model.eq_ignore_ascii_case(&info.asic_model))
+        {
+            issues.push(format!(
+                "Image supports {models} but the device uses {asic} (board {board})",
+                models = app.asic_models.join(", "),
+                asic = info.asic_model,
+                board = info.board_version
+            ));
+        }
+
+        // NerdQAxe firmware does not report a board version
+        if let Some(ref board) = app.board_version
+            && info.board_version != "unknown"
+            && *board != info.board_version
+        {
+            issues.push(format!(
+                "Image is built for board {board} but the device is board {device_board}",
+                device_board = info.board_version
+            ));
+        }
+
+        issues
+    }
+
+    /// Short human readable description
+    pub fn summary(&self) -> String {
+        let size = format!("{kib} KiB", kib = self.size / 1024);
+        match self.app {
+            Some(ref app) => format!(
+                "{project} {version} (ESP32-S3, {size}, built {date} {time})",
+                project = app.project_name,
+                version = app.version,
+                date = app.build_date,
+                time = app.build_time
+            ),
+            None => format!("SPIFFS web interface image ({size})"),
+        }
+    }
+}
+
+/// Read an image from a local file or download it from a URL
+pub async fn read_image(source: &FirmwareSource) -> Result<Vec<u8>> {
+    match source {
+        FirmwareSource::Local(path) => tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read image {path}", path = path.display())),
+        FirmwareSource::Url(url) => {
+            let response = reqwest::get(url)
+                .await
+                .with_context(|| format!("Failed to download image from {url}"))?
+                .error_for_status()
+                .with_context(|| format!("Failed to download image from {url}"))?;
+            let bytes = response
+                .bytes()
+                .await
+                .with_context(|| format!("Failed to download image from {url}"))?;
+            Ok(bytes.to_vec())
+        }
+    }
+}
+
+fn parse_app_image(data: &[u8]) -> Result<AppDescription> {
+    ensure!(
+        data.len() >= IMAGE_HEADER_LEN,
+        "Image is too small to be an ESP32 app image ({len} bytes)",
+        len = data.len()
+    );
+    ensure!(
+        data[0] == APP_IMAGE_MAGIC,
+        "Not an ESP32 app image (magic byte is {magic:#04x}, expected {APP_IMAGE_MAGIC:#04x})",
+        magic = data[0]
+    );
+    ensure!(
+        data.len() <= MAX_APP_IMAGE_SIZE,
+        "Image is {len} bytes, larger than the {MAX_APP_IMAGE_SIZE} byte app partition",
+        len = data.len()
+    );
+
+    let segments = data[1];
+    ensure!(
+        (1..=MAX_SEGMENTS).contains(&segments),
+        "Invalid segment count {segments}"
+    );
+
+    let chip_id = read_u16(data, 12)?;
+    ensure!(
+        chip_id == ESP32_S3_CHIP_ID,
+        "Image is built for {chip}, AxeOS devices use an ESP32-S3",
+        chip = chip_name(chip_id)
+    );
+    let hash_appended = data[23] == 1;
+
+    let mut offset = IMAGE_HEADER_LEN;
+    let mut checksum = CHECKSUM_SEED;
+    for index in 0..segments {
+        ensure!(
+            offset + SEGMENT_HEADER_LEN <= data.len(),
+            "Image is truncated in the header of segment {index}"
+        );
+        let length = read_u32(data, offset + 4)? as usize;
+        let start = offset + SEGMENT_HEADER_LEN;
+        let end = start
+            .checked_add(length)
+            .filter(|end| *end <= data.len())
+            .with_context(|| {
+                format!("Segment {index} ({length} bytes) runs past the end of the image")
+            })?;
+
+        checksum = data[start..end]
+            .iter()
+            .fold(checksum, |acc, byte| acc ^ byte);
+        offset = end;
+    }
+
+    // The checksum byte pads the image to a multiple of 16 bytes
+    let checksum_offset = offset | 0xF;
+    ensure!(
+        checksum_offset < data.len(),
+        "Image is truncated before its checksum"
+    );
+    ensure!(
+        data[checksum_offset] == checksum,
+        "Image checksum mismatch (stored {stored:#04x}, computed {checksum:#04x})",
+        stored = data[checksum_offset]
+    );
+    if hash_appended {
+        ensure!(
+            checksum_offset + 1 + SHA256_LEN <= data.len(),
+            "Image is truncated before its SHA-256 digest"
+        );
+    }
+
+    // The app description sits at the start of the first segment
+    let desc = IMAGE_HEADER_LEN + SEGMENT_HEADER_LEN;
+    ensure!(
+        desc + APP_DESC_LEN <= data.len() && read_u32(data, desc)? == APP_DESC_MAGIC,
+        "Image has no application description"
+    );
+
+    let nerdqaxe = contains(data, NERDQAXE_MARKER);
+    let project_name = read_c_string(&data[desc + 48..desc + 80]);
+    let (asic_model, board_version) = board_target(&project_name);
+
+    Ok(AppDescription {
+        nerdqaxe: nerdqaxe || project_name.to_ascii_lowercase().contains("nerd"),
+        project_name,
+        version: read_c_string(&data[desc + 16..desc + 48]),
+        build_time: read_c_string(&data[desc + 80..desc + 96]),
+        build_date: read_c_string(&data[desc + 96..desc + 112]),
+        idf_version: read_c_string(&data[desc + 112..desc + 144]),
+        segments,
+        asic_models: KNOWN_ASIC_MODELS
+            .iter()
+            .copied()
+            .filter(|model| contains(data, model.as_bytes()))
+            .collect(),
+        asic_model,
+        board_version,
+    })
+}
+
+/// ASIC model and board version named in a board specific project name such as
+/// `esp-miner-BM1370-601`; stock builds are just `esp-miner` and name neither
+fn board_target(project_name: &str) -> (Option<&'static str>, Option<String>) {
+    let tokens: Vec<&str> = project_name.split(['-', '_', ' ']).collect();
+    let asic_model = KNOWN_ASIC_MODELS
+        .iter()
+        .copied()
+        .find(|model| tokens.iter().any(|t| t.eq_ignore_ascii_case(model)));
+    let board_version = tokens
+        .iter()
+        .find(|t| t.len() == 3 && t.bytes().all(|b| b.is_ascii_digit()))
+        .map(|t| (*t).to_string());
+    (asic_model, board_version)
+}
+
+fn parse_www_image(data: &[u8]) -> Result<()> {
+    ensure!(
+        data.first() != Some(&APP_IMAGE_MAGIC),
+        "This is a firmware image, not a www.bin web interface image"
+    );
+    ensure!(
+        !data.is_empty() && data.len().is_multiple_of(SPIFFS_BLOCK_SIZE),
+        "Image size {len} is not a multiple of the {SPIFFS_BLOCK_SIZE} byte SPIFFS block size",
+        len = data.len()
+    );
+    ensure!(
+        data.len() <= MAX_WWW_IMAGE_SIZE,
+        "Image is {len} bytes, larger than the {MAX_WWW_IMAGE_SIZE} byte www partition",
+        len = data.len()
+    );
+
+    // Every formatted SPIFFS block ends its lookup page with a magic derived from the geometry
+    let block_count = (data.len() / SPIFFS_BLOCK_SIZE) as u32;
+    let stored = read_u16(data, SPIFFS_PAGE_SIZE - 2)?;
+    let with_length = (SPIFFS_MAGIC_SEED ^ SPIFFS_PAGE_SIZE as u32 ^ block_count) as u16;
+    let without_length = (SPIFFS_MAGIC_SEED ^ SPIFFS_PAGE_SIZE as u32) as u16;
+    if stored != with_length && stored != without_length {
+        bail!("Not a SPIFFS image (block magic {stored:#06x} does not match)");
+    }
+
+    Ok(())
+}
+
+fn chip_name(chip_id: u16) -> String {
+    match chip_id {
+        0 => "ESP32".to_string(),
+        2 => "ESP32-S2".to_string(),
+        5 => "ESP32-C3".to_string(),
+        9 => "ESP32-S3".to_string(),
+        12 => "ESP32-C2".to_string(),
+        13 => "ESP32-C6".to_string(),
+        16 => "ESP32-H2".to_string(),
+        _ => format!("an unknown chip (ID {chip_id})"),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .context("Unexpected end of image")?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("Unexpected end of image")?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// NUL terminated string from a fixed size field
+fn read_c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal app image the way esptool lays it out
+    fn app_image(project: &str, version: &str, chip_id: u16, extra: &[u8]) -> Vec<u8> {
+        let mut desc = vec![0u8; APP_DESC_LEN];
+        desc[0..4].copy_from_slice(&APP_DESC_MAGIC.to_le_bytes());
+        desc[16..16 + version.len()].copy_from_slice(version.as_bytes());
+        desc[48..48 + project.len()].copy_from_slice(project.as_bytes());
+        desc[80..88].copy_from_slice(b"12:00:00");
+        desc[96..107].copy_from_slice(b"Jan  1 2025");
+        desc[112..118].copy_from_slice(b"v5.3.1");
+
+        let mut code = extra.to_vec();
+        code.resize(code.len().next_multiple_of(4).max(4), 0);
+        let segments: [(u32, &[u8]); 2] = [(0x3C00_0020, &desc), (0x4037_0000, &code)];
+
+        let mut image = vec![0u8; IMAGE_HEADER_LEN];
+        image[0] = APP_IMAGE_MAGIC;
+        image[1] = segments.len() as u8;
+        image[12..14].copy_from_slice(&chip_id.to_le_bytes());
+
+        let mut checksum = CHECKSUM_SEED;
+        for (addr, data) in segments {
+            image.extend_from_slice(&addr.to_le_bytes());
+            image.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            image.extend_from_slice(data);
+            checksum = data.iter().fold(checksum, |acc, byte| acc ^ byte);
+        }
+        image.resize(image.len() | 0xF, 0);
+        image.push(checksum);
+        image
+    }
+
+    /// Build an empty SPIFFS image with formatted blocks
+    fn www_image(blocks: usize) -> Vec<u8> {
+        let mut image = vec![0xFFu8; blocks * SPIFFS_BLOCK_SIZE];
+        for bix in 0..blocks {
+            let magic =
+                (SPIFFS_MAGIC_SEED ^ SPIFFS_PAGE_SIZE as u32 ^ (blocks - bix) as u32) as u16;
+            let offset = bix * SPIFFS_BLOCK_SIZE + SPIFFS_PAGE_SIZE - 2;
+            image[offset..offset + 2].copy_from_slice(&magic.to_le_bytes());
+        }
+        image
+    }
+
+    fn info(asic_model: &str) -> SystemInfoResponse {
+        SystemInfoResponse {
+            asic_model: asic_model.to_string(),
+            board_version: "601".to_string(),
+            device_model: None,
+            firmware_version: "2.4.0".to_string(),
+            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            hostname: "bitaxe-1".to_string(),
+            wifi_ssid: None,
+            wifi_status: None,
+            wifi_rssi: None,
+            pool_url: "public-pool.io".to_string(),
+            pool_port: 21496,
+            pool_user: "bc1qexample".to_string(),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
+            frequency: 525,
+            voltage: 1150.0,
+            fanspeed: 80,
+            auto_fan_speed: None,
+            temp: 55.0,
+            power: 15.0,
+            running_time: 3600,
+        }
+    }
+
+    #[test]
+    fn test_parse_app_image() -> Result<()> {
+        let data = app_image("esp-miner", "v2.5.0", ESP32_S3_CHIP_ID, b"BM1366BM1370");
+        let image = FirmwareImage::parse(&data, ImageKind::Firmware)?;
+        let app = image.app.context("app description")?;
+
+        assert_eq!(app.project_name, "esp-miner");
+        assert_eq!(app.version, "v2.5.0");
+        assert_eq!(app.idf_version, "v5.3.1");
+        assert_eq!(app.build_date, "Jan  1 2025");
+        assert_eq!(app.segments, 2);
+        assert_eq!(app.asic_models, vec!["BM1366", "BM1370"]);
+        assert_eq!(app.asic_model, None);
+        assert_eq!(app.board_version, None);
+        assert!(!app.nerdqaxe);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_malformed_app_images() {
+        let valid = app_image("esp-miner", "v2.5.0", ESP32_S3_CHIP_ID, b"");
+
+        let mut bad_magic = valid.clone();
+        bad_magic[0] = 0x00;
+        assert!(FirmwareImage::parse(&bad_magic, ImageKind::Firmware).is_err());
+
+        let mut bad_checksum = valid.clone();
+        bad_checksum[IMAGE_HEADER_LEN + SEGMENT_HEADER_LEN + 20] ^= 0xFF;
+        assert!(FirmwareImage::parse(&bad_checksum, ImageKind::Firmware).is_err());
+
+        let truncated = &valid[..valid.len() - 40];
+        assert!(FirmwareImage::parse(truncated, ImageKind::Firmware).is_err());
+
+        let wrong_chip = app_image("esp-miner", "v2.5.0", 0, b"");
+        let error = FirmwareImage::parse(&wrong_chip, ImageKind::Firmware)
+            .err()
+            .map(|e| e.to_string());
+        assert!(error.is_some_and(|e| e.contains("built for ESP32,")));
+
+        let mut oversized = valid;
+        oversized.resize(MAX_APP_IMAGE_SIZE + 1, 0xFF);
+        assert!(FirmwareImage::parse(&oversized, ImageKind::Firmware).is_err());
+    }
+
+    #[test]
+    fn test_parse_www_image() -> Result<()> {
+        let image = FirmwareImage::parse(&www_image(16), ImageKind::Www)?;
+        assert_eq!(image.size, 16 * SPIFFS_BLOCK_SIZE);
+        assert!(image.app.is_none());
+
+        // An app image passed as www.bin and random data are both refused
+        let app = app_image("esp-miner", "v2.5.0", ESP32_S3_CHIP_ID, b"");
+        assert!(FirmwareImage::parse(&app, ImageKind::Www).is_err());
+        assert!(FirmwareImage::parse(&vec![0u8; SPIFFS_BLOCK_SIZE], ImageKind::Www).is_err());
+        assert!(FirmwareImage::parse(&www_image(16)[..5000], ImageKind::Www).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compatibility_issues() -> Result<()> {
+        let bitaxe = FirmwareImage::parse(
+            &app_image(
+                "esp-miner",
+                "v2.5.0",
+                ESP32_S3_CHIP_ID,
+                b"BM1366BM1368BM1370",
+            ),
+            ImageKind::Firmware,
+        )?;
+        assert!(
+            bitaxe
+                .compatibility_issues(&info("BM1370"), DeviceType::BitaxeGamma)
+                .is_empty()
+        );
+        assert_eq!(
+            bitaxe
+                .compatibility_issues(&info("BM1397"), DeviceType::BitaxeMax)
+                .len(),
+            1
+        );
+        assert_eq!(
+            bitaxe
+                .compatibility_issues(&info("BM1368"), DeviceType::NerdqaxePlus)
+                .len(),
+            1
+        );
+
+        let nerdqaxe = FirmwareImage::parse(
+            &app_image(
+                "esp-miner",
+                "v1.0.30",
+                ESP32_S3_CHIP_ID,
+                b"NerdQAxe++ BM1368",
+            ),
+            ImageKind::Firmware,
+        )?;
+        let issues = nerdqaxe.compatibility_issues(&info("BM1370"), DeviceType::BitaxeGamma);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("NerdQAxe build"));
+        assert!(
+            nerdqaxe
+                .compatibility_issues(&info("BM1368"), DeviceType::NerdqaxePlus)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_specific_image_must_match_device() -> Result<()> {
+        // An Ultra build still carries every ASIC driver, only its name tells the target apart
+        let ultra = FirmwareImage::parse(
+            &app_image(
+                "esp-miner-BM1366-204",
+                "v2.5.0",
+                ESP32_S3_CHIP_ID,
+                b"BM1366BM1368BM1370",
+            ),
+            ImageKind::Firmware,
+        )?;
+        let app = ultra.app.as_ref().context("app description")?;
+        assert_eq!(app.asic_model, Some("BM1366"));
+        assert_eq!(app.board_version.as_deref(), Some("204"));
+
+        let gamma = info("BM1370");
+        let issues = ultra.compatibility_issues(&gamma, DeviceType::BitaxeGamma);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("built for BM1366"));
+        assert!(issues[1].contains("built for board 204 but the device is board 601"));
+
+        let mut ultra_device = info("BM1366");
+        ultra_device.board_version = "204".to_string();
+        assert!(
+            ultra
+                .compatibility_issues(&ultra_device, DeviceType::BitaxeUltra)
+                .is_empty()
+        );
+
+        // Same ASIC on another board revision is still refused
+        ultra_device.board_version = "205".to_string();
+        assert_eq!(
+            ultra
+                .compatibility_issues(&ultra_device, DeviceType::BitaxeUltra)
+                .len(),
+            1
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod image;
+pub mod server;
+
+pub use image::*;
+pub use server::*;
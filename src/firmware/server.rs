@@ -0,0 +1,315 @@
+use anyhow::{Context, Result, bail};
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::net::{TcpListener, UdpSocket, lookup_host};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Size of the chunks the firmware image is streamed in
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// How long a device gets to download a served image after the OTA request
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Where a firmware image comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirmwareSource {
+    /// Remote URL the device downloads from directly
+    Url(String),
+    /// Local file that has to be served to the device
+    Local(PathBuf),
+}
+
+impl FirmwareSource {
+    /// Interpret a command line argument as a URL or a local file
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.contains("://") {
+            return Ok(Self::Url(source.to_string()));
+        }
+
+        let path = PathBuf::from(source);
+        if !path.is_file() {
+            bail!("Firmware file not found: {source}");
+        }
+        Ok(Self::Local(path))
+    }
+}
+
+/// Download progress of the served image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// Size of the image in bytes
+    pub total_bytes: u64,
+    /// Bytes sent in the most recent download
+    pub sent_bytes: u64,
+    /// Number of downloads that transferred the whole image
+    pub completed: usize,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    image: Bytes,
+    progress: watch::Sender<DownloadProgress>,
+}
+
+/// Temporary HTTP server that serves one firmware file to devices on the LAN
+pub struct FirmwareServer {
+    url: String,
+    progress: watch::Receiver<DownloadProgress>,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<std::io::Result<()>>,
+}
+
+impl FirmwareServer {
+    /// Serve `path` on the local address that routes to `device_ip`
+    pub async fn start(path: &Path, device_ip: &str) -> Result<Self> {
+        let bind_ip = lan_address_for(device_ip).await?;
+        Self::start_on(path, bind_ip).await
+    }
+
+    /// Serve `path` on an ephemeral port of the given address
+    pub async fn start_on(path: &Path, bind_ip: IpAddr) -> Result<Self> {
+        let image = tokio::fs::read(path).await.with_context(|| {
+            format!("Failed to read firmware file {path}", path = path.display())
+        })?;
+        if image.is_empty() {
+            bail!("Firmware file {path} is empty", path = path.display());
+        }
+
+        let listener = TcpListener::bind(SocketAddr::new(bind_ip, 0))
+            .await
+            .with_context(|| format!("Failed to listen on {bind_ip}"))?;
+        let local_addr = listener
+            .local_addr()
+            .context("Failed to get listener address")?;
+
+        let file_name = served_file_name(path);
+        let url = format!("http://{local_addr}/{file_name}");
+
+        let (progress_tx, progress) = watch::channel(DownloadProgress {
+            total_bytes: image.len() as u64,
+            ..Default::default()
+        });
+        let router = Router::new()
+            .route(&format!("/{file_name}"), get(serve_image))
+            .with_state(ServerState {
+                image: Bytes::from(image),
+                progress: progress_tx,
+            });
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+        });
+
+        tracing::debug!("Serving {path} at {url}", path = path.display());
+
+        Ok(Self {
+            url,
+            progress,
+            shutdown,
+            task,
+        })
+    }
+
+    /// URL devices should download the firmware from
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn progress(&self) -> DownloadProgress {
+        *self.progress.borrow()
+    }
+
+    /// Wait until the image has been downloaded completely `count` times in total
+    pub async fn wait_for_downloads(&self, count: usize, timeout: Duration) -> Result<()> {
+        let mut progress = self.progress.clone();
+        let wait = progress.wait_for(|progress| progress.completed >= count);
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(result) => {
+                result.context("Firmware server stopped")?;
+                Ok(())
+            }
+            Err(_) => bail!(
+                "Timed out after {secs}s waiting for firmware downloads ({completed}/{count} complete)",
+                secs = timeout.as_secs(),
+                completed = self.progress().completed
+            ),
+        }
+    }
+
+    /// Stop serving and wait for open connections to finish
+    pub async fn shutdown(self) -> Result<()> {
+        let _ = self.shutdown.send(());
+        self.task
+            .await
+            .context("Firmware server task failed")?
+            .context("Firmware server failed")
+    }
+}
+
+async fn serve_image(State(state): State<ServerState>) -> impl IntoResponse {
+    let total = state.image.len();
+    state
+        .progress
+        .send_modify(|progress| progress.sent_bytes = 0);
+
+    // Stream the image in chunks so progress and completion can be tracked
+    let stream = futures::stream::unfold(0, move |offset| {
+        let state = state.clone();
+        async move {
+            if offset >= total {
+                return None;
+            }
+
+            let end = (offset + CHUNK_SIZE).min(total);
+            state.progress.send_modify(|progress| {
+                progress.sent_bytes = end as u64;
+                if end == total {
+                    progress.completed += 1;
+                }
+            });
+            Some((Ok::<_, std::io::Error>(state.image.slice(offset..end)), end))
+        }
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_LENGTH, total.to_string()),
+        ],
+        Body::from_stream(stream),
+    )
+}
+
+/// Local address of the interface that routes to the device
+pub async fn lan_address_for(device_ip: &str) -> Result<IpAddr> {
+    let target = match device_ip.parse::<SocketAddr>() {
+        Ok(addr) => addr,
+        Err(_) => match device_ip.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, 80),
+            Err(_) => lookup_host((device_ip, 80))
+                .await
+                .with_context(|| format!("Failed to resolve {device_ip}"))?
+                .next()
+                .with_context(|| format!("No address found for {device_ip}"))?,
+        },
+    };
+
+    let bind_addr: SocketAddr = if target.is_ipv4() {
+        (std::net::Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+
+    // Connecting a UDP socket sends nothing but selects the outgoing interface
+    let socket = UdpSocket::bind(bind_addr)
+        .await
+        .context("Failed to open UDP socket")?;
+    socket
+        .connect(target)
+        .await
+        .with_context(|| format!("No route to {device_ip}"))?;
+    let local = socket.local_addr().context("Failed to get local address")?;
+
+    Ok(local.ip())
+}
+
+/// File name used in the URL, limited to characters that need no escaping
+fn served_file_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let safe = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if safe {
+        name
+    } else {
+        "firmware.bin".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_source() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("esp-miner.bin");
+        std::fs::write(&path, b"image")?;
+
+        assert_eq!(
+            FirmwareSource::parse("http://example.com/esp-miner.bin")?,
+            FirmwareSource::Url("http://example.com/esp-miner.bin".to_string())
+        );
+        assert_eq!(
+            FirmwareSource::parse(&path.to_string_lossy())?,
+            FirmwareSource::Local(path)
+        );
+        assert!(FirmwareSource::parse("missing/esp-miner.bin").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_served_file_name() {
+        assert_eq!(served_file_name(Path::new("/tmp/www.bin")), "www.bin");
+        assert_eq!(
+            served_file_name(Path::new("/tmp/my firmware.bin")),
+            "firmware.bin"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lan_address_for_loopback() -> Result<()> {
+        let ip = lan_address_for("127.0.0.1:8080").await?;
+        assert!(ip.is_loopback());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serves_image_and_tracks_downloads() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("esp-miner.bin");
+        let image: Vec<u8> = (0..40_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &image)?;
+
+        let server = FirmwareServer::start_on(&path, IpAddr::V4(Ipv4Addr::LOCALHOST)).await?;
+        assert!(server.url().ends_with("/esp-miner.bin"));
+
+        let downloaded = reqwest::get(server.url()).await?.bytes().await?;
+        assert_eq!(downloaded.as_ref(), image.as_slice());
+
+        server.wait_for_downloads(1, Duration::from_secs(5)).await?;
+        let progress = server.progress();
+        assert_eq!(progress.completed, 1);
+        assert_eq!(progress.sent_bytes, image.len() as u64);
+        assert_eq!(progress.total_bytes, image.len() as u64);
+
+        assert!(
+            server
+                .wait_for_downloads(2, Duration::from_millis(50))
+                .await
+                .is_err()
+        );
+
+        server.shutdown().await
+    }
+}
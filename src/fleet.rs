@@ -0,0 +1,460 @@
+use crate::api::{Device, DeviceFilter, SystemInfoResponse, SystemUpdateRequest};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Placeholder in `pool_user` replaced by the device hostname
+pub const HOSTNAME_PLACEHOLDER: &str = "{hostname}";
+
+/// Voltage differences below this (in mV) are not considered drift
+const VOLTAGE_TOLERANCE: f64 = 0.5;
+
+/// Desired device settings; unset fields are left untouched
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FleetSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_port: Option<u16>,
+    /// Pool user, `{hostname}` is replaced by the device hostname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_user: Option<String>,
+    /// ASIC frequency in MHz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    /// Core voltage in mV
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voltage: Option<f64>,
+    /// Fan speed percentage (0-100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fan_speed: Option<u32>,
+}
+
+impl FleetSettings {
+    /// Overlay `other` on top of these settings
+    fn merge(&mut self, other: &FleetSettings) {
+        if other.pool_url.is_some() {
+            self.pool_url.clone_from(&other.pool_url);
+        }
+        if other.pool_port.is_some() {
+            self.pool_port = other.pool_port;
+        }
+        if other.pool_user.is_some() {
+            self.pool_user.clone_from(&other.pool_user);
+        }
+        if other.frequency.is_some() {
+            self.frequency = other.frequency;
+        }
+        if other.voltage.is_some() {
+            self.voltage = other.voltage;
+        }
+        if other.fan_speed.is_some() {
+            self.fan_speed = other.fan_speed;
+        }
+    }
+
+    /// Whether no setting is declared
+    pub fn is_empty(&self) -> bool {
+        *self == FleetSettings::default()
+    }
+}
+
+/// A named set of devices sharing settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "toml::Table")]
+pub struct FleetGroup {
+    /// Device hostnames or IP addresses
+    #[serde(default)]
+    pub devices: Vec<String>,
+    #[serde(flatten)]
+    pub settings: FleetSettings,
+}
+
+// Flattened settings can't deny unknown fields, so split the table by hand
+impl TryFrom<toml::Table> for FleetGroup {
+    type Error = toml::de::Error;
+
+    fn try_from(mut table: toml::Table) -> Result<Self, Self::Error> {
+        let devices = match table.remove("devices") {
+            Some(devices) => devices.try_into()?,
+            None => Vec::new(),
+        };
+        let settings = toml::Value::Table(table).try_into()?;
+        Ok(Self { devices, settings })
+    }
+}
+
+/// Declarative fleet file (TOML).
+///
+/// Settings are declared as fleet-wide `[defaults]`, per device type or type
+/// group (`[types.bitaxe-gamma]`, `[types.bitaxe]`), per named group of
+/// devices (`[groups.garage]` with a `devices` list) and per device
+/// (`[devices.bitaxe-1]`), with later levels overriding earlier ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FleetConfig {
+    #[serde(default)]
+    pub defaults: FleetSettings,
+    /// Settings keyed by device type filter (e.g. `bitaxe-gamma`, `bitaxe`, `nerdqaxe`)
+    #[serde(default)]
+    pub types: BTreeMap<String, FleetSettings>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, FleetGroup>,
+    /// Settings keyed by device hostname or IP address
+    #[serde(default)]
+    pub devices: BTreeMap<String, FleetSettings>,
+}
+
+impl FleetConfig {
+    /// Load and validate a fleet file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fleet file {path}", path = path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid fleet file {path}", path = path.display()))
+    }
+
+    /// Parse and validate fleet file contents
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: FleetConfig = toml::from_str(content).context("Failed to parse TOML")?;
+
+        for key in config.types.keys() {
+            if let Err(e) = DeviceFilter::from_str(key) {
+                bail!("Unknown device type '{key}' in [types]: {e}");
+            }
+        }
+
+        let all_settings = std::iter::once(&config.defaults)
+            .chain(config.types.values())
+            .chain(config.groups.values().map(|g| &g.settings))
+            .chain(config.devices.values());
+        for settings in all_settings {
+            if let Some(fan_speed) = settings.fan_speed
+                && fan_speed > 100
+            {
+                bail!("Fan speed must be between 0 and 100 percent, got {fan_speed}");
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Resolve the desired settings of a device.
+    ///
+    /// Precedence from lowest to highest: defaults, type groups (`all`, then
+    /// `bitaxe`/`nerdqaxe`, then specific types), named groups in name order,
+    /// and finally the device's own section.
+    pub fn resolve(&self, device: &Device) -> FleetSettings {
+        let mut settings = self.defaults.clone();
+
+        let mut type_sections: Vec<(DeviceFilter, &FleetSettings)> = self
+            .types
+            .iter()
+            .filter_map(|(key, settings)| {
+                let filter = DeviceFilter::from_str(key).ok()?;
+                filter
+                    .matches(device.device_type)
+                    .then_some((filter, settings))
+            })
+            .collect();
+        type_sections.sort_by_key(|(filter, _)| filter_specificity(*filter));
+        for (_, type_settings) in type_sections {
+            settings.merge(type_settings);
+        }
+
+        for group in self.groups.values() {
+            if group.devices.iter().any(|id| matches_device(id, device)) {
+                settings.merge(&group.settings);
+            }
+        }
+
+        for (id, device_settings) in &self.devices {
+            if matches_device(id, device) {
+                settings.merge(device_settings);
+            }
+        }
+
+        settings
+    }
+
+    /// Device identifiers in `[devices]` and groups that match none of `devices`
+    pub fn unmatched_identifiers(&self, devices: &[Device]) -> Vec<String> {
+        let group_members = self.groups.values().flat_map(|g| g.devices.iter());
+        let mut unmatched: Vec<String> = self
+            .devices
+            .keys()
+            .chain(group_members)
+            .filter(|id| !devices.iter().any(|d| matches_device(id, d)))
+            .cloned()
+            .collect();
+        unmatched.sort();
+        unmatched.dedup();
+        unmatched
+    }
+}
+
+/// A single setting that differs from the fleet file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingChange {
+    pub field: &'static str,
+    pub current: String,
+    pub desired: String,
+}
+
+/// Changes needed to bring a device in line with its desired settings
+#[derive(Debug, Clone, Default)]
+pub struct DevicePlan {
+    pub changes: Vec<SettingChange>,
+    /// Update request containing only the differing fields
+    pub request: SystemUpdateRequest,
+}
+
+impl DevicePlan {
+    /// Whether the device already matches the fleet file
+    pub fn is_in_sync(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Compare desired settings with a device's live configuration
+pub fn plan_changes(desired: &FleetSettings, info: &SystemInfoResponse) -> DevicePlan {
+    let mut plan = DevicePlan::default();
+
+    if let Some(ref pool_url) = desired.pool_url
+        && *pool_url != info.pool_url
+    {
+        plan.changes.push(SettingChange {
+            field: "pool_url",
+            current: info.pool_url.clone(),
+            desired: pool_url.clone(),
+        });
+        plan.request.pool_url = Some(pool_url.clone());
+    }
+
+    if let Some(pool_port) = desired.pool_port
+        && pool_port != info.pool_port
+    {
+        plan.changes.push(SettingChange {
+            field: "pool_port",
+            current: info.pool_port.to_string(),
+            desired: pool_port.to_string(),
+        });
+        plan.request.pool_port = Some(pool_port);
+    }
+
+    if let Some(ref template) = desired.pool_user {
+        let pool_user = template.replace(HOSTNAME_PLACEHOLDER, &info.hostname);
+        if pool_user != info.pool_user {
+            plan.changes.push(SettingChange {
+                field: "pool_user",
+                current: info.pool_user.clone(),
+                desired: pool_user.clone(),
+            });
+            plan.request.pool_user = Some(pool_user);
+        }
+    }
+
+    if let Some(frequency) = desired.frequency
+        && frequency != info.frequency
+    {
+        plan.changes.push(SettingChange {
+            field: "frequency",
+            current: info.frequency.to_string(),
+            desired: frequency.to_string(),
+        });
+        plan.request.frequency_value = Some(frequency);
+    }
+
+    if let Some(voltage) = desired.voltage
+        && (voltage - info.voltage).abs() > VOLTAGE_TOLERANCE
+    {
+        plan.changes.push(SettingChange {
+            field: "voltage",
+            current: info.voltage.to_string(),
+            desired: voltage.to_string(),
+        });
+        plan.request.voltage_value = Some(voltage);
+    }
+
+    if let Some(fan_speed) = desired.fan_speed
+        && fan_speed != info.fanspeed
+    {
+        plan.changes.push(SettingChange {
+            field: "fan_speed",
+            current: info.fanspeed.to_string(),
+            desired: fan_speed.to_string(),
+        });
+        plan.request.fan_speed = Some(fan_speed);
+    }
+
+    plan
+}
+
+/// Whether a fleet file identifier refers to the device (hostname or IP, case-insensitive)
+fn matches_device(identifier: &str, device: &Device) -> bool {
+    identifier.eq_ignore_ascii_case(&device.name) || identifier == device.ip_address
+}
+
+/// Order type filters from least to most specific
+fn filter_specificity(filter: DeviceFilter) -> u8 {
+    match filter {
+        DeviceFilter::All => 0,
+        DeviceFilter::AnyBitaxe | DeviceFilter::AnyNerdQaxe => 1,
+        DeviceFilter::Specific(_) => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStatus, DeviceType};
+    use chrono::Utc;
+
+    const FLEET: &str = r#"
+        [defaults]
+        pool_url = "public-pool.io"
+        pool_port = 21496
+        pool_user = "bc1qexample.{hostname}"
+        fan_speed = 80
+
+        [types.bitaxe]
+        frequency = 500
+
+        [types.bitaxe-gamma]
+        frequency = 525
+        voltage = 1150
+
+        [groups.garage]
+        devices = ["bitaxe-2", "192.168.1.30"]
+        fan_speed = 100
+
+        [devices.bitaxe-2]
+        frequency = 575
+    "#;
+
+    fn device(name: &str, ip: &str, device_type: DeviceType) -> Device {
+        Device {
+            name: name.to_string(),
+            ip_address: ip.to_string(),
+            device_type,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn info(hostname: &str) -> SystemInfoResponse {
+        SystemInfoResponse {
+            asic_model: "BM1370".to_string(),
+            board_version: "601".to_string(),
+            device_model: None,
+            firmware_version: "2.4.0".to_string(),
+            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            hostname: hostname.to_string(),
+            wifi_ssid: None,
+            wifi_status: None,
+            wifi_rssi: None,
+            pool_url: "public-pool.io".to_string(),
+            pool_port: 21496,
+            pool_user: format!("bc1qexample.{hostname}"),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
+            frequency: 525,
+            voltage: 1150.0,
+            fanspeed: 80,
+            auto_fan_speed: Some(false),
+            temp: 55.0,
+            power: 15.0,
+            running_time: 3600,
+        }
+    }
+
+    #[test]
+    fn test_resolve_precedence() -> Result<()> {
+        let config = FleetConfig::parse(FLEET)?;
+
+        let gamma = config.resolve(&device("bitaxe-1", "192.168.1.10", DeviceType::BitaxeGamma));
+        assert_eq!(gamma.frequency, Some(525));
+        assert_eq!(gamma.voltage, Some(1150.0));
+        assert_eq!(gamma.fan_speed, Some(80));
+
+        let ultra = config.resolve(&device("bitaxe-3", "192.168.1.12", DeviceType::BitaxeUltra));
+        assert_eq!(ultra.frequency, Some(500));
+        assert_eq!(ultra.voltage, None);
+
+        // Group overrides defaults, device section overrides type and group
+        let grouped = config.resolve(&device("Bitaxe-2", "192.168.1.11", DeviceType::BitaxeGamma));
+        assert_eq!(grouped.fan_speed, Some(100));
+        assert_eq!(grouped.frequency, Some(575));
+
+        let by_ip = config.resolve(&device("nerd", "192.168.1.30", DeviceType::NerdqaxePlus));
+        assert_eq!(by_ip.fan_speed, Some(100));
+        assert_eq!(by_ip.frequency, None);
+        assert_eq!(by_ip.pool_url.as_deref(), Some("public-pool.io"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_only_includes_differences() -> Result<()> {
+        let config = FleetConfig::parse(FLEET)?;
+        let desired = config.resolve(&device("bitaxe-2", "192.168.1.11", DeviceType::BitaxeGamma));
+
+        let plan = plan_changes(&desired, &info("bitaxe-2"));
+        let fields: Vec<_> = plan.changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, vec!["frequency", "fan_speed"]);
+        assert_eq!(plan.request.frequency_value, Some(575));
+        assert_eq!(plan.request.fan_speed, Some(100));
+        assert!(plan.request.pool_url.is_none());
+        assert!(plan.request.pool_user.is_none());
+        assert!(plan.request.voltage_value.is_none());
+
+        let in_sync = config.resolve(&device("bitaxe-1", "192.168.1.10", DeviceType::BitaxeGamma));
+        assert!(plan_changes(&in_sync, &info("bitaxe-1")).is_in_sync());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_user_template() -> Result<()> {
+        let desired = FleetSettings {
+            pool_user: Some("bc1qnew.{hostname}".to_string()),
+            ..Default::default()
+        };
+
+        let plan = plan_changes(&desired, &info("bitaxe-7"));
+        assert_eq!(
+            plan.changes,
+            vec![SettingChange {
+                field: "pool_user",
+                current: "bc1qexample.bitaxe-7".to_string(),
+                desired: "bc1qnew.bitaxe-7".to_string(),
+            }]
+        );
+        assert_eq!(plan.request.pool_user.as_deref(), Some("bc1qnew.bitaxe-7"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_files() {
+        assert!(FleetConfig::parse("[types.antminer]\nfrequency = 500\n").is_err());
+        assert!(FleetConfig::parse("[defaults]\nfrequncy = 500\n").is_err());
+        assert!(FleetConfig::parse("[groups.a]\ndevices = []\nfan = 5\n").is_err());
+        assert!(FleetConfig::parse("[defaults]\nfan_speed = 150\n").is_err());
+        assert!(FleetConfig::parse("[pools]\nurl = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_unmatched_identifiers() -> Result<()> {
+        let config = FleetConfig::parse(FLEET)?;
+        let devices = vec![device("bitaxe-2", "192.168.1.11", DeviceType::BitaxeGamma)];
+
+        assert_eq!(config.unmatched_identifiers(&devices), vec!["192.168.1.30"]);
+        Ok(())
+    }
+}
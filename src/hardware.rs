@@ -0,0 +1,380 @@
+use crate::api::{AsicResponse, DeviceType, SystemInfoResponse, expected_hashrate_ghs};
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Profiles shipped with axectl
+const BUILTIN_PROFILES: &str = include_str!("hardware.toml");
+
+/// What a product built around an ASIC model can safely do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HardwareProfile {
+    pub asic_model: String,
+    /// Board versions this profile is for, empty for any board with this ASIC
+    #[serde(default)]
+    pub boards: Vec<String>,
+    /// Device type of matching devices; a NerdQAxe type makes the profile apply
+    /// to NerdQAxe firmware only, any other profile to Bitaxe firmware only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<DeviceType>,
+    pub product: String,
+    pub asic_count: u32,
+    pub small_core_count: u32,
+    /// Stock ASIC frequency in MHz
+    pub stock_frequency: u32,
+    /// Stock core voltage in mV
+    pub stock_voltage: f64,
+    pub min_frequency: u32,
+    pub max_frequency: u32,
+    pub min_voltage: f64,
+    pub max_voltage: f64,
+    /// Highest safe ASIC temperature in °C
+    pub max_temperature: f64,
+    /// Hashrate at stock frequency in GH/s
+    pub nominal_hashrate_ghs: f64,
+}
+
+impl HardwareProfile {
+    /// Whether the profile applies to a device with this ASIC model and board
+    ///
+    /// Multi-ASIC NerdQAxe boards share chips with single-ASIC Bitaxes, so
+    /// profiles never match across the two firmware families.
+    fn matches(&self, info: &SystemInfoResponse, board_version: Option<&str>) -> bool {
+        let nerdqaxe = self.device_type.is_some_and(|t| t.is_nerdqaxe());
+        nerdqaxe == info.is_nerdqaxe()
+            && self.asic_model.eq_ignore_ascii_case(&info.asic_model)
+            && match board_version {
+                Some(board) => self.boards.iter().any(|b| b == board),
+                None => self.boards.is_empty(),
+            }
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.asic_count > 0 && self.small_core_count > 0,
+            "ASIC and small core counts must be positive"
+        );
+        ensure!(
+            (self.min_frequency..=self.max_frequency).contains(&self.stock_frequency),
+            "Stock frequency {stock} MHz is outside {min}-{max} MHz",
+            stock = self.stock_frequency,
+            min = self.min_frequency,
+            max = self.max_frequency
+        );
+        ensure!(
+            (self.min_voltage..=self.max_voltage).contains(&self.stock_voltage),
+            "Stock voltage {stock} mV is outside {min}-{max} mV",
+            stock = self.stock_voltage,
+            min = self.min_voltage,
+            max = self.max_voltage
+        );
+        ensure!(
+            self.max_temperature > 0.0 && self.nominal_hashrate_ghs > 0.0,
+            "Max temperature and nominal hashrate must be positive"
+        );
+        Ok(())
+    }
+}
+
+/// Hardware profiles, user profiles first so they override the built-in ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HardwareDb {
+    #[serde(default)]
+    pub profiles: Vec<HardwareProfile>,
+}
+
+impl HardwareDb {
+    /// The profiles shipped with axectl
+    pub fn builtin() -> Result<Self> {
+        Self::parse(BUILTIN_PROFILES).context("Invalid built-in hardware profiles")
+    }
+
+    /// Parse and validate hardware profiles
+    pub fn parse(content: &str) -> Result<Self> {
+        let db: HardwareDb = toml::from_str(content).context("Failed to parse TOML")?;
+        for profile in &db.profiles {
+            profile.validate().with_context(|| {
+                format!(
+                    "Invalid profile '{product}' ({model})",
+                    product = profile.product,
+                    model = profile.asic_model
+                )
+            })?;
+        }
+        Ok(db)
+    }
+
+    /// Built-in profiles overridden by the ones in a hardware file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| {
+            format!("Failed to read hardware file {path}", path = path.display())
+        })?;
+        let mut db = Self::parse(&content)
+            .with_context(|| format!("Invalid hardware file {path}", path = path.display()))?;
+        db.profiles.extend(Self::builtin()?.profiles);
+        Ok(db)
+    }
+
+    /// Built-in profiles overridden by the user's hardware file, if there is one
+    pub fn load_user() -> Result<Self> {
+        match Self::user_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Self::builtin(),
+        }
+    }
+
+    /// The user's profiles loaded once per process, for lookups on every poll
+    ///
+    /// An invalid hardware file is logged and the built-in profiles are used instead.
+    pub fn shared() -> Option<&'static Self> {
+        static SHARED: OnceLock<Option<HardwareDb>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| {
+                Self::load_user()
+                    .or_else(|e| {
+                        tracing::warn!("Using built-in hardware profiles: {e:#}");
+                        Self::builtin()
+                    })
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Where the user's hardware file lives (~/.config/axectl/hardware.toml on Linux)
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("axectl").join("hardware.toml"))
+    }
+
+    /// Profile for a device, preferring one made for its board version
+    pub fn lookup(&self, info: &SystemInfoResponse) -> Option<&HardwareProfile> {
+        self.profiles
+            .iter()
+            .find(|p| p.matches(info, Some(&info.board_version)))
+            .or_else(|| self.profiles.iter().find(|p| p.matches(info, None)))
+    }
+
+    /// Theoretical hashrate of a device in GH/s at its current frequency
+    ///
+    /// Core and ASIC counts come from the device's ASIC info when the firmware
+    /// reports them, otherwise from its hardware profile.
+    pub fn expected_hashrate(
+        &self,
+        info: &SystemInfoResponse,
+        asic: Option<&AsicResponse>,
+    ) -> Option<f64> {
+        let (frequency, small_core_count, asic_count) = match asic {
+            Some(AsicResponse {
+                frequency,
+                small_core_count: Some(cores),
+                asic_count,
+                ..
+            }) if *cores > 0 && *asic_count > 0 => (*frequency, *cores, *asic_count),
+            _ => {
+                let profile = self.lookup(info)?;
+                (info.frequency, profile.small_core_count, profile.asic_count)
+            }
+        };
+        Some(expected_hashrate_ghs(
+            frequency,
+            small_core_count,
+            asic_count,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_lookup() -> Result<()> {
+        let db = HardwareDb::builtin()?;
+
+        let gamma = db
+            .lookup(&info("BM1370", "601", 525))
+            .context("Gamma profile")?;
+        assert_eq!(gamma.product, "Bitaxe Gamma");
+        assert_eq!(gamma.asic_count, 1);
+
+        let gt = db
+            .lookup(&info("bm1370", "800", 525))
+            .context("GT profile")?;
+        assert_eq!(gt.product, "Bitaxe GT");
+        assert_eq!(gt.asic_count, 2);
+
+        assert_eq!(
+            db.lookup(&info("BM1366", "unknown", 485))
+                .map(|p| p.product.as_str()),
+            Some("Bitaxe Ultra")
+        );
+        assert!(db.lookup(&info("BM9999", "601", 525)).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nerdqaxe_never_uses_bitaxe_profiles() -> Result<()> {
+        let db = HardwareDb::builtin()?;
+
+        let plus = db
+            .lookup(&nerdqaxe("BM1368", "NerdQAxe+"))
+            .context("NerdQAxe+ profile")?;
+        assert_eq!(plus.product, "NerdQAxe+");
+        assert_eq!(plus.asic_count, 4);
+
+        let plus_plus = db
+            .lookup(&nerdqaxe("BM1370", "NerdQAxe++"))
+            .context("NerdQAxe++ profile")?;
+        assert_eq!(plus_plus.product, "NerdQAxe++");
+        assert_eq!(plus_plus.device_type, Some(DeviceType::NerdqaxePlus));
+
+        // No single-ASIC Bitaxe fallback for a chip without a NerdQAxe profile
+        assert!(db.lookup(&nerdqaxe("BM1366", "NerdAxe")).is_none());
+        // And a Bitaxe with a NerdQAxe chip still gets its own profile
+        assert_eq!(
+            db.lookup(&info("BM1368", "401", 490))
+                .and_then(|p| p.device_type),
+            Some(DeviceType::BitaxeSupra)
+        );
+        Ok(())
+    }
+
+    fn info(asic_model: &str, board_version: &str, frequency: u32) -> SystemInfoResponse {
+        SystemInfoResponse {
+            asic_model: asic_model.to_string(),
+            board_version: board_version.to_string(),
+            device_model: None,
+            firmware_version: "v2.5.0".to_string(),
+            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            hostname: "bitaxe".to_string(),
+            wifi_ssid: None,
+            wifi_status: None,
+            wifi_rssi: None,
+            pool_url: "public-pool.io".to_string(),
+            pool_port: 21496,
+            pool_user: "bc1qtest.bitaxe".to_string(),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
+            frequency,
+            voltage: 1150.0,
+            fanspeed: 50,
+            auto_fan_speed: None,
+            temp: 55.0,
+            power: 15.0,
+            running_time: 3600,
+        }
+    }
+
+    fn nerdqaxe(asic_model: &str, device_model: &str) -> SystemInfoResponse {
+        SystemInfoResponse {
+            board_version: "unknown".to_string(),
+            device_model: Some(device_model.to_string()),
+            ..info(asic_model, "unknown", 600)
+        }
+    }
+
+    #[test]
+    fn test_expected_hashrate() -> Result<()> {
+        let db = HardwareDb::builtin()?;
+
+        // Core and ASIC counts reported by the device win over the profile
+        let asic = AsicResponse {
+            frequency: 600,
+            voltage: 1200.0,
+            asic_count: 4,
+            small_core_count: Some(2040),
+            large_core_count: None,
+        };
+        assert_eq!(
+            db.expected_hashrate(&info("BM1370", "601", 525), Some(&asic)),
+            Some(4896.0)
+        );
+
+        // Without them the profile's counts are used at the current frequency
+        assert_eq!(
+            db.expected_hashrate(&info("BM1370", "601", 525), None),
+            Some(1071.0)
+        );
+        let no_cores = AsicResponse {
+            small_core_count: None,
+            ..asic
+        };
+        assert_eq!(
+            db.expected_hashrate(&info("BM1370", "800", 600), Some(&no_cores)),
+            Some(2448.0)
+        );
+        assert_eq!(db.expected_hashrate(&info("BM9999", "1", 525), None), None);
+
+        // A NerdQAxe++ without ASIC info counts all four chips
+        assert_eq!(
+            db.expected_hashrate(&nerdqaxe("BM1370", "NerdQAxe++"), None),
+            Some(4896.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_profiles_override_builtin() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("hardware.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [[profiles]]
+            asic_model = "BM1370"
+            product = "Water-cooled Gamma"
+            asic_count = 1
+            small_core_count = 2040
+            stock_frequency = 525
+            stock_voltage = 1150
+            min_frequency = 400
+            max_frequency = 800
+            min_voltage = 1000
+            max_voltage = 1400
+            max_temperature = 75
+            nominal_hashrate_ghs = 1071.0
+            "#,
+        )?;
+
+        let db = HardwareDb::load(&path)?;
+        let gamma = db
+            .lookup(&info("BM1370", "601", 525))
+            .context("Gamma profile")?;
+        assert_eq!(gamma.product, "Water-cooled Gamma");
+        assert_eq!(gamma.max_frequency, 800);
+        // Built-in profiles the file doesn't replace are still there
+        assert!(db.lookup(&info("BM1366", "204", 485)).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_profiles_rejected() -> Result<()> {
+        let profile = |stock_frequency: u32| {
+            format!(
+                r#"
+                [[profiles]]
+                asic_model = "BM1370"
+                product = "Gamma"
+                asic_count = 1
+                small_core_count = 2040
+                stock_frequency = {stock_frequency}
+                stock_voltage = 1150
+                min_frequency = 400
+                max_frequency = 625
+                min_voltage = 1000
+                max_voltage = 1300
+                max_temperature = 70
+                nominal_hashrate_ghs = 1071.0
+                "#
+            )
+        };
+        HardwareDb::parse(&profile(525))?;
+        assert!(HardwareDb::parse(&profile(700)).is_err());
+        assert!(HardwareDb::parse("[[profiles]]\nasic_model = \"BM1370\"").is_err());
+        Ok(())
+    }
+}
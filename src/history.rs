@@ -0,0 +1,709 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use strum::{Display, EnumString, VariantNames};
+
+use crate::api::{DeviceStats, hashrate_health_percent, joules_per_terahash};
+
+/// Name of the history directory inside the cache directory
+pub const HISTORY_DIR_NAME: &str = "history";
+
+const RAW_SUFFIX: &str = ".jsonl";
+const DOWNSAMPLED_SUFFIX: &str = ".downsampled.jsonl";
+
+/// How long samples are kept and at which resolution
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// Samples younger than this are kept at full resolution
+    pub raw_retention: Duration,
+    /// Bucket width used when downsampling older samples
+    pub downsample_interval: Duration,
+    /// Samples older than this are deleted
+    pub max_retention: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            raw_retention: Duration::days(2),
+            downsample_interval: Duration::minutes(5),
+            max_retention: Duration::days(30),
+        }
+    }
+}
+
+/// Result of a maintenance pass over the history store
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub files_downsampled: usize,
+    pub files_deleted: usize,
+}
+
+/// Append-only time-series store for device statistics
+///
+/// Samples are written as JSON lines to one file per device and day:
+/// `<cache_dir>/history/<device>/<YYYY-MM-DD>.jsonl`. Days older than the
+/// raw retention are rewritten into `<YYYY-MM-DD>.downsampled.jsonl` with one
+/// averaged sample per bucket, and days older than the max retention are removed.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    root: PathBuf,
+    policy: RetentionPolicy,
+}
+
+impl HistoryStore {
+    /// Open the history store located inside the given cache directory
+    pub fn open(cache_dir: &Path) -> Self {
+        Self::with_policy(cache_dir, RetentionPolicy::default())
+    }
+
+    /// Open the history store with a custom retention policy
+    pub fn with_policy(cache_dir: &Path, policy: RetentionPolicy) -> Self {
+        Self {
+            root: cache_dir.join(HISTORY_DIR_NAME),
+            policy,
+        }
+    }
+
+    /// Root directory of the store
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Retention policy used by this store
+    pub fn policy(&self) -> RetentionPolicy {
+        self.policy
+    }
+
+    /// Append a sample for a device
+    pub fn append(&self, device_ip: &str, stats: &DeviceStats) -> Result<()> {
+        let device_dir = self.device_dir(device_ip);
+        fs::create_dir_all(&device_dir).with_context(|| {
+            format!(
+                "Failed to create history directory: {path}",
+                path = device_dir.display()
+            )
+        })?;
+
+        let file_path = device_dir.join(format!(
+            "{day}{RAW_SUFFIX}",
+            day = stats.timestamp.date_naive()
+        ));
+        let line = serde_json::to_string(stats).context("Failed to serialize history sample")?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)
+            .with_context(|| {
+                format!(
+                    "Failed to open history file: {path}",
+                    path = file_path.display()
+                )
+            })?;
+        writeln!(file, "{line}").with_context(|| {
+            format!(
+                "Failed to append to history file: {path}",
+                path = file_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Get samples for a device within `[from, to]`, ordered by timestamp
+    pub fn query(
+        &self,
+        device_ip: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DeviceStats>> {
+        let device_dir = self.device_dir(device_ip);
+        if !device_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut samples = Vec::new();
+        for (day, path) in list_day_files(&device_dir)? {
+            if day < from.date_naive() || day > to.date_naive() {
+                continue;
+            }
+            samples.extend(
+                read_samples(&path)?
+                    .into_iter()
+                    .filter(|s| s.timestamp >= from && s.timestamp <= to),
+            );
+        }
+
+        samples.sort_by_key(|s| s.timestamp);
+        Ok(samples)
+    }
+
+    /// Get the IP addresses of all devices with recorded history
+    pub fn devices(&self) -> Result<Vec<String>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&self.root).with_context(|| {
+            format!(
+                "Failed to read history directory: {path}",
+                path = self.root.display()
+            )
+        })?;
+
+        let mut devices = Vec::new();
+        for entry in entries {
+            let entry = entry.context("Failed to read history directory entry")?;
+            if entry.path().is_dir() {
+                devices.push(entry.file_name().to_string_lossy().replace('_', ":"));
+            }
+        }
+        devices.sort();
+        Ok(devices)
+    }
+
+    /// Apply the retention policy: downsample old raw files and delete expired ones
+    pub fn maintain(&self, now: DateTime<Utc>) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+        if !self.root.exists() {
+            return Ok(report);
+        }
+
+        let expire_before = (now - self.policy.max_retention).date_naive();
+        let downsample_before = (now - self.policy.raw_retention).date_naive();
+
+        for device_ip in self.devices()? {
+            let device_dir = self.device_dir(&device_ip);
+            for (day, path) in list_day_files(&device_dir)? {
+                if day < expire_before {
+                    fs::remove_file(&path).with_context(|| {
+                        format!(
+                            "Failed to remove expired history file: {path}",
+                            path = path.display()
+                        )
+                    })?;
+                    report.files_deleted += 1;
+                } else if day < downsample_before && is_raw_file(&path) {
+                    self.downsample_file(&device_dir, day, &path)?;
+                    report.files_downsampled += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn downsample_file(&self, device_dir: &Path, day: NaiveDate, raw_path: &Path) -> Result<()> {
+        let target = device_dir.join(format!("{day}{DOWNSAMPLED_SUFFIX}"));
+
+        // A previous pass may already have written part of this day
+        let mut samples = if target.exists() {
+            read_samples(&target)?
+        } else {
+            Vec::new()
+        };
+        samples.extend(read_samples(raw_path)?);
+        samples.sort_by_key(|s| s.timestamp);
+
+        let downsampled = downsample(&samples, self.policy.downsample_interval);
+        let mut content = String::new();
+        for sample in &downsampled {
+            content.push_str(
+                &serde_json::to_string(sample).context("Failed to serialize history sample")?,
+            );
+            content.push('\n');
+        }
+
+        // Write to a temporary file first so a crash never loses the day
+        let tmp_path = target.with_extension("tmp");
+        fs::write(&tmp_path, content).with_context(|| {
+            format!(
+                "Failed to write history file: {path}",
+                path = tmp_path.display()
+            )
+        })?;
+        fs::rename(&tmp_path, &target).with_context(|| {
+            format!(
+                "Failed to replace history file: {path}",
+                path = target.display()
+            )
+        })?;
+        fs::remove_file(raw_path).with_context(|| {
+            format!(
+                "Failed to remove raw history file: {path}",
+                path = raw_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+
+    fn device_dir(&self, device_ip: &str) -> PathBuf {
+        self.root.join(device_key(device_ip))
+    }
+}
+
+/// Metric that can be extracted from recorded samples
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    VariantNames,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum HistoryMetric {
+    Hashrate,
+    #[strum(serialize = "temperature", serialize = "temp")]
+    Temperature,
+    Power,
+    Fan,
+    SharesAccepted,
+    SharesRejected,
+    Voltage,
+    Frequency,
+    Rssi,
+    /// Actual hashrate as a percentage of the expected one
+    HashrateHealth,
+}
+
+impl HistoryMetric {
+    /// Extract the metric value from a sample, if the sample carries it
+    pub fn value(&self, stats: &DeviceStats) -> Option<f64> {
+        match self {
+            HistoryMetric::Hashrate => Some(stats.hashrate_mhs),
+            HistoryMetric::Temperature => Some(stats.temperature_celsius),
+            HistoryMetric::Power => Some(stats.power_watts),
+            HistoryMetric::Fan => Some(stats.fan_speed_rpm as f64),
+            HistoryMetric::SharesAccepted => Some(stats.shares_accepted as f64),
+            HistoryMetric::SharesRejected => Some(stats.shares_rejected as f64),
+            HistoryMetric::Voltage => stats.voltage,
+            HistoryMetric::Frequency => stats.frequency.map(f64::from),
+            HistoryMetric::Rssi => stats.wifi_rssi.map(f64::from),
+            HistoryMetric::HashrateHealth => stats.hashrate_health_percent,
+        }
+    }
+}
+
+/// A single metric value at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeriesPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// Extract a metric series from samples, skipping samples without the metric
+pub fn extract_series(samples: &[DeviceStats], metric: HistoryMetric) -> Vec<SeriesPoint> {
+    samples
+        .iter()
+        .filter_map(|s| {
+            metric.value(s).map(|value| SeriesPoint {
+                timestamp: s.timestamp,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Descriptive statistics of a metric series
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeriesSummary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl SeriesSummary {
+    /// Summarize a set of values, returns None when there are no values
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        Some(Self {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            avg: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p99: percentile(&sorted, 99.0),
+        })
+    }
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Parse a relative duration such as `90s`, `30m`, `6h`, `7d` or `2w`
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let Ok(amount) = number.parse::<i64>() else {
+        bail!("Invalid duration '{input}': expected a number followed by s, m, h, d or w");
+    };
+
+    let duration = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" | "" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        _ => bail!("Invalid duration unit '{unit}' in '{input}': expected s, m, h, d or w"),
+    };
+
+    Ok(duration)
+}
+
+/// Parse a point in time given either as an RFC 3339 timestamp or as a
+/// duration relative to `now` (e.g. `2d` means two days ago)
+pub fn parse_time(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    parse_duration(input)
+        .map(|duration| now - duration)
+        .with_context(|| format!("Invalid time '{input}': expected RFC 3339 or a duration"))
+}
+
+/// Average samples into fixed-width time buckets
+///
+/// Gauges (hashrate, temperature, power, fan, RSSI, voltage) are averaged while
+/// counters and identifiers (shares, uptime, pool, frequency) keep the last value
+/// of each bucket. Input must be sorted by timestamp.
+pub fn downsample(samples: &[DeviceStats], bucket: Duration) -> Vec<DeviceStats> {
+    let bucket_seconds = bucket.num_seconds().max(1);
+    let mut result = Vec::new();
+    let mut current: Vec<&DeviceStats> = Vec::new();
+    let mut current_bucket = None;
+
+    for sample in samples {
+        let bucket_index = sample.timestamp.timestamp().div_euclid(bucket_seconds);
+        if current_bucket.is_some_and(|b| b != bucket_index) {
+            result.push(average_samples(&current));
+            current.clear();
+        }
+        current_bucket = Some(bucket_index);
+        current.push(sample);
+    }
+
+    if !current.is_empty() {
+        result.push(average_samples(&current));
+    }
+
+    result
+}
+
+fn average_samples(samples: &[&DeviceStats]) -> DeviceStats {
+    let count = samples.len() as f64;
+    let mean = |f: &dyn Fn(&DeviceStats) -> f64| samples.iter().map(|s| f(s)).sum::<f64>() / count;
+    let mean_opt = |f: &dyn Fn(&DeviceStats) -> Option<f64>| {
+        let values: Vec<f64> = samples.iter().filter_map(|s| f(s)).collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f64>() / values.len() as f64)
+        }
+    };
+
+    let max_opt = |f: &dyn Fn(&DeviceStats) -> Option<f64>| {
+        samples.iter().filter_map(|s| f(s)).reduce(f64::max)
+    };
+
+    let first = samples[0];
+    let last = samples[samples.len() - 1];
+
+    let hashrate_mhs = mean(&|s| s.hashrate_mhs);
+    let power_watts = mean(&|s| s.power_watts);
+
+    DeviceStats {
+        timestamp: first.timestamp,
+        hashrate_mhs,
+        temperature_celsius: mean(&|s| s.temperature_celsius),
+        power_watts,
+        fan_speed_rpm: mean(&|s| s.fan_speed_rpm as f64).round() as u32,
+        shares_accepted: last.shares_accepted,
+        shares_rejected: last.shares_rejected,
+        uptime_seconds: last.uptime_seconds,
+        pool_url: last.pool_url.clone(),
+        wifi_rssi: mean_opt(&|s| s.wifi_rssi.map(f64::from)).map(|v| v.round() as i32),
+        voltage: mean_opt(&|s| s.voltage),
+        frequency: last.frequency,
+        efficiency_jth: joules_per_terahash(hashrate_mhs, power_watts),
+        best_difficulty: max_opt(&|s| s.best_difficulty),
+        best_session_difficulty: max_opt(&|s| s.best_session_difficulty),
+        using_fallback_pool: last.using_fallback_pool,
+        expected_hashrate_mhs: last.expected_hashrate_mhs,
+        hashrate_health_percent: hashrate_health_percent(hashrate_mhs, last.expected_hashrate_mhs),
+    }
+}
+
+/// Map a device IP address to a directory name that is safe on all platforms
+pub(crate) fn device_key(device_ip: &str) -> String {
+    device_ip.replace(':', "_")
+}
+
+fn is_raw_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.ends_with(RAW_SUFFIX) && !name.ends_with(DOWNSAMPLED_SUFFIX)
+}
+
+/// List the day files of a device directory, sorted by day
+fn list_day_files(device_dir: &Path) -> Result<Vec<(NaiveDate, PathBuf)>> {
+    let entries = fs::read_dir(device_dir).with_context(|| {
+        format!(
+            "Failed to read history directory: {path}",
+            path = device_dir.display()
+        )
+    })?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .context("Failed to read history directory entry")?
+            .path();
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        let day_str = name
+            .strip_suffix(DOWNSAMPLED_SUFFIX)
+            .or_else(|| name.strip_suffix(RAW_SUFFIX));
+        if let Some(day) = day_str.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) {
+            files.push((day, path));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn read_samples(path: &Path) -> Result<Vec<DeviceStats>> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open history file: {path}", path = path.display()))?;
+
+    let mut samples = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| {
+            format!("Failed to read history file: {path}", path = path.display())
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A partially written last line (e.g. after a crash) must not make the whole day unreadable
+        match serde_json::from_str::<DeviceStats>(&line) {
+            Ok(sample) => samples.push(sample),
+            Err(e) => tracing::warn!(
+                "Skipping malformed history line in {path}: {e}",
+                path = path.display()
+            ),
+        }
+    }
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DurationRound;
+    use tempfile::TempDir;
+
+    fn sample(timestamp: DateTime<Utc>, hashrate: f64) -> DeviceStats {
+        DeviceStats {
+            timestamp,
+            hashrate_mhs: hashrate,
+            temperature_celsius: 60.0,
+            power_watts: 15.0,
+            fan_speed_rpm: 4000,
+            shares_accepted: 10,
+            shares_rejected: 0,
+            uptime_seconds: 3600,
+            pool_url: Some("stratum+tcp://pool:3333".to_string()),
+            wifi_rssi: Some(-50),
+            voltage: Some(1200.0),
+            frequency: Some(490),
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_query() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = HistoryStore::open(temp_dir.path());
+        let now = Utc::now();
+
+        store.append("192.168.1.100", &sample(now - Duration::hours(2), 500.0))?;
+        store.append("192.168.1.100", &sample(now - Duration::minutes(30), 510.0))?;
+        store.append("192.168.1.101", &sample(now, 600.0))?;
+
+        let all = store.query("192.168.1.100", now - Duration::days(1), now)?;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].hashrate_mhs, 500.0);
+
+        let recent = store.query("192.168.1.100", now - Duration::hours(1), now)?;
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].hashrate_mhs, 510.0);
+
+        assert_eq!(store.devices()?, vec!["192.168.1.100", "192.168.1.101"]);
+        assert!(
+            store
+                .query("10.0.0.1", now - Duration::days(1), now)?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_downsample_averages_buckets() -> Result<()> {
+        let start = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")?.with_timezone(&Utc);
+        let samples = vec![
+            sample(start, 400.0),
+            sample(start + Duration::minutes(1), 600.0),
+            sample(start + Duration::minutes(6), 300.0),
+        ];
+
+        let result = downsample(&samples, Duration::minutes(5));
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].hashrate_mhs, 500.0);
+        assert_eq!(result[0].timestamp, start);
+        assert_eq!(result[1].hashrate_mhs, 300.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_maintain_downsamples_and_expires() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = HistoryStore::open(temp_dir.path());
+        let now = Utc::now();
+        let ip = "192.168.1.100";
+
+        let old = now - Duration::days(40);
+        // Align to a bucket start so both middle samples land in the same bucket
+        let middle = (now - Duration::days(5)).duration_trunc(Duration::minutes(5))?;
+        store.append(ip, &sample(old, 100.0))?;
+        store.append(ip, &sample(middle, 400.0))?;
+        store.append(ip, &sample(middle + Duration::seconds(10), 600.0))?;
+        store.append(ip, &sample(now, 700.0))?;
+
+        let report = store.maintain(now)?;
+        assert_eq!(report.files_deleted, 1);
+        assert_eq!(report.files_downsampled, 1);
+
+        let samples = store.query(ip, now - Duration::days(60), now)?;
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].hashrate_mhs, 500.0);
+        assert_eq!(samples[1].hashrate_mhs, 700.0);
+
+        // A second pass has nothing left to do
+        assert_eq!(store.maintain(now)?, MaintenanceReport::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_series_summary() -> Result<()> {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        let summary = SeriesSummary::from_values(&values).context("expected summary")?;
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 100.0);
+        assert_eq!(summary.avg, 50.5);
+        assert_eq!(summary.p50, 50.0);
+        assert_eq!(summary.p90, 90.0);
+        assert_eq!(summary.p99, 99.0);
+        assert!(SeriesSummary::from_values(&[]).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration_and_time() -> Result<()> {
+        assert_eq!(parse_duration("90s")?, Duration::seconds(90));
+        assert_eq!(parse_duration("30m")?, Duration::minutes(30));
+        assert_eq!(parse_duration("6h")?, Duration::hours(6));
+        assert_eq!(parse_duration("7d")?, Duration::days(7));
+        assert_eq!(parse_duration("2w")?, Duration::weeks(2));
+        assert!(parse_duration("6x").is_err());
+        assert!(parse_duration("h").is_err());
+
+        let now = Utc::now();
+        assert_eq!(parse_time("1d", now)?, now - Duration::days(1));
+        assert_eq!(
+            parse_time("2025-01-01T00:00:00Z", now)?,
+            DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")?.with_timezone(&Utc)
+        );
+        assert!(parse_time("yesterday", now).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_metric_parsing_and_extraction() -> Result<()> {
+        use std::str::FromStr;
+
+        assert_eq!(HistoryMetric::from_str("temp")?, HistoryMetric::Temperature);
+        assert_eq!(
+            HistoryMetric::from_str("shares-rejected")?,
+            HistoryMetric::SharesRejected
+        );
+
+        let now = Utc::now();
+        let mut without_voltage = sample(now, 500.0);
+        without_voltage.voltage = None;
+        let samples = vec![sample(now, 500.0), without_voltage];
+        assert_eq!(extract_series(&samples, HistoryMetric::Hashrate).len(), 2);
+        assert_eq!(extract_series(&samples, HistoryMetric::Voltage).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_lines_are_skipped() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = HistoryStore::open(temp_dir.path());
+        let now = Utc::now();
+        let ip = "192.168.1.100";
+
+        store.append(ip, &sample(now, 500.0))?;
+        let path = store
+            .root()
+            .join(ip)
+            .join(format!("{day}{RAW_SUFFIX}", day = now.date_naive()));
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        write!(file, "{{\"timestamp\": \"trunc")?;
+
+        let samples = store.query(ip, now - Duration::hours(1), now)?;
+        assert_eq!(samples.len(), 1);
+        Ok(())
+    }
+}
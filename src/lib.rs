@@ -1,14 +1,48 @@
+pub mod alerts;
 pub mod api;
+pub mod backup;
+pub mod best_difficulty;
 pub mod cache;
 pub mod cli;
+pub mod daemon;
 pub mod discovery;
+pub mod energy;
+pub mod events;
+pub mod firmware;
+pub mod fleet;
+pub mod hardware;
+pub mod history;
+pub mod mqtt;
 pub mod output;
+pub mod pool;
+pub mod profit;
+pub mod rollout;
+pub mod settings;
+pub mod thermal;
+pub mod tune;
 
 #[cfg(feature = "mcp")]
 pub mod mcp_server;
 
+pub use alerts::*;
 pub use api::*;
+pub use backup::*;
+pub use best_difficulty::*;
 pub use cache::*;
 pub use cli::*;
+pub use daemon::*;
 pub use discovery::*;
+pub use energy::*;
+pub use events::*;
+pub use firmware::*;
+pub use fleet::*;
+pub use hardware::*;
+pub use history::*;
+pub use mqtt::*;
 pub use output::*;
+pub use pool::*;
+pub use profit::*;
+pub use rollout::*;
+pub use settings::*;
+pub use thermal::*;
+pub use tune::*;
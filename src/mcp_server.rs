@@ -18,6 +18,7 @@ use tracing::info;
 use crate::api::client::AxeOsClient;
 use crate::api::models::DeviceFilter;
 use crate::cache::{DeviceCache, get_cache_dir};
+use crate::settings::SettingsPolicy;
 
 /// Configuration for the MCP server
 #[derive(Debug, Clone, Default)]
@@ -84,6 +85,9 @@ pub struct UpdateSettingsRequest {
     pub device: String,
     #[schemars(description = "Settings to update as JSON object")]
     pub settings: serde_json::Value,
+    #[serde(default, rename = "unsafe")]
+    #[schemars(description = "Allow frequency and voltage outside the device's safe range")]
+    pub allow_unsafe: bool,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -385,7 +389,11 @@ impl AxectlMcpServer {
     #[tool(description = "Update device settings")]
     async fn update_settings(
         &self,
-        UpdateSettingsRequest { device, settings }: UpdateSettingsRequest,
+        UpdateSettingsRequest {
+            device,
+            settings,
+            allow_unsafe,
+        }: UpdateSettingsRequest,
     ) -> CallToolResult {
         let device_info = match self.get_device_from_cache(&device).await {
             Ok(d) => d,
@@ -412,6 +420,14 @@ impl AxectlMcpServer {
                 }
             };
 
+        let supported = match SettingsPolicy::load(allow_unsafe) {
+            Ok(policy) => policy.ensure_supported(&client, &update_request).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = supported {
+            return CallToolResult::error(vec![Content::text(format!("{e:#}"))]);
+        }
+
         match client.update_system(update_request).await {
             Ok(result) => CallToolResult::success(vec![Content::text(
                 serde_json::to_string_pretty(&result).unwrap_or_else(|e| e.to_string()),
@@ -861,6 +877,10 @@ impl ServerHandler for AxectlMcpServer {
                         "settings": {
                             "type": "object",
                             "description": "Settings to update as JSON object"
+                        },
+                        "unsafe": {
+                            "type": "boolean",
+                            "description": "Allow frequency and voltage outside the device's safe range"
                         }
                     },
                     "required": ["device", "settings"]
@@ -0,0 +1,735 @@
+use anyhow::{Context, Result, bail, ensure};
+use rustls::ClientConfig;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use url::Url;
+
+use crate::api::{Device, DeviceStats, SystemUpdateRequest};
+use crate::daemon::DaemonCommand;
+
+/// Default port of plain MQTT brokers
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+
+/// Default port of MQTT brokers over TLS
+pub const DEFAULT_MQTTS_PORT: u16 = 8883;
+
+/// Topic under which device state is published
+pub const DEFAULT_BASE_TOPIC: &str = "axectl";
+
+/// Home Assistant's default discovery prefix
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Payload of the restart button
+const PRESS_PAYLOAD: &str = "PRESS";
+
+/// Frequency range offered by the Home Assistant frequency control (MHz)
+const FREQUENCY_RANGE: (u32, u32) = (100, 1200);
+
+/// MQTT broker to connect to, from `[mqtt://|mqtts://]host[:port]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl std::str::FromStr for Broker {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let with_scheme = if s.contains("://") {
+            s.to_string()
+        } else {
+            format!("mqtt://{s}")
+        };
+        let url = Url::parse(&with_scheme).with_context(|| format!("Invalid MQTT broker: {s}"))?;
+
+        let tls = match url.scheme() {
+            "mqtt" | "tcp" => false,
+            "mqtts" | "ssl" => true,
+            scheme => bail!("Unsupported MQTT broker scheme '{scheme}', use mqtt:// or mqtts://"),
+        };
+        let host = url
+            .host_str()
+            .filter(|h| !h.is_empty())
+            .with_context(|| format!("MQTT broker has no host: {s}"))?
+            .to_string();
+        let port = url.port().unwrap_or(if tls {
+            DEFAULT_MQTTS_PORT
+        } else {
+            DEFAULT_MQTT_PORT
+        });
+
+        Ok(Self { host, port, tls })
+    }
+}
+
+/// TLS settings trusting the bundled web PKI roots
+pub fn tls_client_config() -> Result<Arc<ClientConfig>> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .context("Failed to configure TLS")?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+/// A `DeviceStats` field exposed as a Home Assistant sensor
+struct Sensor {
+    field: &'static str,
+    name: &'static str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+    state_class: Option<&'static str>,
+}
+
+const SENSORS: &[Sensor] = &[
+    Sensor {
+        field: "hashrate_mhs",
+        name: "Hashrate",
+        unit: Some("GH/s"),
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "temperature_celsius",
+        name: "Temperature",
+        unit: Some("°C"),
+        device_class: Some("temperature"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "power_watts",
+        name: "Power",
+        unit: Some("W"),
+        device_class: Some("power"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "fan_speed_rpm",
+        name: "Fan speed",
+        unit: Some("RPM"),
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "shares_accepted",
+        name: "Accepted shares",
+        unit: None,
+        device_class: None,
+        state_class: Some("total_increasing"),
+    },
+    Sensor {
+        field: "shares_rejected",
+        name: "Rejected shares",
+        unit: None,
+        device_class: None,
+        state_class: Some("total_increasing"),
+    },
+    Sensor {
+        field: "uptime_seconds",
+        name: "Uptime",
+        unit: Some("s"),
+        device_class: Some("duration"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "pool_url",
+        name: "Pool",
+        unit: None,
+        device_class: None,
+        state_class: None,
+    },
+    Sensor {
+        field: "wifi_rssi",
+        name: "WiFi signal",
+        unit: Some("dBm"),
+        device_class: Some("signal_strength"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "voltage",
+        name: "Core voltage",
+        unit: Some("mV"),
+        device_class: Some("voltage"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "frequency",
+        name: "Frequency",
+        unit: Some("MHz"),
+        device_class: Some("frequency"),
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "efficiency_jth",
+        name: "Efficiency",
+        unit: Some("J/TH"),
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "hashrate_health_percent",
+        name: "Hashrate health",
+        unit: Some("%"),
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "best_difficulty",
+        name: "Best difficulty",
+        unit: None,
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "best_session_difficulty",
+        name: "Session best difficulty",
+        unit: None,
+        device_class: None,
+        state_class: Some("measurement"),
+    },
+    Sensor {
+        field: "timestamp",
+        name: "Last update",
+        unit: None,
+        device_class: Some("timestamp"),
+        state_class: None,
+    },
+];
+
+/// Topic layout of the MQTT bridge
+///
+/// Device state lives under `{base}/{device_id}/...`, where the device ID is the
+/// device name reduced to lowercase letters, digits, `_` and `-`.
+#[derive(Debug, Clone)]
+pub struct MqttTopics {
+    base: String,
+    discovery_prefix: String,
+}
+
+impl MqttTopics {
+    pub fn new(base: &str, discovery_prefix: &str) -> Self {
+        Self {
+            base: base.trim_end_matches('/').to_string(),
+            discovery_prefix: discovery_prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Topic segment identifying the device with this name
+    pub fn device_id(name: &str) -> String {
+        name.chars()
+            .map(|c| match c.to_ascii_lowercase() {
+                c @ ('a'..='z' | '0'..='9' | '_' | '-') => c,
+                _ => '_',
+            })
+            .collect()
+    }
+
+    /// Bridge availability, `online` while axectl is connected
+    pub fn status(&self) -> String {
+        format!("{base}/status", base = self.base)
+    }
+
+    /// Device availability, `online` or `offline`
+    pub fn availability(&self, device_id: &str) -> String {
+        format!("{base}/{device_id}/availability", base = self.base)
+    }
+
+    /// State topic of one `DeviceStats` field
+    pub fn field(&self, device_id: &str, field: &str) -> String {
+        format!("{base}/{device_id}/{field}", base = self.base)
+    }
+
+    fn fan_speed_command(&self, device_id: &str) -> String {
+        format!("{base}/{device_id}/fan_speed/set", base = self.base)
+    }
+
+    fn frequency_command(&self, device_id: &str) -> String {
+        format!("{base}/{device_id}/frequency/set", base = self.base)
+    }
+
+    fn restart_command(&self, device_id: &str) -> String {
+        format!("{base}/{device_id}/restart", base = self.base)
+    }
+
+    /// Topic filters of all device commands
+    pub fn command_filters(&self) -> Vec<String> {
+        vec![
+            self.fan_speed_command("+"),
+            self.frequency_command("+"),
+            self.restart_command("+"),
+        ]
+    }
+
+    /// Retained state messages, one per non-empty `DeviceStats` field
+    pub fn stats_messages(
+        &self,
+        device: &Device,
+        stats: &DeviceStats,
+    ) -> Result<Vec<(String, String)>> {
+        let device_id = Self::device_id(&device.name);
+        let Value::Object(fields) =
+            serde_json::to_value(stats).context("Failed to serialize device stats")?
+        else {
+            bail!("Device stats did not serialize to an object");
+        };
+
+        Ok(fields
+            .into_iter()
+            .filter_map(|(field, value)| {
+                let payload = match value {
+                    Value::Null => return None,
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                Some((self.field(&device_id, &field), payload))
+            })
+            .collect())
+    }
+
+    /// Retained Home Assistant discovery configs for a device's sensors and,
+    /// with `controls`, its fan speed, frequency and restart entities
+    pub fn discovery_messages(&self, device: &Device, controls: bool) -> Vec<(String, Value)> {
+        let device_id = Self::device_id(&device.name);
+        let node_id = format!("axectl_{device_id}");
+        let device_info = json!({
+            "identifiers": [node_id],
+            "name": device.name,
+            "model": device.device_type.as_str(),
+            "configuration_url": format!("http://{ip}", ip = device.ip_address),
+        });
+        let availability = json!([
+            { "topic": self.status() },
+            { "topic": self.availability(&device_id) },
+        ]);
+        let config_topic = |component: &str, object: &str| {
+            format!(
+                "{prefix}/{component}/{node_id}/{object}/config",
+                prefix = self.discovery_prefix
+            )
+        };
+
+        let mut messages: Vec<(String, Value)> = SENSORS
+            .iter()
+            .map(|sensor| {
+                let mut config = json!({
+                    "name": sensor.name,
+                    "unique_id": format!("{node_id}_{field}", field = sensor.field),
+                    "state_topic": self.field(&device_id, sensor.field),
+                    "availability": availability,
+                    "availability_mode": "all",
+                    "device": device_info,
+                });
+                for (key, value) in [
+                    ("unit_of_measurement", sensor.unit),
+                    ("device_class", sensor.device_class),
+                    ("state_class", sensor.state_class),
+                ] {
+                    if let Some(value) = value {
+                        config[key] = json!(value);
+                    }
+                }
+                (config_topic("sensor", sensor.field), config)
+            })
+            .collect();
+
+        if controls {
+            messages.push((
+                config_topic("number", "fan_speed"),
+                json!({
+                    "name": "Fan speed setting",
+                    "unique_id": format!("{node_id}_fan_speed_set"),
+                    "command_topic": self.fan_speed_command(&device_id),
+                    "min": 0,
+                    "max": 100,
+                    "step": 1,
+                    "unit_of_measurement": "%",
+                    "mode": "slider",
+                    "availability": availability,
+                    "availability_mode": "all",
+                    "device": device_info,
+                }),
+            ));
+            messages.push((
+                config_topic("number", "frequency"),
+                json!({
+                    "name": "Frequency setting",
+                    "unique_id": format!("{node_id}_frequency_set"),
+                    "state_topic": self.field(&device_id, "frequency"),
+                    "command_topic": self.frequency_command(&device_id),
+                    "min": FREQUENCY_RANGE.0,
+                    "max": FREQUENCY_RANGE.1,
+                    "step": 1,
+                    "unit_of_measurement": "MHz",
+                    "mode": "box",
+                    "availability": availability,
+                    "availability_mode": "all",
+                    "device": device_info,
+                }),
+            ));
+            messages.push((
+                config_topic("button", "restart"),
+                json!({
+                    "name": "Restart",
+                    "unique_id": format!("{node_id}_restart"),
+                    "command_topic": self.restart_command(&device_id),
+                    "payload_press": PRESS_PAYLOAD,
+                    "device_class": "restart",
+                    "availability": availability,
+                    "availability_mode": "all",
+                    "device": device_info,
+                }),
+            ));
+        }
+
+        messages
+    }
+
+    /// Parse a message on a command topic into the device ID and the action to run
+    ///
+    /// Returns `None` for topics that are not commands.
+    pub fn parse_command(
+        &self,
+        topic: &str,
+        payload: &[u8],
+    ) -> Result<Option<(String, DaemonCommand)>> {
+        let Some(rest) = topic
+            .strip_prefix(&self.base)
+            .and_then(|t| t.strip_prefix('/'))
+        else {
+            return Ok(None);
+        };
+        let Some((device_id, command)) = rest.split_once('/') else {
+            return Ok(None);
+        };
+        let payload = std::str::from_utf8(payload)
+            .context("Command payload is not UTF-8")?
+            .trim();
+
+        let command = match command {
+            "fan_speed/set" => {
+                let speed = parse_number(payload, "fan speed")?;
+                ensure!(
+                    speed <= 100.0,
+                    "Fan speed must be between 0 and 100, got {payload}"
+                );
+                DaemonCommand::SetFanSpeed(speed.round() as u8)
+            }
+            "frequency/set" => {
+                let frequency = parse_number(payload, "frequency")?;
+                ensure!(
+                    frequency >= 1.0,
+                    "Frequency must be positive, got {payload}"
+                );
+                DaemonCommand::UpdateSettings {
+                    settings: Box::new(SystemUpdateRequest {
+                        frequency_value: Some(frequency.round() as u32),
+                        ..SystemUpdateRequest::default()
+                    }),
+                    allow_unsafe: false,
+                }
+            }
+            "restart" => DaemonCommand::Restart,
+            _ => return Ok(None),
+        };
+
+        Ok(Some((device_id.to_string(), command)))
+    }
+}
+
+/// Parse a non-negative number; Home Assistant sends number entities as e.g. `80.0`
+fn parse_number(payload: &str, what: &str) -> Result<f64> {
+    let value: f64 = payload
+        .parse()
+        .with_context(|| format!("Invalid {what}: {payload}"))?;
+    ensure!(
+        value.is_finite() && value >= 0.0,
+        "Invalid {what}: {payload}"
+    );
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{AxeOsClient, CommandResult, DeviceStatus, DeviceType};
+    use chrono::Utc;
+    use mockito::Matcher;
+
+    const DEVICE_INFO: &str = r#"{
+        "ASICModel": "BM1370",
+        "boardVersion": "601",
+        "version": "2.4.0",
+        "macAddr": "AA:BB:CC:DD:EE:FF",
+        "hostname": "bitaxe-1",
+        "stratumURL": "public-pool.io",
+        "stratumPort": 21496,
+        "stratumUser": "bc1qold.bitaxe-1",
+        "frequency": 525,
+        "voltage": 1150,
+        "fanspeed": 80,
+        "temp": 55.0,
+        "power": 15.0,
+        "hashRate": 1100.0,
+        "uptimeSeconds": 3600,
+        "sharesAccepted": 10,
+        "sharesRejected": 0
+    }"#;
+
+    fn device() -> Device {
+        Device {
+            name: "Bitaxe Gamma.1".to_string(),
+            ip_address: "192.168.1.10".to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn parses_broker_addresses() -> Result<()> {
+        assert_eq!(
+            "localhost".parse::<Broker>()?,
+            Broker {
+                host: "localhost".to_string(),
+                port: DEFAULT_MQTT_PORT,
+                tls: false
+            }
+        );
+        assert_eq!(
+            "mqtts://broker.example.com".parse::<Broker>()?,
+            Broker {
+                host: "broker.example.com".to_string(),
+                port: DEFAULT_MQTTS_PORT,
+                tls: true
+            }
+        );
+        assert_eq!("192.168.1.2:1884".parse::<Broker>()?.port, 1884);
+        assert!("http://localhost".parse::<Broker>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn publishes_stats_fields_and_discovery_configs() -> Result<()> {
+        let topics = MqttTopics::new("axectl/", DEFAULT_DISCOVERY_PREFIX);
+        let device = device();
+        assert_eq!(MqttTopics::device_id(&device.name), "bitaxe_gamma_1");
+
+        let stats = DeviceStats {
+            timestamp: Utc::now(),
+            hashrate_mhs: 1150.5,
+            temperature_celsius: 58.5,
+            power_watts: 17.5,
+            fan_speed_rpm: 4200,
+            shares_accepted: 420,
+            shares_rejected: 3,
+            uptime_seconds: 7200,
+            pool_url: Some("public-pool.io".to_string()),
+            wifi_rssi: None,
+            voltage: None,
+            frequency: Some(525),
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        };
+        let messages = topics.stats_messages(&device, &stats)?;
+        assert!(messages.contains(&(
+            "axectl/bitaxe_gamma_1/hashrate_mhs".to_string(),
+            "1150.5".to_string()
+        )));
+        assert!(messages.contains(&(
+            "axectl/bitaxe_gamma_1/pool_url".to_string(),
+            "public-pool.io".to_string()
+        )));
+        assert!(
+            !messages
+                .iter()
+                .any(|(topic, _)| topic.ends_with("wifi_rssi"))
+        );
+
+        let configs = topics.discovery_messages(&device, true);
+        let (_, temperature) = configs
+            .iter()
+            .find(|(topic, _)| {
+                topic == "homeassistant/sensor/axectl_bitaxe_gamma_1/temperature_celsius/config"
+            })
+            .context("temperature sensor")?;
+        assert_eq!(
+            temperature["state_topic"],
+            "axectl/bitaxe_gamma_1/temperature_celsius"
+        );
+        assert_eq!(temperature["device_class"], "temperature");
+        assert_eq!(
+            temperature["device"]["identifiers"][0],
+            "axectl_bitaxe_gamma_1"
+        );
+        assert!(
+            configs
+                .iter()
+                .any(|(topic, _)| topic
+                    == "homeassistant/button/axectl_bitaxe_gamma_1/restart/config")
+        );
+        assert_eq!(
+            topics.discovery_messages(&device, false).len(),
+            SENSORS.len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_command_topics() -> Result<()> {
+        let topics = MqttTopics::new(DEFAULT_BASE_TOPIC, DEFAULT_DISCOVERY_PREFIX);
+
+        let (device_id, command) = topics
+            .parse_command("axectl/bitaxe_1/fan_speed/set", b"80.0")?
+            .context("fan speed command")?;
+        assert_eq!(device_id, "bitaxe_1");
+        assert!(matches!(command, DaemonCommand::SetFanSpeed(80)));
+
+        let (_, command) = topics
+            .parse_command("axectl/bitaxe_1/frequency/set", b"550")?
+            .context("frequency command")?;
+        assert!(matches!(
+            command,
+            DaemonCommand::UpdateSettings { settings, allow_unsafe: false }
+                if settings.frequency_value == Some(550)
+        ));
+
+        assert!(matches!(
+            topics.parse_command("axectl/bitaxe_1/restart", b"PRESS")?,
+            Some((_, DaemonCommand::Restart))
+        ));
+        assert!(
+            topics
+                .parse_command("axectl/bitaxe_1/hashrate_mhs", b"1")?
+                .is_none()
+        );
+        assert!(
+            topics
+                .parse_command("other/bitaxe_1/restart", b"PRESS")?
+                .is_none()
+        );
+        assert!(
+            topics
+                .parse_command("axectl/bitaxe_1/fan_speed/set", b"120")
+                .is_err()
+        );
+        Ok(())
+    }
+    #[test]
+    fn rejects_bad_command_payloads() -> Result<()> {
+        let topics = MqttTopics::new(DEFAULT_BASE_TOPIC, DEFAULT_DISCOVERY_PREFIX);
+        let fan = "axectl/bitaxe_1/fan_speed/set";
+        let frequency = "axectl/bitaxe_1/frequency/set";
+
+        for (topic, payload) in [
+            (fan, &b"abc"[..]),
+            (fan, b""),
+            (fan, b"-5"),
+            (fan, b"NaN"),
+            (fan, b"100.6"),
+            (fan, b"\xff\xfe"),
+            (frequency, b"0"),
+            (frequency, b"inf"),
+        ] {
+            assert!(
+                topics.parse_command(topic, payload).is_err(),
+                "{topic} accepted {payload:?}"
+            );
+        }
+        if let Err(e) = topics.parse_command(fan, b"abc") {
+            assert!(format!("{e:#}").contains("Invalid fan speed: abc"));
+        }
+
+        // Surrounding whitespace is fine and fractions are rounded
+        assert!(matches!(
+            topics.parse_command(fan, b" 79.6\n")?,
+            Some((_, DaemonCommand::SetFanSpeed(80)))
+        ));
+
+        // Topics that are not commands are ignored, whatever the payload
+        assert!(topics.parse_command("axectl/bitaxe_1", b"abc")?.is_none());
+        assert!(
+            topics
+                .parse_command("axectl/bitaxe_1/identify", b"abc")?
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn command_topics_drive_device_requests() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(DEVICE_INFO)
+            // The ASIC model both frequencies are checked against
+            .expect(2)
+            .create_async()
+            .await;
+        let fan = server
+            .mock("PATCH", "/api/system")
+            .match_body(Matcher::Json(serde_json::json!({ "fanspeed": 80 })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let frequency = server
+            .mock("PATCH", "/api/system")
+            .match_body(Matcher::Json(serde_json::json!({ "frequencyvalue": 550 })))
+            .with_status(200)
+            .create_async()
+            .await;
+        let restart = server
+            .mock("POST", "/api/system/restart")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let topics = MqttTopics::new(DEFAULT_BASE_TOPIC, DEFAULT_DISCOVERY_PREFIX);
+        let client = AxeOsClient::new(&server.host_with_port())?;
+        let send = async |topic: &str, payload: &[u8]| -> Result<CommandResult> {
+            let (_, command) = topics
+                .parse_command(topic, payload)?
+                .context("command topic")?;
+            command.send(&client).await
+        };
+
+        assert!(
+            send("axectl/bitaxe_1/fan_speed/set", b"80.0")
+                .await?
+                .success
+        );
+        assert!(send("axectl/bitaxe_1/frequency/set", b"550").await?.success);
+        assert!(send("axectl/bitaxe_1/restart", b"PRESS").await?.success);
+
+        // Out of range for the ASIC: refused before anything is sent
+        let error = send("axectl/bitaxe_1/frequency/set", b"2000")
+            .await
+            .err()
+            .context("unsafe frequency was sent")?;
+        assert!(
+            error
+                .to_string()
+                .contains("Frequency 2000 MHz is outside the safe")
+        );
+
+        info.assert_async().await;
+        fan.assert_async().await;
+        frequency.assert_async().await;
+        restart.assert_async().await;
+        Ok(())
+    }
+}
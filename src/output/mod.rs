@@ -1,5 +1,7 @@
 pub mod json;
+pub mod prometheus;
 pub mod text;
 
 pub use json::*;
+pub use prometheus::*;
 pub use text::*;
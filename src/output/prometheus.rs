@@ -0,0 +1,411 @@
+use crate::api::{Device, DeviceStats, DeviceStatus, SwarmSummary, TypeSummary};
+use alphanumeric_sort::compare_str;
+use std::fmt::Write;
+
+/// Content type of the Prometheus text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Per-device metric exported from the latest stats sample
+struct DeviceMetric {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    value: fn(&DeviceStats) -> Option<f64>,
+}
+
+const DEVICE_METRICS: &[DeviceMetric] = &[
+    DeviceMetric {
+        name: "axectl_device_hashrate_hashes_per_second",
+        help: "Current hashrate of the device",
+        kind: "gauge",
+        // Stats report hashrate in GH/s
+        value: |s| Some(s.hashrate_mhs * 1e9),
+    },
+    DeviceMetric {
+        name: "axectl_device_temperature_celsius",
+        help: "ASIC temperature of the device",
+        kind: "gauge",
+        value: |s| Some(s.temperature_celsius),
+    },
+    DeviceMetric {
+        name: "axectl_device_power_watts",
+        help: "Power consumption of the device",
+        kind: "gauge",
+        value: |s| Some(s.power_watts),
+    },
+    DeviceMetric {
+        name: "axectl_device_fan_speed_rpm",
+        help: "Fan speed reported by the device",
+        kind: "gauge",
+        value: |s| Some(f64::from(s.fan_speed_rpm)),
+    },
+    DeviceMetric {
+        name: "axectl_device_shares_accepted_total",
+        help: "Shares accepted by the pool since the device booted",
+        kind: "counter",
+        value: |s| Some(s.shares_accepted as f64),
+    },
+    DeviceMetric {
+        name: "axectl_device_shares_rejected_total",
+        help: "Shares rejected by the pool since the device booted",
+        kind: "counter",
+        value: |s| Some(s.shares_rejected as f64),
+    },
+    DeviceMetric {
+        name: "axectl_device_uptime_seconds",
+        help: "Time since the device booted",
+        kind: "gauge",
+        value: |s| Some(s.uptime_seconds as f64),
+    },
+    DeviceMetric {
+        name: "axectl_device_wifi_rssi_dbm",
+        help: "WiFi signal strength of the device",
+        kind: "gauge",
+        value: |s| s.wifi_rssi.map(f64::from),
+    },
+    DeviceMetric {
+        name: "axectl_device_voltage_volts",
+        help: "Voltage reported by the device",
+        kind: "gauge",
+        // Stats report voltage in mV
+        value: |s| s.voltage.map(|mv| mv / 1000.0),
+    },
+    DeviceMetric {
+        name: "axectl_device_frequency_hertz",
+        help: "ASIC frequency of the device",
+        kind: "gauge",
+        // Stats report frequency in MHz
+        value: |s| s.frequency.map(|mhz| f64::from(mhz) * 1e6),
+    },
+    DeviceMetric {
+        name: "axectl_device_expected_hashrate_hashes_per_second",
+        help: "Theoretical hashrate from frequency, small cores and ASIC count",
+        kind: "gauge",
+        value: |s| s.expected_hashrate_mhs.map(|ghs| ghs * 1e9),
+    },
+    DeviceMetric {
+        name: "axectl_device_hashrate_health_ratio",
+        help: "Actual hashrate as a fraction of the expected hashrate",
+        kind: "gauge",
+        value: |s| s.hashrate_health_percent.map(|percent| percent / 100.0),
+    },
+];
+
+/// Per-type gauge exported from the type summaries
+struct TypeMetric {
+    name: &'static str,
+    help: &'static str,
+    value: fn(&TypeSummary) -> f64,
+}
+
+const TYPE_METRICS: &[TypeMetric] = &[
+    TypeMetric {
+        name: "axectl_type_hashrate_hashes_per_second",
+        help: "Total hashrate of online devices per device type",
+        value: |s| s.total_hashrate_mhs * 1e9,
+    },
+    TypeMetric {
+        name: "axectl_type_power_watts",
+        help: "Total power consumption of online devices per device type",
+        value: |s| s.total_power_watts,
+    },
+    TypeMetric {
+        name: "axectl_type_temperature_celsius",
+        help: "Average temperature of online devices per device type",
+        value: |s| s.average_temperature,
+    },
+];
+
+/// Render device metrics plus swarm and per-type summaries in the Prometheus text format
+pub fn render_prometheus_metrics(
+    devices: &[Device],
+    swarm_summary: &SwarmSummary,
+    type_summaries: &[TypeSummary],
+) -> String {
+    let mut sorted_devices: Vec<_> = devices.iter().collect();
+    sorted_devices.sort_by(|a, b| compare_str(&a.name, &b.name));
+
+    let mut type_summaries: Vec<_> = type_summaries.iter().collect();
+    type_summaries.sort_by(|a, b| a.type_name.cmp(&b.type_name));
+
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "axectl_device_up",
+        "Whether the device responded to the last poll",
+        "gauge",
+    );
+    for device in &sorted_devices {
+        let up = device.status == DeviceStatus::Online && device.stats.is_some();
+        write_sample(
+            &mut out,
+            "axectl_device_up",
+            &device_labels(device),
+            if up { 1.0 } else { 0.0 },
+        );
+    }
+
+    for metric in DEVICE_METRICS {
+        let samples: Vec<_> = sorted_devices
+            .iter()
+            .filter(|device| device.status == DeviceStatus::Online)
+            .filter_map(|device| {
+                let stats = device.stats.as_ref()?;
+                let value = (metric.value)(stats)?;
+                Some((device_labels(device), value))
+            })
+            .collect();
+
+        if samples.is_empty() {
+            continue;
+        }
+
+        write_header(&mut out, metric.name, metric.help, metric.kind);
+        for (labels, value) in samples {
+            write_sample(&mut out, metric.name, &labels, value);
+        }
+    }
+
+    write_header(
+        &mut out,
+        "axectl_swarm_devices",
+        "Number of monitored devices by status",
+        "gauge",
+    );
+    write_sample(
+        &mut out,
+        "axectl_swarm_devices",
+        &[("status", "online")],
+        swarm_summary.devices_online as f64,
+    );
+    write_sample(
+        &mut out,
+        "axectl_swarm_devices",
+        &[("status", "offline")],
+        swarm_summary.devices_offline as f64,
+    );
+
+    let swarm_gauges = [
+        (
+            "axectl_swarm_hashrate_hashes_per_second",
+            "Total hashrate of all online devices",
+            swarm_summary.total_hashrate_mhs * 1e9,
+        ),
+        (
+            "axectl_swarm_power_watts",
+            "Total power consumption of all online devices",
+            swarm_summary.total_power_watts,
+        ),
+        (
+            "axectl_swarm_temperature_celsius",
+            "Average temperature of all online devices",
+            swarm_summary.average_temperature,
+        ),
+        (
+            "axectl_swarm_efficiency_hashes_per_joule",
+            "Total hashrate divided by total power",
+            swarm_summary.average_efficiency * 1e9,
+        ),
+    ];
+    for (name, help, value) in swarm_gauges {
+        write_header(&mut out, name, help, "gauge");
+        write_sample(&mut out, name, &[], value);
+    }
+
+    if !type_summaries.is_empty() {
+        write_header(
+            &mut out,
+            "axectl_type_devices",
+            "Number of monitored devices per device type and status",
+            "gauge",
+        );
+        for summary in &type_summaries {
+            let device_type = summary.device_type.cli_name();
+            write_sample(
+                &mut out,
+                "axectl_type_devices",
+                &[("device_type", device_type), ("status", "online")],
+                summary.devices_online as f64,
+            );
+            write_sample(
+                &mut out,
+                "axectl_type_devices",
+                &[("device_type", device_type), ("status", "offline")],
+                summary.devices_offline as f64,
+            );
+        }
+
+        for metric in TYPE_METRICS {
+            write_header(&mut out, metric.name, metric.help, "gauge");
+            for summary in &type_summaries {
+                write_sample(
+                    &mut out,
+                    metric.name,
+                    &[("device_type", summary.device_type.cli_name())],
+                    (metric.value)(summary),
+                );
+            }
+        }
+    }
+
+    out
+}
+
+fn device_labels(device: &Device) -> Vec<(&'static str, &str)> {
+    vec![
+        ("hostname", device.name.as_str()),
+        ("ip", device.ip_address.as_str()),
+        ("device_type", device.device_type.cli_name()),
+        (
+            "pool",
+            device
+                .stats
+                .as_ref()
+                .and_then(|s| s.pool_url.as_deref())
+                .unwrap_or(""),
+        ),
+    ]
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    // Writing to a String cannot fail
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(key, value)| format!("{key}=\"{value}\"", value = escape_label_value(value)))
+            .collect();
+        let _ = write!(out, "{{{labels}}}", labels = labels.join(","));
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+/// Escape a label value as required by the text exposition format
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::DeviceType;
+    use anyhow::Result;
+    use chrono::Utc;
+
+    fn device(name: &str, ip: &str, stats: Option<DeviceStats>) -> Device {
+        Device {
+            name: name.to_string(),
+            ip_address: ip.to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: if stats.is_some() {
+                DeviceStatus::Online
+            } else {
+                DeviceStatus::Offline
+            },
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats,
+        }
+    }
+
+    fn stats() -> DeviceStats {
+        DeviceStats {
+            timestamp: Utc::now(),
+            hashrate_mhs: 1200.0,
+            temperature_celsius: 62.5,
+            power_watts: 18.0,
+            fan_speed_rpm: 4200,
+            shares_accepted: 150,
+            shares_rejected: 2,
+            uptime_seconds: 3600,
+            pool_url: Some("stratum+tcp://pool.example:3333".to_string()),
+            wifi_rssi: Some(-55),
+            voltage: Some(5100.0),
+            frequency: Some(525),
+            efficiency_jth: None,
+            best_difficulty: None,
+            best_session_difficulty: None,
+            using_fallback_pool: None,
+            expected_hashrate_mhs: None,
+            hashrate_health_percent: None,
+        }
+    }
+
+    #[test]
+    fn test_render_device_metrics() -> Result<()> {
+        // A device that went offline keeps its last stats, which must not be exported
+        let mut offline = device("bitaxe-2", "192.168.1.11", Some(stats()));
+        offline.status = DeviceStatus::Offline;
+        let devices = vec![device("bitaxe-1", "192.168.1.10", Some(stats())), offline];
+        let summary = SwarmSummary::from_devices(&devices);
+        let type_summaries = TypeSummary::from_all_devices(&devices);
+
+        let output = render_prometheus_metrics(&devices, &summary, &type_summaries);
+        let labels = r#"hostname="bitaxe-1",ip="192.168.1.10",device_type="bitaxe-gamma",pool="stratum+tcp://pool.example:3333""#;
+
+        assert!(output.contains("# TYPE axectl_device_up gauge"));
+        assert!(output.contains(&format!("axectl_device_up{{{labels}}} 1\n")));
+        assert!(output.contains(
+            r#"axectl_device_up{hostname="bitaxe-2",ip="192.168.1.11",device_type="bitaxe-gamma",pool="stratum+tcp://pool.example:3333"} 0"#
+        ));
+        assert!(output.contains(&format!(
+            "axectl_device_hashrate_hashes_per_second{{{labels}}} 1200000000000\n"
+        )));
+        assert!(output.contains("# TYPE axectl_device_shares_accepted_total counter"));
+        assert!(output.contains(&format!(
+            "axectl_device_shares_accepted_total{{{labels}}} 150\n"
+        )));
+        assert!(output.contains(&format!("axectl_device_voltage_volts{{{labels}}} 5.1\n")));
+        assert!(output.contains(&format!(
+            "axectl_device_frequency_hertz{{{labels}}} 525000000\n"
+        )));
+        assert!(output.contains(&format!("axectl_device_wifi_rssi_dbm{{{labels}}} -55\n")));
+
+        // Offline devices only export the up metric
+        assert_eq!(output.matches(r#"ip="192.168.1.11""#).count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_summary_metrics() -> Result<()> {
+        let devices = vec![
+            device("bitaxe-1", "192.168.1.10", Some(stats())),
+            device("bitaxe-2", "192.168.1.11", None),
+        ];
+        let summary = SwarmSummary::from_devices(&devices);
+        let type_summaries = TypeSummary::from_all_devices(&devices);
+
+        let output = render_prometheus_metrics(&devices, &summary, &type_summaries);
+
+        assert!(output.contains("axectl_swarm_devices{status=\"online\"} 1\n"));
+        assert!(output.contains("axectl_swarm_devices{status=\"offline\"} 1\n"));
+        assert!(output.contains("axectl_swarm_power_watts 18\n"));
+        assert!(
+            output.contains(
+                "axectl_type_devices{device_type=\"bitaxe-gamma\",status=\"online\"} 1\n"
+            )
+        );
+        assert!(output.contains("axectl_type_power_watts{device_type=\"bitaxe-gamma\"} 18\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() -> Result<()> {
+        let devices = vec![device("rig \"a\"\\b\nc", "192.168.1.10", Some(stats()))];
+        let output = render_prometheus_metrics(&devices, &SwarmSummary::default(), &[]);
+
+        assert!(output.contains(r#"hostname="rig \"a\"\\b\nc""#));
+        assert!(!output.contains("axectl_type_devices"));
+        Ok(())
+    }
+}
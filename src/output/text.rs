@@ -1,5 +1,6 @@
 use colored::*;
 use std::fmt;
+use tabled::settings::{Remove, location::ByColumnName};
 use tabled::{Table, Tabled};
 
 pub trait TextOutput {
@@ -86,6 +87,15 @@ pub fn format_table<T: Tabled>(data: Vec<T>, _color: bool) -> String {
     table.to_string()
 }
 
+/// Format a table, leaving out the columns with the given headers
+pub fn format_table_without<T: Tabled>(data: Vec<T>, columns: &[&str], _color: bool) -> String {
+    let mut table = Table::new(data);
+    for column in columns {
+        table.with(Remove::column(ByColumnName::new(*column)));
+    }
+    table.to_string()
+}
+
 // Helper functions for common formatting
 pub fn format_hashrate(hashrate_mhs: f64) -> String {
     if hashrate_mhs >= 1_000.0 {
@@ -96,6 +106,37 @@ pub fn format_hashrate(hashrate_mhs: f64) -> String {
         format!("{:.2} MH/s", hashrate_mhs)
     }
 }
+/// Format a share or network difficulty with an SI suffix (e.g. "126.27T")
+pub fn format_difficulty(difficulty: f64) -> String {
+    const SUFFIXES: [&str; 7] = ["", "K", "M", "G", "T", "P", "E"];
+
+    let mut value = difficulty;
+    let mut index = 0;
+    while value.abs() >= 1000.0 && index < SUFFIXES.len() - 1 {
+        value /= 1000.0;
+        index += 1;
+    }
+    if index == 0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}{suffix}", suffix = SUFFIXES[index])
+    }
+}
+
+/// Format energy efficiency in J/TH, or "-" when unknown
+pub fn format_efficiency(efficiency_jth: Option<f64>) -> String {
+    efficiency_jth
+        .map(|e| format!("{e:.1} J/TH"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Format hashrate health as a percentage of the expected hashrate, or "-" when unknown
+pub fn format_health(health_percent: Option<f64>) -> String {
+    health_percent
+        .map(|h| format!("{h:.0}%"))
+        .unwrap_or_else(|| "-".to_string())
+}
+
 pub fn format_temperature(temp_celsius: f64, color: bool) -> String {
     let temp_str = format!("{:.1}°C", temp_celsius);
 
@@ -150,3 +191,42 @@ pub fn format_percentage(value: f64, total: f64, color: bool) -> String {
         percent_str.red().to_string()
     }
 }
+
+/// Render values as a unicode sparkline of at most `width` characters.
+/// Longer series are averaged into `width` buckets.
+pub fn format_sparkline(values: &[f64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if values.is_empty() || width == 0 {
+        return String::new();
+    }
+
+    let buckets: Vec<f64> = if values.len() <= width {
+        values.to_vec()
+    } else {
+        (0..width)
+            .map(|i| {
+                let start = i * values.len() / width;
+                let end = ((i + 1) * values.len() / width).max(start + 1);
+                let chunk = &values[start..end];
+                chunk.iter().sum::<f64>() / chunk.len() as f64
+            })
+            .collect()
+    };
+
+    let min = buckets.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    buckets
+        .iter()
+        .map(|value| {
+            if range <= f64::EPSILON {
+                BARS[BARS.len() / 2]
+            } else {
+                let index = ((value - min) / range * (BARS.len() - 1) as f64).round() as usize;
+                BARS[index.min(BARS.len() - 1)]
+            }
+        })
+        .collect()
+}
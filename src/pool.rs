@@ -0,0 +1,545 @@
+use anyhow::{Context, Result, bail, ensure};
+use futures::future::join_all;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{Instant, timeout_at};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::pki_types::ServerName;
+
+use crate::mqtt::tls_client_config;
+
+/// Default time allowed for a whole pool check
+pub const DEFAULT_POOL_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default worker password, most pools ignore it
+pub const DEFAULT_POOL_PASSWORD: &str = "x";
+
+/// How long to wait for `mining.set_difficulty` once all users are authorized
+const DIFFICULTY_GRACE: Duration = Duration::from_secs(2);
+
+/// Name sent to the pool in `mining.subscribe`
+const USER_AGENT: &str = concat!("axectl/", env!("CARGO_PKG_VERSION"));
+
+/// ID of the `mining.subscribe` request, authorizations follow from 2
+const SUBSCRIBE_ID: u64 = 1;
+
+/// A stratum v1 pool, from a device's pool URL and port
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoolEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl PoolEndpoint {
+    /// Parse an AxeOS pool URL (`pool.com`, `stratum+tcp://pool.com`,
+    /// `stratum+ssl://pool.com`) with the separately configured port
+    ///
+    /// A port in the URL itself takes precedence over `port`.
+    pub fn parse(url: &str, port: u16) -> Result<Self> {
+        let url = url.trim();
+        let (tls, rest) = match url.split_once("://") {
+            None => (false, url),
+            Some((scheme, rest)) => match scheme.to_ascii_lowercase().as_str() {
+                "stratum+tcp" | "stratum" | "tcp" => (false, rest),
+                "stratum+ssl" | "stratum+tls" | "stratums" | "ssl" | "tls" => (true, rest),
+                other => bail!("Unsupported pool URL scheme '{other}' in {url}"),
+            },
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, embedded)) if !host.contains(':') || host.ends_with(']') => {
+                let embedded = embedded
+                    .parse()
+                    .with_context(|| format!("Invalid port in pool URL {url}"))?;
+                (host, embedded)
+            }
+            _ => (authority, port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        ensure!(!host.is_empty(), "Pool URL has no host: {url}");
+        ensure!(port != 0, "Pool {host} has no port");
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            tls,
+        })
+    }
+}
+
+impl std::fmt::Display for PoolEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = if self.tls {
+            "stratum+ssl"
+        } else {
+            "stratum+tcp"
+        };
+        write!(
+            f,
+            "{scheme}://{host}:{port}",
+            host = self.host,
+            port = self.port
+        )
+    }
+}
+
+/// A device's pool and worker, to be checked
+#[derive(Debug, Clone)]
+pub struct PoolTarget {
+    pub device: String,
+    pub endpoint: PoolEndpoint,
+    pub user: String,
+}
+
+/// Outcome of authorizing one worker
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolAuthorization {
+    pub user: String,
+    /// Devices mining as this user
+    pub devices: Vec<String>,
+    /// `None` if the pool never answered
+    pub authorized: Option<bool>,
+    pub error: Option<String>,
+}
+
+/// Outcome of checking one pool
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolCheck {
+    pub pool: String,
+    /// Time to open the TCP connection
+    pub connect_ms: Option<f64>,
+    /// Time from sending `mining.subscribe` to its response
+    pub subscribe_ms: Option<f64>,
+    /// Share difficulty from `mining.set_difficulty`
+    pub difficulty: Option<f64>,
+    pub authorizations: Vec<PoolAuthorization>,
+    /// Connection or subscription failure
+    pub error: Option<String>,
+}
+
+impl PoolCheck {
+    /// Whether the pool is reachable and accepted every worker
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+            && self
+                .authorizations
+                .iter()
+                .all(|auth| auth.authorized == Some(true))
+    }
+
+    /// Human readable reasons the check failed
+    pub fn problems(&self) -> Vec<String> {
+        if let Some(ref error) = self.error {
+            return vec![format!("{pool}: {error}", pool = self.pool)];
+        }
+        self.authorizations
+            .iter()
+            .filter(|auth| auth.authorized != Some(true))
+            .map(|auth| {
+                format!(
+                    "{pool}: worker {user} ({devices}) {reason}",
+                    pool = self.pool,
+                    user = auth.user,
+                    devices = auth.devices.join(", "),
+                    reason = auth.error.as_deref().unwrap_or("was not authorized")
+                )
+            })
+            .collect()
+    }
+}
+
+/// Check every distinct pool of the targets concurrently, with one connection
+/// per pool that authorizes each of its workers
+pub async fn check_pools(
+    targets: &[PoolTarget],
+    password: &str,
+    check_timeout: Duration,
+) -> Vec<PoolCheck> {
+    let mut pools: BTreeMap<&PoolEndpoint, BTreeMap<&str, Vec<String>>> = BTreeMap::new();
+    for target in targets {
+        pools
+            .entry(&target.endpoint)
+            .or_default()
+            .entry(&target.user)
+            .or_default()
+            .push(target.device.clone());
+    }
+
+    join_all(pools.into_iter().map(|(endpoint, users)| async move {
+        let users: Vec<(String, Vec<String>)> = users
+            .into_iter()
+            .map(|(user, devices)| (user.to_string(), devices))
+            .collect();
+        check_pool(endpoint, &users, password, check_timeout).await
+    }))
+    .await
+}
+
+/// Connect to a pool, subscribe and authorize each `(user, devices)` worker
+pub async fn check_pool(
+    endpoint: &PoolEndpoint,
+    users: &[(String, Vec<String>)],
+    password: &str,
+    check_timeout: Duration,
+) -> PoolCheck {
+    let mut check = PoolCheck {
+        pool: endpoint.to_string(),
+        connect_ms: None,
+        subscribe_ms: None,
+        difficulty: None,
+        authorizations: users
+            .iter()
+            .map(|(user, devices)| PoolAuthorization {
+                user: user.clone(),
+                devices: devices.clone(),
+                authorized: None,
+                error: None,
+            })
+            .collect(),
+        error: None,
+    };
+    let deadline = Instant::now() + check_timeout;
+
+    let started = Instant::now();
+    let address = (endpoint.host.as_str(), endpoint.port);
+    let stream = match timeout_at(deadline, TcpStream::connect(address)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            check.error = Some(format!("Connection failed: {e}"));
+            return check;
+        }
+        Err(_) => {
+            check.error = Some("Connection timed out".to_string());
+            return check;
+        }
+    };
+    check.connect_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+
+    let result = if endpoint.tls {
+        match timeout_at(deadline, tls_handshake(stream, &endpoint.host)).await {
+            Ok(Ok(stream)) => run_session(stream, &mut check, password, deadline).await,
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(anyhow::anyhow!("TLS handshake timed out")),
+        }
+    } else {
+        run_session(stream, &mut check, password, deadline).await
+    };
+    if let Err(e) = result {
+        check.error = Some(format!("{e:#}"));
+    }
+
+    check
+}
+
+async fn tls_handshake(
+    stream: TcpStream,
+    host: &str,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let name = ServerName::try_from(host.to_string())
+        .with_context(|| format!("Invalid TLS server name: {host}"))?;
+    TlsConnector::from(tls_client_config()?)
+        .connect(name, stream)
+        .await
+        .context("TLS handshake failed")
+}
+
+/// Run `mining.subscribe` and `mining.authorize` on an open connection,
+/// recording the results in `check`
+async fn run_session<S>(
+    stream: S,
+    check: &mut PoolCheck,
+    password: &str,
+    deadline: Instant,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    // Pools accept pipelined requests, so send everything up front
+    let mut requests = vec![json!({
+        "id": SUBSCRIBE_ID,
+        "method": "mining.subscribe",
+        "params": [USER_AGENT],
+    })];
+    requests.extend(check.authorizations.iter().enumerate().map(|(i, auth)| {
+        json!({
+            "id": SUBSCRIBE_ID + 1 + i as u64,
+            "method": "mining.authorize",
+            "params": [auth.user, password],
+        })
+    }));
+    let payload: String = requests
+        .iter()
+        .map(|request| format!("{request}\n"))
+        .collect();
+    let sent = Instant::now();
+    timeout_at(deadline, writer.write_all(payload.as_bytes()))
+        .await
+        .context("Timed out sending requests")?
+        .context("Failed to send requests")?;
+
+    let mut subscribed = false;
+    let mut deadline = deadline;
+    loop {
+        let pending = check
+            .authorizations
+            .iter()
+            .any(|auth| auth.authorized.is_none());
+        if subscribed && !pending && check.difficulty.is_some() {
+            return Ok(());
+        }
+
+        let line = match timeout_at(deadline, lines.next_line()).await {
+            Ok(line) => line.context("Failed to read from pool")?,
+            // Without a difficulty by now the pool just doesn't send one
+            Err(_) if subscribed && !pending => return Ok(()),
+            Err(_) if !subscribed => bail!("Timed out waiting for the subscription"),
+            Err(_) => {
+                for auth in check.authorizations.iter_mut() {
+                    if auth.authorized.is_none() {
+                        auth.error = Some("timed out waiting for authorization".to_string());
+                    }
+                }
+                return Ok(());
+            }
+        };
+        let Some(line) = line else {
+            if subscribed && !pending {
+                return Ok(());
+            }
+            bail!("Pool closed the connection");
+        };
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            tracing::debug!("Ignoring non-JSON line from pool: {line}");
+            continue;
+        };
+
+        match message["method"].as_str() {
+            Some("mining.set_difficulty") => {
+                check.difficulty = message["params"][0].as_f64();
+                continue;
+            }
+            Some(_) => continue,
+            None => {}
+        }
+
+        let Some(id) = message["id"].as_u64() else {
+            continue;
+        };
+        if id == SUBSCRIBE_ID {
+            if let Some(error) = stratum_error(&message) {
+                bail!("Subscription rejected: {error}");
+            }
+            subscribed = true;
+            check.subscribe_ms = Some(sent.elapsed().as_secs_f64() * 1000.0);
+        } else if let Some(auth) = id
+            .checked_sub(SUBSCRIBE_ID + 1)
+            .and_then(|index| check.authorizations.get_mut(index as usize))
+        {
+            let error = stratum_error(&message);
+            auth.authorized = Some(error.is_none() && message["result"] == Value::Bool(true));
+            auth.error = match error {
+                Some(error) => Some(format!("was rejected: {error}")),
+                None if auth.authorized == Some(false) => Some("was rejected".to_string()),
+                None => None,
+            };
+        }
+
+        let all_answered = check
+            .authorizations
+            .iter()
+            .all(|auth| auth.authorized.is_some());
+        if subscribed && all_answered {
+            deadline = deadline.min(Instant::now() + DIFFICULTY_GRACE);
+        }
+    }
+}
+
+/// The error of a stratum response, which pools send as `[code, "message", ...]`
+fn stratum_error(message: &Value) -> Option<String> {
+    match &message["error"] {
+        Value::Null => None,
+        Value::Array(parts) => Some(
+            parts
+                .get(1)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| message["error"].to_string()),
+        ),
+        Value::String(error) => Some(error.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Check the targets and fail with every problem found
+pub async fn ensure_pools_reachable(
+    targets: &[PoolTarget],
+    password: &str,
+    check_timeout: Duration,
+) -> Result<Vec<PoolCheck>> {
+    let checks = check_pools(targets, password, check_timeout).await;
+
+    let problems: Vec<String> = checks.iter().flat_map(PoolCheck::problems).collect();
+    ensure!(
+        problems.is_empty(),
+        "Pool check failed:\n  - {list}",
+        list = problems.join("\n  - ")
+    );
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Stand-in stratum server accepting the workers in `accepted`
+    async fn stratum_server(accepted: &'static [&'static str]) -> Result<PoolEndpoint> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let Ok((stream, _)) = listener.accept().await else {
+                return;
+            };
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap_or_default();
+                let id = request["id"].clone();
+                let replies = match request["method"].as_str() {
+                    Some("mining.subscribe") => vec![json!({
+                        "id": id,
+                        "result": [[["mining.notify", "ae6812eb4cd7735a302a8a9dd95cf71f"]], "08000002", 4],
+                        "error": null,
+                    })],
+                    Some("mining.authorize") => {
+                        let user = request["params"][0].as_str().unwrap_or_default();
+                        if accepted.contains(&user) {
+                            vec![
+                                json!({ "id": null, "method": "mining.set_difficulty", "params": [1024] }),
+                                json!({ "id": id, "result": true, "error": null }),
+                            ]
+                        } else {
+                            vec![
+                                json!({ "id": id, "result": null, "error": [24, "Unauthorized worker", null] }),
+                            ]
+                        }
+                    }
+                    _ => vec![],
+                };
+                for reply in replies {
+                    if writer
+                        .write_all(format!("{reply}\n").as_bytes())
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(PoolEndpoint {
+            host: "127.0.0.1".to_string(),
+            port,
+            tls: false,
+        })
+    }
+
+    #[test]
+    fn parses_pool_urls() -> Result<()> {
+        assert_eq!(
+            PoolEndpoint::parse("stratum+tcp://public-pool.io", 21496)?,
+            PoolEndpoint {
+                host: "public-pool.io".to_string(),
+                port: 21496,
+                tls: false
+            }
+        );
+        let tls = PoolEndpoint::parse("stratum+ssl://pool.example.com:4443", 3333)?;
+        assert!(tls.tls);
+        assert_eq!(tls.port, 4443);
+        assert_eq!(tls.to_string(), "stratum+ssl://pool.example.com:4443");
+        assert_eq!(PoolEndpoint::parse("solo.ckpool.org", 3333)?.port, 3333);
+        assert!(PoolEndpoint::parse("http://pool.example.com", 3333).is_err());
+        assert!(PoolEndpoint::parse("stratum+tcp://", 3333).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn checks_subscription_authorization_and_difficulty() -> Result<()> {
+        let endpoint = stratum_server(&["bc1qgood.bitaxe"]).await?;
+        let targets = vec![
+            PoolTarget {
+                device: "bitaxe".to_string(),
+                endpoint: endpoint.clone(),
+                user: "bc1qgood.bitaxe".to_string(),
+            },
+            PoolTarget {
+                device: "nerdqaxe".to_string(),
+                endpoint: endpoint.clone(),
+                user: "bc1qbad.nerdqaxe".to_string(),
+            },
+        ];
+
+        let checks = check_pools(&targets, DEFAULT_POOL_PASSWORD, Duration::from_secs(5)).await;
+        assert_eq!(checks.len(), 1);
+        let check = &checks[0];
+        assert_eq!(check.error, None);
+        assert!(check.connect_ms.is_some());
+        assert!(check.subscribe_ms.is_some());
+        assert_eq!(check.difficulty, Some(1024.0));
+
+        let good = &check.authorizations[1];
+        assert_eq!(good.user, "bc1qgood.bitaxe");
+        assert_eq!(good.authorized, Some(true));
+        let bad = &check.authorizations[0];
+        assert_eq!(bad.devices, vec!["nerdqaxe".to_string()]);
+        assert_eq!(bad.authorized, Some(false));
+        assert!(!check.is_ok());
+        assert!(check.problems()[0].contains("Unauthorized worker"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_unreachable_pools() -> Result<()> {
+        // Grab a free port and close it again so nothing listens there
+        let port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+        let endpoint = PoolEndpoint {
+            host: "127.0.0.1".to_string(),
+            port,
+            tls: false,
+        };
+
+        let check = check_pool(
+            &endpoint,
+            &[("bc1q.bitaxe".to_string(), vec!["bitaxe".to_string()])],
+            DEFAULT_POOL_PASSWORD,
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(check.connect_ms.is_none());
+        assert!(check.error.is_some());
+        assert!(!check.is_ok());
+
+        let targets = [PoolTarget {
+            device: "bitaxe".to_string(),
+            endpoint,
+            user: "bc1q.bitaxe".to_string(),
+        }];
+        assert!(
+            ensure_pools_reachable(&targets, DEFAULT_POOL_PASSWORD, Duration::from_secs(5))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
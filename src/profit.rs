@@ -0,0 +1,436 @@
+use anyhow::{Context, Result, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::energy::{EnergyEstimate, Tariff};
+
+/// Block subsidy since the April 2024 halving, in BTC
+pub const DEFAULT_BLOCK_REWARD: f64 = 3.125;
+
+/// Expected number of hashes needed to find a share of difficulty 1
+const HASHES_PER_DIFFICULTY: f64 = 4_294_967_296.0;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const DAYS_PER_YEAR: f64 = 365.25;
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+/// Timeout of a single market data request
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Inputs of the profitability estimate as written in a TOML file
+///
+/// Static values are used unless a source or a command line flag provides them.
+///
+/// ```toml
+/// currency = "EUR"
+/// fees = 0.02
+///
+/// [[sources]]
+/// url = "https://blockchain.info/q/getdifficulty"
+/// difficulty = ""          # empty pointer: the whole response is the number
+///
+/// [[sources]]
+/// url = "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=eur"
+/// price = "/bitcoin/eur"   # JSON pointer into the response
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfitConfig {
+    /// Network difficulty
+    pub difficulty: Option<f64>,
+    /// Block subsidy in BTC
+    pub block_reward: Option<f64>,
+    /// Average transaction fees per block in BTC
+    pub fees: Option<f64>,
+    /// BTC price
+    pub price: Option<f64>,
+    /// Currency of the price, shown next to revenue and cost
+    pub currency: Option<String>,
+    /// HTTP endpoints to fetch current values from
+    #[serde(default)]
+    pub sources: Vec<MarketSource>,
+}
+
+/// HTTP endpoint providing some of the market inputs
+///
+/// Each input is located with a JSON pointer (RFC 6901) into the response.
+/// An empty pointer takes the whole response body as a plain number.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketSource {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub difficulty: Option<String>,
+    pub block_reward: Option<String>,
+    pub fees: Option<String>,
+    pub price: Option<String>,
+}
+
+/// Partially known market inputs, merged from flags, sources and the config file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MarketInputs {
+    pub difficulty: Option<f64>,
+    pub block_reward: Option<f64>,
+    pub fees: Option<f64>,
+    pub price: Option<f64>,
+}
+
+impl MarketInputs {
+    /// Fill the inputs that are still missing from `fallback`
+    pub fn or(self, fallback: MarketInputs) -> Self {
+        Self {
+            difficulty: self.difficulty.or(fallback.difficulty),
+            block_reward: self.block_reward.or(fallback.block_reward),
+            fees: self.fees.or(fallback.fees),
+            price: self.price.or(fallback.price),
+        }
+    }
+}
+
+impl ProfitConfig {
+    /// Load the profitability config from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read profit config file {path}",
+                path = path.display()
+            )
+        })?;
+        Self::parse(&content)
+            .with_context(|| format!("Invalid profit config file {path}", path = path.display()))
+    }
+
+    /// Parse the profitability config from TOML
+    pub fn parse(content: &str) -> Result<Self> {
+        let config: Self = toml::from_str(content).context("Failed to parse TOML")?;
+        for source in &config.sources {
+            url::Url::parse(&source.url)
+                .with_context(|| format!("Invalid source URL '{url}'", url = source.url))?;
+            ensure!(
+                source.difficulty.is_some()
+                    || source.block_reward.is_some()
+                    || source.fees.is_some()
+                    || source.price.is_some(),
+                "Source {url} does not provide any value",
+                url = source.url
+            );
+        }
+        Ok(config)
+    }
+
+    /// Values set directly in the file
+    pub fn inputs(&self) -> MarketInputs {
+        MarketInputs {
+            difficulty: self.difficulty,
+            block_reward: self.block_reward,
+            fees: self.fees,
+            price: self.price,
+        }
+    }
+
+    /// Fetch the values provided by the configured sources; earlier sources win
+    pub async fn fetch(&self) -> Result<MarketInputs> {
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        let mut inputs = MarketInputs::default();
+        for source in &self.sources {
+            inputs = inputs.or(fetch_source(&client, source).await?);
+        }
+        Ok(inputs)
+    }
+}
+
+/// Fetch the inputs one source provides
+pub async fn fetch_source(client: &reqwest::Client, source: &MarketSource) -> Result<MarketInputs> {
+    let mut request = client.get(&source.url);
+    for (name, value) in &source.headers {
+        request = request.header(name, value);
+    }
+    let body = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to fetch market data from {url}", url = source.url))?
+        .text()
+        .await
+        .with_context(|| format!("Failed to read market data from {url}", url = source.url))?;
+
+    let json: Option<serde_json::Value> = serde_json::from_str(&body).ok();
+    let extract = |pointer: &Option<String>| -> Result<Option<f64>> {
+        pointer
+            .as_deref()
+            .map(|pointer| {
+                extract_number(&body, json.as_ref(), pointer).with_context(|| {
+                    format!("Failed to read '{pointer}' from {url}", url = source.url)
+                })
+            })
+            .transpose()
+    };
+
+    Ok(MarketInputs {
+        difficulty: extract(&source.difficulty)?,
+        block_reward: extract(&source.block_reward)?,
+        fees: extract(&source.fees)?,
+        price: extract(&source.price)?,
+    })
+}
+
+/// Read a number from a response, either the whole body or a JSON pointer into it
+fn extract_number(body: &str, json: Option<&serde_json::Value>, pointer: &str) -> Result<f64> {
+    if pointer.is_empty() {
+        return body
+            .trim()
+            .trim_matches('"')
+            .parse()
+            .context("Response is not a number");
+    }
+
+    let value = json
+        .context("Response is not JSON")?
+        .pointer(pointer)
+        .context("No value at this pointer")?;
+    match value {
+        serde_json::Value::Number(number) => number.as_f64().context("Number out of range"),
+        serde_json::Value::String(text) => text.trim().parse().context("Value is not a number"),
+        other => bail!("Value is not a number: {other}"),
+    }
+}
+
+/// Complete market inputs used for an estimate
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarketData {
+    pub difficulty: f64,
+    pub block_reward: f64,
+    pub fees: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+impl MarketData {
+    /// Validate merged inputs, defaulting the block reward and fees
+    pub fn from_inputs(inputs: MarketInputs, currency: Option<String>) -> Result<Self> {
+        let difficulty = inputs.difficulty.context(
+            "Network difficulty is unknown; pass --difficulty or configure a source providing it",
+        )?;
+        ensure!(
+            difficulty.is_finite() && difficulty > 0.0,
+            "Network difficulty must be positive, got {difficulty}"
+        );
+
+        let block_reward = inputs.block_reward.unwrap_or(DEFAULT_BLOCK_REWARD);
+        let fees = inputs.fees.unwrap_or_default();
+        for (name, value) in [("Block reward", block_reward), ("Fees", fees)]
+            .into_iter()
+            .chain(inputs.price.map(|p| ("BTC price", p)))
+        {
+            ensure!(
+                value.is_finite() && value >= 0.0,
+                "{name} must be a non-negative number, got {value}"
+            );
+        }
+
+        Ok(Self {
+            difficulty,
+            block_reward,
+            fees,
+            price: inputs.price,
+            currency,
+        })
+    }
+
+    /// BTC paid out for a block
+    pub fn block_value(&self) -> f64 {
+        self.block_reward + self.fees
+    }
+
+    /// Estimate earnings, power cost and solo odds for a hashrate in GH/s
+    pub fn estimate(
+        &self,
+        hashrate_ghs: f64,
+        power_watts: f64,
+        tariff: Option<&Tariff>,
+    ) -> Estimate {
+        let hashes_per_day = hashrate_ghs.max(0.0) * 1e9 * SECONDS_PER_DAY;
+        let blocks_per_day = hashes_per_day / (self.difficulty * HASHES_PER_DIFFICULTY);
+        let btc_per_day = blocks_per_day * self.block_value();
+        let revenue_per_day = self.price.map(|price| btc_per_day * price);
+        let energy = EnergyEstimate::from_power(power_watts, tariff);
+
+        Estimate {
+            hashrate_ghs,
+            power_watts,
+            blocks_per_day,
+            sats_per_day: btc_per_day * SATS_PER_BTC,
+            revenue_per_day,
+            energy,
+            profit_per_day: revenue_per_day
+                .zip(energy.cost_per_day)
+                .map(|(revenue, cost)| revenue - cost),
+            solo_chance_day: solo_chance(blocks_per_day),
+            solo_chance_year: solo_chance(blocks_per_day * DAYS_PER_YEAR),
+            expected_days_to_block: (blocks_per_day > 0.0).then(|| 1.0 / blocks_per_day),
+        }
+    }
+}
+
+/// Probability of finding at least one block when `expected_blocks` are expected
+pub fn solo_chance(expected_blocks: f64) -> f64 {
+    // Block finding is a Poisson process
+    -(-expected_blocks).exp_m1()
+}
+
+/// Expected earnings and costs of some hashrate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Estimate {
+    pub hashrate_ghs: f64,
+    pub power_watts: f64,
+    pub blocks_per_day: f64,
+    /// Expected earnings in satoshis per day, as a share of the block value
+    pub sats_per_day: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revenue_per_day: Option<f64>,
+    pub energy: EnergyEstimate,
+    /// Revenue minus power cost, when both the price and a tariff are known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profit_per_day: Option<f64>,
+    /// Chance of solo mining a block within a day
+    pub solo_chance_day: f64,
+    /// Chance of solo mining a block within a year
+    pub solo_chance_year: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_days_to_block: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_matches_expected_block_rate() -> Result<()> {
+        let market = MarketData::from_inputs(
+            MarketInputs {
+                difficulty: Some(100e12),
+                price: Some(50_000.0),
+                fees: Some(0.125),
+                ..Default::default()
+            },
+            Some("EUR".to_string()),
+        )?;
+        assert_eq!(market.block_value(), 3.25);
+
+        // 1.2 TH/s at 20 W with power at 0.25 per kWh
+        let tariff = Tariff::flat(0.25)?;
+        let estimate = market.estimate(1200.0, 20.0, Some(&tariff));
+
+        let blocks_per_day = 1.2e12 * 86_400.0 / (100e12 * 4_294_967_296.0);
+        assert!((estimate.blocks_per_day - blocks_per_day).abs() < 1e-15);
+        assert!((estimate.sats_per_day - blocks_per_day * 3.25 * 1e8).abs() < 1e-6);
+        let revenue = blocks_per_day * 3.25 * 50_000.0;
+        assert!((estimate.revenue_per_day.unwrap_or_default() - revenue).abs() < 1e-9);
+        assert!((estimate.profit_per_day.unwrap_or_default() - (revenue - 0.12)).abs() < 1e-9);
+        assert!((estimate.solo_chance_day - blocks_per_day).abs() < 1e-12);
+        assert!(estimate.solo_chance_year > estimate.solo_chance_day * 300.0);
+        assert!(estimate.solo_chance_year < 1.0);
+
+        // Without a tariff there is no profit, just revenue
+        assert!(market.estimate(1200.0, 20.0, None).profit_per_day.is_none());
+
+        assert!((solo_chance(1.0) - (1.0 - (-1.0f64).exp())).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn inputs_require_difficulty_and_valid_values() -> Result<()> {
+        assert!(MarketData::from_inputs(MarketInputs::default(), None).is_err());
+        assert!(
+            MarketData::from_inputs(
+                MarketInputs {
+                    difficulty: Some(1e12),
+                    price: Some(-1.0),
+                    ..Default::default()
+                },
+                None
+            )
+            .is_err()
+        );
+
+        let flags = MarketInputs {
+            difficulty: Some(2.0),
+            ..Default::default()
+        };
+        let config = ProfitConfig::parse("difficulty = 1.0\nprice = 60000.0")?;
+        let market = MarketData::from_inputs(flags.or(config.inputs()), None)?;
+        assert_eq!(market.difficulty, 2.0);
+        assert_eq!(market.price, Some(60_000.0));
+        assert_eq!(market.block_reward, DEFAULT_BLOCK_REWARD);
+        assert_eq!(market.fees, 0.0);
+
+        assert!(ProfitConfig::parse("difficulty = 1.0\nunknown = 2").is_err());
+        assert!(ProfitConfig::parse("[[sources]]\nurl = \"http://example.com\"").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetches_inputs_from_mocked_sources() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let difficulty = server
+            .mock("GET", "/q/getdifficulty")
+            .with_body("126271255279307.3\n")
+            .create_async()
+            .await;
+        let stats = server
+            .mock("GET", "/stats")
+            .match_header("x-api-key", "secret")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"bitcoin": {"eur": "61234.5"}, "fees": {"avg_block": 0.021}, "difficulty": 1}"#,
+            )
+            .create_async()
+            .await;
+
+        let config = ProfitConfig::parse(&format!(
+            r#"
+            difficulty = 5.0
+            currency = "EUR"
+
+            [[sources]]
+            url = "{base}/q/getdifficulty"
+            difficulty = ""
+
+            [[sources]]
+            url = "{base}/stats"
+            headers = {{ "x-api-key" = "secret" }}
+            price = "/bitcoin/eur"
+            fees = "/fees/avg_block"
+            difficulty = "/difficulty"
+            "#,
+            base = server.url()
+        ))?;
+
+        let fetched = config.fetch().await?;
+        difficulty.assert_async().await;
+        stats.assert_async().await;
+
+        // The first source wins, and fetched values beat static ones
+        let market = MarketData::from_inputs(fetched.or(config.inputs()), config.currency)?;
+        assert_eq!(market.difficulty, 126_271_255_279_307.3);
+        assert_eq!(market.price, Some(61_234.5));
+        assert_eq!(market.fees, 0.021);
+        assert_eq!(market.currency.as_deref(), Some("EUR"));
+
+        let broken = ProfitConfig::parse(&format!(
+            "[[sources]]\nurl = \"{base}/stats\"\nheaders = {{ \"x-api-key\" = \"secret\" }}\nprice = \"/bitcoin/usd\"",
+            base = server.url()
+        ))?;
+        assert!(broken.fetch().await.is_err());
+        Ok(())
+    }
+}
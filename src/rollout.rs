@@ -0,0 +1,451 @@
+use anyhow::{Context, Result, bail, ensure};
+use futures::future::join_all;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::time::{Instant, sleep};
+
+use crate::api::{AxeOsClient, Device};
+
+/// Settings for a staged firmware rollout
+#[derive(Debug, Clone)]
+pub struct RolloutConfig {
+    /// Number of devices updated in the first (canary) wave
+    pub canary: usize,
+    /// Number of devices updated in parallel in each following wave
+    pub wave_size: usize,
+    /// How long to wait for a device to come back with the new firmware
+    pub reboot_timeout: Duration,
+    /// How long to wait for the hashrate to recover after the reboot
+    pub recovery_timeout: Duration,
+    /// Minimum hashrate after the update, as a percentage of the hashrate before it
+    pub min_hashrate_percent: f64,
+    /// Delay between device polls while waiting on a gate
+    pub poll_interval: Duration,
+}
+
+impl Default for RolloutConfig {
+    fn default() -> Self {
+        Self {
+            canary: 1,
+            wave_size: 5,
+            reboot_timeout: Duration::from_secs(300),
+            recovery_timeout: Duration::from_secs(600),
+            min_hashrate_percent: 80.0,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutStatus {
+    /// Firmware changed and the hashrate recovered
+    Updated,
+    /// The update or one of its gates failed
+    Failed,
+    /// Not attempted because the rollout halted
+    Skipped,
+}
+
+/// Outcome of the rollout for one device
+#[derive(Debug, Clone, Serialize)]
+pub struct RolloutDeviceResult {
+    pub device: String,
+    pub ip: String,
+    pub wave: usize,
+    pub status: RolloutStatus,
+    pub version_before: Option<String>,
+    pub version_after: Option<String>,
+    pub hashrate_before: Option<f64>,
+    pub hashrate_after: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// Progress notifications emitted while the rollout runs
+#[derive(Debug, Clone)]
+pub enum RolloutEvent<'a> {
+    WaveStarted { wave: usize, devices: &'a [Device] },
+    DeviceFinished(&'a RolloutDeviceResult),
+    Halted { wave: usize },
+}
+
+/// Split devices into the canary wave followed by waves of `wave_size`
+pub fn plan_waves(devices: &[Device], canary: usize, wave_size: usize) -> Vec<&[Device]> {
+    let canary = canary.min(devices.len());
+    let (canary_devices, rest) = devices.split_at(canary);
+
+    let mut waves = Vec::new();
+    if !canary_devices.is_empty() {
+        waves.push(canary_devices);
+    }
+    waves.extend(rest.chunks(wave_size.max(1)));
+    waves
+}
+
+/// Update devices wave by wave, halting as soon as a device fails its gates
+pub async fn run_rollout<F>(
+    devices: &[Device],
+    firmware_url: &str,
+    config: &RolloutConfig,
+    mut on_event: F,
+) -> Vec<RolloutDeviceResult>
+where
+    F: FnMut(RolloutEvent<'_>),
+{
+    let waves = plan_waves(devices, config.canary, config.wave_size);
+    let mut results = Vec::new();
+    let mut halted = false;
+
+    for (index, wave_devices) in waves.iter().enumerate() {
+        let wave = index + 1;
+
+        if halted {
+            results.extend(wave_devices.iter().map(|device| RolloutDeviceResult {
+                device: device.name.clone(),
+                ip: device.ip_address.clone(),
+                wave,
+                status: RolloutStatus::Skipped,
+                version_before: None,
+                version_after: None,
+                hashrate_before: None,
+                hashrate_after: None,
+                error: None,
+            }));
+            continue;
+        }
+
+        on_event(RolloutEvent::WaveStarted {
+            wave,
+            devices: wave_devices,
+        });
+
+        let wave_results = join_all(
+            wave_devices
+                .iter()
+                .map(|device| update_device(device, firmware_url, wave, config)),
+        )
+        .await;
+
+        for result in &wave_results {
+            on_event(RolloutEvent::DeviceFinished(result));
+        }
+
+        if wave_results
+            .iter()
+            .any(|r| r.status == RolloutStatus::Failed)
+        {
+            halted = true;
+            on_event(RolloutEvent::Halted { wave });
+        }
+
+        results.extend(wave_results);
+    }
+
+    results
+}
+
+/// Update a single device and check its gates
+async fn update_device(
+    device: &Device,
+    firmware_url: &str,
+    wave: usize,
+    config: &RolloutConfig,
+) -> RolloutDeviceResult {
+    let mut result = RolloutDeviceResult {
+        device: device.name.clone(),
+        ip: device.ip_address.clone(),
+        wave,
+        status: RolloutStatus::Failed,
+        version_before: None,
+        version_after: None,
+        hashrate_before: None,
+        hashrate_after: None,
+        error: None,
+    };
+
+    match update_device_inner(device, firmware_url, config, &mut result).await {
+        Ok(()) => result.status = RolloutStatus::Updated,
+        Err(e) => result.error = Some(format!("{e:#}")),
+    }
+
+    result
+}
+
+async fn update_device_inner(
+    device: &Device,
+    firmware_url: &str,
+    config: &RolloutConfig,
+    result: &mut RolloutDeviceResult,
+) -> Result<()> {
+    let client = AxeOsClient::with_timeout(
+        &device.ip_address,
+        config.poll_interval.max(Duration::from_secs(10)),
+    )?;
+
+    let (info, stats) = client
+        .get_complete_info()
+        .await
+        .context("Failed to read device state before the update")?;
+    let version_before = info.firmware_version;
+    let hashrate_before = stats.hashrate;
+    result.version_before = Some(version_before.clone());
+    result.hashrate_before = Some(hashrate_before);
+
+    let started = client.update_firmware(firmware_url).await?;
+    ensure!(started.success, "{message}", message = started.message);
+
+    // Gate 1: the device reboots into a different firmware version
+    let deadline = Instant::now() + config.reboot_timeout;
+    let version_after = loop {
+        sleep(config.poll_interval).await;
+
+        if let Ok(info) = client.get_system_info().await {
+            result.version_after = Some(info.firmware_version.clone());
+            if info.firmware_version != version_before {
+                break info.firmware_version;
+            }
+        }
+
+        if Instant::now() >= deadline {
+            match result.version_after {
+                Some(ref version) => bail!(
+                    "Firmware version is still {version} after {secs}s",
+                    secs = config.reboot_timeout.as_secs()
+                ),
+                None => bail!(
+                    "Device did not come back within {secs}s",
+                    secs = config.reboot_timeout.as_secs()
+                ),
+            }
+        }
+    };
+    tracing::debug!(
+        "{name} now runs firmware {version_after}",
+        name = device.name
+    );
+
+    // Gate 2: the hashrate recovers to the configured share of its previous value
+    let required = if hashrate_before > 0.0 {
+        hashrate_before * config.min_hashrate_percent / 100.0
+    } else {
+        0.0
+    };
+    let deadline = Instant::now() + config.recovery_timeout;
+    loop {
+        if let Ok(stats) = client.get_system_stats().await {
+            result.hashrate_after = Some(stats.hashrate);
+            if stats.hashrate > 0.0 && stats.hashrate >= required {
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            bail!(
+                "Hashrate did not recover to {required:.0} GH/s within {secs}s (last {last})",
+                secs = config.recovery_timeout.as_secs(),
+                last = result
+                    .hashrate_after
+                    .map(|h| format!("{h:.0} GH/s"))
+                    .unwrap_or_else(|| "unknown".to_string())
+            );
+        }
+
+        sleep(config.poll_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DeviceStatus, DeviceType};
+    use chrono::Utc;
+    use mockito::{Mock, ServerGuard};
+
+    fn device(name: &str, ip: &str) -> Device {
+        Device {
+            name: name.to_string(),
+            ip_address: ip.to_string(),
+            device_type: DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        }
+    }
+
+    fn info_body(version: &str, hashrate: f64) -> String {
+        serde_json::json!({
+            "ASICModel": "BM1370",
+            "boardVersion": "601",
+            "version": version,
+            "macAddr": "AA:BB:CC:DD:EE:FF",
+            "hostname": "bitaxe",
+            "stratumURL": "public-pool.io",
+            "stratumPort": 21496,
+            "stratumUser": "bc1qexample.bitaxe",
+            "frequency": 525,
+            "voltage": 1150,
+            "fanspeed": 80,
+            "temp": 55.0,
+            "power": 15.0,
+            "hashRate": hashrate,
+            "uptimeSeconds": 3600,
+            "sharesAccepted": 10,
+            "sharesRejected": 0
+        })
+        .to_string()
+    }
+
+    fn test_config() -> RolloutConfig {
+        RolloutConfig {
+            canary: 1,
+            wave_size: 2,
+            reboot_timeout: Duration::from_millis(300),
+            recovery_timeout: Duration::from_millis(300),
+            min_hashrate_percent: 80.0,
+            poll_interval: Duration::from_millis(20),
+        }
+    }
+
+    /// Mock device that reports `before` until the OTA request, then `after`
+    async fn mock_device(
+        server: &mut ServerGuard,
+        before: (&str, f64),
+        after: (&str, f64),
+    ) -> Vec<Mock> {
+        let before_info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_body(info_body(before.0, before.1))
+            .expect(2)
+            .create_async()
+            .await;
+        let ota = server
+            .mock("POST", "/api/system/OTA")
+            .with_status(200)
+            .create_async()
+            .await;
+        let after_info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_body(info_body(after.0, after.1))
+            .create_async()
+            .await;
+        vec![before_info, ota, after_info]
+    }
+
+    #[test]
+    fn test_plan_waves() {
+        let devices: Vec<_> = (1..=6)
+            .map(|i| device(&format!("bitaxe-{i}"), &format!("192.168.1.{i}")))
+            .collect();
+
+        let sizes: Vec<_> = plan_waves(&devices, 1, 2).iter().map(|w| w.len()).collect();
+        assert_eq!(sizes, vec![1, 2, 2, 1]);
+
+        let sizes: Vec<_> = plan_waves(&devices, 0, 4).iter().map(|w| w.len()).collect();
+        assert_eq!(sizes, vec![4, 2]);
+
+        let sizes: Vec<_> = plan_waves(&devices, 10, 4)
+            .iter()
+            .map(|w| w.len())
+            .collect();
+        assert_eq!(sizes, vec![6]);
+    }
+
+    #[tokio::test]
+    async fn test_rollout_updates_all_waves() -> Result<()> {
+        let mut canary = mockito::Server::new_async().await;
+        let mut second = mockito::Server::new_async().await;
+        let _canary_mocks = mock_device(&mut canary, ("2.3.0", 1000.0), ("2.4.0", 950.0)).await;
+        let _second_mocks = mock_device(&mut second, ("2.3.0", 1100.0), ("2.4.0", 1050.0)).await;
+
+        let devices = vec![
+            device("bitaxe-1", &canary.host_with_port()),
+            device("bitaxe-2", &second.host_with_port()),
+        ];
+
+        let mut waves_started = Vec::new();
+        let results = run_rollout(
+            &devices,
+            "http://fw/esp-miner.bin",
+            &test_config(),
+            |event| {
+                if let RolloutEvent::WaveStarted { wave, .. } = event {
+                    waves_started.push(wave);
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(waves_started, vec![1, 2]);
+        assert!(results.iter().all(|r| r.status == RolloutStatus::Updated));
+        assert_eq!(results[0].version_before.as_deref(), Some("2.3.0"));
+        assert_eq!(results[0].version_after.as_deref(), Some("2.4.0"));
+        assert_eq!(results[1].hashrate_after, Some(1050.0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rollout_halts_when_canary_fails() -> Result<()> {
+        let mut canary = mockito::Server::new_async().await;
+        let mut second = mockito::Server::new_async().await;
+        // The canary reboots into the new firmware but never hashes again
+        let _canary_mocks = mock_device(&mut canary, ("2.3.0", 1000.0), ("2.4.0", 0.0)).await;
+        let second_ota = second
+            .mock("POST", "/api/system/OTA")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let devices = vec![
+            device("bitaxe-1", &canary.host_with_port()),
+            device("bitaxe-2", &second.host_with_port()),
+        ];
+
+        let mut halted_at = None;
+        let results = run_rollout(
+            &devices,
+            "http://fw/esp-miner.bin",
+            &test_config(),
+            |event| {
+                if let RolloutEvent::Halted { wave } = event {
+                    halted_at = Some(wave);
+                }
+            },
+        )
+        .await;
+
+        second_ota.assert_async().await;
+        assert_eq!(halted_at, Some(1));
+        assert_eq!(results[0].status, RolloutStatus::Failed);
+        assert!(
+            results[0]
+                .error
+                .as_deref()
+                .is_some_and(|e| e.contains("Hashrate did not recover"))
+        );
+        assert_eq!(results[1].status, RolloutStatus::Skipped);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rollout_fails_when_version_unchanged() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server, ("2.3.0", 1000.0), ("2.3.0", 1000.0)).await;
+
+        let devices = vec![device("bitaxe-1", &server.host_with_port())];
+        let results =
+            run_rollout(&devices, "http://fw/esp-miner.bin", &test_config(), |_| {}).await;
+
+        assert_eq!(results[0].status, RolloutStatus::Failed);
+        assert!(
+            results[0]
+                .error
+                .as_deref()
+                .is_some_and(|e| e.contains("still 2.3.0"))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use crate::api::{AxeOsClient, SystemInfoResponse, SystemUpdateRequest};
+use crate::hardware::HardwareDb;
+use anyhow::{Context, Result, ensure};
+
+/// Longest hostname AxeOS accepts
+const MAX_HOSTNAME_LEN: usize = 32;
+
+/// Parse a JSON settings update, rejecting keys the device would silently ignore
+pub fn parse_update_request(json: &str) -> Result<SystemUpdateRequest> {
+    serde_json::from_str(json).context("Invalid settings JSON")
+}
+
+/// Whether the update needs the device's ASIC model to be validated
+pub fn needs_asic_model(request: &SystemUpdateRequest) -> bool {
+    request.frequency_value.is_some() || request.voltage_value.is_some()
+}
+
+/// Everything wrong with an update regardless of the device it is sent to
+pub fn setting_problems(request: &SystemUpdateRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(hostname) = &request.hostname
+        && (hostname.is_empty()
+            || hostname.len() > MAX_HOSTNAME_LEN
+            || hostname.starts_with('-')
+            || !hostname
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-'))
+    {
+        problems.push(format!(
+            "Hostname '{hostname}' must be 1-{MAX_HOSTNAME_LEN} letters, digits or hyphens, not starting with a hyphen"
+        ));
+    }
+    for (name, url) in [
+        ("Pool URL", &request.pool_url),
+        ("Fallback pool URL", &request.fallback_pool_url),
+    ] {
+        if url.as_deref().is_some_and(|url| url.trim().is_empty()) {
+            problems.push(format!("{name} must not be empty"));
+        }
+    }
+    for (name, port) in [
+        ("Pool port", request.pool_port),
+        ("Fallback pool port", request.fallback_pool_port),
+    ] {
+        if port == Some(0) {
+            problems.push(format!("{name} must not be 0"));
+        }
+    }
+    if let Some(speed) = request.fan_speed
+        && speed > 100
+    {
+        problems.push(format!("Fan speed {speed}% is above 100%"));
+    }
+    for (name, flag) in [
+        ("autofanspeed", request.auto_fan_speed),
+        ("flipscreen", request.flip_screen),
+        ("invertscreen", request.invert_screen),
+    ] {
+        if let Some(value) = flag
+            && value > 1
+        {
+            problems.push(format!("{name} must be 0 or 1, got {value}"));
+        }
+    }
+    if let Some(timeout) = request.display_timeout
+        && timeout < -1
+    {
+        problems.push(format!(
+            "Display timeout must be -1 (always on) or a number of minutes, got {timeout}"
+        ));
+    }
+
+    problems
+}
+
+/// Fail with every problem of an update that holds for any device
+pub fn validate_settings(request: &SystemUpdateRequest) -> Result<()> {
+    fail_on_problems(setting_problems(request))
+}
+
+fn fail_on_problems(problems: Vec<String>) -> Result<()> {
+    ensure!(
+        problems.is_empty(),
+        "Invalid settings:\n  - {list}",
+        list = problems.join("\n  - ")
+    );
+    Ok(())
+}
+
+/// How settings updates are checked against the hardware they are sent to
+#[derive(Debug, Clone)]
+pub struct SettingsPolicy {
+    pub hardware: HardwareDb,
+    /// Allow frequency and voltage outside the hardware's safe range
+    pub allow_unsafe: bool,
+}
+
+impl SettingsPolicy {
+    pub fn new(hardware: HardwareDb, allow_unsafe: bool) -> Self {
+        Self {
+            hardware,
+            allow_unsafe,
+        }
+    }
+
+    /// Policy using the built-in hardware profiles and the user's hardware file
+    pub fn load(allow_unsafe: bool) -> Result<Self> {
+        Ok(Self::new(HardwareDb::load_user()?, allow_unsafe))
+    }
+
+    /// Whether the update must be checked against the device's hardware profile
+    pub fn needs_device(&self, request: &SystemUpdateRequest) -> bool {
+        !self.allow_unsafe && needs_asic_model(request)
+    }
+
+    /// Everything wrong with an update for a device
+    pub fn problems(
+        &self,
+        request: &SystemUpdateRequest,
+        info: &SystemInfoResponse,
+    ) -> Vec<String> {
+        let mut problems = setting_problems(request);
+        if !self.needs_device(request) {
+            return problems;
+        }
+
+        let Some(profile) = self.hardware.lookup(info) else {
+            problems.push(format!(
+                "No hardware profile for ASIC {model} on board {board}, refusing to change frequency or voltage. Add one to hardware.toml or use --unsafe",
+                model = info.asic_model,
+                board = info.board_version
+            ));
+            return problems;
+        };
+        if let Some(frequency) = request.frequency_value
+            && !(profile.min_frequency..=profile.max_frequency).contains(&frequency)
+        {
+            problems.push(format!(
+                "Frequency {frequency} MHz is outside the safe {min}-{max} MHz of the {product} ({model}), use --unsafe to set it anyway",
+                min = profile.min_frequency,
+                max = profile.max_frequency,
+                product = profile.product,
+                model = profile.asic_model
+            ));
+        }
+        if let Some(voltage) = request.voltage_value
+            && !(profile.min_voltage..=profile.max_voltage).contains(&voltage)
+        {
+            problems.push(format!(
+                "Core voltage {voltage} mV is outside the safe {min}-{max} mV of the {product} ({model}), use --unsafe to set it anyway",
+                min = profile.min_voltage,
+                max = profile.max_voltage,
+                product = profile.product,
+                model = profile.asic_model
+            ));
+        }
+        problems
+    }
+
+    /// Fail with every problem of an update for a device
+    pub fn validate(&self, request: &SystemUpdateRequest, info: &SystemInfoResponse) -> Result<()> {
+        fail_on_problems(self.problems(request, info))
+    }
+
+    /// Validate an update against the device it is sent to
+    ///
+    /// The device is only asked for its ASIC model and board when its hardware profile matters.
+    pub async fn ensure_supported(
+        &self,
+        client: &AxeOsClient,
+        request: &SystemUpdateRequest,
+    ) -> Result<()> {
+        if !self.needs_device(request) {
+            return validate_settings(request);
+        }
+        let info = client
+            .get_system_info()
+            .await
+            .context("Failed to read the ASIC model to validate settings")?;
+        self.validate(request, &info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_update_request_rejects_unknown_keys() -> Result<()> {
+        let request = parse_update_request(r#"{"frequencyvalue": 525, "fanspeed": 80}"#)?;
+        assert_eq!(request.frequency_value, Some(525));
+        assert_eq!(request.fan_speed, Some(80));
+
+        let result = parse_update_request(r#"{"pool_url": "stratum+tcp://pool:3333"}"#);
+        assert!(result.is_err());
+        if let Err(e) = result {
+            assert!(format!("{e:#}").contains("unknown field `pool_url`"));
+        }
+        Ok(())
+    }
+
+    fn info(asic_model: &str, board_version: &str) -> SystemInfoResponse {
+        SystemInfoResponse {
+            asic_model: asic_model.to_string(),
+            board_version: board_version.to_string(),
+            device_model: None,
+            firmware_version: "v2.5.0".to_string(),
+            mac_address: "AA:BB:CC:DD:EE:FF".to_string(),
+            hostname: "bitaxe".to_string(),
+            wifi_ssid: None,
+            wifi_status: None,
+            wifi_rssi: None,
+            pool_url: "public-pool.io".to_string(),
+            pool_port: 21496,
+            pool_user: "bc1qtest.bitaxe".to_string(),
+            fallback_pool_url: None,
+            fallback_pool_port: None,
+            fallback_pool_user: None,
+            using_fallback_pool: None,
+            frequency: 525,
+            voltage: 1150.0,
+            fanspeed: 50,
+            auto_fan_speed: None,
+            temp: 55.0,
+            power: 15.0,
+            running_time: 3600,
+        }
+    }
+
+    #[test]
+    fn test_hardware_limits() -> Result<()> {
+        let policy = SettingsPolicy::new(HardwareDb::builtin()?, false);
+        let request = SystemUpdateRequest {
+            frequency_value: Some(600),
+            voltage_value: Some(1150.0),
+            ..Default::default()
+        };
+        assert!(policy.problems(&request, &info("BM1370", "601")).is_empty());
+
+        let problems = policy.problems(&request, &info("BM1366", "204"));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("safe 400-575 MHz of the Bitaxe Ultra (BM1366)"));
+
+        let low_voltage = SystemUpdateRequest {
+            voltage_value: Some(1000.0),
+            ..Default::default()
+        };
+        assert!(
+            policy
+                .problems(&low_voltage, &info("BM1370", "601"))
+                .is_empty()
+        );
+        assert_eq!(
+            policy.problems(&low_voltage, &info("BM1368", "401")).len(),
+            1
+        );
+        assert_eq!(policy.problems(&low_voltage, &info("BM9999", "1")).len(), 1);
+
+        // --unsafe skips the hardware checks, but not the ones for every device
+        let policy = SettingsPolicy::new(HardwareDb::builtin()?, true);
+        assert!(
+            policy
+                .problems(&low_voltage, &info("BM9999", "1"))
+                .is_empty()
+        );
+        let bad_fan = SystemUpdateRequest {
+            fan_speed: Some(150),
+            frequency_value: Some(900),
+            ..Default::default()
+        };
+        assert_eq!(policy.problems(&bad_fan, &info("BM1370", "601")).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_problems() -> Result<()> {
+        let request = SystemUpdateRequest {
+            hostname: Some("bad host".to_string()),
+            pool_url: Some(" ".to_string()),
+            pool_port: Some(0),
+            fan_speed: Some(120),
+            flip_screen: Some(2),
+            display_timeout: Some(-5),
+            ..Default::default()
+        };
+        assert_eq!(setting_problems(&request).len(), 6);
+
+        let request = SystemUpdateRequest {
+            hostname: Some("bitaxe-401".to_string()),
+            fan_speed: Some(100),
+            display_timeout: Some(-1),
+            ..Default::default()
+        };
+        validate_settings(&request)?;
+        Ok(())
+    }
+}
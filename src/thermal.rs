@@ -0,0 +1,543 @@
+use anyhow::{Context, Result, ensure};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::{AxeOsClient, Device, SystemInfoResponse, SystemUpdateRequest};
+
+/// Name of the thermal guard action log inside the cache directory
+pub const THERMAL_LOG_FILE_NAME: &str = "thermal-guard.jsonl";
+
+/// Settings of the thermal guard
+#[derive(Debug, Clone)]
+pub struct ThermalGuardConfig {
+    /// Temperature at which the guard starts acting
+    pub trigger_temp: f64,
+    /// Temperature the device must cool down to before settings are restored
+    pub clear_temp: f64,
+    /// Fan speed percentage used while the device is too hot
+    pub fan_speed: u8,
+    /// Frequency reduction per step in MHz
+    pub frequency_step: u32,
+    /// Frequency the guard never steps below
+    pub min_frequency: u32,
+    /// Minimum time between two actions on the same device
+    pub cooldown: Duration,
+}
+
+/// Settings a device had before the guard touched it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OriginalSettings {
+    pub frequency: u32,
+    pub fan_speed: u32,
+    pub auto_fan: bool,
+}
+
+impl From<&SystemInfoResponse> for OriginalSettings {
+    fn from(info: &SystemInfoResponse) -> Self {
+        Self {
+            frequency: info.frequency,
+            fan_speed: info.fanspeed,
+            auto_fan: info.auto_fan_speed.unwrap_or(false),
+        }
+    }
+}
+
+/// Change the guard makes to a device
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum GuardAction {
+    /// Switch to a fixed high fan speed
+    RaiseFan { to: u8 },
+    /// Step the ASIC frequency down
+    LowerFrequency { from: u32, to: u32 },
+    /// Put back the settings from before the guard acted
+    Restore { settings: OriginalSettings },
+}
+
+/// Logged guard action with the affected values before and after
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardEvent {
+    pub timestamp: DateTime<Utc>,
+    pub device: String,
+    pub ip: String,
+    pub temperature: f64,
+    #[serde(flatten)]
+    pub action: GuardAction,
+    pub before: String,
+    pub after: String,
+}
+
+impl GuardEvent {
+    /// One line description for the monitor alert list
+    pub fn message(&self) -> String {
+        let what = match self.action {
+            GuardAction::RaiseFan { .. } => "raised fan",
+            GuardAction::LowerFrequency { .. } => "lowered frequency",
+            GuardAction::Restore { .. } => "restored settings",
+        };
+        format!(
+            "🧊 {device} at {temp:.1}°C, thermal guard {what}: {before} → {after}",
+            device = self.device,
+            temp = self.temperature,
+            before = self.before,
+            after = self.after
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GuardedDevice {
+    original: OriginalSettings,
+    frequency: u32,
+    fan_raised: bool,
+    last_action: DateTime<Utc>,
+}
+
+/// Per-device state of the thermal guard
+#[derive(Debug, Clone, Default)]
+pub struct ThermalGuard {
+    devices: HashMap<String, GuardedDevice>,
+}
+
+impl ThermalGuard {
+    /// Whether the guard has changed settings on the device
+    pub fn is_guarding(&self, ip: &str) -> bool {
+        self.devices.contains_key(ip)
+    }
+
+    /// Devices with guard changes and the settings to restore
+    pub fn guarded(&self) -> impl Iterator<Item = (&str, OriginalSettings)> {
+        self.devices
+            .iter()
+            .map(|(ip, device)| (ip.as_str(), device.original))
+    }
+
+    /// Next action for a device at the given temperature
+    ///
+    /// `current` is only consulted when the guard starts acting on a device.
+    pub fn evaluate(
+        &self,
+        config: &ThermalGuardConfig,
+        ip: &str,
+        temperature: f64,
+        current: Option<&OriginalSettings>,
+        now: DateTime<Utc>,
+    ) -> Option<GuardAction> {
+        let Some(guarded) = self.devices.get(ip) else {
+            if temperature < config.trigger_temp {
+                return None;
+            }
+            // Starting out: the fan is the cheapest lever
+            return match current {
+                Some(current) if fan_below_target(config, current) => Some(GuardAction::RaiseFan {
+                    to: config.fan_speed,
+                }),
+                Some(current) => next_frequency(config, current.frequency),
+                None => None,
+            };
+        };
+
+        let cooling_down = now.signed_duration_since(guarded.last_action)
+            < chrono::Duration::from_std(config.cooldown).unwrap_or_default();
+        if cooling_down {
+            return None;
+        }
+
+        if temperature <= config.clear_temp {
+            return Some(GuardAction::Restore {
+                settings: guarded.original,
+            });
+        }
+
+        if temperature < config.trigger_temp {
+            return None;
+        }
+
+        // A fan that already ran at or above the target is never turned down
+        if !guarded.fan_raised && fan_below_target(config, &guarded.original) {
+            return Some(GuardAction::RaiseFan {
+                to: config.fan_speed,
+            });
+        }
+        next_frequency(config, guarded.frequency)
+    }
+
+    /// Remember an action that was applied successfully
+    pub fn record(
+        &mut self,
+        ip: &str,
+        action: &GuardAction,
+        original: OriginalSettings,
+        now: DateTime<Utc>,
+    ) {
+        if let GuardAction::Restore { .. } = action {
+            self.devices.remove(ip);
+            return;
+        }
+
+        let guarded = self
+            .devices
+            .entry(ip.to_string())
+            .or_insert_with(|| GuardedDevice {
+                original,
+                frequency: original.frequency,
+                fan_raised: false,
+                last_action: now,
+            });
+        guarded.last_action = now;
+        match *action {
+            GuardAction::RaiseFan { .. } => guarded.fan_raised = true,
+            GuardAction::LowerFrequency { to, .. } => guarded.frequency = to,
+            GuardAction::Restore { .. } => {}
+        }
+    }
+
+    /// Describe the values an action changes, before and after
+    fn describe(
+        &self,
+        ip: &str,
+        action: &GuardAction,
+        original: &OriginalSettings,
+    ) -> (String, String) {
+        match *action {
+            GuardAction::RaiseFan { to } => {
+                let before = if original.auto_fan {
+                    "fan auto".to_string()
+                } else {
+                    format!("fan {speed}%", speed = original.fan_speed)
+                };
+                (before, format!("fan {to}%"))
+            }
+            GuardAction::LowerFrequency { from, to } => {
+                (format!("{from} MHz"), format!("{to} MHz"))
+            }
+            GuardAction::Restore { settings } => {
+                let guarded = self.devices.get(ip);
+                let fan = if settings.auto_fan {
+                    "fan auto".to_string()
+                } else {
+                    format!("fan {speed}%", speed = settings.fan_speed)
+                };
+                let before = match guarded {
+                    Some(guarded) if guarded.fan_raised => {
+                        format!("{freq} MHz, raised fan", freq = guarded.frequency)
+                    }
+                    Some(guarded) => format!("{freq} MHz", freq = guarded.frequency),
+                    None => "-".to_string(),
+                };
+                (
+                    before,
+                    format!("{freq} MHz, {fan}", freq = settings.frequency),
+                )
+            }
+        }
+    }
+
+    /// Check one device and apply the next action, if any
+    pub async fn check_device(
+        &mut self,
+        config: &ThermalGuardConfig,
+        device: &Device,
+        temperature: f64,
+    ) -> Result<Option<GuardEvent>> {
+        let client = AxeOsClient::new(&device.ip_address)?;
+        let now = Utc::now();
+
+        let original = match self.devices.get(&device.ip_address) {
+            Some(guarded) => guarded.original,
+            None if temperature >= config.trigger_temp => {
+                let info = client.get_system_info().await.with_context(|| {
+                    format!("Failed to read settings of {name}", name = device.name)
+                })?;
+                OriginalSettings::from(&info)
+            }
+            None => return Ok(None),
+        };
+
+        let Some(action) = self.evaluate(
+            config,
+            &device.ip_address,
+            temperature,
+            Some(&original),
+            now,
+        ) else {
+            return Ok(None);
+        };
+
+        apply_action(&client, &action).await.with_context(|| {
+            format!("Thermal guard failed to update {name}", name = device.name)
+        })?;
+
+        let (before, after) = self.describe(&device.ip_address, &action, &original);
+        self.record(&device.ip_address, &action, original, now);
+
+        Ok(Some(GuardEvent {
+            timestamp: now,
+            device: device.name.clone(),
+            ip: device.ip_address.clone(),
+            temperature,
+            action,
+            before,
+            after,
+        }))
+    }
+}
+
+/// Whether raising the fan to the guard's speed would cool the device more
+fn fan_below_target(config: &ThermalGuardConfig, settings: &OriginalSettings) -> bool {
+    settings.auto_fan || settings.fan_speed < u32::from(config.fan_speed)
+}
+
+fn next_frequency(config: &ThermalGuardConfig, frequency: u32) -> Option<GuardAction> {
+    let to = frequency
+        .saturating_sub(config.frequency_step)
+        .max(config.min_frequency);
+    (to < frequency).then_some(GuardAction::LowerFrequency {
+        from: frequency,
+        to,
+    })
+}
+
+/// Send the settings change for an action to the device
+pub async fn apply_action(client: &AxeOsClient, action: &GuardAction) -> Result<()> {
+    let request = match *action {
+        GuardAction::RaiseFan { to } => SystemUpdateRequest {
+            fan_speed: Some(u32::from(to)),
+            auto_fan_speed: Some(0),
+            ..Default::default()
+        },
+        GuardAction::LowerFrequency { to, .. } => SystemUpdateRequest {
+            frequency_value: Some(to),
+            ..Default::default()
+        },
+        GuardAction::Restore { settings } => SystemUpdateRequest {
+            frequency_value: Some(settings.frequency),
+            fan_speed: (!settings.auto_fan).then_some(settings.fan_speed),
+            auto_fan_speed: Some(u8::from(settings.auto_fan)),
+            ..Default::default()
+        },
+    };
+
+    let result = client.update_system(request).await?;
+    ensure!(result.success, "{message}", message = result.message);
+    Ok(())
+}
+
+/// Append-only JSON lines log of guard actions
+#[derive(Debug, Clone)]
+pub struct ThermalLog {
+    path: PathBuf,
+}
+
+impl ThermalLog {
+    /// Open the log inside the given cache directory
+    pub fn open(cache_dir: &Path) -> Self {
+        Self {
+            path: cache_dir.join(THERMAL_LOG_FILE_NAME),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, event: &GuardEvent) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {path}", path = self.path.display()))?;
+        let line = serde_json::to_string(event).context("Failed to serialize guard event")?;
+        writeln!(file, "{line}")
+            .with_context(|| format!("Failed to write {path}", path = self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ThermalGuardConfig {
+        ThermalGuardConfig {
+            trigger_temp: 70.0,
+            clear_temp: 62.0,
+            fan_speed: 100,
+            frequency_step: 25,
+            min_frequency: 450,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    const ORIGINAL: OriginalSettings = OriginalSettings {
+        frequency: 500,
+        fan_speed: 60,
+        auto_fan: false,
+    };
+
+    #[test]
+    fn test_escalates_fan_then_frequency_then_restores() {
+        let config = config();
+        let mut guard = ThermalGuard::default();
+        let ip = "192.168.1.10";
+        let start = Utc::now();
+        let minutes = |m: i64| start + chrono::Duration::minutes(m);
+
+        // Below the trigger nothing happens
+        assert_eq!(
+            guard.evaluate(&config, ip, 69.0, Some(&ORIGINAL), start),
+            None
+        );
+
+        // Fan first
+        let action = guard.evaluate(&config, ip, 72.0, Some(&ORIGINAL), start);
+        assert_eq!(action, Some(GuardAction::RaiseFan { to: 100 }));
+        guard.record(ip, &GuardAction::RaiseFan { to: 100 }, ORIGINAL, start);
+        assert!(guard.is_guarding(ip));
+
+        // Still hot, but within the cooldown
+        assert_eq!(guard.evaluate(&config, ip, 72.0, None, start), None);
+
+        // Then frequency, step by step down to the floor
+        let step = GuardAction::LowerFrequency { from: 500, to: 475 };
+        assert_eq!(
+            guard.evaluate(&config, ip, 72.0, None, minutes(1)),
+            Some(step)
+        );
+        guard.record(ip, &step, ORIGINAL, minutes(1));
+        let step = GuardAction::LowerFrequency { from: 475, to: 450 };
+        assert_eq!(
+            guard.evaluate(&config, ip, 71.0, None, minutes(2)),
+            Some(step)
+        );
+        guard.record(ip, &step, ORIGINAL, minutes(2));
+        assert_eq!(guard.evaluate(&config, ip, 71.0, None, minutes(3)), None);
+
+        // Hysteresis: below the trigger but above the clear temperature holds
+        assert_eq!(guard.evaluate(&config, ip, 65.0, None, minutes(4)), None);
+
+        let restore = GuardAction::Restore { settings: ORIGINAL };
+        assert_eq!(
+            guard.evaluate(&config, ip, 61.0, None, minutes(5)),
+            Some(restore)
+        );
+        guard.record(ip, &restore, ORIGINAL, minutes(5));
+        assert!(!guard.is_guarding(ip));
+    }
+
+    #[test]
+    fn test_skips_fan_when_already_at_target() {
+        let config = config();
+        let mut guard = ThermalGuard::default();
+        let ip = "192.168.1.10";
+        let start = Utc::now();
+        // The fan already runs faster than the guard's target
+        let config = ThermalGuardConfig {
+            fan_speed: 80,
+            ..config
+        };
+        let fast_fan = OriginalSettings {
+            fan_speed: 90,
+            ..ORIGINAL
+        };
+
+        let step = GuardAction::LowerFrequency { from: 500, to: 475 };
+        assert_eq!(
+            guard.evaluate(&config, ip, 75.0, Some(&fast_fan), start),
+            Some(step)
+        );
+        guard.record(ip, &step, fast_fan, start);
+
+        // The next hot tick keeps stepping the frequency instead of slowing the fan to 80%
+        assert_eq!(
+            guard.evaluate(
+                &config,
+                ip,
+                75.0,
+                None,
+                start + chrono::Duration::minutes(1)
+            ),
+            Some(GuardAction::LowerFrequency { from: 475, to: 450 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_device_applies_and_describes_actions() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "ASICModel": "BM1370",
+                    "boardVersion": "601",
+                    "version": "2.4.0",
+                    "macAddr": "AA:BB:CC:DD:EE:FF",
+                    "hostname": "bitaxe-1",
+                    "stratumURL": "public-pool.io",
+                    "stratumPort": 21496,
+                    "stratumUser": "bc1qexample.bitaxe-1",
+                    "frequency": 500,
+                    "voltage": 1150,
+                    "fanspeed": 60,
+                    "autofanspeed": 1,
+                    "temp": 72.0,
+                    "power": 15.0,
+                    "hashRate": 1000.0,
+                    "uptimeSeconds": 3600,
+                    "sharesAccepted": 10,
+                    "sharesRejected": 0
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let raise_fan = server
+            .mock("PATCH", "/api/system")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "fanspeed": 100,
+                "autofanspeed": 0,
+            })))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let device = Device {
+            name: "bitaxe-1".to_string(),
+            ip_address: server.host_with_port(),
+            device_type: crate::api::DeviceType::BitaxeGamma,
+            serial_number: None,
+            status: crate::api::DeviceStatus::Online,
+            discovered_at: Utc::now(),
+            last_seen: Utc::now(),
+            stats: None,
+        };
+
+        let mut guard = ThermalGuard::default();
+        let event = guard
+            .check_device(&config(), &device, 72.0)
+            .await?
+            .context("guard event")?;
+
+        raise_fan.assert_async().await;
+        assert_eq!(event.action, GuardAction::RaiseFan { to: 100 });
+        assert_eq!(event.before, "fan auto");
+        assert_eq!(event.after, "fan 100%");
+        assert_eq!(
+            guard.guarded().collect::<Vec<_>>(),
+            vec![(
+                device.ip_address.as_str(),
+                OriginalSettings {
+                    frequency: 500,
+                    fan_speed: 60,
+                    auto_fan: true,
+                }
+            )]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,863 @@
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use strum::{Display, EnumString, VariantNames};
+use tokio::time::{Instant, sleep};
+
+use crate::api::{
+    AxeOsClient, SystemInfoResponse, SystemStatsResponse, SystemUpdateRequest, joules_per_terahash,
+};
+use crate::history::device_key;
+use crate::settings::SettingsPolicy;
+
+/// Name of the tuning session directory inside the cache directory
+pub const TUNE_DIR_NAME: &str = "tune";
+
+/// How often temperature and power are checked while a point settles
+const SETTLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What the tuner optimizes for
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString, VariantNames,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum TuneGoal {
+    /// Lowest energy per terahash
+    #[strum(serialize = "efficiency", serialize = "jth")]
+    Efficiency,
+    /// Highest hashrate within the limits
+    Hashrate,
+}
+
+/// Settings of a tuning run
+#[derive(Debug, Clone)]
+pub struct TuneConfig {
+    /// Frequencies to try in MHz
+    pub frequencies: Vec<u32>,
+    /// Core voltages to try in mV
+    pub voltages: Vec<u32>,
+    /// Time to let the device stabilize after applying a point
+    pub settle: Duration,
+    /// Number of stats samples averaged per point
+    pub samples: usize,
+    /// Delay between stats samples
+    pub sample_interval: Duration,
+    /// Points reaching this ASIC temperature are rejected
+    pub max_temp: f64,
+    /// Points reaching this power draw in watts are rejected
+    pub max_power: Option<f64>,
+    /// Restart the device after applying each point
+    pub restart: bool,
+    /// How every frequency and voltage is checked against the device's hardware profile
+    pub policy: SettingsPolicy,
+}
+
+impl TuneConfig {
+    /// Fail unless the whole grid is within the device's safe range
+    ///
+    /// Frequency and voltage limits are independent, so each value of an axis
+    /// is checked once instead of every pair.
+    pub fn ensure_supported(&self, info: &SystemInfoResponse) -> Result<()> {
+        let requests = self
+            .frequencies
+            .iter()
+            .map(|&frequency| SystemUpdateRequest {
+                frequency_value: Some(frequency),
+                ..Default::default()
+            })
+            .chain(self.voltages.iter().map(|&voltage| SystemUpdateRequest {
+                voltage_value: Some(f64::from(voltage)),
+                ..Default::default()
+            }));
+        let mut problems: Vec<String> = requests
+            .flat_map(|request| self.policy.problems(&request, info))
+            .collect();
+        // A missing hardware profile is reported once per grid value
+        problems.dedup();
+
+        ensure!(
+            problems.is_empty(),
+            "Tuning grid is outside the safe range of {name}:\n  - {list}",
+            name = info.hostname,
+            list = problems.join("\n  - ")
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointStatus {
+    /// Measured within the limits
+    Accepted,
+    /// Exceeded a temperature or power limit
+    Rejected,
+    /// Not tried because a lower frequency at the same voltage was rejected
+    Skipped,
+    /// The device could not be configured or measured
+    Failed,
+}
+
+/// Measurement of one frequency/voltage pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunePoint {
+    pub frequency: u32,
+    pub voltage: u32,
+    pub status: PointStatus,
+    /// Average hashrate in GH/s
+    pub hashrate: Option<f64>,
+    /// Highest temperature seen while sampling
+    pub temperature: Option<f64>,
+    /// Average power in watts
+    pub power: Option<f64>,
+    pub reason: Option<String>,
+    pub measured_at: DateTime<Utc>,
+}
+
+impl TunePoint {
+    /// Energy per terahash in J/TH
+    pub fn efficiency(&self) -> Option<f64> {
+        joules_per_terahash(self.hashrate?, self.power?)
+    }
+}
+
+/// Progress of a tuning run, saved after every point so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TuneSession {
+    pub device_name: String,
+    pub ip_address: String,
+    pub started_at: DateTime<Utc>,
+    /// Settings the device had before tuning started
+    pub original_frequency: u32,
+    pub original_voltage: f64,
+    pub frequencies: Vec<u32>,
+    pub voltages: Vec<u32>,
+    pub points: Vec<TunePoint>,
+}
+
+impl TuneSession {
+    pub fn new(
+        device_name: &str,
+        ip_address: &str,
+        original_frequency: u32,
+        original_voltage: f64,
+        config: &TuneConfig,
+    ) -> Self {
+        Self {
+            device_name: device_name.to_string(),
+            ip_address: ip_address.to_string(),
+            started_at: Utc::now(),
+            original_frequency,
+            original_voltage,
+            frequencies: config.frequencies.clone(),
+            voltages: config.voltages.clone(),
+            points: Vec::new(),
+        }
+    }
+
+    /// Whether the session was started with the same grid
+    pub fn matches_grid(&self, config: &TuneConfig) -> bool {
+        self.frequencies == config.frequencies && self.voltages == config.voltages
+    }
+
+    fn point(&self, frequency: u32, voltage: u32) -> Option<&TunePoint> {
+        self.points
+            .iter()
+            .find(|p| p.frequency == frequency && p.voltage == voltage)
+    }
+
+    /// Grid points not measured yet, by voltage then frequency
+    pub fn remaining(&self) -> Vec<(u32, u32)> {
+        self.voltages
+            .iter()
+            .flat_map(|&voltage| self.frequencies.iter().map(move |&freq| (freq, voltage)))
+            .filter(|&(freq, voltage)| self.point(freq, voltage).is_none())
+            .collect()
+    }
+
+    /// Best accepted point for the goal
+    pub fn best(&self, goal: TuneGoal) -> Option<&TunePoint> {
+        let accepted = self
+            .points
+            .iter()
+            .filter(|p| p.status == PointStatus::Accepted);
+
+        match goal {
+            TuneGoal::Efficiency => accepted
+                .filter_map(|p| p.efficiency().map(|e| (p, e)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(p, _)| p),
+            TuneGoal::Hashrate => accepted
+                .filter_map(|p| p.hashrate.map(|h| (p, h)))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(p, _)| p),
+        }
+    }
+}
+
+/// Tuning sessions stored as `<cache_dir>/tune/<device>.json`
+#[derive(Debug, Clone)]
+pub struct TuneStore {
+    root: PathBuf,
+}
+
+impl TuneStore {
+    /// Open the tuning store inside the given cache directory
+    pub fn open(cache_dir: &Path) -> Self {
+        Self {
+            root: cache_dir.join(TUNE_DIR_NAME),
+        }
+    }
+
+    pub fn load(&self, device_ip: &str) -> Result<Option<TuneSession>> {
+        let path = self.path(device_ip);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {path}", path = path.display()))?;
+        let session = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {path}", path = path.display()))?;
+        Ok(Some(session))
+    }
+
+    pub fn save(&self, session: &TuneSession) -> Result<PathBuf> {
+        fs::create_dir_all(&self.root).with_context(|| {
+            format!(
+                "Failed to create tune directory {path}",
+                path = self.root.display()
+            )
+        })?;
+
+        let path = self.path(&session.ip_address);
+        let content =
+            serde_json::to_string_pretty(session).context("Failed to serialize tune session")?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write {path}", path = path.display()))?;
+        Ok(path)
+    }
+
+    pub fn remove(&self, device_ip: &str) -> Result<()> {
+        let path = self.path(device_ip);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {path}", path = path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn path(&self, device_ip: &str) -> PathBuf {
+        self.root
+            .join(format!("{key}.json", key = device_key(device_ip)))
+    }
+}
+
+/// Parse a grid axis given as `start:end:step` or a comma separated list
+pub fn parse_grid_values(spec: &str) -> Result<Vec<u32>> {
+    let values = if spec.contains(':') {
+        let parts: Vec<u32> = spec
+            .split(':')
+            .map(|part| {
+                part.trim()
+                    .parse()
+                    .with_context(|| format!("Invalid number '{part}' in '{spec}'"))
+            })
+            .collect::<Result<_>>()?;
+        let [start, end, step] = parts[..] else {
+            bail!("Expected start:end:step, got '{spec}'");
+        };
+        ensure!(step > 0, "Step must be greater than zero in '{spec}'");
+        ensure!(start <= end, "Start must not exceed end in '{spec}'");
+        (start..=end).step_by(step as usize).collect()
+    } else {
+        spec.split(',')
+            .map(|part| {
+                part.trim()
+                    .parse()
+                    .with_context(|| format!("Invalid number '{part}' in '{spec}'"))
+            })
+            .collect::<Result<Vec<u32>>>()?
+    };
+
+    ensure!(!values.is_empty(), "No values in '{spec}'");
+    Ok(values)
+}
+
+/// Measure every remaining grid point, saving the session after each one
+pub async fn run_tune<F>(
+    client: &AxeOsClient,
+    config: &TuneConfig,
+    session: &mut TuneSession,
+    store: &TuneStore,
+    mut on_point: F,
+) -> Result<()>
+where
+    F: FnMut(&TunePoint),
+{
+    // Settings to fall back to when a point crosses a limit
+    let mut previous = session
+        .points
+        .iter()
+        .rev()
+        .find(|p| p.status == PointStatus::Accepted)
+        .map_or(
+            (session.original_frequency, session.original_voltage),
+            |p| (p.frequency, f64::from(p.voltage)),
+        );
+
+    for (frequency, voltage) in session.remaining() {
+        // Higher frequencies at a voltage that already hit a limit only get hotter
+        let limited = session.points.iter().any(|p| {
+            p.voltage == voltage && p.frequency < frequency && p.status == PointStatus::Rejected
+        });
+
+        let point = if limited {
+            TunePoint {
+                frequency,
+                voltage,
+                status: PointStatus::Skipped,
+                hashrate: None,
+                temperature: None,
+                power: None,
+                reason: Some("A lower frequency exceeded the limits".to_string()),
+                measured_at: Utc::now(),
+            }
+        } else {
+            measure_point(client, config, frequency, voltage, previous).await
+        };
+        if point.status == PointStatus::Accepted {
+            previous = (frequency, f64::from(voltage));
+        }
+
+        on_point(&point);
+        session.points.push(point);
+        store.save(session)?;
+    }
+
+    Ok(())
+}
+
+/// Run the remaining grid points, putting the original settings back when
+/// tuning fails or `interrupt` (Ctrl-C) completes before it is done
+///
+/// The session is saved after every point, so a later run resumes from there.
+pub async fn run_tune_or_restore<F>(
+    client: &AxeOsClient,
+    config: &TuneConfig,
+    session: &mut TuneSession,
+    store: &TuneStore,
+    on_point: F,
+    interrupt: impl Future,
+) -> Result<()>
+where
+    F: FnMut(&TunePoint),
+{
+    let outcome = tokio::select! {
+        result = run_tune(client, config, session, store, on_point) => result,
+        _ = interrupt => Err(anyhow!("Tuning was interrupted")),
+    };
+    let Err(e) = outcome else {
+        return Ok(());
+    };
+
+    // Whatever grid point was last applied may not be stable
+    let (frequency, voltage) = (session.original_frequency, session.original_voltage);
+    match apply_point(client, &config.policy, frequency, voltage, config.restart).await {
+        Ok(()) => Err(e.context(format!(
+            "Tuning stopped, restored the original {frequency} MHz/{voltage} mV"
+        ))),
+        Err(restore) => Err(e.context(format!(
+            "Tuning stopped and restoring the original {frequency} MHz/{voltage} mV failed: {restore:#}"
+        ))),
+    }
+}
+
+/// Apply frequency and voltage to the device if the policy allows them
+pub async fn apply_point(
+    client: &AxeOsClient,
+    policy: &SettingsPolicy,
+    frequency: u32,
+    voltage: f64,
+    restart: bool,
+) -> Result<()> {
+    let request = SystemUpdateRequest {
+        frequency_value: Some(frequency),
+        voltage_value: Some(voltage),
+        ..Default::default()
+    };
+    policy.ensure_supported(client, &request).await?;
+    let result = client.update_system(request).await?;
+    ensure!(result.success, "{message}", message = result.message);
+
+    if restart {
+        let result = client.restart_system().await?;
+        ensure!(result.success, "{message}", message = result.message);
+    }
+    Ok(())
+}
+
+/// Measure one grid point, going back to `previous` if it crosses a limit
+async fn measure_point(
+    client: &AxeOsClient,
+    config: &TuneConfig,
+    frequency: u32,
+    voltage: u32,
+    previous: (u32, f64),
+) -> TunePoint {
+    let mut point = TunePoint {
+        frequency,
+        voltage,
+        status: PointStatus::Failed,
+        hashrate: None,
+        temperature: None,
+        power: None,
+        reason: None,
+        measured_at: Utc::now(),
+    };
+
+    if let Err(e) = apply_point(
+        client,
+        &config.policy,
+        frequency,
+        f64::from(voltage),
+        config.restart,
+    )
+    .await
+    {
+        point.reason = Some(format!("Failed to apply settings: {e:#}"));
+        return point;
+    }
+
+    // Keep an eye on the device while it settles instead of sleeping blind
+    let settled_at = Instant::now() + config.settle;
+    loop {
+        let remaining = settled_at.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        sleep(remaining.min(SETTLE_POLL_INTERVAL)).await;
+
+        match client.get_system_stats().await {
+            Ok(stats) => {
+                point.temperature =
+                    Some(point.temperature.map_or(stats.temp, |t| t.max(stats.temp)));
+                if let Some(reason) = limit_exceeded(config, &stats) {
+                    point.status = PointStatus::Rejected;
+                    point.reason = Some(format!("{reason} while settling"));
+                    restore_previous(client, config, previous, &mut point).await;
+                    point.measured_at = Utc::now();
+                    return point;
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Settle check at {frequency} MHz/{voltage} mV failed: {e:#}");
+            }
+        }
+    }
+
+    let mut hashrates = Vec::new();
+    let mut powers = Vec::new();
+    for index in 0..config.samples {
+        if index > 0 {
+            sleep(config.sample_interval).await;
+        }
+
+        let stats = match client.get_system_stats().await {
+            Ok(stats) => stats,
+            Err(e) => {
+                tracing::debug!("Sample {index} at {frequency} MHz/{voltage} mV failed: {e:#}");
+                continue;
+            }
+        };
+
+        hashrates.push(stats.hashrate);
+        powers.push(stats.power);
+        point.temperature = Some(point.temperature.map_or(stats.temp, |t| t.max(stats.temp)));
+
+        // Stop sampling as soon as a limit is crossed
+        if let Some(reason) = limit_exceeded(config, &stats) {
+            point.status = PointStatus::Rejected;
+            point.reason = Some(reason);
+            restore_previous(client, config, previous, &mut point).await;
+            break;
+        }
+    }
+
+    if hashrates.is_empty() {
+        point.reason = Some("No stats samples could be read".to_string());
+        return point;
+    }
+
+    point.hashrate = Some(hashrates.iter().sum::<f64>() / hashrates.len() as f64);
+    point.power = Some(powers.iter().sum::<f64>() / powers.len() as f64);
+    if point.status != PointStatus::Rejected {
+        point.status = PointStatus::Accepted;
+    }
+    point.measured_at = Utc::now();
+    point
+}
+
+/// Which limit, if any, the stats sample crossed
+fn limit_exceeded(config: &TuneConfig, stats: &SystemStatsResponse) -> Option<String> {
+    if stats.temp >= config.max_temp {
+        return Some(format!(
+            "Temperature {temp:.1}°C reached the {max:.1}°C limit",
+            temp = stats.temp,
+            max = config.max_temp
+        ));
+    }
+    config
+        .max_power
+        .filter(|max_power| stats.power >= *max_power)
+        .map(|max_power| {
+            format!(
+                "Power {power:.1} W reached the {max_power:.1} W limit",
+                power = stats.power
+            )
+        })
+}
+
+/// Go back to the last known good settings after a point crossed a limit
+async fn restore_previous(
+    client: &AxeOsClient,
+    config: &TuneConfig,
+    (frequency, voltage): (u32, f64),
+    point: &mut TunePoint,
+) {
+    if let Err(e) = apply_point(client, &config.policy, frequency, voltage, config.restart).await {
+        tracing::warn!("Failed to restore {frequency} MHz/{voltage} mV: {e:#}");
+        if let Some(ref mut reason) = point.reason {
+            reason.push_str(&format!(
+                ", restoring {frequency} MHz/{voltage} mV failed: {e:#}"
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::HardwareDb;
+    use mockito::Matcher;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    fn config(frequencies: Vec<u32>, voltages: Vec<u32>) -> TuneConfig {
+        TuneConfig {
+            frequencies,
+            voltages,
+            settle: Duration::ZERO,
+            samples: 2,
+            sample_interval: Duration::ZERO,
+            max_temp: 65.0,
+            max_power: Some(25.0),
+            restart: false,
+            // The mock device runs its grid well past the Gamma's safe range
+            policy: SettingsPolicy::new(
+                HardwareDb {
+                    profiles: Vec::new(),
+                },
+                true,
+            ),
+        }
+    }
+
+    /// Mock device whose hashrate, power and temperature follow its frequency
+    async fn mock_device(server: &mut mockito::ServerGuard) -> Vec<mockito::Mock> {
+        let frequency = Arc::new(Mutex::new(400u32));
+
+        let patch_frequency = frequency.clone();
+        let patch = server
+            .mock("PATCH", "/api/system")
+            .with_status(200)
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value = request
+                    .body()
+                    .ok()
+                    .and_then(|body| serde_json::from_slice(body).ok())
+                    .unwrap_or_default();
+                if let (Some(freq), Ok(mut current)) =
+                    (body["frequencyvalue"].as_u64(), patch_frequency.lock())
+                {
+                    *current = freq as u32;
+                }
+                Vec::new()
+            })
+            .create_async()
+            .await;
+
+        let info = server
+            .mock("GET", "/api/system/info")
+            .with_status(200)
+            .with_body_from_request(move |_| {
+                let mhz = frequency.lock().map(|f| *f).unwrap_or(0);
+                let freq = f64::from(mhz);
+                serde_json::json!({
+                    "ASICModel": "BM1370",
+                    "boardVersion": "601",
+                    "version": "2.4.0",
+                    "macAddr": "AA:BB:CC:DD:EE:FF",
+                    "hostname": "bitaxe",
+                    "stratumURL": "public-pool.io",
+                    "stratumPort": 21496,
+                    "stratumUser": "bc1qexample.bitaxe",
+                    "frequency": mhz,
+                    "voltage": 1150,
+                    "fanspeed": 80,
+                    "temp": 30.0 + freq * 0.05,
+                    "power": 5.0 + freq * 0.03,
+                    "hashRate": freq * 2.0,
+                    "uptimeSeconds": 3600,
+                    "sharesAccepted": 10,
+                    "sharesRejected": 0
+                })
+                .to_string()
+                .into_bytes()
+            })
+            .create_async()
+            .await;
+
+        vec![patch, info]
+    }
+
+    #[test]
+    fn test_parse_grid_values() -> Result<()> {
+        assert_eq!(parse_grid_values("400:500:50")?, vec![400, 450, 500]);
+        assert_eq!(parse_grid_values("1100, 1150")?, vec![1100, 1150]);
+        assert!(parse_grid_values("500:400:50").is_err());
+        assert!(parse_grid_values("400:500:0").is_err());
+        assert!(parse_grid_values("400:500").is_err());
+        assert!(parse_grid_values("fast").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_grid_checked_against_hardware_profile() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+        let info = client.get_system_info().await?;
+
+        let safe = |config: TuneConfig| -> Result<TuneConfig> {
+            Ok(TuneConfig {
+                policy: SettingsPolicy::new(HardwareDb::builtin()?, false),
+                ..config
+            })
+        };
+        safe(config(vec![400, 500, 600], vec![1100, 1200]))?.ensure_supported(&info)?;
+
+        let result = safe(config(vec![500, 700, 750], vec![900, 1200]))?.ensure_supported(&info);
+        let message = match result {
+            Ok(()) => bail!("Grid outside the Gamma's range was accepted"),
+            Err(e) => format!("{e:#}"),
+        };
+        assert!(message.contains("Frequency 700 MHz"));
+        assert!(message.contains("Frequency 750 MHz"));
+        assert!(message.contains("Core voltage 900 mV"));
+        assert!(!message.contains("500 MHz"));
+
+        // --unsafe skips the profile
+        config(vec![700], vec![900]).ensure_supported(&info)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tune_finds_best_points_within_limits() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+
+        let temp_dir = TempDir::new()?;
+        let store = TuneStore::open(temp_dir.path());
+        // 700 MHz runs at 65°C and is rejected, 750 MHz is never tried
+        let config = config(vec![400, 500, 600, 700, 750], vec![1150]);
+        let mut session =
+            TuneSession::new("bitaxe", &server.host_with_port(), 400, 1150.0, &config);
+
+        run_tune(&client, &config, &mut session, &store, |_| {}).await?;
+
+        let statuses: Vec<_> = session.points.iter().map(|p| p.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                PointStatus::Accepted,
+                PointStatus::Accepted,
+                PointStatus::Accepted,
+                PointStatus::Rejected,
+                PointStatus::Skipped,
+            ]
+        );
+
+        let fastest = session.best(TuneGoal::Hashrate).context("best hashrate")?;
+        assert_eq!(fastest.frequency, 600);
+        assert_eq!(fastest.hashrate, Some(1200.0));
+
+        // Fixed power overhead makes the highest accepted frequency the most efficient too
+        let efficient = session
+            .best(TuneGoal::Efficiency)
+            .context("best efficiency")?;
+        assert_eq!(efficient.frequency, 600);
+        assert!(
+            efficient
+                .efficiency()
+                .is_some_and(|e| (e - 19.166).abs() < 0.01)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overheating_while_settling_restores_previous_point() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+
+        let temp_dir = TempDir::new()?;
+        let store = TuneStore::open(temp_dir.path());
+        let config = TuneConfig {
+            settle: Duration::from_millis(10),
+            ..config(vec![600, 700], vec![1150])
+        };
+        let mut session =
+            TuneSession::new("bitaxe", &server.host_with_port(), 400, 1150.0, &config);
+
+        run_tune(&client, &config, &mut session, &store, |_| {}).await?;
+
+        let hot = session.points.last().context("700 MHz point")?;
+        assert_eq!(hot.status, PointStatus::Rejected);
+        assert!(
+            hot.reason
+                .as_deref()
+                .is_some_and(|r| r.contains("65.0°C") && r.ends_with("while settling"))
+        );
+        // Aborted before any sample was taken
+        assert_eq!(hot.hashrate, None);
+
+        // The device went back to the last accepted point
+        assert_eq!(client.get_system_info().await?.frequency, 600);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_restores_original_settings() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+
+        // A file where the cache directory should be makes saving the session fail
+        let temp_dir = TempDir::new()?;
+        let not_a_dir = temp_dir.path().join("cache");
+        fs::write(&not_a_dir, "")?;
+        let store = TuneStore::open(&not_a_dir);
+        let config = config(vec![500, 550], vec![1150]);
+        let mut session =
+            TuneSession::new("bitaxe", &server.host_with_port(), 400, 1150.0, &config);
+
+        let result = run_tune_or_restore(
+            &client,
+            &config,
+            &mut session,
+            &store,
+            |_| {},
+            std::future::pending::<()>(),
+        )
+        .await;
+        let message = match result {
+            Ok(()) => bail!("Tuning succeeded without a writable store"),
+            Err(e) => format!("{e:#}"),
+        };
+        assert!(message.contains("restored the original 400 MHz/1150 mV"));
+        assert_eq!(client.get_system_info().await?.frequency, 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_restores_original_settings() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+
+        let temp_dir = TempDir::new()?;
+        let store = TuneStore::open(temp_dir.path());
+        // Long enough to still be settling on the first point when interrupted
+        let config = TuneConfig {
+            settle: Duration::from_secs(30),
+            ..config(vec![500, 550], vec![1150])
+        };
+        let mut session =
+            TuneSession::new("bitaxe", &server.host_with_port(), 400, 1150.0, &config);
+
+        let result = run_tune_or_restore(
+            &client,
+            &config,
+            &mut session,
+            &store,
+            |_| {},
+            sleep(Duration::from_millis(200)),
+        )
+        .await;
+        let message = match result {
+            Ok(()) => bail!("Interrupted tuning reported success"),
+            Err(e) => format!("{e:#}"),
+        };
+        assert!(message.contains("interrupted"));
+        assert!(session.points.is_empty());
+        assert_eq!(client.get_system_info().await?.frequency, 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tune_resumes_saved_session() -> Result<()> {
+        let mut server = mockito::Server::new_async().await;
+        let _mocks = mock_device(&mut server).await;
+        let patch = server
+            .mock("PATCH", "/api/system")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"frequencyvalue": 400}),
+            ))
+            .expect(0)
+            .create_async()
+            .await;
+        let client = AxeOsClient::new(&server.host_with_port())?;
+
+        let temp_dir = TempDir::new()?;
+        let store = TuneStore::open(temp_dir.path());
+        let config = config(vec![400, 500], vec![1150]);
+
+        // A previous run already measured 400 MHz
+        let mut session =
+            TuneSession::new("bitaxe", &server.host_with_port(), 400, 1150.0, &config);
+        session.points.push(TunePoint {
+            frequency: 400,
+            voltage: 1150,
+            status: PointStatus::Accepted,
+            hashrate: Some(800.0),
+            temperature: Some(50.0),
+            power: Some(17.0),
+            reason: None,
+            measured_at: Utc::now(),
+        });
+        store.save(&session)?;
+
+        let mut resumed = store
+            .load(&server.host_with_port())?
+            .context("saved session")?;
+        assert!(resumed.matches_grid(&config));
+        assert_eq!(resumed.remaining(), vec![(500, 1150)]);
+
+        run_tune(&client, &config, &mut resumed, &store, |_| {}).await?;
+
+        patch.assert_async().await;
+        let saved = store
+            .load(&server.host_with_port())?
+            .context("saved session")?;
+        assert_eq!(saved.points.len(), 2);
+        assert!(saved.remaining().is_empty());
+        Ok(())
+    }
+}